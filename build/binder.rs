@@ -1,8 +1,16 @@
 use quote::{format_ident, quote};
+use std::env;
 use std::io::Write;
 
 pub fn generate<W: Write>(modules: Vec<String>, out: &mut W) {
-    let modules_tokens = modules.into_iter().map(|module| {
+    // cargo tells the build script which features are enabled
+    let mut enabled = modules
+        .iter()
+        .filter(|module| env::var_os(format!("CARGO_FEATURE_{}", module.to_uppercase())).is_some())
+        .collect::<Vec<_>>();
+    enabled.sort();
+
+    let modules_tokens = modules.iter().map(|module| {
         let module_ident = format_ident!("{}", module);
 
         quote! {
@@ -11,6 +19,7 @@ pub fn generate<W: Write>(modules: Vec<String>, out: &mut W) {
             #[allow(clippy::field_reassign_with_default)]
             #[allow(non_snake_case)]
             #[allow(clippy::unnecessary_cast)]
+            #[allow(deprecated)]
             #[cfg(feature = #module)]
             pub mod #module_ident;
         }
@@ -18,6 +27,9 @@ pub fn generate<W: Write>(modules: Vec<String>, out: &mut W) {
 
     let tokens = quote! {
         #(#modules_tokens)*
+
+        /// Names of the message sets enabled in this build
+        pub const ENABLED_DIALECTS: &[&str] = &[#(#enabled),*];
     };
 
     writeln!(out, "{tokens}").unwrap();
@@ -0,0 +1,303 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::env;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Environment variable pointing to a file with per-message `cfg` predicates
+pub const MESSAGE_CFG_ENV: &str = "MAVLINK_MESSAGE_CFG";
+
+/// Environment variable pointing to a file with the naming scheme of the generated enums
+pub const NAMING_ENV: &str = "MAVLINK_NAMING";
+
+/// User configuration of the code generator
+#[derive(Debug, Default)]
+pub struct CodegenConfig {
+    /// `cfg` predicate attached to a message, by MAVLink message name
+    pub message_cfg: HashMap<String, String>,
+    pub naming: Naming,
+}
+
+/// How the MAVLink names of enums like `GPS_FIX_TYPE` become Rust type names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamingScheme {
+    /// `GpsFixType`
+    CamelCase,
+    /// `GPS_FIX_TYPE`
+    Original,
+}
+
+impl Default for NamingScheme {
+    fn default() -> Self {
+        Self::CamelCase
+    }
+}
+
+/// Naming of the generated enum types, messages and enum entries keep their MAVLink names
+#[derive(Debug, Default)]
+pub struct Naming {
+    pub scheme: NamingScheme,
+    /// Words kept in upper case by the camel case scheme, `GPS` gives `GPSFixType`
+    pub acronyms: HashSet<String>,
+    /// Rust name of an enum, by MAVLink enum name, taking precedence over the scheme. Naming a
+    /// message or an enum entry fails the build.
+    pub overrides: HashMap<String, String>,
+}
+
+impl Naming {
+    pub fn is_default(&self) -> bool {
+        self.scheme == NamingScheme::CamelCase
+            && self.acronyms.is_empty()
+            && self.overrides.is_empty()
+    }
+
+    /// Rust name of the enum called `name` in the MAVLink definitions
+    pub fn enum_name(&self, name: &str) -> String {
+        if let Some(rust_name) = self.overrides.get(name) {
+            return rust_name.clone();
+        }
+        match self.scheme {
+            NamingScheme::Original => name.to_string(),
+            NamingScheme::CamelCase => name
+                .split('_')
+                .map(|word| {
+                    if self.acronyms.contains(word) {
+                        word.to_string()
+                    } else {
+                        camel_case_word(word)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Name of an enum in the default scheme, `GPS_FIX_TYPE` gives `GpsFixType`
+pub fn camel_case(name: &str) -> String {
+    name.split('_').map(camel_case_word).collect()
+}
+
+fn camel_case_word(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + &chars.as_str().to_lowercase(),
+        None => String::new(),
+    }
+}
+
+impl CodegenConfig {
+    pub fn from_env() -> Self {
+        println!("cargo:rerun-if-env-changed={MESSAGE_CFG_ENV}");
+
+        let mut config = Self::default();
+        if let Some(path) = env::var_os(MESSAGE_CFG_ENV) {
+            println!("cargo:rerun-if-changed={}", path.to_string_lossy());
+            let content = read_to_string(&path).unwrap_or_else(|error| {
+                panic!(
+                    "could not read {} file {:?}: {}",
+                    MESSAGE_CFG_ENV, path, error
+                )
+            });
+            let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+            let manifest = read_to_string(manifest).expect("could not read Cargo.toml");
+            config.message_cfg = parse_message_cfg(&content, &declared_features(&manifest));
+
+            // declare the custom options for the `unexpected_cfgs` lint
+            let names: BTreeSet<String> = config
+                .message_cfg
+                .values()
+                .flat_map(|predicate| scan_predicate(predicate).0)
+                .collect();
+            for name in names {
+                println!("cargo:rustc-check-cfg=cfg({name})");
+            }
+        }
+
+        println!("cargo:rerun-if-env-changed={NAMING_ENV}");
+        if let Some(path) = env::var_os(NAMING_ENV) {
+            println!("cargo:rerun-if-changed={}", path.to_string_lossy());
+            let content = read_to_string(&path).unwrap_or_else(|error| {
+                panic!("could not read {} file {:?}: {}", NAMING_ENV, path, error)
+            });
+            config.naming = parse_naming(&content);
+        }
+        config
+    }
+}
+
+/// Parse lines of the form `MESSAGE_NAME = predicate`.
+///
+/// The predicate is any `cfg` predicate such as `mavlink_camera` or
+/// `all(mavlink_camera, not(feature = "embedded"))`. The features must be declared in `features`,
+/// as the predicates are evaluated in this crate. Empty lines and lines starting with `#` are
+/// ignored.
+pub fn parse_message_cfg(content: &str, features: &HashSet<String>) -> HashMap<String, String> {
+    let mut message_cfg = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, predicate) = line.split_once('=').unwrap_or_else(|| {
+            panic!(
+                "{MESSAGE_CFG_ENV}:{}: expected `MESSAGE_NAME = predicate`, got {line:?}",
+                number + 1
+            )
+        });
+        let (name, predicate) = (name.trim(), predicate.trim());
+        assert!(
+            !predicate.is_empty(),
+            "{MESSAGE_CFG_ENV}:{}: missing predicate for {name}",
+            number + 1
+        );
+
+        let is_name = predicate
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        assert!(
+            !is_name || !predicate.contains('-'),
+            "{MESSAGE_CFG_ENV}:{}: {predicate:?} is not a cfg option name, features are tested \
+             with `feature = \"...\"`",
+            number + 1
+        );
+        for feature in scan_predicate(predicate).1 {
+            assert!(
+                features.contains(&feature),
+                "{MESSAGE_CFG_ENV}:{}: {feature:?} is not a feature of mavlink, use a custom cfg \
+                 option like `mavlink_{}` set with RUSTFLAGS=\"--cfg ...\" instead",
+                number + 1,
+                feature.replace('-', "_")
+            );
+        }
+
+        message_cfg.insert(name.to_string(), predicate.to_string());
+    }
+    message_cfg
+}
+
+/// Names of the options and of the features tested by a `cfg` predicate
+pub fn scan_predicate(predicate: &str) -> (Vec<String>, Vec<String>) {
+    #[derive(PartialEq)]
+    enum Token {
+        Ident(String),
+        Str(String),
+        Punct(char),
+    }
+
+    let mut tokens = vec![];
+    let mut chars = predicate.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '"' {
+            tokens.push(Token::Str(
+                chars.by_ref().take_while(|c| *c != '"').collect(),
+            ));
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut ident = c.to_string();
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                ident.push(c);
+            }
+            tokens.push(Token::Ident(ident));
+        } else {
+            tokens.push(Token::Punct(c));
+        }
+    }
+
+    let (mut names, mut features) = (vec![], vec![]);
+    for (index, token) in tokens.iter().enumerate() {
+        let next = tokens.get(index + 1);
+        match token {
+            // `all(`, `any(` and `not(`
+            Token::Ident(_) if next == Some(&Token::Punct('(')) => (),
+            Token::Ident(key) if key == "feature" && next == Some(&Token::Punct('=')) => {
+                if let Some(Token::Str(feature)) = tokens.get(index + 2) {
+                    features.push(feature.clone());
+                }
+            }
+            // other `key = "value"` options
+            Token::Ident(_) if next == Some(&Token::Punct('=')) => (),
+            Token::Ident(name) => names.push(name.clone()),
+            _ => (),
+        }
+    }
+    (names, features)
+}
+
+/// Features declared in a Cargo.toml, including the implicit features of optional dependencies
+pub fn declared_features(manifest: &str) -> HashSet<String> {
+    let mut features = HashSet::new();
+    let mut in_features = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_features = line == "[features]";
+            continue;
+        }
+        let key = match line.split_once('=') {
+            Some((key, _)) => key.trim().trim_matches('"'),
+            None => continue,
+        };
+        if in_features || line.contains("optional = true") {
+            features.insert(key.to_string());
+        }
+    }
+    features
+}
+
+/// Parse lines of the form `scheme = camel-case` or `scheme = original`, `acronyms = GPS, MAV`
+/// and `ENUM_NAME = RustName`.
+///
+/// Only enum types can be renamed, the keys are checked against the parsed definitions by
+/// `MavProfile::apply_naming`. Empty lines and lines starting with `#` are ignored.
+pub fn parse_naming(content: &str) -> Naming {
+    let mut naming = Naming::default();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .unwrap_or_else(|| {
+                panic!(
+                    "{NAMING_ENV}:{}: expected `key = value`, got {line:?}",
+                    number + 1
+                )
+            });
+        match key {
+            "scheme" => {
+                naming.scheme = match value {
+                    "camel-case" => NamingScheme::CamelCase,
+                    "original" => NamingScheme::Original,
+                    _ => panic!(
+                        "{NAMING_ENV}:{}: unknown scheme {value:?}, expected `camel-case` or `original`",
+                        number + 1
+                    ),
+                }
+            }
+            "acronyms" => naming.acronyms.extend(
+                value
+                    .split(',')
+                    .map(|acronym| acronym.trim().to_ascii_uppercase())
+                    .filter(|acronym| !acronym.is_empty()),
+            ),
+            _ => {
+                let is_identifier = value
+                    .chars()
+                    .next()
+                    .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+                    && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                assert!(
+                    is_identifier,
+                    "{NAMING_ENV}:{}: {value:?} is not a valid name for {key}",
+                    number + 1
+                );
+                naming.overrides.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    naming
+}
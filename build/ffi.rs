@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::parser::{merge_profiles, MavProfile};
+
+/// Declarations of `mavlink::ffi`, to be kept in sync with it
+const DECLARATIONS: &str = r#"#include <stddef.h>
+#include <stdint.h>
+
+#define MAVLINK_OK 0
+/* a pointer is null or a string is not valid UTF-8 */
+#define MAVLINK_ERROR_INVALID_ARGUMENT -1
+#define MAVLINK_ERROR_UNKNOWN_FIELD -2
+/* the value does not fit the type of the field */
+#define MAVLINK_ERROR_INVALID_VALUE -3
+#define MAVLINK_ERROR_BUFFER_TOO_SMALL -4
+/* the message cannot be sent in the requested protocol version */
+#define MAVLINK_ERROR_SERIALIZE -5
+
+/* A message with its header */
+typedef struct FfiMessage mavlink_message;
+
+/* Create a message with default fields from its name, or return NULL if there is no such
+ * message */
+mavlink_message *mavlink_message_new(const char *name);
+void mavlink_message_free(mavlink_message *msg);
+
+/* Parse the first frame of buf. Returns the number of bytes consumed, and sets *msg to the
+ * parsed message or to NULL if bytes which are not a valid frame were skipped. Returns 0 if
+ * more data is needed. */
+intptr_t mavlink_parse(const uint8_t *buf, size_t len, mavlink_message **msg);
+/* Serialize a message as a frame of the protocol version 1 or 2, returns the length of the
+ * frame or an error code */
+intptr_t mavlink_message_serialize(const mavlink_message *msg, uint8_t version, uint8_t *buf,
+                                   size_t len);
+
+uint32_t mavlink_message_id(const mavlink_message *msg);
+/* valid as long as the message */
+const char *mavlink_message_name(const mavlink_message *msg);
+void mavlink_message_get_header(const mavlink_message *msg, uint8_t *system_id,
+                                uint8_t *component_id, uint8_t *sequence);
+void mavlink_message_set_header(mavlink_message *msg, uint8_t system_id, uint8_t component_id,
+                                uint8_t sequence);
+
+/* Fields are addressed by name, elements of arrays as "name[index]". Enum fields are read and
+ * written by the name of their value as strings, bitmasks as integers and char arrays as
+ * strings. */
+int mavlink_message_get_int(const mavlink_message *msg, const char *field, int64_t *value);
+int mavlink_message_get_float(const mavlink_message *msg, const char *field, double *value);
+/* returns the length of the string or an error code */
+int mavlink_message_get_string(const mavlink_message *msg, const char *field, char *buf,
+                               size_t len);
+int mavlink_message_set_int(mavlink_message *msg, const char *field, int64_t value);
+int mavlink_message_set_float(mavlink_message *msg, const char *field, double value);
+int mavlink_message_set_string(mavlink_message *msg, const char *field, const char *value);
+"#;
+
+/// Generate the C header of `mavlink::ffi`, with the message ids and enum values of the given
+/// profiles
+pub fn generate<W: Write>(profiles: &[MavProfile], out: &mut W) {
+    let (messages, enums) = merge_profiles(profiles);
+
+    writeln!(
+        out,
+        "/* C interface of the mavlink crate, generated from the enabled dialects */\n\
+         #ifndef MAVLINK_RS_H\n#define MAVLINK_RS_H\n\n#ifdef __cplusplus\nextern \"C\" {{\n#endif\n"
+    )
+    .unwrap();
+    out.write_all(DECLARATIONS.as_bytes()).unwrap();
+
+    writeln!(out, "\n/* Message ids */").unwrap();
+    for msg in messages.values() {
+        writeln!(out, "#define MAVLINK_MSG_ID_{} {}", msg.name, msg.id).unwrap();
+    }
+
+    // entry names are unique across the definitions, except in a few enums which would
+    // redefine them with another value
+    let mut defined: HashMap<&str, u32> = HashMap::new();
+    for mavenum in enums.values() {
+        let entries: Vec<_> = mavenum
+            .entries
+            .iter()
+            .filter_map(|entry| entry.value.map(|value| (entry.name.as_str(), value)))
+            .collect();
+        if entries
+            .iter()
+            .any(|(name, value)| defined.get(name).map_or(false, |known| known != value))
+        {
+            continue;
+        }
+        writeln!(out, "\n/* {} */", mavenum.name).unwrap();
+        for (name, value) in entries {
+            if defined.insert(name, value).is_none() {
+                writeln!(out, "#define {name} {value}u").unwrap();
+            }
+        }
+    }
+
+    writeln!(out, "\n#ifdef __cplusplus\n}}\n#endif\n\n#endif").unwrap();
+}
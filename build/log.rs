@@ -0,0 +1,49 @@
+use std::env;
+use std::fmt::Display;
+use std::time::Instant;
+
+/// Environment variable enabling progress output of the build script
+pub const BUILD_LOG_ENV: &str = "MAVLINK_BUILD_LOG";
+
+/// Progress reporting for the build script.
+///
+/// When `MAVLINK_BUILD_LOG` is set, every stage of the code generation is reported as a
+/// `key=value` line through `cargo:warning`, so the output is visible without `-vv`.
+pub struct BuildLog {
+    enabled: bool,
+}
+
+impl BuildLog {
+    pub fn from_env() -> Self {
+        println!("cargo:rerun-if-env-changed={BUILD_LOG_ENV}");
+
+        let enabled = match env::var(BUILD_LOG_ENV) {
+            Ok(value) => !matches!(value.as_str(), "" | "0" | "false"),
+            Err(_) => false,
+        };
+
+        Self { enabled }
+    }
+
+    /// Emit a single structured line
+    pub fn event(&self, dialect: &str, stage: &str, fields: &[(&str, &dyn Display)]) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut line = format!("mavlink-build dialect={dialect} stage={stage}");
+        for (key, value) in fields {
+            line.push_str(&format!(" {key}={value}"));
+        }
+        println!("cargo:warning={line}");
+    }
+
+    /// Run `f` and report how long it took
+    pub fn time<T>(&self, dialect: &str, stage: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let elapsed_ms = format!("{:.3}", start.elapsed().as_secs_f64() * 1000.0);
+        self.event(dialect, stage, &[("elapsed_ms", &elapsed_ms)]);
+        result
+    }
+}
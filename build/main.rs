@@ -1,9 +1,19 @@
 #![recursion_limit = "256"]
 
 mod binder;
+mod config;
+mod ffi;
+mod log;
 mod parser;
+mod protobuf;
+mod python;
+#[cfg(feature = "uom")]
+mod uom;
 mod util;
+mod wireshark;
 
+use crate::config::CodegenConfig;
+use crate::log::BuildLog;
 use crate::util::to_module_name;
 use std::env;
 use std::ffi::OsStr;
@@ -11,8 +21,12 @@ use std::fs::{read_dir, File};
 use std::io::BufWriter;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Instant;
 
 pub fn main() {
+    let start = Instant::now();
+    let log = BuildLog::from_env();
+    let config = CodegenConfig::from_env();
     let src_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
 
     // Update and init submodule
@@ -51,6 +65,8 @@ pub fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
 
     let mut modules = vec![];
+    // profiles of the dialects enabled by features, for the dissector
+    let mut enabled_profiles = vec![];
 
     for entry in read_dir(&definitions_dir).expect("could not read definitions directory") {
         let entry = entry.expect("could not read directory entry");
@@ -61,21 +77,66 @@ pub fn main() {
         let mut definition_rs = PathBuf::from(&module_name);
         definition_rs.set_extension("rs");
 
-        modules.push(module_name);
-
         let dest_path = Path::new(&out_dir).join(definition_rs);
         let mut outf = BufWriter::new(File::create(&dest_path).unwrap());
 
         // generate code
-        parser::generate(
+        let profile = parser::generate(
             &definitions_dir,
             &definition_file.into_string().unwrap(),
             &mut outf,
+            &log,
+            &config,
         );
-        dbg_format_code(&out_dir, &dest_path);
+        log.time(&module_name, "format", || {
+            dbg_format_code(&out_dir, &dest_path)
+        });
 
         // Re-run build if definition file changes
         println!("cargo:rerun-if-changed={}", entry.path().to_string_lossy());
+
+        let feature = format!("CARGO_FEATURE_{}", module_name.to_uppercase());
+        if env::var_os(feature).is_some() {
+            enabled_profiles.push(profile);
+        }
+        modules.push(module_name);
+    }
+
+    // output the Wireshark dissector
+    if env::var_os("CARGO_FEATURE_WIRESHARK").is_some() {
+        let dest_path = Path::new(&out_dir).join("mavlink.lua");
+        let mut outf = BufWriter::new(File::create(dest_path).unwrap());
+        log.time("*", "wireshark", || {
+            wireshark::generate(&enabled_profiles, &mut outf)
+        });
+    }
+
+    // output the C header of the FFI
+    if env::var_os("CARGO_FEATURE_FFI").is_some() {
+        let dest_path = Path::new(&out_dir).join("mavlink.h");
+        let mut outf = BufWriter::new(File::create(dest_path).unwrap());
+        log.time("*", "ffi", || ffi::generate(&enabled_profiles, &mut outf));
+    }
+
+    // output the protobuf schema and the payload layouts of its messages
+    if env::var_os("CARGO_FEATURE_PROTOBUF").is_some() {
+        log.time("*", "protobuf", || {
+            let dest_path = Path::new(&out_dir).join("mavlink.proto");
+            let mut outf = BufWriter::new(File::create(dest_path).unwrap());
+            protobuf::generate_schema(&enabled_profiles, &mut outf);
+            let dest_path = Path::new(&out_dir).join("protobuf.rs");
+            let mut outf = BufWriter::new(File::create(dest_path).unwrap());
+            protobuf::generate_layouts(&enabled_profiles, &mut outf);
+        });
+    }
+
+    // output the table of the char array fields for the Python bindings
+    if env::var_os("CARGO_FEATURE_PYTHON").is_some() {
+        let dest_path = Path::new(&out_dir).join("python.rs");
+        let mut outf = BufWriter::new(File::create(dest_path).unwrap());
+        log.time("*", "python", || {
+            python::generate(&enabled_profiles, &mut outf)
+        });
     }
 
     // output mod.rs
@@ -84,8 +145,16 @@ pub fn main() {
         let mut outf = File::create(&dest_path).unwrap();
 
         // generate code
+        let dialect_count = modules.len();
         binder::generate(modules, &mut outf);
         dbg_format_code(out_dir, dest_path);
+
+        let elapsed_ms = format!("{:.3}", start.elapsed().as_secs_f64() * 1000.0);
+        log.event(
+            "*",
+            "total",
+            &[("dialects", &dialect_count), ("elapsed_ms", &elapsed_ms)],
+        );
     }
 }
 
@@ -1,7 +1,8 @@
 use crc_any::CRCu16;
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::default::Default;
 use std::fs::File;
 use std::io::{BufReader, Write};
@@ -9,11 +10,18 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::u32;
 
-use quick_xml::{events::Event, Reader};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
 
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 
+use crate::config::{camel_case, CodegenConfig, Naming, NAMING_ENV};
+use crate::log::BuildLog;
+use crate::util::to_module_name;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -22,14 +30,25 @@ use serde::{Deserialize, Serialize};
 pub struct MavProfile {
     pub messages: HashMap<String, MavMessage>,
     pub enums: HashMap<String, MavEnum>,
+    /// Value of the `<version>` element, inherited from the includes if not given
+    pub version: Option<u8>,
+    /// Value of the `<dialect>` element, inherited from the includes if not given
+    pub dialect: Option<u8>,
+    /// Resolved include graph: the files directly included by each parsed definition file
+    pub includes: BTreeMap<String, Vec<String>>,
 }
 
 impl MavProfile {
     fn add_message(&mut self, message: &MavMessage) {
         match self.messages.entry(message.name.clone()) {
             Entry::Occupied(entry) => {
+                // the same message may be defined by several files
+                let known = MavMessage {
+                    definition_file: message.definition_file.clone(),
+                    ..entry.get().clone()
+                };
                 assert!(
-                    entry.get() == message,
+                    known == *message,
                     "Message '{}' defined twice but definitions are different",
                     message.name
                 );
@@ -121,7 +140,126 @@ impl MavProfile {
             .collect()
     }
 
-    fn emit_rust(&self) -> TokenStream {
+    /// Emit the `cfg` attributes gating each message
+    fn emit_cfgs(&self) -> Vec<TokenStream> {
+        self.messages.values().map(|msg| msg.emit_cfg()).collect()
+    }
+
+    /// Attach the configured `cfg` predicates to the messages
+    fn apply_config(mut self, config: &CodegenConfig) -> Self {
+        for msg in self.messages.values_mut() {
+            msg.cfg = config.message_cfg.get(&msg.name).cloned();
+        }
+        self
+    }
+
+    /// Rename the enums and the enum types of the fields following the configured naming.
+    ///
+    /// Only enum types can be renamed, the helper modules of this crate rely on the names of the
+    /// messages and the enum entries, so overriding one of those is an error.
+    pub fn apply_naming(mut self, naming: &Naming) -> Result<Self, String> {
+        for key in naming.overrides.keys() {
+            if self.enums.values().any(|enm| enm.mavlink_name == *key) {
+                continue;
+            }
+            if self.messages.contains_key(key) {
+                return Err(format!(
+                    "{NAMING_ENV}: {key} is a message, only enum types can be renamed"
+                ));
+            }
+            if let Some(enm) = self
+                .enums
+                .values()
+                .find(|enm| enm.entries.iter().any(|entry| entry.name == *key))
+            {
+                return Err(format!(
+                    "{NAMING_ENV}: {key} is an entry of {}, only enum types can be renamed",
+                    enm.mavlink_name
+                ));
+            }
+        }
+
+        let renamed: HashMap<String, String> = self
+            .enums
+            .values()
+            .map(|enm| (enm.name.clone(), naming.enum_name(&enm.mavlink_name)))
+            .collect();
+
+        // renamed enums keep an alias with their default name
+        let mut taken = HashSet::new();
+        for (default_name, name) in &renamed {
+            let mut idents = vec![name];
+            if default_name != name {
+                idents.push(default_name);
+            }
+            for ident in idents {
+                if !taken.insert(ident) {
+                    return Err(format!(
+                        "{NAMING_ENV}: the naming configuration gives two enums the name {ident}"
+                    ));
+                }
+            }
+        }
+
+        self.enums = self
+            .enums
+            .into_values()
+            .map(|mut enm| {
+                enm.name = renamed[&enm.name].clone();
+                (enm.name.clone(), enm)
+            })
+            .collect();
+        for msg in self.messages.values_mut() {
+            for field in &mut msg.fields {
+                if let Some(name) = renamed.get(field.enumtype.as_deref().unwrap_or_default()) {
+                    field.enumtype = Some(name.clone());
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Turn the names of messages, enums and enum entries in the descriptions into intra-doc
+    /// links to the generated items
+    #[cfg_attr(not(feature = "emit-description"), allow(dead_code))]
+    pub fn link_descriptions(mut self) -> Self {
+        let mut targets: HashMap<String, String> = HashMap::new();
+        // gated messages may not exist
+        for msg in self.messages.values().filter(|msg| msg.cfg.is_none()) {
+            targets.insert(msg.name.clone(), format!("{}_DATA", msg.name));
+        }
+        for enm in self.enums.values() {
+            targets
+                .entry(enm.mavlink_name.clone())
+                .or_insert_with(|| enm.name.clone());
+            for entry in &enm.entries {
+                targets
+                    .entry(entry.name.clone())
+                    .or_insert_with(|| format!("{}::{}", enm.name, entry.name));
+            }
+        }
+
+        let link = |description: &mut Option<String>| {
+            if let Some(text) = description {
+                *text = link_names(text, &targets);
+            }
+        };
+        for msg in self.messages.values_mut() {
+            link(&mut msg.description);
+            for field in &mut msg.fields {
+                link(&mut field.description);
+            }
+        }
+        for enm in self.enums.values_mut() {
+            link(&mut enm.description);
+            for entry in &mut enm.entries {
+                link(&mut entry.description);
+            }
+        }
+        self
+    }
+
+    fn emit_rust(&self, dialect_name: &str) -> TokenStream {
         //TODO verify that id_width of u8 is OK even in mavlink v1
         let id_width = format_ident!("u32");
 
@@ -129,17 +267,23 @@ impl MavProfile {
         let msgs = self.emit_msgs();
         let enum_names = self.emit_enum_names();
         let struct_names = self.emit_struct_names();
+        let cfgs = self.emit_cfgs();
         let enums = self.emit_enums();
 
-        let mav_message = self.emit_mav_message(&enum_names, &struct_names);
-        let mav_message_parse = self.emit_mav_message_parse(&enum_names, &struct_names);
-        let mav_message_crc = self.emit_mav_message_crc(&id_width, &struct_names);
-        let mav_message_name = self.emit_mav_message_name(&enum_names, &struct_names);
-        let mav_message_id = self.emit_mav_message_id(&enum_names, &struct_names);
-        let mav_message_id_from_name = self.emit_mav_message_id_from_name(&struct_names);
+        let mav_message = self.emit_mav_message(&cfgs, &enum_names, &struct_names);
+        let mav_message_parse = self.emit_mav_message_parse(&cfgs, &enum_names, &struct_names);
+        let mav_message_crc = self.emit_mav_message_crc(&id_width, &cfgs, &struct_names);
+        let mav_message_name = self.emit_mav_message_name(&cfgs, &enum_names, &struct_names);
+        let mav_message_id = self.emit_mav_message_id(&cfgs, &enum_names, &struct_names);
+        let mav_message_id_from_name = self.emit_mav_message_id_from_name(&cfgs, &struct_names);
         let mav_message_default_from_id =
-            self.emit_mav_message_default_from_id(&enum_names, &struct_names);
-        let mav_message_serialize = self.emit_mav_message_serialize(&enum_names);
+            self.emit_mav_message_default_from_id(&cfgs, &enum_names, &struct_names);
+        let mav_message_serialize = self.emit_mav_message_serialize(&cfgs, &enum_names);
+        let mav_message_dialect = self.emit_mav_message_dialect(dialect_name);
+        let mav_message_target_system = self.emit_mav_message_target("target_system");
+        let mav_message_target_component = self.emit_mav_message_target("target_component");
+        let try_from_raw = emit_try_from_raw(&quote!(MavMessage), &quote!(parse), &quote!());
+        let include_graph = self.emit_include_graph();
 
         quote! {
             #comment
@@ -160,6 +304,8 @@ impl MavProfile {
             #[cfg(feature = "serde")]
             use serde::{Serialize, Deserialize};
 
+            #include_graph
+
             #(#enums)*
 
             #(#msgs)*
@@ -167,6 +313,8 @@ impl MavProfile {
             #[derive(Clone, PartialEq, Debug)]
             #mav_message
 
+            #try_from_raw
+
             impl Message for MavMessage {
                 #mav_message_parse
                 #mav_message_name
@@ -175,22 +323,31 @@ impl MavProfile {
                 #mav_message_default_from_id
                 #mav_message_serialize
                 #mav_message_crc
+                #mav_message_dialect
+                #mav_message_target_system
+                #mav_message_target_component
             }
         }
     }
 
-    fn emit_mav_message(&self, enums: &[TokenStream], structs: &[TokenStream]) -> TokenStream {
+    fn emit_mav_message(
+        &self,
+        cfgs: &[TokenStream],
+        enums: &[TokenStream],
+        structs: &[TokenStream],
+    ) -> TokenStream {
         quote! {
             #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
             #[cfg_attr(feature = "serde", serde(tag = "type"))]
             pub enum MavMessage {
-                #(#enums(#structs),)*
+                #(#cfgs #enums(#structs),)*
             }
         }
     }
 
     fn emit_mav_message_parse(
         &self,
+        cfgs: &[TokenStream],
         enums: &[TokenStream],
         structs: &[TokenStream],
     ) -> TokenStream {
@@ -199,7 +356,7 @@ impl MavProfile {
         quote! {
             fn parse(version: MavlinkVersion, id: #id_width, payload: &[u8]) -> Result<Self, ParserError> {
                 match id {
-                    #(#structs::ID => #structs::deser(version, payload).map(Self::#enums),)*
+                    #(#cfgs #structs::ID => #structs::deser(version, payload).map(Self::#enums),)*
                     _ => {
                         Err(ParserError::UnknownMessage { id })
                     },
@@ -208,11 +365,16 @@ impl MavProfile {
         }
     }
 
-    fn emit_mav_message_crc(&self, id_width: &Ident, structs: &[TokenStream]) -> TokenStream {
+    fn emit_mav_message_crc(
+        &self,
+        id_width: &Ident,
+        cfgs: &[TokenStream],
+        structs: &[TokenStream],
+    ) -> TokenStream {
         quote! {
             fn extra_crc(id: #id_width) -> u8 {
                 match id {
-                    #(#structs::ID => #structs::EXTRA_CRC,)*
+                    #(#cfgs #structs::ID => #structs::EXTRA_CRC,)*
                     _ => {
                         0
                     },
@@ -221,32 +383,46 @@ impl MavProfile {
         }
     }
 
-    fn emit_mav_message_name(&self, enums: &[TokenStream], structs: &[TokenStream]) -> TokenStream {
+    fn emit_mav_message_name(
+        &self,
+        cfgs: &[TokenStream],
+        enums: &[TokenStream],
+        structs: &[TokenStream],
+    ) -> TokenStream {
         quote! {
             fn message_name(&self) -> &'static str {
                 match self {
-                    #(Self::#enums(..) => #structs::NAME,)*
+                    #(#cfgs Self::#enums(..) => #structs::NAME,)*
                 }
             }
         }
     }
 
-    fn emit_mav_message_id(&self, enums: &[TokenStream], structs: &[TokenStream]) -> TokenStream {
+    fn emit_mav_message_id(
+        &self,
+        cfgs: &[TokenStream],
+        enums: &[TokenStream],
+        structs: &[TokenStream],
+    ) -> TokenStream {
         let id_width = format_ident!("u32");
         quote! {
             fn message_id(&self) -> #id_width {
                 match self {
-                    #(Self::#enums(..) => #structs::ID,)*
+                    #(#cfgs Self::#enums(..) => #structs::ID,)*
                 }
             }
         }
     }
 
-    fn emit_mav_message_id_from_name(&self, structs: &[TokenStream]) -> TokenStream {
+    fn emit_mav_message_id_from_name(
+        &self,
+        cfgs: &[TokenStream],
+        structs: &[TokenStream],
+    ) -> TokenStream {
         quote! {
             fn message_id_from_name(name: &str) -> Result<u32, &'static str> {
                 match name {
-                    #(#structs::NAME => Ok(#structs::ID),)*
+                    #(#cfgs #structs::NAME => Ok(#structs::ID),)*
                     _ => {
                         Err("Invalid message name.")
                     }
@@ -257,13 +433,14 @@ impl MavProfile {
 
     fn emit_mav_message_default_from_id(
         &self,
+        cfgs: &[TokenStream],
         enums: &[TokenStream],
         structs: &[TokenStream],
     ) -> TokenStream {
         quote! {
             fn default_message_from_id(id: u32) -> Result<Self, &'static str> {
                 match id {
-                    #(#structs::ID => Ok(Self::#enums(#structs::default())),)*
+                    #(#cfgs #structs::ID => Ok(Self::#enums(#structs::default())),)*
                     _ => {
                         Err("Invalid message id.")
                     }
@@ -272,11 +449,82 @@ impl MavProfile {
         }
     }
 
-    fn emit_mav_message_serialize(&self, enums: &Vec<TokenStream>) -> TokenStream {
+    fn emit_include_graph(&self) -> TokenStream {
+        let files = self.includes.keys();
+        let includes = self.includes.values();
+        quote! {
+            /// Definition files of this message set by name, each with the files it includes
+            pub const INCLUDE_GRAPH: &[(&str, &[&str])] = &[#((#files, &[#(#includes),*])),*];
+        }
+    }
+
+    fn emit_mav_message_dialect(&self, dialect_name: &str) -> TokenStream {
+        let version = match self.version {
+            Some(version) => quote!(Some(#version)),
+            None => quote!(None),
+        };
+        let dialect = match self.dialect {
+            Some(dialect) => quote!(Some(#dialect)),
+            None => quote!(None),
+        };
+        quote! {
+            fn dialect_name() -> &'static str {
+                #dialect_name
+            }
+
+            fn dialect_version() -> Option<u8> {
+                #version
+            }
+
+            fn dialect_number() -> Option<u8> {
+                #dialect
+            }
+        }
+    }
+
+    /// Accessor for the `target_system` or `target_component` field, left to the default
+    /// implementation of the trait if no message has the field
+    fn emit_mav_message_target(&self, field_name: &str) -> TokenStream {
+        let mut messages: Vec<&MavMessage> = self
+            .messages
+            .values()
+            .filter(|msg| {
+                msg.fields.iter().any(|field| {
+                    field.name == field_name && matches!(field.mavtype, MavType::UInt8)
+                })
+            })
+            .collect();
+        if messages.is_empty() {
+            return quote!();
+        }
+        messages.sort_by_key(|msg| msg.id);
+
+        let cfgs: Vec<TokenStream> = messages.iter().map(|msg| msg.emit_cfg()).collect();
+        let enums: Vec<Ident> = messages
+            .iter()
+            .map(|msg| format_ident!("{}", msg.name))
+            .collect();
+        let fn_name = format_ident!("{}_id", field_name);
+        let field = format_ident!("{}", field_name);
+        quote! {
+            fn #fn_name(&self) -> Option<u8> {
+                match self {
+                    #(#cfgs Self::#enums(body) => Some(body.#field),)*
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn emit_mav_message_serialize(
+        &self,
+        cfgs: &[TokenStream],
+        enums: &[TokenStream],
+    ) -> TokenStream {
         quote! {
             fn ser(&self, version: MavlinkVersion, bytes: &mut [u8]) -> usize {
                 match self {
-                    #(Self::#enums(body) => body.ser(version, bytes),)*
+                    #(#cfgs Self::#enums(body) => body.ser(version, bytes),)*
                 }
             }
         }
@@ -287,10 +535,14 @@ impl MavProfile {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MavEnum {
     pub name: String,
+    /// Name in the MAVLink definitions, e.g. `GPS_FIX_TYPE` for `GpsFixType`
+    pub mavlink_name: String,
     pub description: Option<String>,
     pub entries: Vec<MavEnumEntry>,
     /// If contains Some, the string represents the type witdh for bitflags
     pub bitfield: Option<String>,
+    /// Definition file of the enum followed by the files extending it with more entries
+    pub definition_files: Vec<String>,
 }
 
 impl MavEnum {
@@ -305,14 +557,28 @@ impl MavEnum {
                     None => self.entries.push(enum_entry.clone()),
                 }
             }
+            for file in &enm.definition_files {
+                if !self.definition_files.contains(file) {
+                    self.definition_files.push(file.clone());
+                }
+            }
         }
     }
 
+    /// Replacement of a deprecated entry if it is an entry of this enum, the deprecated entry is
+    /// then an alias of it rather than a variant of its own
+    fn replacement(&self, entry: &MavEnumEntry) -> Option<&MavEnumEntry> {
+        let replaced_by = &entry.deprecated.as_ref()?.replaced_by;
+        self.entries
+            .iter()
+            .find(|other| other.name == *replaced_by && other.name != entry.name)
+    }
+
     fn emit_defs(&self) -> Vec<TokenStream> {
         let mut cnt = 0isize;
         self.entries
             .iter()
-            .map(|enum_entry| {
+            .filter_map(|enum_entry| {
                 let name = format_ident!("{}", enum_entry.name.clone());
                 let value;
 
@@ -335,17 +601,43 @@ impl MavEnum {
                     let tmp = TokenStream::from_str(&tmp_value.to_string()).unwrap();
                     value = quote!(#tmp);
                 };
-                if self.bitfield.is_some() {
+                if self.replacement(enum_entry).is_some() {
+                    return None;
+                }
+                let deprecated = enum_entry
+                    .deprecated
+                    .as_ref()
+                    .map(MavDeprecated::emit_attribute);
+                Some(if self.bitfield.is_some() {
                     quote! {
                         #description
+                        #deprecated
                         const #name = #value;
                     }
                 } else {
                     quote! {
                         #description
+                        #deprecated
                         #name = #value,
                     }
-                }
+                })
+            })
+            .collect()
+    }
+
+    /// Deprecated entries replaced by another entry of the enum, so the old names keep compiling
+    fn emit_aliases(&self) -> Vec<TokenStream> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let replacement = self.replacement(entry)?;
+                let name = format_ident!("{}", entry.name);
+                let replacement = format_ident!("{}", replacement.name);
+                let deprecated = entry.deprecated.as_ref().map(MavDeprecated::emit_attribute);
+                Some(quote! {
+                    #deprecated
+                    pub const #name: Self = Self::#replacement;
+                })
             })
             .collect()
     }
@@ -360,22 +652,58 @@ impl MavEnum {
         quote!(pub const DEFAULT: Self = Self::#default;)
     }
 
+    /// Doc line naming the definition files of the enum
+    #[cfg(feature = "emit-description")]
+    fn emit_provenance(&self) -> TokenStream {
+        let mut doc = match self.definition_files.split_first() {
+            Some((defined, extended)) if !extended.is_empty() => {
+                format!(
+                    "Defined in `{defined}`, extended in `{}`.",
+                    extended.join("`, `")
+                )
+            }
+            Some((defined, _)) => format!("Defined in `{defined}`."),
+            None => return quote!(),
+        };
+        if self.description.is_some() {
+            doc.insert_str(0, "\n\n");
+        }
+        quote!(#[doc = #doc])
+    }
+
     fn emit_rust(&self) -> TokenStream {
         let defs = self.emit_defs();
+        let aliases = self.emit_aliases();
         let enum_name = self.emit_name();
         let const_default = self.emit_const_default();
+        let definition_files = &self.definition_files;
 
         #[cfg(feature = "emit-description")]
-        let description = if let Some(description) = self.description.as_ref() {
-            let desc = format!("{description}");
-            quote!(#[doc = #desc])
-        } else {
-            quote!()
+        let description = {
+            let provenance = self.emit_provenance();
+            if let Some(description) = self.description.as_ref() {
+                let desc = format!("{description}");
+                quote!(#[doc = #desc] #provenance)
+            } else {
+                provenance
+            }
         };
 
         #[cfg(not(feature = "emit-description"))]
         let description = quote!();
 
+        // the helpers of the crate use the default names of renamed enums
+        let default_name = camel_case(&self.mavlink_name);
+        let alias = if self.name != default_name {
+            let default_name = format_ident!("{}", default_name);
+            quote! {
+                #[doc(hidden)]
+                pub type #default_name = #enum_name;
+            }
+        } else {
+            quote!()
+        };
+
         let enum_def;
         if let Some(width) = self.bitfield.clone() {
             let width = format_ident!("{}", width);
@@ -403,8 +731,13 @@ impl MavEnum {
         quote! {
             #enum_def
 
+            #alias
+
             impl #enum_name {
                 #const_default
+                #(#aliases)*
+                /// Definition file of the enum followed by the files extending it
+                pub const DEFINITION_FILES: &'static [&'static str] = &[#(#definition_files),*];
             }
 
             impl Default for #enum_name {
@@ -423,6 +756,46 @@ pub struct MavEnumEntry {
     pub name: String,
     pub description: Option<String>,
     pub params: Option<Vec<String>>,
+    pub deprecated: Option<MavDeprecated>,
+}
+
+/// The `deprecated` element of an enum entry
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MavDeprecated {
+    pub since: String,
+    /// Name of the replacement, empty if there is none
+    pub replaced_by: String,
+}
+
+impl MavDeprecated {
+    fn from_attributes(bytes: &BytesStart) -> Self {
+        let mut deprecated = Self::default();
+        for attr in bytes.attributes() {
+            let attr = attr.unwrap();
+            match attr.key.into_inner() {
+                b"since" => deprecated.since = String::from_utf8(attr.value.to_vec()).unwrap(),
+                b"replaced_by" => {
+                    deprecated.replaced_by = String::from_utf8(attr.value.to_vec()).unwrap();
+                }
+                _ => (),
+            }
+        }
+        deprecated
+    }
+
+    /// The dates of the definitions are no versions, so they are part of the note
+    fn emit_attribute(&self) -> TokenStream {
+        let note = if self.replaced_by.is_empty() {
+            format!("deprecated since {}", self.since)
+        } else {
+            format!(
+                "deprecated since {}, replaced by {}",
+                self.since, self.replaced_by
+            )
+        };
+        quote!(#[deprecated(note = #note)])
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Default)]
@@ -432,6 +805,10 @@ pub struct MavMessage {
     pub name: String,
     pub description: Option<String>,
     pub fields: Vec<MavField>,
+    /// `cfg` predicate gating the generated message, from the codegen configuration
+    pub cfg: Option<String>,
+    /// Definition file the message was parsed from, e.g. `common.xml`
+    pub definition_file: String,
 }
 
 impl MavMessage {
@@ -442,6 +819,19 @@ impl MavMessage {
         quote!(#name)
     }
 
+    /// Emit the `cfg` attribute gating this message, if any
+    fn emit_cfg(&self) -> TokenStream {
+        match &self.cfg {
+            Some(predicate) => {
+                let predicate = TokenStream::from_str(predicate).unwrap_or_else(|_| {
+                    panic!("invalid cfg predicate {predicate:?} for {}", self.name)
+                });
+                quote!(#[cfg(#predicate)])
+            }
+            None => quote!(),
+        }
+    }
+
     fn emit_name_types(&self) -> (Vec<TokenStream>, usize) {
         let mut encoded_payload_len: usize = 0;
         let field_toks = self
@@ -497,6 +887,8 @@ impl MavMessage {
             let doc = &format!("{val}.");
             ts.extend(quote!(#[doc = #doc]));
         }
+        let provenance = format!("\n\nDefined in `{}`.", self.definition_file);
+        ts.extend(quote!(#[doc = #provenance]));
         ts
     }
 
@@ -528,17 +920,8 @@ impl MavMessage {
             }
         } else {
             quote! {
-                let avail_len = _input.len();
-
-                let mut payload_buf  = [0; Self::ENCODED_LEN];
-                let mut buf = if avail_len < Self::ENCODED_LEN {
-                    //copy available bytes into an oversized buffer filled with zeros
-                    payload_buf[0..avail_len].copy_from_slice(_input);
-                    Bytes::new(&payload_buf)
-                } else {
-                    // fast zero copy
-                    Bytes::new(_input)
-                };
+                // truncated payloads are read as zero-filled, without a buffer on the stack
+                let mut buf = Bytes::new_zero_filled(_input, Self::ENCODED_LEN);
 
                 let mut _struct = Self::default();
                 #(#deser_vars)*
@@ -558,6 +941,72 @@ impl MavMessage {
         }
     }
 
+    /// Emit `builder()`, taking the target ids of the message, and a chainable setter per field
+    fn emit_builder(&self) -> TokenStream {
+        let targets: Vec<_> = self
+            .fields
+            .iter()
+            .filter(|field| matches!(field.name.as_str(), "target_system" | "target_component"))
+            .collect();
+        let target_names: Vec<_> = targets.iter().map(|field| field.emit_name()).collect();
+        let target_types: Vec<_> = targets.iter().map(|field| field.emit_type()).collect();
+        let setters = self.fields.iter().map(|field| field.emit_setter());
+        let defaults = if targets.len() < self.fields.len() {
+            quote!(..Self::DEFAULT)
+        } else {
+            quote!()
+        };
+        quote! {
+            /// A message with the given targets and default values for all other fields, to be
+            /// completed with the `with_` setters
+            pub fn builder(#(#target_names: #target_types),*) -> Self {
+                Self {
+                    #(#target_names,)*
+                    #defaults
+                }
+            }
+            #(#setters)*
+        }
+    }
+
+    /// Emit the derived traits of the struct, and a `Debug` implementation showing the `char[N]`
+    /// fields as text if there are any
+    fn emit_debug(&self, msg_name: &TokenStream, cfg: &TokenStream) -> (TokenStream, TokenStream) {
+        let is_text = |field: &MavField| matches!(&field.mavtype, MavType::Array(elem_type, _) if **elem_type == MavType::Char);
+        if !self.fields.iter().any(is_text) {
+            return (quote!(#[derive(Debug, Clone, PartialEq)]), quote!());
+        }
+
+        let struct_name = msg_name.to_string();
+        let fields = self.fields.iter().map(|field| {
+            let name = field.emit_name();
+            let name_str = &field.name;
+            if is_text(field) {
+                quote!(.field(#name_str, &crate::chars::CharArray(&self.#name)))
+            } else {
+                quote!(.field(#name_str, &self.#name))
+            }
+        });
+        let debug_impl = quote! {
+            #cfg
+            impl core::fmt::Debug for #msg_name {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    f.debug_struct(#struct_name)
+                        #(#fields)*
+                        .finish()
+                }
+            }
+        };
+        (quote!(#[derive(Clone, PartialEq)]), debug_impl)
+    }
+
+    fn emit_checked_getters(&self) -> Vec<TokenStream> {
+        self.fields
+            .iter()
+            .filter_map(|field| field.emit_checked_getter())
+            .collect()
+    }
+
     fn emit_const_default(&self) -> TokenStream {
         let initializers = self
             .fields
@@ -568,8 +1017,10 @@ impl MavMessage {
 
     fn emit_rust(&self) -> TokenStream {
         let msg_name = self.emit_struct_name();
+        let variant = format_ident!("{}", self.name);
         let id = self.id;
         let name = self.name.clone();
+        let definition_file = &self.definition_file;
         let extra_crc = extra_crc(self);
         let (name_types, msg_encoded_len) = self.emit_name_types();
 
@@ -577,6 +1028,11 @@ impl MavMessage {
         let serialize_vars = self.emit_serialize_vars();
         let const_default = self.emit_const_default();
         let default_impl = self.emit_default_impl();
+        let builder = self.emit_builder();
+        let checked_getters = self.emit_checked_getters();
+        let cfg = self.emit_cfg();
+        let (derives, debug_impl) = self.emit_debug(&msg_name, &cfg);
+        let try_from_raw = emit_try_from_raw(&msg_name, &quote!(parse_data), &cfg);
 
         #[cfg(feature = "emit-description")]
         let description = self.emit_description();
@@ -584,21 +1040,39 @@ impl MavMessage {
         #[cfg(not(feature = "emit-description"))]
         let description = quote!();
 
+        #[cfg(feature = "uom")]
+        let uom_accessors = crate::uom::emit_accessors(self, &msg_name, &cfg);
+
+        #[cfg(not(feature = "uom"))]
+        let uom_accessors = quote!();
+
         quote! {
             #description
-            #[derive(Debug, Clone, PartialEq)]
+            #cfg
+            #derives
             #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
             pub struct #msg_name {
                 #(#name_types)*
             }
 
+            #cfg
             impl #msg_name {
                 pub const ENCODED_LEN: usize = #msg_encoded_len;
+                /// Definition file the message was generated from
+                pub const DEFINITION_FILE: &'static str = #definition_file;
                 #const_default
+                #builder
+                #(#checked_getters)*
             }
 
+            #cfg
             #default_impl
 
+            #debug_impl
+
+            #try_from_raw
+
+            #cfg
             impl MessageData for #msg_name {
                 type Message = MavMessage;
 
@@ -614,11 +1088,53 @@ impl MavMessage {
                 fn ser(&self, version: MavlinkVersion, bytes: &mut [u8]) -> usize {
                     #serialize_vars
                 }
+
+                fn from_message(msg: MavMessage) -> Result<Self, MavMessage> {
+                    match msg {
+                        MavMessage::#variant(data) => Ok(data),
+                        #[allow(unreachable_patterns)]
+                        msg => Err(msg),
+                    }
+                }
+
+                fn from_message_ref(msg: &MavMessage) -> Option<&Self> {
+                    match msg {
+                        MavMessage::#variant(data) => Some(data),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
             }
+
+            #uom_accessors
         }
     }
 }
 
+/// `TryFrom` the raw MAVLink 1 and 2 frames for a message type, with the parse method of the
+/// frames to use
+fn emit_try_from_raw(
+    type_name: &TokenStream,
+    parse: &TokenStream,
+    cfg: &TokenStream,
+) -> TokenStream {
+    [quote!(MAVLinkV1MessageRaw), quote!(MAVLinkV2MessageRaw)]
+        .iter()
+        .map(|raw_type| {
+            quote! {
+                #cfg
+                impl core::convert::TryFrom<&crate::#raw_type> for #type_name {
+                    type Error = ParserError;
+
+                    fn try_from(raw: &crate::#raw_type) -> Result<Self, Self::Error> {
+                        raw.#parse()
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug, PartialEq, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MavField {
@@ -627,6 +1143,8 @@ pub struct MavField {
     pub description: Option<String>,
     pub enumtype: Option<String>,
     pub display: Option<String>,
+    pub units: Option<String>,
+    pub invalid: Option<String>,
     pub is_extension: bool,
 }
 
@@ -728,10 +1246,19 @@ impl MavField {
                 // handle enum by FromPrimitive
                 let tmp = self.mavtype.rust_reader(&quote!(let tmp), buf);
                 let val = format_ident!("from_{}", &self.mavtype.rust_type());
+                // unknown values are either rejected or replaced by the enum default,
+                // depending on the "lenient-decoding" feature of the crate
                 quote!(
                     #tmp
-                    #name = FromPrimitive::#val(tmp)
-                        .ok_or(ParserError::InvalidEnum { enum_type: #enum_name, value: tmp as u32 })?;
+                    #[cfg(not(feature = "lenient-decoding"))]
+                    {
+                        #name = FromPrimitive::#val(tmp)
+                            .ok_or(ParserError::InvalidEnum { enum_type: #enum_name, value: tmp as u32 })?;
+                    }
+                    #[cfg(feature = "lenient-decoding")]
+                    {
+                        #name = FromPrimitive::#val(tmp).unwrap_or_default();
+                    }
                 )
             }
         } else {
@@ -739,6 +1266,80 @@ impl MavField {
         }
     }
 
+    /// Emit a chainable setter of the field for the builder of the message
+    fn emit_setter(&self) -> TokenStream {
+        let name = self.emit_name();
+        let fn_name = format_ident!("with_{}", self.name);
+        let fieldtype = self.emit_type();
+        quote! {
+            pub fn #fn_name(mut self, #name: #fieldtype) -> Self {
+                self.#name = #name;
+                self
+            }
+        }
+    }
+
+    /// Emit a getter returning `None` when the field holds the sentinel of its `invalid` attribute.
+    /// `[value]` marks an array as invalid if all elements are the value, `[value:]` if the
+    /// first one is.
+    fn emit_checked_getter(&self) -> Option<TokenStream> {
+        let invalid = self.invalid.as_deref()?;
+        let name = self.emit_name();
+        let fn_name = format_ident!("{}_checked", self.name);
+        let fieldtype = self.emit_type();
+        let doc = format!("`{}`, `None` if it is invalid (`{invalid}`)", self.name);
+
+        if let MavType::Array(elem_type, _) = &self.mavtype {
+            let sentinel = invalid.strip_prefix('[')?.strip_suffix(']')?;
+            let is_invalid = if let Some(sentinel) = sentinel.strip_suffix(':') {
+                elem_type.emit_is_sentinel(&quote!(self.#name[0]), sentinel)?
+            } else {
+                let is_sentinel = elem_type.emit_is_sentinel(&quote!(value), sentinel)?;
+                quote!(self.#name.iter().all(|&value| #is_sentinel))
+            };
+            return Some(quote! {
+                #[doc = #doc]
+                pub fn #fn_name(&self) -> Option<&#fieldtype> {
+                    if #is_invalid {
+                        None
+                    } else {
+                        Some(&self.#name)
+                    }
+                }
+            });
+        }
+
+        let is_invalid = match (&self.enumtype, &self.display) {
+            // the sentinel is an entry of the enum
+            (Some(enum_name), _) if self.mavtype.emit_literal(invalid).is_none() => {
+                let enum_name = format_ident!("{}", enum_name);
+                let entry = format_ident!("{}", invalid);
+                quote!(self.#name == #enum_name::#entry)
+            }
+            (Some(_), Some(dsp)) if dsp == "bitmask" => self
+                .mavtype
+                .emit_is_sentinel(&quote!(self.#name.bits()), invalid)?,
+            (Some(_), _) => {
+                let rust_type = TokenStream::from_str(&self.mavtype.rust_type()).unwrap();
+                self.mavtype
+                    .emit_is_sentinel(&quote!((self.#name as #rust_type)), invalid)?
+            }
+            (None, _) => self
+                .mavtype
+                .emit_is_sentinel(&quote!(self.#name), invalid)?,
+        };
+        Some(quote! {
+            #[doc = #doc]
+            pub fn #fn_name(&self) -> Option<#fieldtype> {
+                if #is_invalid {
+                    None
+                } else {
+                    Some(self.#name)
+                }
+            }
+        })
+    }
+
     fn emit_default_initializer(&self) -> TokenStream {
         let field = self.emit_name();
         // FIXME: Is this actually expected behaviour??
@@ -865,7 +1466,7 @@ impl MavType {
     }
 
     /// Size of a given Mavtype
-    fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         use self::MavType::*;
         match self.clone() {
             UInt8MavlinkVersion | UInt8 | Int8 | Char => 1,
@@ -928,6 +1529,50 @@ impl MavType {
         }
     }
 
+    /// Emit a sentinel of an `invalid` attribute (a number or a limit like `UINT16_MAX`) as a
+    /// literal of this type. Limits of other types are converted like a C cast.
+    fn emit_literal(&self, sentinel: &str) -> Option<TokenStream> {
+        use self::MavType::*;
+
+        let value: f64 = match sentinel {
+            "UINT8_MAX" => u8::MAX.into(),
+            "UINT16_MAX" => u16::MAX.into(),
+            "UINT32_MAX" => u32::MAX.into(),
+            "INT8_MAX" => i8::MAX.into(),
+            "INT16_MAX" => i16::MAX.into(),
+            "INT32_MAX" => i32::MAX.into(),
+            _ => match sentinel.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?.into(),
+                None => sentinel.parse().ok()?,
+            },
+        };
+        let literal = match self {
+            Float => format!("{:?}_f32", value as f32),
+            Double => format!("{value:?}_f64"),
+            _ if value.fract() != 0.0 => return None,
+            UInt8 | UInt8MavlinkVersion | Char => format!("{}_u8", value as i64 as u8),
+            Int8 => format!("{}_i8", value as i64 as i8),
+            UInt16 => format!("{}_u16", value as i64 as u16),
+            Int16 => format!("{}_i16", value as i64 as i16),
+            UInt32 => format!("{}_u32", value as i64 as u32),
+            Int32 => format!("{}_i32", value as i64 as i32),
+            UInt64 => format!("{}_u64", value as i64 as u64),
+            Int64 => format!("{}_i64", value as i64),
+            Array(_, _) => return None,
+        };
+        Some(TokenStream::from_str(&literal).unwrap())
+    }
+
+    /// Emit a check whether a value of this type is the sentinel of an `invalid` attribute
+    fn emit_is_sentinel(&self, value: &TokenStream, sentinel: &str) -> Option<TokenStream> {
+        if sentinel.eq_ignore_ascii_case("nan") {
+            return matches!(self, MavType::Float | MavType::Double)
+                .then(|| quote!(#value.is_nan()));
+        }
+        let literal = self.emit_literal(sentinel)?;
+        Some(quote!(#value == #literal))
+    }
+
     pub fn emit_default_value(&self) -> TokenStream {
         use self::MavType::*;
 
@@ -1032,6 +1677,114 @@ fn is_valid_parent(p: Option<MavXmlElement>, s: MavXmlElement) -> bool {
     }
 }
 
+/// Replace the names in `text` found in `targets` by a link to their path, except within code
+/// spans, links and URLs
+#[cfg_attr(not(feature = "emit-description"), allow(dead_code))]
+pub fn link_names(text: &str, targets: &HashMap<String, String>) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut linked = String::with_capacity(text.len());
+    let mut in_code = false;
+    let mut in_link = false;
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| matches!(c, '`' | '[' | ']') || is_name_char(c)) {
+        linked.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix('`') {
+            in_code = !in_code;
+            linked.push('`');
+            rest = after;
+            continue;
+        }
+        if rest.starts_with('[') || rest.starts_with(']') {
+            // the text of a link is left as is, as is its destination
+            let end = match rest.strip_prefix("](") {
+                Some(destination) if !in_code => {
+                    destination.find(')').map_or(rest.len(), |end| end + 3)
+                }
+                _ => 1,
+            };
+            if !in_code {
+                in_link = rest.starts_with('[');
+            }
+            linked.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        let (name, after) = rest.split_at(end);
+        if (name == "http" || name == "https") && after.starts_with("://") {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            linked.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        match targets.get(name) {
+            Some(path) if !in_code && !in_link => linked.push_str(&format!("[`{name}`]({path})")),
+            _ => linked.push_str(name),
+        }
+        rest = after;
+    }
+    linked.push_str(rest);
+    linked
+}
+
+/// Value of an enum entry: a decimal, hexadecimal (`0x`) or binary (`0b`) number, or a left shift
+/// of two of them like `1 << 4`.
+///
+/// Numbers may contain `_` digit separators, and a leading `-` for values given as the two's
+/// complement of their negation, like `-1` for `0xFFFFFFFF`.
+pub fn parse_entry_value(value: &str) -> Result<u32, String> {
+    let (base, shift) = match value.split_once("<<") {
+        Some((base, shift)) => (base, shift),
+        None => return parse_entry_number(value.trim()),
+    };
+    let in_shift = |error| format!("{error} in {value:?}");
+    let base = parse_entry_number(base.trim()).map_err(in_shift)?;
+    let shift = parse_entry_number(shift.trim()).map_err(in_shift)?;
+    if shift >= 32 || base.leading_zeros() < shift {
+        return Err(format!("value {value:?} does not fit in 32 bits"));
+    }
+    Ok(base << shift)
+}
+
+pub fn parse_entry_number(number: &str) -> Result<u32, String> {
+    let (negative, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, number),
+    };
+    let (radix, digits) = if let Some(digits) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        (16, digits)
+    } else if let Some(digits) = unsigned
+        .strip_prefix("0b")
+        .or_else(|| unsigned.strip_prefix("0B"))
+    {
+        (2, digits)
+    } else {
+        (10, unsigned)
+    };
+    let digits: String = digits.chars().filter(|c| *c != '_').collect();
+    let magnitude = u32::from_str_radix(&digits, radix)
+        .map_err(|error| format!("invalid value {number:?}: {error}"))?;
+    match negative {
+        false => Ok(magnitude),
+        true if magnitude <= 1 << 31 => Ok(magnitude.wrapping_neg()),
+        true => Err(format!("value {number:?} is below the 32 bit minimum")),
+    }
+}
+
+/// Value of the `<version>` and `<dialect>` elements, a number from 0 to 255
+pub fn parse_header_number(value: &str) -> Result<u8, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|error| format!("invalid value {value:?}: {error}"))
+}
+
 pub fn parse_profile(
     definitions_dir: &Path,
     definition_file: &String,
@@ -1043,6 +1796,7 @@ pub fn parse_profile(
     let mut stack: Vec<MavXmlElement> = vec![];
 
     let mut profile = MavProfile::default();
+    profile.includes.insert(definition_file.clone(), vec![]);
     let mut field = MavField::default();
     let mut message = MavMessage::default();
     let mut mavenum = MavEnum::default();
@@ -1095,14 +1849,20 @@ pub fn parse_profile(
                         is_in_extension = true;
                     }
                     MavXmlElement::Message => {
-                        message = Default::default();
+                        message = MavMessage {
+                            definition_file: definition_file.clone(),
+                            ..Default::default()
+                        };
                     }
                     MavXmlElement::Field => {
                         field = Default::default();
                         field.is_extension = is_in_extension;
                     }
                     MavXmlElement::Enum => {
-                        mavenum = Default::default();
+                        mavenum = MavEnum {
+                            definition_files: vec![definition_file.clone()],
+                            ..Default::default()
+                        };
                     }
                     MavXmlElement::Entry => {
                         entry = Default::default();
@@ -1116,6 +1876,10 @@ pub fn parse_profile(
                     _ => (),
                 }
 
+                if id == MavXmlElement::Deprecated && stack.last() == Some(&MavXmlElement::Entry) {
+                    entry.deprecated = Some(MavDeprecated::from_attributes(&bytes));
+                }
+
                 stack.push(id);
 
                 for attr in bytes.attributes() {
@@ -1123,43 +1887,24 @@ pub fn parse_profile(
                     match stack.last() {
                         Some(&MavXmlElement::Enum) => {
                             if let b"name" = attr.key.into_inner() {
-                                mavenum.name = attr
-                                    .value
-                                    .clone()
-                                    .split(|b| *b == b'_')
-                                    .map(|x| x.to_ascii_lowercase())
-                                    .map(|mut v| {
-                                        v[0] = v[0].to_ascii_uppercase();
-                                        String::from_utf8(v).unwrap()
-                                    })
-                                    .collect();
-                                //mavenum.name = attr.value.clone();
+                                mavenum.mavlink_name =
+                                    String::from_utf8(attr.value.to_vec()).unwrap();
+                                mavenum.name = camel_case(&mavenum.mavlink_name);
                             }
                         }
-                        Some(&MavXmlElement::Entry) => {
-                            match attr.key.into_inner() {
-                                b"name" => {
-                                    let name = String::from_utf8(attr.value.to_vec()).unwrap();
-                                    entry.name = name;
-                                }
-                                b"value" => {
-                                    // Deal with hexadecimal numbers
-                                    if attr.value.starts_with(b"0x") {
-                                        entry.value = Some(
-                                            u32::from_str_radix(
-                                                std::str::from_utf8(&attr.value[2..]).unwrap(),
-                                                16,
-                                            )
-                                            .unwrap(),
-                                        );
-                                    } else {
-                                        let s = std::str::from_utf8(&attr.value[..]).unwrap();
-                                        entry.value = Some(s.parse::<u32>().unwrap());
-                                    }
-                                }
-                                _ => (),
+                        Some(&MavXmlElement::Entry) => match attr.key.into_inner() {
+                            b"name" => {
+                                let name = String::from_utf8(attr.value.to_vec()).unwrap();
+                                entry.name = name;
                             }
-                        }
+                            b"value" => {
+                                let s = std::str::from_utf8(&attr.value).unwrap();
+                                entry.value = Some(parse_entry_value(s).unwrap_or_else(|error| {
+                                    panic!("{definition_file}: enum {}: {error}", mavenum.name)
+                                }));
+                            }
+                            _ => (),
+                        },
                         Some(&MavXmlElement::Message) => {
                             match attr.key.into_inner() {
                                 b"name" => {
@@ -1185,40 +1930,35 @@ pub fn parse_profile(
                                 _ => (),
                             }
                         }
-                        Some(&MavXmlElement::Field) => {
-                            match attr.key.into_inner() {
-                                b"name" => {
-                                    let name = String::from_utf8(attr.value.to_vec()).unwrap();
-                                    field.name = name;
-                                    if field.name == "type" {
-                                        field.name = "mavtype".to_string();
-                                    }
-                                }
-                                b"type" => {
-                                    let s = std::str::from_utf8(&attr.value).unwrap();
-                                    field.mavtype = MavType::parse_type(s).unwrap();
-                                }
-                                b"enum" => {
-                                    field.enumtype = Some(
-                                        attr.value
-                                            .clone()
-                                            .split(|b| *b == b'_')
-                                            .map(|x| x.to_ascii_lowercase())
-                                            .map(|mut v| {
-                                                v[0] = v[0].to_ascii_uppercase();
-                                                String::from_utf8(v).unwrap()
-                                            })
-                                            .collect(),
-                                    );
-                                    //field.enumtype = Some(attr.value.clone());
-                                }
-                                b"display" => {
-                                    field.display =
-                                        Some(String::from_utf8(attr.value.to_vec()).unwrap());
+                        Some(&MavXmlElement::Field) => match attr.key.into_inner() {
+                            b"name" => {
+                                let name = String::from_utf8(attr.value.to_vec()).unwrap();
+                                field.name = name;
+                                if field.name == "type" {
+                                    field.name = "mavtype".to_string();
                                 }
-                                _ => (),
                             }
-                        }
+                            b"type" => {
+                                let s = std::str::from_utf8(&attr.value).unwrap();
+                                field.mavtype = MavType::parse_type(s).unwrap();
+                            }
+                            b"enum" => {
+                                field.enumtype =
+                                    Some(camel_case(std::str::from_utf8(&attr.value).unwrap()));
+                            }
+                            b"display" => {
+                                field.display =
+                                    Some(String::from_utf8(attr.value.to_vec()).unwrap());
+                            }
+                            b"units" => {
+                                field.units = Some(String::from_utf8(attr.value.to_vec()).unwrap());
+                            }
+                            b"invalid" => {
+                                field.invalid =
+                                    Some(String::from_utf8(attr.value.to_vec()).unwrap());
+                            }
+                            _ => (),
+                        },
                         Some(&MavXmlElement::Param) => {
                             if entry.params.is_none() {
                                 entry.params = Some(vec![]);
@@ -1246,13 +1986,18 @@ pub fn parse_profile(
                             }
                             b"value" => {
                                 let s = std::str::from_utf8(&attr.value).unwrap();
-                                entry.value = Some(s.parse().unwrap());
+                                entry.value = Some(parse_entry_value(s).unwrap_or_else(|error| {
+                                    panic!("{definition_file}: enum {}: {error}", mavenum.name)
+                                }));
                             }
                             _ => (),
                         }
                     }
                     mavenum.entries.push(entry.clone());
                 }
+                b"deprecated" if stack.last() == Some(&MavXmlElement::Entry) => {
+                    entry.deprecated = Some(MavDeprecated::from_attributes(&bytes));
+                }
                 _ => (),
             },
             Ok(Event::Text(bytes)) => {
@@ -1288,10 +2033,14 @@ pub fn parse_profile(
                         include = s.replace('\n', "");
                     }
                     (Some(&Version), Some(&Mavlink)) => {
-                        eprintln!("TODO: version {s:?}");
+                        profile.version = Some(parse_header_number(&s).unwrap_or_else(|error| {
+                            panic!("{}: <version>: {}", definition_file, error)
+                        }));
                     }
                     (Some(&Dialect), Some(&Mavlink)) => {
-                        eprintln!("TODO: dialect {s:?}");
+                        profile.dialect = Some(parse_header_number(&s).unwrap_or_else(|error| {
+                            panic!("{}: <dialect>: {}", definition_file, error)
+                        }));
                     }
                     (Some(Deprecated), _) => {
                         eprintln!("TODO: deprecated {s:?}");
@@ -1331,6 +2080,11 @@ pub fn parse_profile(
                         profile.add_enum(&mavenum);
                     }
                     Some(&MavXmlElement::Include) => {
+                        profile
+                            .includes
+                            .entry(definition_file.clone())
+                            .or_default()
+                            .push(include.clone());
                         let include_file = Path::new(&definitions_dir).join(include.clone());
                         if !parsed_files.contains(&include_file) {
                             let included_profile =
@@ -1341,6 +2095,11 @@ pub fn parse_profile(
                             for enm in included_profile.enums.values() {
                                 profile.add_enum(enm);
                             }
+                            for (file, includes) in included_profile.includes {
+                                profile.includes.entry(file).or_insert(includes);
+                            }
+                            profile.version = profile.version.or(included_profile.version);
+                            profile.dialect = profile.dialect.or(included_profile.dialect);
                         }
                     }
                     _ => (),
@@ -1357,18 +2116,92 @@ pub fn parse_profile(
     }
 
     //let profile = profile.update_messages(); //TODO verify no longer needed
-    profile.update_enums()
+    profile
 }
 
 /// Generate protobuf represenation of mavlink message set
 /// Generate rust representation of mavlink message set with appropriate conversion methods
-pub fn generate<W: Write>(definitions_dir: &Path, definition_file: &String, output_rust: &mut W) {
+pub fn generate<W: Write>(
+    definitions_dir: &Path,
+    definition_file: &String,
+    output_rust: &mut W,
+    log: &BuildLog,
+    config: &CodegenConfig,
+) -> MavProfile {
+    let dialect = to_module_name(definition_file);
+
     let mut parsed_files: HashSet<PathBuf> = HashSet::new();
-    let profile = parse_profile(definitions_dir, definition_file, &mut parsed_files);
+    let profile = log.time(&dialect, "parse", || {
+        parse_profile(definitions_dir, definition_file, &mut parsed_files)
+    });
+    let profile = log.time(&dialect, "normalise", || {
+        profile.update_enums().apply_config(config)
+    });
+    log.event(
+        &dialect,
+        "summary",
+        &[
+            ("files", &parsed_files.len()),
+            ("messages", &profile.messages.len()),
+            ("enums", &profile.enums.len()),
+        ],
+    );
 
     // rust file
-    let rust_tokens = profile.emit_rust();
-    writeln!(output_rust, "{rust_tokens}").unwrap();
+    log.time(&dialect, "emit", || {
+        // the other backends use the profile as parsed
+        let mut rust_profile = Cow::Borrowed(&profile);
+        if !config.naming.is_default() {
+            match rust_profile.into_owned().apply_naming(&config.naming) {
+                Ok(named) => rust_profile = Cow::Owned(named),
+                Err(error) => {
+                    // a configuration error rather than a bug of the build script
+                    eprintln!("error: {error}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        #[cfg(feature = "emit-description")]
+        {
+            rust_profile = Cow::Owned(rust_profile.into_owned().link_descriptions());
+        }
+        let rust_tokens = rust_profile.emit_rust(&dialect);
+        writeln!(output_rust, "{rust_tokens}").unwrap();
+    });
+    profile
+}
+
+/// Messages by id and enums by name of several profiles, for the backends covering all the
+/// enabled dialects. Entries of enums extended by some of the dialects are merged.
+pub fn merge_profiles(
+    profiles: &[MavProfile],
+) -> (BTreeMap<u32, &MavMessage>, BTreeMap<String, MavEnum>) {
+    let mut messages: BTreeMap<u32, &MavMessage> = BTreeMap::new();
+    let mut enums: BTreeMap<String, MavEnum> = BTreeMap::new();
+    for profile in profiles {
+        for msg in profile.messages.values() {
+            messages.insert(msg.id, msg);
+        }
+        for mavenum in profile.enums.values() {
+            let merged = enums
+                .entry(mavenum.name.clone())
+                .or_insert_with(|| MavEnum {
+                    entries: vec![],
+                    ..mavenum.clone()
+                });
+            for entry in &mavenum.entries {
+                if !merged.entries.iter().any(|known| known.name == entry.name) {
+                    merged.entries.push(entry.clone());
+                }
+            }
+            for file in &mavenum.definition_files {
+                if !merged.definition_files.contains(file) {
+                    merged.definition_files.push(file.clone());
+                }
+            }
+        }
+    }
+    (messages, enums)
 }
 
 /// CRC operates over names of the message and names of its fields
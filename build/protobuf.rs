@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::parser::{merge_profiles, MavEnum, MavMessage, MavProfile, MavType};
+
+/// Field number of a message in the `oneof` of the envelope, after the header fields
+fn envelope_number(msg: &MavMessage) -> u32 {
+    let number = msg.id + 16;
+    // reserved for the protobuf implementation
+    assert!(
+        !(19000..=19999).contains(&number),
+        "message {} has no protobuf field number",
+        msg.name
+    );
+    number
+}
+
+/// Protobuf type of a scalar MAVLink type
+fn scalar_type(mavtype: &MavType) -> &'static str {
+    match mavtype {
+        MavType::UInt8MavlinkVersion | MavType::UInt8 | MavType::UInt16 | MavType::UInt32 => {
+            "uint32"
+        }
+        MavType::Int8 | MavType::Int16 | MavType::Int32 | MavType::Char => "int32",
+        MavType::UInt64 => "uint64",
+        MavType::Int64 => "int64",
+        MavType::Float => "float",
+        MavType::Double => "double",
+        MavType::Array(..) => unreachable!("arrays have no scalar type"),
+    }
+}
+
+/// `MavType` to `MAV_TYPE`
+fn screaming_case(name: &str) -> String {
+    let mut screaming = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            screaming.push('_');
+        }
+        screaming.push(c.to_ascii_uppercase());
+    }
+    screaming
+}
+
+/// Enums which can be protobuf enums: not bitmasks, with values fitting an `int32` and names
+/// unique in the package
+fn schema_enums(enums: &BTreeMap<String, MavEnum>) -> BTreeMap<&str, &MavEnum> {
+    let mut names = HashSet::new();
+    let mut schema_enums = BTreeMap::new();
+    for mavenum in enums.values() {
+        let fits = mavenum.bitfield.is_none()
+            && mavenum
+                .entries
+                .iter()
+                .all(|entry| entry.value.map_or(false, |value| value <= i32::MAX as u32));
+        let mut entry_names: Vec<String> = mavenum
+            .entries
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect();
+        entry_names.push(format!("{}_UNSPECIFIED", screaming_case(&mavenum.name)));
+        if fits && entry_names.iter().all(|name| !names.contains(name)) {
+            names.extend(entry_names);
+            schema_enums.insert(mavenum.name.as_str(), mavenum);
+        }
+    }
+    schema_enums
+}
+
+fn emit_enum<W: Write>(out: &mut W, mavenum: &MavEnum) {
+    writeln!(out, "enum {} {{", mavenum.name).unwrap();
+    let mut values = HashSet::new();
+    let aliased = !mavenum
+        .entries
+        .iter()
+        .all(|entry| values.insert(entry.value));
+    if aliased {
+        writeln!(out, "  option allow_alias = true;").unwrap();
+    }
+    // proto3 enums start with a zero value
+    if !values.contains(&Some(0)) {
+        writeln!(out, "  {}_UNSPECIFIED = 0;", screaming_case(&mavenum.name)).unwrap();
+    }
+    let mut entries: Vec<_> = mavenum.entries.iter().collect();
+    entries.sort_by_key(|entry| entry.value);
+    for entry in entries {
+        writeln!(out, "  {} = {};", entry.name, entry.value.unwrap()).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn emit_message<W: Write>(out: &mut W, msg: &MavMessage, enums: &BTreeMap<&str, &MavEnum>) {
+    writeln!(out, "// id {}", msg.id).unwrap();
+    writeln!(out, "message {} {{", msg.name).unwrap();
+    for (index, field) in msg.fields.iter().enumerate() {
+        // the generated rust code renames the `type` fields
+        let name = if field.name == "mavtype" {
+            "type"
+        } else {
+            &field.name
+        };
+        let mavenum = field.enumtype.as_deref().and_then(|name| enums.get(name));
+        let proto_type = match (&field.mavtype, mavenum) {
+            (MavType::Array(element, _), _) if **element == MavType::Char => "string".to_string(),
+            (MavType::Array(element, _), _) if **element == MavType::UInt8 => "bytes".to_string(),
+            (MavType::Array(element, _), _) => format!("repeated {}", scalar_type(element)),
+            (MavType::Float | MavType::Double, _) => scalar_type(&field.mavtype).to_string(),
+            (_, Some(mavenum)) => mavenum.name.clone(),
+            (mavtype, None) => scalar_type(mavtype).to_string(),
+        };
+        write!(out, "  {proto_type} {name} = {};", index + 1).unwrap();
+        let mut comments = vec![];
+        if let Some(units) = &field.units {
+            comments.push(units.clone());
+        }
+        if let (Some(name), None) = (&field.enumtype, mavenum) {
+            comments.push(name.clone());
+        }
+        if !comments.is_empty() {
+            write!(out, " // {}", comments.join(", ")).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+/// Generate a proto3 schema covering the messages of all the given profiles
+pub fn generate_schema<W: Write>(profiles: &[MavProfile], out: &mut W) {
+    let (messages, enums) = merge_profiles(profiles);
+    let schema_enums = schema_enums(&enums);
+
+    writeln!(
+        out,
+        "// MAVLink messages, generated by the mavlink crate from the enabled dialects\n\
+         syntax = \"proto3\";\n\npackage mavlink;\n"
+    )
+    .unwrap();
+    for mavenum in schema_enums.values() {
+        emit_enum(out, mavenum);
+    }
+    for msg in messages.values() {
+        emit_message(out, msg, &schema_enums);
+    }
+
+    writeln!(out, "// A message with its header").unwrap();
+    writeln!(out, "message MavlinkMessage {{").unwrap();
+    writeln!(out, "  uint32 system_id = 1;").unwrap();
+    writeln!(out, "  uint32 component_id = 2;").unwrap();
+    writeln!(out, "  uint32 sequence = 3;").unwrap();
+    writeln!(out, "  oneof message {{").unwrap();
+    for msg in messages.values() {
+        writeln!(
+            out,
+            "    {} {} = {};",
+            msg.name,
+            msg.name.to_lowercase(),
+            envelope_number(msg)
+        )
+        .unwrap();
+    }
+    writeln!(out, "  }}\n}}").unwrap();
+}
+
+fn field_kind(mavtype: &MavType) -> TokenStream {
+    let kind = match mavtype {
+        MavType::UInt8MavlinkVersion | MavType::UInt8 => "U8",
+        MavType::UInt16 => "U16",
+        MavType::UInt32 => "U32",
+        MavType::UInt64 => "U64",
+        MavType::Int8 => "I8",
+        MavType::Int16 => "I16",
+        MavType::Int32 => "I32",
+        MavType::Int64 => "I64",
+        MavType::Char => "Char",
+        MavType::Float => "F32",
+        MavType::Double => "F64",
+        MavType::Array(element, _) => return field_kind(element),
+    };
+    let kind = format_ident!("{}", kind);
+    quote!(FieldKind::#kind)
+}
+
+/// Generate the table of the payload layouts used by `mavlink::protobuf`, in the same order
+/// as the schema
+pub fn generate_layouts<W: Write>(profiles: &[MavProfile], out: &mut W) {
+    let (messages, _) = merge_profiles(profiles);
+    let layouts = messages.values().map(|msg| {
+        let id = msg.id;
+        let mut offset = 0usize;
+        let fields = msg.fields.iter().enumerate().map(|(index, field)| {
+            let number = index as u32 + 1;
+            let kind = field_kind(&field.mavtype);
+            let (count, repeated) = match &field.mavtype {
+                MavType::Array(_, count) => (*count, true),
+                _ => (1, false),
+            };
+            let field_offset = offset;
+            offset += field.mavtype.len();
+            quote! {
+                FieldLayout {
+                    number: #number,
+                    kind: #kind,
+                    offset: #field_offset,
+                    count: #count,
+                    repeated: #repeated,
+                }
+            }
+        });
+        let fields: Vec<_> = fields.collect();
+        let envelope_number = envelope_number(msg);
+        quote! {
+            MessageLayout {
+                id: #id,
+                envelope_number: #envelope_number,
+                payload_len: #offset,
+                fields: &[#(#fields),*],
+            }
+        }
+    });
+    let tokens = quote!(&[#(#layouts),*]);
+    writeln!(out, "{tokens}").unwrap();
+}
@@ -0,0 +1,31 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use quote::quote;
+
+use crate::parser::{MavProfile, MavType};
+
+/// Generate the table of the `char` array fields of each message, sorted by message name,
+/// which `mavlink::python` reads and writes as strings
+pub fn generate<W: Write>(profiles: &[MavProfile], out: &mut W) {
+    let mut char_fields = BTreeMap::new();
+    for msg in profiles
+        .iter()
+        .flat_map(|profile| profile.messages.values())
+    {
+        let fields: Vec<_> = msg
+            .fields
+            .iter()
+            .filter(|field| matches!(&field.mavtype, MavType::Array(t, _) if **t == MavType::Char))
+            .map(|field| field.name.as_str())
+            .collect();
+        if !fields.is_empty() {
+            char_fields.insert(msg.name.as_str(), fields);
+        }
+    }
+    let entries = char_fields
+        .iter()
+        .map(|(name, fields)| quote!((#name, &[#(#fields),*])));
+    let tokens = quote!(&[#(#entries),*]);
+    writeln!(out, "{tokens}").unwrap();
+}
@@ -0,0 +1,72 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::parser::{MavField, MavMessage, MavType};
+
+/// The `uom` quantity of a unit of the definitions: the quantity, the module and name of its
+/// base unit and the factor from the field value to the base unit
+fn quantity(units: &str) -> Option<(&'static str, &'static str, &'static str, f64)> {
+    Some(match units {
+        "m" => ("Length", "length", "meter", 1.0),
+        "dm" => ("Length", "length", "meter", 0.1),
+        "cm" => ("Length", "length", "meter", 0.01),
+        "mm" => ("Length", "length", "meter", 0.001),
+        "dam" => ("Length", "length", "meter", 10.0),
+        "m/s" => ("Velocity", "velocity", "meter_per_second", 1.0),
+        "dm/s" => ("Velocity", "velocity", "meter_per_second", 0.1),
+        "cm/s" => ("Velocity", "velocity", "meter_per_second", 0.01),
+        "mm/s" => ("Velocity", "velocity", "meter_per_second", 0.001),
+        "rad" => ("Angle", "angle", "radian", 1.0),
+        "deg" => ("Angle", "angle", "degree", 1.0),
+        "cdeg" => ("Angle", "angle", "degree", 0.01),
+        "degE5" => ("Angle", "angle", "degree", 1e-5),
+        "degE7" => ("Angle", "angle", "degree", 1e-7),
+        _ => return None,
+    })
+}
+
+/// Accessor of a scalar field with a length, velocity or angle unit
+fn emit_accessor(field: &MavField) -> Option<TokenStream> {
+    if field.enumtype.is_some() || matches!(field.mavtype, MavType::Array(_, _) | MavType::Char) {
+        return None;
+    }
+    let (quantity, module, unit, factor) = quantity(field.units.as_deref()?)?;
+
+    let name = format_ident!("{}", field.name);
+    let fn_name = format_ident!("{}_quantity", field.name);
+    let quantity = format_ident!("{}", quantity);
+    let module = format_ident!("{}", module);
+    let unit = format_ident!("{}", unit);
+    let value = if factor == 1.0 {
+        quote!(self.#name as f64)
+    } else {
+        let factor = Literal::f64_unsuffixed(factor);
+        quote!(self.#name as f64 * #factor)
+    };
+    let doc = format!("`{}` as a {}", field.name, module);
+
+    Some(quote! {
+        #[doc = #doc]
+        pub fn #fn_name(&self) -> uom::si::f64::#quantity {
+            uom::si::f64::#quantity::new::<uom::si::#module::#unit>(#value)
+        }
+    })
+}
+
+/// Accessors returning the fields with units as `uom` quantities
+pub fn emit_accessors(
+    msg: &MavMessage,
+    struct_name: &TokenStream,
+    cfg: &TokenStream,
+) -> TokenStream {
+    let accessors: Vec<_> = msg.fields.iter().filter_map(emit_accessor).collect();
+    if accessors.is_empty() {
+        return quote!();
+    }
+    quote! {
+        #cfg
+        impl #struct_name {
+            #(#accessors)*
+        }
+    }
+}
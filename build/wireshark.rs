@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::parser::{merge_profiles, MavEnum, MavField, MavMessage, MavProfile, MavType};
+
+/// UDP ports the dissector is registered for, the usual GCS and companion ports
+const UDP_PORTS: &str = "14550-14580";
+
+/// Quote a string as a Lua string literal
+fn lua_str(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            // other line breaks and control characters would end the literal
+            c if c.is_control() => quoted.push_str(&format!("\\{:03}", u32::from(c))),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Wireshark field type of a scalar MAVLink type
+fn ftype(mavtype: &MavType) -> &'static str {
+    match mavtype {
+        MavType::UInt8MavlinkVersion | MavType::UInt8 => "ftypes.UINT8",
+        MavType::UInt16 => "ftypes.UINT16",
+        MavType::UInt32 => "ftypes.UINT32",
+        MavType::UInt64 => "ftypes.UINT64",
+        MavType::Int8 => "ftypes.INT8",
+        MavType::Int16 => "ftypes.INT16",
+        MavType::Int32 => "ftypes.INT32",
+        MavType::Int64 => "ftypes.INT64",
+        MavType::Char => "ftypes.STRING",
+        MavType::Float => "ftypes.FLOAT",
+        MavType::Double => "ftypes.DOUBLE",
+        MavType::Array(..) => unreachable!("arrays are split into their elements"),
+    }
+}
+
+fn is_integer(mavtype: &MavType) -> bool {
+    !matches!(
+        mavtype,
+        MavType::Char | MavType::Float | MavType::Double | MavType::Array(..)
+    )
+}
+
+/// One element dissected from a payload
+struct Element {
+    /// Lua identifier of the field, also used in the filter name
+    id: String,
+    label: String,
+    mavtype: MavType,
+    offset: usize,
+    len: usize,
+}
+
+/// Split the fields of a message into dissected elements, arrays other than strings become one
+/// element per entry
+fn elements(msg: &MavMessage) -> Vec<(&MavField, Element)> {
+    let mut elements = Vec::new();
+    let mut offset = 0;
+    for field in &msg.fields {
+        // the generated rust code renames the `type` fields
+        let name = if field.name == "mavtype" {
+            "type"
+        } else {
+            &field.name
+        };
+        let mut label = name.to_string();
+        if let Some(units) = &field.units {
+            label.push_str(&format!(" ({units})"));
+        }
+
+        match &field.mavtype {
+            MavType::Array(element, count) if **element == MavType::Char => {
+                elements.push((
+                    field,
+                    Element {
+                        id: format!("{}_{name}", msg.name),
+                        label,
+                        mavtype: MavType::Char,
+                        offset,
+                        len: *count,
+                    },
+                ));
+                offset += count;
+            }
+            MavType::Array(element, count) => {
+                let len = element.len();
+                for index in 0..*count {
+                    elements.push((
+                        field,
+                        Element {
+                            id: format!("{}_{name}_{index}", msg.name),
+                            label: format!("{name}[{index}]{}", &label[name.len()..]),
+                            mavtype: (**element).clone(),
+                            offset,
+                            len,
+                        },
+                    ));
+                    offset += len;
+                }
+            }
+            mavtype => {
+                let len = mavtype.len();
+                elements.push((
+                    field,
+                    Element {
+                        id: format!("{}_{name}", msg.name),
+                        label,
+                        mavtype: mavtype.clone(),
+                        offset,
+                        len,
+                    },
+                ));
+                offset += len;
+            }
+        }
+    }
+    elements
+}
+
+fn emit_header<W: Write>(out: &mut W) {
+    writeln!(
+        out,
+        r#"-- Wireshark dissector for MAVLink, generated by the mavlink crate, do not edit
+--
+-- Copy this file into the Wireshark plugin directory, e.g. ~/.local/lib/wireshark/plugins,
+-- UDP traffic on the ports {UDP_PORTS} is decoded, other ports can be added with "Decode As".
+
+local mavlink_proto = Proto("mavlink_proto", "MAVLink protocol")
+local f = mavlink_proto.fields
+
+f.magic = ProtoField.uint8("mavlink_proto.magic", "Magic value / version", base.HEX)
+f.length = ProtoField.uint8("mavlink_proto.length", "Payload length")
+f.incompatibility_flag = ProtoField.uint8("mavlink_proto.incompatibility_flag", "Incompatibility flag", base.HEX)
+f.compatibility_flag = ProtoField.uint8("mavlink_proto.compatibility_flag", "Compatibility flag", base.HEX)
+f.sequence = ProtoField.uint8("mavlink_proto.seq", "Packet sequence")
+f.sysid = ProtoField.uint8("mavlink_proto.sysid", "System id")
+f.compid = ProtoField.uint8("mavlink_proto.compid", "Component id")
+f.msgid = ProtoField.uint24("mavlink_proto.msgid", "Message id")
+f.payload = ProtoField.bytes("mavlink_proto.payload", "Payload")
+f.crc = ProtoField.uint16("mavlink_proto.crc", "Message CRC", base.HEX)
+f.signature_link = ProtoField.uint8("mavlink_proto.signature_link", "Link id")
+f.signature_time = ProtoField.uint64("mavlink_proto.signature_time", "Signature timestamp")
+f.signature_signature = ProtoField.bytes("mavlink_proto.signature_signature", "Signature")
+"#
+    )
+    .unwrap();
+}
+
+fn emit_enums<W: Write>(out: &mut W, enums: &BTreeMap<String, MavEnum>) {
+    // in a table as a Lua function is limited to 200 local variables
+    writeln!(out, "local enums = {{}}").unwrap();
+    for mavenum in enums.values() {
+        writeln!(out, "enums.{} = {{", mavenum.name).unwrap();
+        for entry in &mavenum.entries {
+            if let Some(value) = entry.value {
+                writeln!(out, "    [{value}] = {},", lua_str(&entry.name)).unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+fn emit_message<W: Write>(out: &mut W, msg: &MavMessage, enums: &BTreeMap<String, MavEnum>) {
+    let elements = elements(msg);
+
+    for (field, element) in &elements {
+        let abbrev = lua_str(&format!("mavlink_proto.{}", element.id));
+        let mut label = element.label.clone();
+        let mut value_strings = "nil".to_string();
+        let mut base = "nil";
+        let mut description = "nil".to_string();
+
+        if let Some(mavenum) = field.enumtype.as_ref().and_then(|name| enums.get(name)) {
+            label.push_str(&format!(" ({})", mavenum.name));
+            // only integers are shown with the names of their values
+            if is_integer(&element.mavtype) {
+                if mavenum.bitfield.is_some() {
+                    base = "base.HEX";
+                    description = lua_str(&format!("Bitmask of {}", mavenum.name));
+                } else {
+                    value_strings = format!("enums.{}", mavenum.name);
+                }
+            }
+        }
+        if let Some(field_description) = &field.description {
+            description = lua_str(field_description.trim());
+        }
+
+        writeln!(
+            out,
+            "f.{} = ProtoField.new({}, {abbrev}, {}, {value_strings}, {base}, nil, {description})",
+            element.id,
+            lua_str(&label),
+            ftype(&element.mavtype),
+        )
+        .unwrap();
+    }
+
+    let payload_len: usize = elements.iter().map(|(_, element)| element.len).sum();
+    writeln!(
+        out,
+        r#"
+function payload_fns.payload_{id}(buffer, tree, offset, limit)
+    -- MAVLink 2 strips trailing zeros from the payload
+    local padded = buffer
+    if offset + {payload_len} > limit then
+        padded = buffer(0, limit):bytes()
+        padded:set_size(offset + {payload_len})
+        padded = padded:tvb("Untruncated payload")
+    end"#,
+        id = msg.id,
+    )
+    .unwrap();
+    for (_, element) in &elements {
+        writeln!(
+            out,
+            "    tree:add_le(f.{}, padded(offset + {}, {}))",
+            element.id, element.offset, element.len
+        )
+        .unwrap();
+    }
+    writeln!(out, "end").unwrap();
+}
+
+fn emit_dissector<W: Write>(out: &mut W) {
+    writeln!(
+        out,
+        r#"
+local function dissect_frame(buffer, pinfo, tree, offset)
+    local version = buffer(offset, 1):uint()
+    local header_len
+    if version == 0xfe then
+        header_len = 6
+    elseif version == 0xfd then
+        header_len = 10
+    else
+        return buffer:len()
+    end
+    if buffer:len() - offset < header_len then
+        return buffer:len()
+    end
+
+    local length = buffer(offset + 1, 1):uint()
+    local signed = version == 0xfd and bit.band(buffer(offset + 2, 1):uint(), 0x01) == 0x01
+    local frame_len = header_len + length + 2
+    if signed then
+        frame_len = frame_len + 13
+    end
+    frame_len = math.min(frame_len, buffer:len() - offset)
+
+    local subtree = tree:add(mavlink_proto, buffer(offset, frame_len), "MAVLink Protocol")
+    local header = subtree:add(buffer(offset, header_len), "Header")
+    header:add(f.magic, buffer(offset, 1))
+    header:add(f.length, buffer(offset + 1, 1))
+
+    local msgid
+    if version == 0xfe then
+        header:add(f.sequence, buffer(offset + 2, 1))
+        header:add(f.sysid, buffer(offset + 3, 1))
+        header:add(f.compid, buffer(offset + 4, 1))
+        header:add(f.msgid, buffer(offset + 5, 1))
+        msgid = buffer(offset + 5, 1):uint()
+    else
+        header:add(f.incompatibility_flag, buffer(offset + 2, 1))
+        header:add(f.compatibility_flag, buffer(offset + 3, 1))
+        header:add(f.sequence, buffer(offset + 4, 1))
+        header:add(f.sysid, buffer(offset + 5, 1))
+        header:add(f.compid, buffer(offset + 6, 1))
+        header:add_le(f.msgid, buffer(offset + 7, 3))
+        msgid = buffer(offset + 7, 3):le_uint()
+    end
+
+    local name = message_names[msgid] or "UNKNOWN"
+    pinfo.cols.info:append(name .. " ")
+
+    local payload_offset = offset + header_len
+    local payload_end = math.min(payload_offset + length, buffer:len())
+    if length > 0 and payload_end > payload_offset then
+        local payload = subtree:add(f.payload, buffer(payload_offset, payload_end - payload_offset))
+        payload:set_text(name .. " (" .. msgid .. ")")
+        local dissect_payload = payload_fns["payload_" .. msgid]
+        if dissect_payload ~= nil then
+            dissect_payload(buffer, payload, payload_offset, payload_end)
+        end
+    end
+
+    if payload_end + 2 <= buffer:len() then
+        subtree:add_le(f.crc, buffer(payload_end, 2))
+    end
+    if signed and payload_end + 15 <= buffer:len() then
+        local signature = subtree:add(buffer(payload_end + 2, 13), "Signature")
+        signature:add(f.signature_link, buffer(payload_end + 2, 1))
+        signature:add_le(f.signature_time, buffer(payload_end + 3, 6))
+        signature:add(f.signature_signature, buffer(payload_end + 9, 6))
+    end
+    return frame_len
+end
+
+function mavlink_proto.dissector(buffer, pinfo, tree)
+    pinfo.cols.protocol = mavlink_proto.name
+    pinfo.cols.info:clear()
+    local offset = 0
+    while offset < buffer:len() do
+        offset = offset + dissect_frame(buffer, pinfo, tree, offset)
+    end
+end
+
+DissectorTable.get("udp.port"):add("{UDP_PORTS}", mavlink_proto)"#
+    )
+    .unwrap();
+}
+
+/// Generate a Lua Wireshark dissector covering the messages of all the given profiles
+pub fn generate<W: Write>(profiles: &[MavProfile], out: &mut W) {
+    let (messages, enums) = merge_profiles(profiles);
+
+    emit_header(out);
+    emit_enums(out, &enums);
+
+    writeln!(out, "local message_names = {{").unwrap();
+    for msg in messages.values() {
+        writeln!(out, "    [{}] = {},", msg.id, lua_str(&msg.name)).unwrap();
+    }
+    writeln!(out, "}}\n\nlocal payload_fns = {{}}\n").unwrap();
+
+    for msg in messages.values() {
+        emit_message(out, msg, &enums);
+    }
+    emit_dissector(out);
+}
@@ -0,0 +1,226 @@
+//! Ground station on tokio: heartbeat task, command with ACK, parameter download and graceful
+//! shutdown on Ctrl-C.
+//!
+//! Usage: mavlink-async-gcs (tcpout|tcpin|udpout|udpin|udpbcast):(ip):(port)
+
+use std::env;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use mavlink::commands::{CommandTransaction, DEFAULT_TIMEOUT};
+use mavlink::common::{
+    MavAutopilot, MavCmd, MavMessage, MavModeFlag, MavResult, MavState, MavType,
+    AUTOPILOT_VERSION_DATA, COMMAND_LONG_DATA, HEARTBEAT_DATA,
+};
+use mavlink::error::{MessageReadError, ProtocolError};
+use mavlink::params::{ParamDownload, ParamValue};
+use mavlink::request::{Exchange, Step};
+use mavlink::{MavConnection, MavHeader, MessageData};
+use tokio::sync::{broadcast, watch};
+use tokio::time::{self, Instant};
+
+type Connection = Arc<Box<dyn MavConnection<MavMessage> + Send + Sync>>;
+type Messages = broadcast::Receiver<(MavHeader, MavMessage)>;
+
+const GCS: MavHeader = MavHeader {
+    system_id: 255,
+    component_id: 190,
+    sequence: 0,
+};
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() < 2 {
+        println!("Usage: mavlink-async-gcs (tcpout|tcpin|udpout|udpin|udpbcast):(ip):(port)");
+        return;
+    }
+    let connection: Connection = Arc::new(mavlink::connect::<MavMessage>(&args[1]).unwrap());
+
+    // the connections block, so receive on a thread and hand the messages to the tasks
+    let (sender, _) = broadcast::channel(1024);
+    let running = Arc::new(AtomicBool::new(true));
+    thread::spawn({
+        let connection = connection.clone();
+        let sender = sender.clone();
+        let running = running.clone();
+        move || {
+            while running.load(Ordering::Relaxed) {
+                match connection.recv() {
+                    Ok(received) => {
+                        // no task is listening yet
+                        let _ = sender.send(received);
+                    }
+                    Err(MessageReadError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                    Err(MessageReadError::Io(e)) => {
+                        println!("recv error: {e:?}");
+                        break;
+                    }
+                    // messages that didn't get through due to parser errors are ignored
+                    Err(_) => {}
+                }
+            }
+        }
+    });
+
+    let (shutdown, shutdown_rx) = watch::channel(false);
+    let heartbeat = tokio::spawn(send_heartbeats(connection.clone(), shutdown_rx));
+
+    let session = run_session(connection.clone(), sender.subscribe(), sender.subscribe());
+    tokio::select! {
+        _ = session => {}
+        _ = tokio::signal::ctrl_c() => println!("shutting down"),
+    }
+
+    // stop the tasks and the reader before the connection goes away
+    let _ = shutdown.send(true);
+    let _ = heartbeat.await;
+    running.store(false, Ordering::Relaxed);
+}
+
+async fn send_heartbeats(connection: Connection, mut shutdown: watch::Receiver<bool>) {
+    let heartbeat = MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+        custom_mode: 0,
+        mavtype: MavType::MAV_TYPE_GCS,
+        autopilot: MavAutopilot::MAV_AUTOPILOT_INVALID,
+        base_mode: MavModeFlag::empty(),
+        system_status: MavState::MAV_STATE_ACTIVE,
+        mavlink_version: 3,
+    });
+    let mut interval = time::interval(Duration::from_secs(1));
+    let mut header = GCS;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                // an udpin connection cannot send before it received something
+                let _ = connection.send(&header, &heartbeat);
+                header.sequence = header.sequence.wrapping_add(1);
+            }
+            _ = shutdown.changed() => return,
+        }
+    }
+}
+
+async fn run_session(connection: Connection, mut messages: Messages, mut printed: Messages) {
+    let (system_id, component_id) = loop {
+        match next_message(&mut messages, None).await {
+            Some((header, MavMessage::HEARTBEAT(heartbeat)))
+                if heartbeat.autopilot != MavAutopilot::MAV_AUTOPILOT_INVALID =>
+            {
+                break (header.system_id, header.component_id)
+            }
+            Some(_) => {}
+            None => return,
+        }
+    };
+    println!("vehicle {system_id}:{component_id} found");
+
+    // ask for the autopilot version, which any autopilot acknowledges
+    let request = COMMAND_LONG_DATA {
+        target_system: system_id,
+        target_component: component_id,
+        command: MavCmd::MAV_CMD_REQUEST_MESSAGE,
+        param1: AUTOPILOT_VERSION_DATA::ID as f32,
+        ..Default::default()
+    };
+    match send_command(&connection, &mut messages, request).await {
+        Ok(result) => println!("AUTOPILOT_VERSION request: {result:?}"),
+        Err(e) => println!("AUTOPILOT_VERSION request failed: {e}"),
+    }
+
+    match fetch_params(&connection, &mut messages, system_id, component_id).await {
+        Ok(params) => {
+            println!("{} parameters", params.len());
+            for (name, value) in params {
+                println!("{name} = {value:?}");
+            }
+        }
+        Err(e) => println!("parameter download failed: {e}"),
+    }
+
+    // the printing receiver kept the messages that arrived meanwhile
+    while let Some((_header, msg)) = next_message(&mut printed, None).await {
+        println!("received: {msg:?}");
+    }
+}
+
+/// Next received message, `None` once the deadline passed or the reader stopped
+async fn next_message(
+    messages: &mut Messages,
+    deadline: Option<Instant>,
+) -> Option<(MavHeader, MavMessage)> {
+    loop {
+        let received = match deadline {
+            Some(deadline) => time::timeout_at(deadline, messages.recv()).await.ok()?,
+            None => messages.recv().await,
+        };
+        match received {
+            Ok(received) => return Some(received),
+            // this task was too slow and missed some messages
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+async fn send_command(
+    connection: &Connection,
+    messages: &mut Messages,
+    command: COMMAND_LONG_DATA,
+) -> Result<MavResult, ProtocolError> {
+    run(connection, messages, &mut CommandTransaction::new(command)).await
+}
+
+async fn fetch_params(
+    connection: &Connection,
+    messages: &mut Messages,
+    system_id: u8,
+    component_id: u8,
+) -> Result<Vec<(String, ParamValue)>, ProtocolError> {
+    run(
+        connection,
+        messages,
+        &mut ParamDownload::new(system_id, component_id),
+    )
+    .await
+}
+
+/// Drive a protocol state machine of the crate until it completes or fails
+async fn run<E: Exchange<Message = MavMessage>>(
+    connection: &Connection,
+    messages: &mut Messages,
+    exchange: &mut E,
+) -> Result<E::Output, E::Error> {
+    let send = |msg: &MavMessage| -> Result<(), E::Error> {
+        connection.send(&GCS, msg).map_err(ProtocolError::from)?;
+        Ok(())
+    };
+
+    send(&exchange.start())?;
+    let mut deadline = Instant::now() + exchange.timeout(DEFAULT_TIMEOUT);
+    loop {
+        let step = match next_message(messages, Some(deadline)).await {
+            Some((header, msg)) => exchange.handle(&header, &msg)?,
+            None => exchange.on_timeout()?,
+        };
+        match step {
+            Step::Send(msg) => {
+                send(&msg)?;
+                deadline = Instant::now() + exchange.timeout(DEFAULT_TIMEOUT);
+            }
+            Step::Progress => deadline = Instant::now() + exchange.timeout(DEFAULT_TIMEOUT),
+            Step::Wait => {}
+            Step::Done(last, output) => {
+                if let Some(msg) = last {
+                    send(&msg)?;
+                }
+                return Ok(output);
+            }
+        }
+    }
+}
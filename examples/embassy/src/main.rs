@@ -0,0 +1,159 @@
+//! Target board: stm32f303RETx (stm32nucleo)
+//!
+//! MAVLink 2 over USART2 on the embassy executor: heartbeats from one task, frames assembled
+//! byte by byte with the push parser and COMMAND_LONG answered with COMMAND_ACK from another.
+#![no_main]
+#![no_std]
+
+// Panic handler
+use panic_halt as _;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_executor::Spawner;
+use embassy_stm32::gpio::{Level, Output, Speed};
+use embassy_stm32::mode::Async;
+use embassy_stm32::usart::{self, Config, Uart, UartRx, UartTx};
+use embassy_stm32::{bind_interrupts, peripherals};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Ticker};
+use mavlink::common::{
+    MavAutopilot, MavCmd, MavMessage, MavModeFlag, MavResult, MavState, MavType, COMMAND_ACK_DATA,
+    COMMAND_LONG_DATA, HEARTBEAT_DATA,
+};
+use mavlink::parser::PushParser;
+use mavlink::{MAVLinkV2MessageRaw, MavHeader, MavlinkVersion};
+
+bind_interrupts!(struct Irqs {
+    USART2 => usart::InterruptHandler<peripherals::USART2>;
+});
+
+const SYSTEM_ID: u8 = 1;
+const COMPONENT_ID: u8 = 1;
+
+/// Transmit half of the UART, shared by the tasks
+static TX: Mutex<ThreadModeRawMutex, Option<Sender>> = Mutex::new(None);
+
+/// Whether a ground station armed the board
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Serializes messages with consecutive sequence numbers
+struct Sender {
+    tx: UartTx<'static, Async>,
+    sequence: u8,
+}
+
+impl Sender {
+    async fn send(&mut self, msg: &MavMessage) {
+        let header = MavHeader {
+            system_id: SYSTEM_ID,
+            component_id: COMPONENT_ID,
+            sequence: self.sequence,
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let mut frame = MAVLinkV2MessageRaw::new();
+        frame.serialize_message(header, msg);
+        // a lost frame is not worth stopping for, the ground station will ask again
+        let _ = self.tx.write(frame.raw_bytes()).await;
+    }
+}
+
+async fn send(msg: &MavMessage) {
+    if let Some(sender) = TX.lock().await.as_mut() {
+        sender.send(msg).await;
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+
+    // USART2 uses Pins A2 (TX) and A3 (RX), routed to the ST-LINK virtual COM port
+    let mut config = Config::default();
+    config.baudrate = 115_200;
+    let uart = Uart::new(p.USART2, p.PA3, p.PA2, Irqs, p.DMA1_CH7, p.DMA1_CH6, config).unwrap();
+    let (tx, rx) = uart.split();
+    *TX.lock().await = Some(Sender { tx, sequence: 0 });
+
+    // stm32nucleo has a LED on pin PA5, lit while armed
+    let led = Output::new(p.PA5, Level::Low, Speed::Low);
+
+    spawner.spawn(heartbeat_task()).unwrap();
+    spawner.spawn(receive_task(rx, led)).unwrap();
+}
+
+#[embassy_executor::task]
+async fn heartbeat_task() {
+    let mut ticker = Ticker::every(Duration::from_secs(1));
+    loop {
+        let base_mode = if ARMED.load(Ordering::Relaxed) {
+            MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED
+        } else {
+            MavModeFlag::empty()
+        };
+        send(&MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode: 0,
+            mavtype: MavType::MAV_TYPE_SUBMARINE,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_GENERIC,
+            base_mode,
+            system_status: MavState::MAV_STATE_STANDBY,
+            mavlink_version: 0x3,
+        }))
+        .await;
+        ticker.next().await;
+    }
+}
+
+#[embassy_executor::task]
+async fn receive_task(mut rx: UartRx<'static, Async>, mut led: Output<'static>) {
+    let mut parser = PushParser::new(MavlinkVersion::V2);
+    let mut buf = [0u8; 64];
+    loop {
+        // returns what arrived once the line goes idle, frames may span several reads
+        let len = match rx.read_until_idle(&mut buf).await {
+            Ok(len) => len,
+            Err(_) => {
+                parser.reset();
+                continue;
+            }
+        };
+        for byte in &buf[..len] {
+            // messages that didn't get through due to parser errors are ignored
+            if let Some(Ok((_header, msg))) = parser.push::<MavMessage>(*byte) {
+                if let MavMessage::COMMAND_LONG(command) = msg {
+                    handle_command(&command, &mut led).await;
+                }
+            }
+        }
+    }
+}
+
+async fn handle_command(command: &COMMAND_LONG_DATA, led: &mut Output<'_>) {
+    if (command.target_system != 0 && command.target_system != SYSTEM_ID)
+        || (command.target_component != 0 && command.target_component != COMPONENT_ID)
+    {
+        return;
+    }
+
+    let result = match command.command {
+        MavCmd::MAV_CMD_COMPONENT_ARM_DISARM => {
+            let armed = command.param1 == 1.0;
+            ARMED.store(armed, Ordering::Relaxed);
+            if armed {
+                led.set_high();
+            } else {
+                led.set_low();
+            }
+            MavResult::MAV_RESULT_ACCEPTED
+        }
+        _ => MavResult::MAV_RESULT_UNSUPPORTED,
+    };
+
+    send(&MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+        command: command.command,
+        result,
+    }))
+    .await;
+}
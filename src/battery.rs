@@ -0,0 +1,238 @@
+//! Aggregation of the battery and power supply state of a vehicle.
+//!
+//! Batteries are reported in SYS_STATUS (only the main battery, with little detail) and in one
+//! BATTERY_STATUS per battery. [`PowerMonitor`] merges both into one [`BatteryState`] per battery
+//! with the invalid markers resolved and units converted to SI, tracks POWER_STATUS, and reports
+//! what changed as [`PowerEvent`]s.
+
+use core::convert::TryFrom;
+use std::collections::BTreeMap;
+#[cfg(feature = "emit-extensions")]
+use std::time::Duration;
+
+#[cfg(feature = "emit-extensions")]
+use crate::common::MavBatteryChargeState;
+use crate::common::{
+    MavBatteryFunction, MavBatteryType, MavMessage, MavPowerStatus, BATTERY_STATUS_DATA,
+    SYS_STATUS_DATA,
+};
+
+/// Where the state of a battery comes from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BatterySource {
+    /// Only SYS_STATUS was received, which describes the main battery
+    SysStatus,
+    BatteryStatus,
+}
+
+/// The state of one battery, `None` where the vehicle does not know the value
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryState {
+    pub id: u8,
+    pub source: BatterySource,
+    pub function: MavBatteryFunction,
+    pub battery_type: MavBatteryType,
+    /// Total voltage in V
+    pub voltage: Option<f32>,
+    /// Current in A, positive when discharging
+    pub current: Option<f32>,
+    /// Remaining capacity in percent
+    pub remaining: Option<u8>,
+    /// Consumed charge in mAh
+    pub consumed: Option<f32>,
+    /// Consumed energy in J
+    pub energy_consumed: Option<f32>,
+    /// Temperature in °C
+    pub temperature: Option<f32>,
+    /// Voltage of each cell in V, empty if the cells are not measured
+    pub cell_voltages: Vec<f32>,
+    #[cfg(feature = "emit-extensions")]
+    pub time_remaining: Option<Duration>,
+    #[cfg(feature = "emit-extensions")]
+    pub charge_state: MavBatteryChargeState,
+}
+
+impl BatteryState {
+    fn from_sys_status(sys_status: &SYS_STATUS_DATA) -> Self {
+        Self {
+            id: 0,
+            source: BatterySource::SysStatus,
+            function: MavBatteryFunction::DEFAULT,
+            battery_type: MavBatteryType::DEFAULT,
+            voltage: Some(sys_status.voltage_battery)
+                .filter(|voltage| *voltage != u16::MAX)
+                .map(|voltage| f32::from(voltage) / 1000.0),
+            current: current(sys_status.current_battery),
+            remaining: remaining(sys_status.battery_remaining),
+            consumed: None,
+            energy_consumed: None,
+            temperature: None,
+            cell_voltages: vec![],
+            #[cfg(feature = "emit-extensions")]
+            time_remaining: None,
+            #[cfg(feature = "emit-extensions")]
+            charge_state: MavBatteryChargeState::DEFAULT,
+        }
+    }
+
+    fn from_battery_status(status: &BATTERY_STATUS_DATA) -> Self {
+        let cells = cells(status);
+
+        // without cell voltages the total voltage is sent as the only cell
+        let (voltage, cell_voltages) = match cells.len() {
+            0 => (None, vec![]),
+            1 => (Some(f32::from(cells[0]) / 1000.0), vec![]),
+            _ => {
+                let cell_voltages: Vec<f32> = cells
+                    .iter()
+                    .map(|voltage| f32::from(*voltage) / 1000.0)
+                    .collect();
+                (Some(cell_voltages.iter().sum()), cell_voltages)
+            }
+        };
+
+        Self {
+            id: status.id,
+            source: BatterySource::BatteryStatus,
+            function: status.battery_function,
+            battery_type: status.mavtype,
+            voltage,
+            current: current(status.current_battery),
+            remaining: remaining(status.battery_remaining),
+            consumed: Some(status.current_consumed)
+                .filter(|consumed| *consumed != -1)
+                .map(|consumed| consumed as f32),
+            energy_consumed: Some(status.energy_consumed)
+                .filter(|energy| *energy != -1)
+                .map(|energy| energy as f32 * 100.0),
+            temperature: Some(status.temperature)
+                .filter(|temperature| *temperature != i16::MAX)
+                .map(|temperature| f32::from(temperature) / 100.0),
+            cell_voltages,
+            #[cfg(feature = "emit-extensions")]
+            time_remaining: Some(status.time_remaining)
+                .filter(|seconds| *seconds > 0)
+                .map(|seconds| Duration::from_secs(seconds as u64)),
+            #[cfg(feature = "emit-extensions")]
+            charge_state: status.charge_state,
+        }
+    }
+}
+
+/// Cell voltages in mV, up to the first unused cell
+fn cells(status: &BATTERY_STATUS_DATA) -> Vec<u16> {
+    let cells = status
+        .voltages
+        .iter()
+        .copied()
+        .take_while(|voltage| *voltage != u16::MAX);
+    // cells 11 to 14 follow in the extension, unused ones are 0
+    #[cfg(feature = "emit-extensions")]
+    let cells = {
+        let all_used = status.voltages.iter().all(|voltage| *voltage != u16::MAX);
+        cells.chain(
+            status
+                .voltages_ext
+                .iter()
+                .copied()
+                .take_while(move |voltage| all_used && *voltage != 0),
+        )
+    };
+    cells.collect()
+}
+
+fn current(centiamperes: i16) -> Option<f32> {
+    Some(centiamperes)
+        .filter(|current| *current != -1)
+        .map(|current| f32::from(current) / 100.0)
+}
+
+fn remaining(percent: i8) -> Option<u8> {
+    u8::try_from(percent).ok()
+}
+
+/// Supply voltages of the flight controller from POWER_STATUS
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PowerState {
+    /// 5V rail voltage in V
+    pub vcc: f32,
+    /// Servo rail voltage in V
+    pub vservo: f32,
+    pub flags: MavPowerStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PowerEvent {
+    /// First report of a battery
+    BatteryAdded(BatteryState),
+    BatteryChanged(BatteryState),
+    PowerChanged(PowerState),
+}
+
+/// Battery and power supply state of one vehicle.
+///
+/// Feed it the messages of a single system, SYS_STATUS is only used for the main battery as long
+/// as no BATTERY_STATUS with id 0 was received.
+#[derive(Debug, Clone, Default)]
+pub struct PowerMonitor {
+    batteries: BTreeMap<u8, BatteryState>,
+    power: Option<PowerState>,
+}
+
+impl PowerMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a received message, returning what changed
+    pub fn update(&mut self, msg: &MavMessage) -> Option<PowerEvent> {
+        match msg {
+            MavMessage::SYS_STATUS(sys_status) => {
+                if matches!(
+                    self.batteries.get(&0),
+                    Some(battery) if battery.source == BatterySource::BatteryStatus
+                ) {
+                    return None;
+                }
+                self.update_battery(BatteryState::from_sys_status(sys_status))
+            }
+            MavMessage::BATTERY_STATUS(status) => {
+                self.update_battery(BatteryState::from_battery_status(status))
+            }
+            MavMessage::POWER_STATUS(status) => {
+                let power = PowerState {
+                    vcc: f32::from(status.Vcc) / 1000.0,
+                    vservo: f32::from(status.Vservo) / 1000.0,
+                    flags: status.flags,
+                };
+                if self.power == Some(power) {
+                    return None;
+                }
+                self.power = Some(power);
+                Some(PowerEvent::PowerChanged(power))
+            }
+            _ => None,
+        }
+    }
+
+    fn update_battery(&mut self, battery: BatteryState) -> Option<PowerEvent> {
+        match self.batteries.insert(battery.id, battery.clone()) {
+            None => Some(PowerEvent::BatteryAdded(battery)),
+            Some(previous) if previous != battery => Some(PowerEvent::BatteryChanged(battery)),
+            Some(_) => None,
+        }
+    }
+
+    pub fn battery(&self, id: u8) -> Option<&BatteryState> {
+        self.batteries.get(&id)
+    }
+
+    /// All known batteries, ordered by id
+    pub fn batteries(&self) -> impl Iterator<Item = &BatteryState> {
+        self.batteries.values()
+    }
+
+    pub fn power(&self) -> Option<&PowerState> {
+        self.power.as_ref()
+    }
+}
@@ -0,0 +1,265 @@
+//! Live view of the traffic on a MAVLink connection: the rate, bandwidth and last value of every
+//! message, per sending system and component.
+//!
+//! `mavinspect latency` measures the round trip time of the link instead.
+
+#[cfg(feature = "std")]
+use std::{
+    collections::BTreeMap,
+    env,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "std")]
+use mavlink::{
+    ardupilotmega::MavMessage,
+    error::MessageReadError,
+    latency::{LatencyMeter, ProbeKind},
+    stats::{message_frame_size, TrafficSnapshot, TrafficStats},
+    MavHeader, Message,
+};
+
+#[cfg(not(feature = "std"))]
+fn main() {}
+
+/// Longest shown part of the last value of a message
+#[cfg(feature = "std")]
+const LAST_VALUE_LEN: usize = 80;
+
+/// Last value of one message from one component
+#[cfg(feature = "std")]
+struct MessageInfo {
+    name: &'static str,
+    total: u64,
+    last: String,
+}
+
+#[cfg(feature = "std")]
+struct Stats {
+    traffic: TrafficStats,
+    /// Traffic of the last refresh period
+    snapshot: TrafficSnapshot,
+    /// Keyed by system id, component id and message id
+    messages: BTreeMap<(u8, u8, u32), MessageInfo>,
+    errors: u64,
+}
+
+#[cfg(feature = "std")]
+impl Stats {
+    fn new(now: Instant) -> Self {
+        Self {
+            traffic: TrafficStats::new(now),
+            snapshot: TrafficSnapshot::default(),
+            messages: BTreeMap::new(),
+            errors: 0,
+        }
+    }
+
+    fn record(&mut self, header: &MavHeader, msg: &MavMessage, len: usize) {
+        self.traffic.record(header, msg.message_id(), len);
+        let info = self
+            .messages
+            .entry((header.system_id, header.component_id, msg.message_id()))
+            .or_insert(MessageInfo {
+                name: msg.message_name(),
+                total: 0,
+                last: String::new(),
+            });
+        info.total += 1;
+        info.last = format!("{msg:?}");
+    }
+
+    /// Turn the counts since the last refresh into rates
+    fn update_rates(&mut self, now: Instant) {
+        self.snapshot = self.traffic.take_snapshot(now);
+    }
+
+    fn print(&self, address: &str, uptime: Duration) {
+        let total = self.snapshot.total();
+
+        // clear the terminal and move to the top left corner
+        print!("\x1B[2J\x1B[H");
+        println!(
+            "{address}  up {}s  {:.1} msg/s  {:.2} kB/s  {} parse errors",
+            uptime.as_secs(),
+            total.rate,
+            total.bandwidth / 1000.0,
+            self.errors,
+        );
+
+        let mut component = None;
+        for ((system_id, component_id, message_id), info) in &self.messages {
+            if component != Some((*system_id, *component_id)) {
+                component = Some((*system_id, *component_id));
+                let traffic = self.snapshot.component(*system_id, *component_id);
+                println!();
+                println!(
+                    "system {system_id} component {component_id}: {:.1} msg/s {:.0} B/s",
+                    traffic.rate, traffic.bandwidth
+                );
+                println!(
+                    "  {:>5} {:<32} {:>8} {:>8} {:>8}  last value",
+                    "id", "message", "Hz", "B/s", "total"
+                );
+            }
+            let traffic = self
+                .snapshot
+                .get(*system_id, *component_id, *message_id)
+                .copied()
+                .unwrap_or_default();
+            let mut last = info.last.clone();
+            if let Some((index, _)) = last.char_indices().nth(LAST_VALUE_LEN) {
+                last.truncate(index);
+                last.push_str("...");
+            }
+            println!(
+                "  {:>5} {:<32} {:>8.1} {:>8.0} {:>8}  {}",
+                message_id, info.name, traffic.rate, traffic.bandwidth, info.total, last
+            );
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+enum Received {
+    Message(MavHeader, Box<MavMessage>, usize),
+    ParseError,
+}
+
+/// Send TIMESYNC or PING probes and print the round trip statistics
+#[cfg(feature = "std")]
+fn latency(args: &[String]) {
+    if args.is_empty() {
+        println!(
+            "Usage: mavinspect latency (tcpout|tcpin|udpout|udpin|udpbcast|serial):(ip|dev):(port|baud) [probe count] [timesync|ping]"
+        );
+        return;
+    }
+    let address = &args[0];
+    let count = match args.get(1).map(|count| count.parse::<u32>()) {
+        None => 20,
+        Some(Ok(count)) => count,
+        Some(Err(_)) => {
+            println!("Invalid probe count {}", args[1]);
+            return;
+        }
+    };
+    let kind = match args.get(2).map(String::as_str) {
+        None | Some("timesync") => ProbeKind::Timesync,
+        Some("ping") => ProbeKind::Ping,
+        Some(kind) => {
+            println!("Unknown probe {kind}, expected timesync or ping");
+            return;
+        }
+    };
+
+    let connection = match mavlink::connect::<MavMessage>(address) {
+        Ok(connection) => connection,
+        Err(e) => {
+            println!("Failed to connect to {address}: {e}");
+            return;
+        }
+    };
+    let header = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+    match LatencyMeter::new(connection.as_ref())
+        .with_header(header)
+        .with_kind(kind)
+        .measure(count)
+    {
+        Ok(stats) => println!("{address}: {stats}"),
+        Err(e) => println!("{e}"),
+    }
+}
+
+#[cfg(feature = "std")]
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() >= 2 && args[1] == "latency" {
+        latency(&args[2..]);
+        return;
+    }
+    if args.len() < 2 {
+        println!(
+            "Usage: mavinspect (tcpout|tcpin|udpout|udpin|udpbcast|serial|file):(ip|dev|path):(port|baud) [refresh interval in s]"
+        );
+        println!("       mavinspect latency (address) [probe count] [timesync|ping]");
+        return;
+    }
+    let address = args[1].clone();
+    let interval = match args.get(2).map(|interval| interval.parse::<f64>()) {
+        None => Duration::from_secs(1),
+        Some(Ok(seconds)) if seconds > 0.0 => Duration::from_secs_f64(seconds),
+        Some(_) => {
+            println!("Invalid refresh interval {}", args[2]);
+            return;
+        }
+    };
+
+    let connection = match mavlink::connect::<MavMessage>(&address) {
+        Ok(connection) => connection,
+        Err(e) => {
+            println!("Failed to connect to {address}: {e}");
+            return;
+        }
+    };
+
+    // receive on a separate thread so the table is refreshed on a silent link too
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let received = match connection.recv() {
+            Ok((header, msg)) => {
+                let len = message_frame_size(connection.get_protocol_version(), &msg);
+                Received::Message(header, Box::new(msg), len)
+            }
+            Err(MessageReadError::Io(e)) => {
+                if let std::io::ErrorKind::WouldBlock = e.kind() {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+                println!("recv error: {e:?}");
+                return;
+            }
+            Err(MessageReadError::Parse(_)) => Received::ParseError,
+        };
+        if sender.send(received).is_err() {
+            return;
+        }
+    });
+
+    let start = Instant::now();
+    let mut stats = Stats::new(start);
+    let mut last_refresh = start;
+    loop {
+        let timeout = (last_refresh + interval).saturating_duration_since(Instant::now());
+        let disconnected = match receiver.recv_timeout(timeout) {
+            Ok(Received::Message(header, msg, len)) => {
+                stats.record(&header, &msg, len);
+                false
+            }
+            Ok(Received::ParseError) => {
+                stats.errors += 1;
+                false
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => false,
+            Err(mpsc::RecvTimeoutError::Disconnected) => true,
+        };
+
+        // the table is shown a last time when the connection ends, e.g. at the end of a file
+        let now = Instant::now();
+        if now >= last_refresh + interval || disconnected {
+            stats.update_rates(now);
+            stats.print(&address, now - start);
+            last_refresh = now;
+        }
+        if disconnected {
+            break;
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Conversion of telemetry logs into JSON Lines or CSV.
+//!
+//! Reads tlog files, where every frame is preceded by its reception time as big endian
+//! microseconds since the Unix epoch, or raw dumps of MAVLink 1 and 2 frames. JSON Lines output
+//! holds one object per message, CSV output one file per message type with one column per
+//! field, array elements and nested values flattened into their own columns.
+
+#[cfg(feature = "std")]
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+#[cfg(feature = "std")]
+use mavlink::{
+    ardupilotmega::MavMessage, error::MessageReadError, MavHeader, MavlinkVersion, Message,
+};
+#[cfg(feature = "std")]
+use serde_json::{json, Map, Value};
+
+#[cfg(not(feature = "std"))]
+fn main() {}
+
+#[cfg(feature = "std")]
+const MAV_STX_V1: u8 = 0xFE;
+#[cfg(feature = "std")]
+const MAV_STX_V2: u8 = 0xFD;
+
+#[cfg(feature = "std")]
+struct Record {
+    /// Reception time in microseconds since the Unix epoch, only known for tlogs
+    timestamp: Option<u64>,
+    header: MavHeader,
+    msg: MavMessage,
+}
+
+/// Outcome of parsing the frame at the start of a buffer
+#[cfg(feature = "std")]
+enum Frame {
+    /// The message and the length of its frame
+    Parsed(MavHeader, Box<MavMessage>, usize),
+    /// The buffer ends within the frame
+    Incomplete,
+    /// No valid frame starts here
+    Invalid,
+}
+
+#[cfg(feature = "std")]
+fn parse_frame(data: &[u8]) -> Frame {
+    let mut reader = data;
+    let parsed = match data.first() {
+        None => return Frame::Incomplete,
+        Some(&MAV_STX_V1) => mavlink::read_v1_raw_message(&mut reader).map(|raw| {
+            let header = MavHeader {
+                sequence: raw.sequence(),
+                system_id: raw.system_id(),
+                component_id: raw.component_id(),
+            };
+            let msg = if raw.has_valid_crc::<MavMessage>() {
+                let id = u32::from(raw.message_id());
+                MavMessage::parse(MavlinkVersion::V1, id, raw.payload()).ok()
+            } else {
+                None
+            };
+            msg.map(|msg| (header, msg))
+        }),
+        Some(&MAV_STX_V2) => mavlink::read_v2_raw_message(&mut reader).map(|raw| {
+            let header = MavHeader {
+                sequence: raw.sequence(),
+                system_id: raw.system_id(),
+                component_id: raw.component_id(),
+            };
+            let msg = if raw.has_valid_crc::<MavMessage>() {
+                MavMessage::parse(MavlinkVersion::V2, raw.message_id(), raw.payload()).ok()
+            } else {
+                None
+            };
+            msg.map(|msg| (header, msg))
+        }),
+        Some(_) => return Frame::Invalid,
+    };
+
+    match parsed {
+        Ok(Some((header, msg))) => Frame::Parsed(header, Box::new(msg), data.len() - reader.len()),
+        Ok(None) => Frame::Invalid,
+        Err(MessageReadError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Frame::Incomplete
+        }
+        Err(_) => Frame::Invalid,
+    }
+}
+
+/// Reads the frames of a tlog or raw dump held in memory.
+///
+/// Frames are checked one at a time rather than with the stream readers, which skip ahead to
+/// the next start byte after a bad frame and would lose the frames in between.
+#[cfg(feature = "std")]
+struct LogReader {
+    data: Vec<u8>,
+    position: usize,
+    tlog: bool,
+}
+
+#[cfg(feature = "std")]
+impl LogReader {
+    fn next_record(&mut self) -> io::Result<Option<Record>> {
+        loop {
+            let timestamp = if self.tlog {
+                let bytes = match self.data.get(self.position..self.position + 8) {
+                    Some(bytes) => bytes,
+                    None => return Ok(None),
+                };
+                self.position += 8;
+                Some(u64::from_be_bytes(bytes.try_into().unwrap()))
+            } else {
+                None
+            };
+
+            match parse_frame(&self.data[self.position..]) {
+                Frame::Parsed(header, msg, len) => {
+                    self.position += len;
+                    return Ok(Some(Record {
+                        timestamp,
+                        header,
+                        msg: *msg,
+                    }));
+                }
+                Frame::Incomplete => return Ok(None),
+                Frame::Invalid if self.tlog => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("No valid frame at offset {}", self.position),
+                    ))
+                }
+                // garbage between the frames of a raw dump
+                Frame::Invalid => self.position += 1,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn to_json(record: &Record) -> Value {
+    let mut value = json!({
+        "header": record.header,
+        "message": record.msg,
+    });
+    if let Some(timestamp) = record.timestamp {
+        value["timestamp"] = json!(timestamp);
+    }
+    value
+}
+
+/// Add the leaves of a JSON value as columns, named by their path
+#[cfg(feature = "std")]
+fn flatten(prefix: &str, value: &Value, columns: &mut Vec<(String, String)>) {
+    match value {
+        // enum values are tagged with their name
+        Value::Object(map) if map.len() == 1 && map.contains_key("type") => {
+            flatten(prefix, &map["type"], columns)
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&join(prefix, key), value, columns);
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                flatten(&join(prefix, &index.to_string()), value, columns);
+            }
+        }
+        Value::String(value) => columns.push((prefix.to_string(), value.clone())),
+        Value::Null => columns.push((prefix.to_string(), String::new())),
+        value => columns.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+#[cfg(feature = "std")]
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+#[cfg(feature = "std")]
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One CSV file per message type, created when the first message of the type is seen
+#[cfg(feature = "std")]
+struct CsvWriter<'a> {
+    directory: &'a Path,
+    files: BTreeMap<&'static str, (BufWriter<File>, Vec<String>)>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> CsvWriter<'a> {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        let mut columns = Vec::new();
+        if let Some(timestamp) = record.timestamp {
+            columns.push(("timestamp".to_string(), timestamp.to_string()));
+        }
+        let header = &record.header;
+        columns.push(("system_id".to_string(), header.system_id.to_string()));
+        columns.push(("component_id".to_string(), header.component_id.to_string()));
+        columns.push(("sequence".to_string(), header.sequence.to_string()));
+
+        let mut message = match serde_json::to_value(&record.msg)? {
+            Value::Object(message) => message,
+            _ => Map::new(),
+        };
+        // the tag naming the message is the file name already
+        message.remove("type");
+        flatten("", &Value::Object(message), &mut columns);
+
+        let name = record.msg.message_name();
+        let (file, names) = match self.files.get_mut(name) {
+            Some(file) => file,
+            None => {
+                let path = self.directory.join(format!("{name}.csv"));
+                let mut file = BufWriter::new(File::create(path)?);
+                let names: Vec<String> = columns.iter().map(|(name, _)| name.clone()).collect();
+                let row: Vec<String> = names.iter().map(|name| csv_field(name)).collect();
+                writeln!(file, "{}", row.join(","))?;
+                self.files.entry(name).or_insert((file, names))
+            }
+        };
+
+        // the columns are fixed by the first message, which only differs if the log mixes
+        // messages with and without extension fields
+        let row: Vec<String> = names
+            .iter()
+            .map(|name| {
+                columns
+                    .iter()
+                    .find(|(column, _)| column == name)
+                    .map(|(_, value)| csv_field(value))
+                    .unwrap_or_default()
+            })
+            .collect();
+        writeln!(file, "{}", row.join(","))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for (file, _) in self.files.values_mut() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn convert(input: &str, tlog: bool, format: &str, output: Option<&str>) -> io::Result<()> {
+    let mut reader = LogReader {
+        data: fs::read(input)?,
+        position: 0,
+        tlog,
+    };
+
+    match format {
+        "jsonl" => {
+            let mut out: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout())),
+            };
+            while let Some(record) = reader.next_record()? {
+                serde_json::to_writer(&mut out, &to_json(&record))?;
+                writeln!(out)?;
+            }
+            out.flush()
+        }
+        "csv" => {
+            let directory = Path::new(output.unwrap_or("."));
+            fs::create_dir_all(directory)?;
+            let mut writer = CsvWriter {
+                directory,
+                files: BTreeMap::new(),
+            };
+            while let Some(record) = reader.next_record()? {
+                writer.write(&record)?;
+            }
+            writer.flush()
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(feature = "std")]
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    let usage = || {
+        println!("Usage: mavlink-convert (tlog|raw) <input> (jsonl|csv) [output]");
+        println!("  jsonl writes to the output file or stdout,");
+        println!("  csv writes one file per message into the output directory");
+    };
+    if args.len() < 4 || args.len() > 5 {
+        usage();
+        return;
+    }
+    let tlog = match args[1].as_str() {
+        "tlog" => true,
+        "raw" => false,
+        _ => return usage(),
+    };
+    if !matches!(args[3].as_str(), "jsonl" | "csv") {
+        return usage();
+    }
+
+    if let Err(e) = convert(
+        &args[2],
+        tlog,
+        &args[3],
+        args.get(4).map(|output| output.as_str()),
+    ) {
+        eprintln!("conversion failed: {e}");
+        std::process::exit(1);
+    }
+}
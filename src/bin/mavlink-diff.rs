@@ -0,0 +1,85 @@
+//! Comparison of two telemetry logs, e.g. recorded before and after a firmware change.
+//!
+//! Prints the messages whose rate changed or whose values differ, see `mavlink::diff`, and exits
+//! with status 1 if there are any, so it can guard regression tests.
+
+#[cfg(feature = "std")]
+use std::{env, fs::File, io::BufReader, process, time::Duration};
+
+#[cfg(feature = "std")]
+use mavlink::{ardupilotmega::MavMessage, diff::Differ, recording::Recording, MavlinkVersion};
+
+#[cfg(not(feature = "std"))]
+fn main() {}
+
+#[cfg(feature = "std")]
+const USAGE: &str = "Usage: mavlink-diff [options] <before.tlog> <after.tlog>
+
+Options:
+  --v1                  the logs hold MAVLink 1 frames
+  --skew MS             largest time between compared messages, default 100
+  --tolerance X         largest numeric difference not reported, default 0
+  --rate-tolerance X    largest relative rate change not reported, default 0.1";
+
+#[cfg(feature = "std")]
+fn parse_args(args: &[String]) -> Result<(Differ, MavlinkVersion, String, String), String> {
+    let mut differ = Differ::new();
+    let mut version = MavlinkVersion::V2;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or(format!("{name} needs a number"))
+        };
+        match arg.as_str() {
+            "--v1" => version = MavlinkVersion::V1,
+            "--skew" => {
+                differ = differ.with_max_skew(Duration::from_secs_f64(value("--skew")? / 1000.0))
+            }
+            "--tolerance" => differ = differ.with_tolerance(value("--tolerance")?),
+            "--rate-tolerance" => differ = differ.with_rate_tolerance(value("--rate-tolerance")?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+            path => paths.push(path.to_string()),
+        }
+    }
+
+    match (paths.pop(), paths.pop(), paths.is_empty()) {
+        (Some(after), Some(before), true) => Ok((differ, version, before, after)),
+        _ => Err("Expected two logs".to_string()),
+    }
+}
+
+#[cfg(feature = "std")]
+fn read(path: &str, version: MavlinkVersion) -> Recording<MavMessage> {
+    let recording =
+        File::open(path).and_then(|file| Recording::read_tlog(&mut BufReader::new(file), version));
+    match recording {
+        Ok(recording) => recording,
+        Err(e) => {
+            eprintln!("reading {path} failed: {e}");
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    let (differ, version, before, after) = match parse_args(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            println!("{e}");
+            println!("{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let diff = differ.diff(&read(&before, version), &read(&after, version));
+    print!("{diff}");
+    if !diff.is_empty() {
+        process::exit(1);
+    }
+}
@@ -1,23 +1,237 @@
+//! Print the messages received on a connection, as debug output, JSON Lines or CSV, or only
+//! their rates.
+//!
+//! `--json` and `--csv` need the `json` feature.
+
 use mavlink::error::MessageReadError;
 #[cfg(feature = "std")]
-use std::{env, sync::Arc, thread, time::Duration};
+use mavlink::{
+    ardupilotmega::MavMessage,
+    stats::{message_frame_size, TrafficStats},
+    MavHeader, Message,
+};
+#[cfg(all(feature = "std", feature = "json"))]
+use std::collections::BTreeSet;
+#[cfg(feature = "std")]
+use std::{
+    collections::HashSet,
+    env,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
 #[cfg(not(feature = "std"))]
 fn main() {}
 
 #[cfg(feature = "std")]
-fn main() {
-    let args: Vec<_> = env::args().collect();
+const USAGE: &str = "Usage: mavlink-dump [options] (tcpout|tcpin|udpout|udpin|udpbcast|serial|file):(ip|dev|path):(port|baud)
+
+Options:
+  --filter NAME,...  only show these messages, e.g. HEARTBEAT,ATTITUDE
+  --sysid ID         only show messages of this system
+  --json             print one JSON object per message
+  --csv              print CSV rows, with a header row before the first message of every type
+  --rate-only        print the rate and bandwidth of every message once per second";
 
-    if args.len() < 2 {
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Debug,
+    Json,
+    Csv,
+    RateOnly,
+}
+
+#[cfg(feature = "std")]
+struct Options {
+    address: String,
+    /// Ids of the shown messages, all if empty
+    filter: HashSet<u32>,
+    system_id: Option<u8>,
+    format: Format,
+}
+
+#[cfg(feature = "std")]
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut address = None;
+        let mut filter = HashSet::new();
+        let mut system_id = None;
+        let mut format = Format::Debug;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--filter" => {
+                    let names = args.next().ok_or("--filter needs message names")?;
+                    for name in names.split(',').filter(|name| !name.is_empty()) {
+                        let id = MavMessage::message_id_from_name(&name.to_uppercase())
+                            .map_err(|_| format!("Unknown message {name}"))?;
+                        filter.insert(id);
+                    }
+                }
+                "--sysid" => {
+                    let id = args.next().ok_or("--sysid needs a system id")?;
+                    system_id = Some(id.parse().map_err(|_| format!("Invalid system id {id}"))?);
+                }
+                "--json" => format = Format::Json,
+                "--csv" => format = Format::Csv,
+                "--rate-only" => format = Format::RateOnly,
+                flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+                _ if address.is_some() => return Err(format!("Unexpected argument {arg}")),
+                _ => address = Some(arg.clone()),
+            }
+        }
+
+        #[cfg(not(feature = "json"))]
+        if matches!(format, Format::Json | Format::Csv) {
+            return Err("--json and --csv need the json feature".to_string());
+        }
+
+        Ok(Self {
+            address: address.ok_or("Missing connection address")?,
+            filter,
+            system_id,
+            format,
+        })
+    }
+
+    fn shows(&self, header: &MavHeader, msg: &MavMessage) -> bool {
+        self.system_id.map_or(true, |id| id == header.system_id)
+            && (self.filter.is_empty() || self.filter.contains(&msg.message_id()))
+    }
+}
+
+#[cfg(feature = "std")]
+struct Printer {
+    format: Format,
+    /// Message types whose CSV header row was printed
+    #[cfg(feature = "json")]
+    csv_headers: BTreeSet<u32>,
+    traffic: TrafficStats,
+    last_rates: Instant,
+}
+
+#[cfg(feature = "std")]
+impl Printer {
+    fn new(format: Format) -> Self {
+        let now = Instant::now();
+        Self {
+            format,
+            #[cfg(feature = "json")]
+            csv_headers: BTreeSet::new(),
+            traffic: TrafficStats::new(now),
+            last_rates: now,
+        }
+    }
+
+    fn print(&mut self, header: &MavHeader, msg: &MavMessage, len: usize) {
+        match self.format {
+            Format::Debug => println!("received: {msg:?}"),
+            #[cfg(feature = "json")]
+            Format::Json => println!(
+                "{}",
+                serde_json::json!({ "header": header, "message": msg })
+            ),
+            #[cfg(feature = "json")]
+            Format::Csv => self.print_csv(header, msg),
+            Format::RateOnly => self.traffic.record(header, msg.message_id(), len),
+            #[cfg(not(feature = "json"))]
+            Format::Json | Format::Csv => unreachable!(),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn print_csv(&mut self, header: &MavHeader, msg: &MavMessage) {
+        use serde_json::Value;
+
+        let fields = match serde_json::to_value(msg) {
+            Ok(Value::Object(mut fields)) => {
+                // the tag is the first column already
+                fields.remove("type");
+                fields
+            }
+            _ => return,
+        };
+
+        if self.csv_headers.insert(msg.message_id()) {
+            let names: Vec<_> = fields.keys().map(|name| csv_field(name)).collect();
+            println!(
+                "message,system_id,component_id,sequence,{}",
+                names.join(",")
+            );
+        }
+        let values: Vec<_> = fields
+            .values()
+            .map(|value| match value {
+                Value::String(value) => csv_field(value),
+                // enum values are tagged with their name
+                Value::Object(map) if map.len() == 1 && map.contains_key("type") => {
+                    csv_field(map["type"].as_str().unwrap_or_default())
+                }
+                // bitflags are wrapped in their bits
+                Value::Object(map) if map.len() == 1 && map.contains_key("bits") => {
+                    map["bits"].to_string()
+                }
+                Value::Null => String::new(),
+                value => csv_field(&value.to_string()),
+            })
+            .collect();
         println!(
-            "Usage: mavlink-dump (tcpout|tcpin|udpout|udpin|udpbcast|serial|file):(ip|dev|path):(port|baud)"
+            "{},{},{},{},{}",
+            msg.message_name(),
+            header.system_id,
+            header.component_id,
+            header.sequence,
+            values.join(",")
         );
-        return;
     }
 
+    /// Print the rates of the last second, if it passed
+    fn print_rates(&mut self) {
+        let now = Instant::now();
+        if self.format != Format::RateOnly || now < self.last_rates + Duration::from_secs(1) {
+            return;
+        }
+        self.last_rates = now;
+        let snapshot = self.traffic.take_snapshot(now);
+        let total = snapshot.total();
+        println!("{:.1} msg/s {:.0} B/s", total.rate, total.bandwidth);
+        for ((system_id, component_id, message_id), traffic) in &snapshot.entries {
+            let name = MavMessage::default_message_from_id(*message_id)
+                .map_or("UNKNOWN", |msg| msg.message_name());
+            println!(
+                "  {system_id:>3}:{component_id:<3} {name:<32} {:>8.1} Hz {:>8.0} B/s",
+                traffic.rate, traffic.bandwidth
+            );
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "json"))]
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(feature = "std")]
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{e}");
+            println!("{USAGE}");
+            return;
+        }
+    };
+
     // It's possible to change the mavlink dialect to be used in the connect call
-    let mut mavconn = mavlink::connect::<mavlink::ardupilotmega::MavMessage>(&args[1]).unwrap();
+    let mut mavconn = mavlink::connect::<MavMessage>(&options.address).unwrap();
 
     // here as an example we force the protocol version to mavlink V1:
     // the default for this library is mavlink V2
@@ -27,9 +241,14 @@ fn main() {
     vehicle
         .send(&mavlink::MavHeader::default(), &request_parameters())
         .unwrap();
-    vehicle
-        .send(&mavlink::MavHeader::default(), &request_stream())
-        .unwrap();
+    mavlink::telemetry::request_telemetry(
+        &**vehicle,
+        &mavlink::MavHeader::default(),
+        0,
+        0,
+        mavlink::telemetry::TelemetryProfile::All,
+    )
+    .unwrap();
 
     thread::spawn({
         let vehicle = vehicle.clone();
@@ -43,16 +262,19 @@ fn main() {
         }
     });
 
+    let mut printer = Printer::new(options.format);
     loop {
         match vehicle.recv() {
-            Ok((_header, msg)) => {
-                println!("received: {msg:?}");
+            Ok((header, msg)) => {
+                if options.shows(&header, &msg) {
+                    let len = message_frame_size(vehicle.get_protocol_version(), &msg);
+                    printer.print(&header, &msg, len);
+                }
             }
             Err(MessageReadError::Io(e)) => {
                 if let std::io::ErrorKind::WouldBlock = e.kind() {
                     //no messages currently available to receive -- wait a while
                     thread::sleep(Duration::from_secs(1));
-                    continue;
                 } else {
                     println!("recv error: {e:?}");
                     break;
@@ -61,6 +283,7 @@ fn main() {
             // messages that didn't get through due to parser errors are ignored
             _ => {}
         }
+        printer.print_rates();
     }
 }
 
@@ -87,17 +310,3 @@ pub fn request_parameters() -> mavlink::ardupilotmega::MavMessage {
         },
     )
 }
-
-/// Create a message enabling data streaming
-#[cfg(feature = "std")]
-pub fn request_stream() -> mavlink::ardupilotmega::MavMessage {
-    mavlink::ardupilotmega::MavMessage::REQUEST_DATA_STREAM(
-        mavlink::ardupilotmega::REQUEST_DATA_STREAM_DATA {
-            target_system: 0,
-            target_component: 0,
-            req_stream_id: 0,
-            req_message_rate: 10,
-            start_stop: 1,
-        },
-    )
-}
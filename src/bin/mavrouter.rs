@@ -0,0 +1,336 @@
+//! Routes messages between MAVLink endpoints, configured by a file like
+//!
+//! ```ini
+//! [General]
+//! # write all received messages into a tlog in this directory
+//! Log = /var/log/mavlink
+//! # key provisioned with SETUP_SIGNING to the endpoints with SetupSigning enabled
+//! SigningKey = 000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f
+//!
+//! [Endpoint autopilot]
+//! Address = serial:/dev/ttyACM0:115200
+//! SetupSigning = true
+//!
+//! [Endpoint gcs]
+//! Address = udpin:0.0.0.0:14550
+//! Version = 1
+//! # only forward these message ids to the endpoint
+//! AllowMsgIdOut = 0,1,24,30,33
+//! # never forward these message ids to the endpoint
+//! BlockMsgIdOut = 22
+//! # only accept messages from these systems on the endpoint
+//! AllowSrcSysIn = 255
+//! ```
+
+#[cfg(feature = "std")]
+use std::{
+    collections::HashSet,
+    env,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "std")]
+use mavlink::{
+    ardupilotmega::MavMessage,
+    error::{MessageReadError, MessageWriteError},
+    router::Router,
+    signing::{send_setup_signing, SigningKey, SECRET_KEY_LEN},
+    MavHeader, MavlinkVersion, Message,
+};
+
+#[cfg(not(feature = "std"))]
+fn main() {}
+
+#[cfg(feature = "std")]
+struct EndpointConfig {
+    name: String,
+    address: String,
+    version: MavlinkVersion,
+    allow_msg_id_out: Option<HashSet<u32>>,
+    block_msg_id_out: HashSet<u32>,
+    allow_src_sys_in: Option<HashSet<u8>>,
+    setup_signing: bool,
+}
+
+#[cfg(feature = "std")]
+impl EndpointConfig {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            address: String::new(),
+            version: MavlinkVersion::V2,
+            allow_msg_id_out: None,
+            block_msg_id_out: HashSet::new(),
+            allow_src_sys_in: None,
+            setup_signing: false,
+        }
+    }
+
+    fn accepts(&self, header: &MavHeader) -> bool {
+        self.allow_src_sys_in
+            .as_ref()
+            .map_or(true, |allowed| allowed.contains(&header.system_id))
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Config {
+    log: Option<PathBuf>,
+    signing_key: Option<[u8; SECRET_KEY_LEN]>,
+    endpoints: Vec<EndpointConfig>,
+}
+
+#[cfg(feature = "std")]
+fn parse_list<T: std::str::FromStr + Eq + std::hash::Hash>(
+    value: &str,
+) -> Result<HashSet<T>, String> {
+    value
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse()
+                .map_err(|_| format!("invalid list entry {item:?}"))
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+fn parse_key(value: &str) -> Result<[u8; SECRET_KEY_LEN], String> {
+    let invalid = || format!("signing key must be {} hex digits", SECRET_KEY_LEN * 2);
+    if value.len() != SECRET_KEY_LEN * 2 || !value.is_ascii() {
+        return Err(invalid());
+    }
+    let mut key = [0u8; SECRET_KEY_LEN];
+    for (index, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
+
+#[cfg(feature = "std")]
+fn parse_config(content: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut in_general = false;
+
+    for (number, line) in content.lines().enumerate() {
+        let error = |message: String| format!("line {}: {message}", number + 1);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(section) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            let mut words = section.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("General"), None) => in_general = true,
+                (Some("Endpoint"), Some(name)) => {
+                    in_general = false;
+                    config.endpoints.push(EndpointConfig::new(name));
+                }
+                _ => return Err(error(format!("unknown section [{section}]"))),
+            }
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| error(format!("expected `Key = value`, got {line:?}")))?;
+
+        if in_general {
+            match key {
+                "Log" => config.log = Some(PathBuf::from(value)),
+                "SigningKey" => config.signing_key = Some(parse_key(value).map_err(error)?),
+                _ => return Err(error(format!("unknown option {key}"))),
+            }
+            continue;
+        }
+
+        let endpoint = config
+            .endpoints
+            .last_mut()
+            .ok_or_else(|| error(format!("option {key} outside of a section")))?;
+        match key {
+            "Address" => endpoint.address = value.to_string(),
+            "Version" => {
+                endpoint.version = match value {
+                    "1" => MavlinkVersion::V1,
+                    "2" => MavlinkVersion::V2,
+                    _ => return Err(error(format!("invalid version {value}"))),
+                }
+            }
+            "AllowMsgIdOut" => endpoint.allow_msg_id_out = Some(parse_list(value).map_err(error)?),
+            "BlockMsgIdOut" => endpoint.block_msg_id_out = parse_list(value).map_err(error)?,
+            "AllowSrcSysIn" => endpoint.allow_src_sys_in = Some(parse_list(value).map_err(error)?),
+            "SetupSigning" => {
+                endpoint.setup_signing = value
+                    .parse()
+                    .map_err(|_| error(format!("invalid boolean {value}")))?
+            }
+            _ => return Err(error(format!("unknown option {key}"))),
+        }
+    }
+
+    if config.endpoints.is_empty() {
+        return Err("no endpoints configured".to_string());
+    }
+    if let Some(endpoint) = config
+        .endpoints
+        .iter()
+        .find(|endpoint| endpoint.address.is_empty())
+    {
+        return Err(format!("endpoint {} has no Address", endpoint.name));
+    }
+    if config.signing_key.is_none() && config.endpoints.iter().any(|e| e.setup_signing) {
+        return Err("SetupSigning needs a SigningKey".to_string());
+    }
+    Ok(config)
+}
+
+/// Writes all received messages in the tlog format
+#[cfg(feature = "std")]
+struct TlogWriter {
+    file: Mutex<BufWriter<File>>,
+}
+
+#[cfg(feature = "std")]
+impl TlogWriter {
+    fn create(directory: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(directory)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let path = directory.join(format!("mavrouter-{}.tlog", now.as_secs()));
+        println!("logging to {}", path.display());
+        Ok(Self {
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    fn write(&self, header: &MavHeader, msg: &MavMessage) -> Result<(), MessageWriteError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&(now.as_micros() as u64).to_be_bytes())?;
+        mavlink::write_v2_msg(&mut *file, *header, msg)?;
+        // flushed right away as the router only stops when it is killed
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() != 2 {
+        println!("Usage: mavrouter <config file>");
+        return;
+    }
+
+    let config = match fs::read_to_string(&args[1])
+        .map_err(|e| e.to_string())
+        .and_then(|content| parse_config(&content))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Invalid configuration {}: {e}", args[1]);
+            std::process::exit(1);
+        }
+    };
+
+    let log = match config.log.as_deref().map(TlogWriter::create).transpose() {
+        Ok(log) => log,
+        Err(e) => {
+            println!("Failed to create log: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut router = Router::<MavMessage>::new();
+    for endpoint in &config.endpoints {
+        let mut connection = match mavlink::connect::<MavMessage>(&endpoint.address) {
+            Ok(connection) => connection,
+            Err(e) => {
+                println!(
+                    "Failed to open endpoint {} at {}: {e}",
+                    endpoint.name, endpoint.address
+                );
+                std::process::exit(1);
+            }
+        };
+        connection.set_protocol_version(endpoint.version);
+
+        if let (true, Some(secret_key)) = (endpoint.setup_signing, config.signing_key) {
+            let setup = SigningKey::new(secret_key).setup_message(0, 0);
+            if let Err(e) = send_setup_signing(&*connection, &MavHeader::default(), setup) {
+                println!("Failed to set up signing on {}: {e}", endpoint.name);
+            }
+        }
+
+        let filter = {
+            let allow = endpoint.allow_msg_id_out.clone();
+            let block = endpoint.block_msg_id_out.clone();
+            move |_: &MavHeader, msg: &MavMessage| {
+                let id = msg.message_id();
+                !block.contains(&id) && allow.as_ref().map_or(true, |allow| allow.contains(&id))
+            }
+        };
+        router.add_link_with_filter(connection, Box::new(filter));
+        println!("endpoint {} at {}", endpoint.name, endpoint.address);
+    }
+
+    let router = Arc::new(router);
+    let log = Arc::new(log);
+    let endpoints = Arc::new(config.endpoints);
+    let threads: Vec<_> = (0..router.link_count())
+        .map(|index| {
+            let router = router.clone();
+            let log = log.clone();
+            let endpoints = endpoints.clone();
+            thread::spawn(move || {
+                let endpoint = &endpoints[index];
+                let connection = router.link(index).unwrap();
+                loop {
+                    let (header, msg) = match connection.recv() {
+                        Ok(received) => received,
+                        Err(MessageReadError::Io(e)) => {
+                            if e.kind() == std::io::ErrorKind::WouldBlock {
+                                thread::sleep(Duration::from_millis(1));
+                                continue;
+                            }
+                            println!("endpoint {} failed: {e}", endpoint.name);
+                            return;
+                        }
+                        // messages that could not be parsed are dropped
+                        Err(_) => continue,
+                    };
+                    if !endpoint.accepts(&header) {
+                        continue;
+                    }
+                    if let Some(Err(e)) = log.as_ref().as_ref().map(|log| log.write(&header, &msg))
+                    {
+                        println!("log write failed: {e}");
+                    }
+                    for error in router.handle(index, &header, &msg) {
+                        println!("endpoint {}: {error}", endpoint.name);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
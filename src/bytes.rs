@@ -1,20 +1,35 @@
 pub struct Bytes<'a> {
     data: &'a [u8],
+    len: usize,
     pos: usize,
 }
 
 impl<'a> Bytes<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        Self { data, pos: 0 }
+        Self {
+            data,
+            len: data.len(),
+            pos: 0,
+        }
+    }
+
+    /// Reads `data` as if it was followed by zeros up to `len` bytes, like MAVLink 2 payloads
+    /// with their trailing zeros truncated, without copying it into a buffer of `len` bytes.
+    pub fn new_zero_filled(data: &'a [u8], len: usize) -> Self {
+        Self {
+            data,
+            len: len.max(data.len()),
+            pos: 0,
+        }
     }
 
     #[inline]
     fn remaining(&self) -> usize {
-        self.data.len() - self.pos
+        self.len - self.pos
     }
 
     pub fn remaining_bytes(&self) -> &'a [u8] {
-        &self.data[self.pos..]
+        &self.data[self.pos.min(self.data.len())..]
     }
 
     fn check_remaining(&self, count: usize) {
@@ -26,39 +41,41 @@ impl<'a> Bytes<'a> {
         );
     }
 
-    pub fn get_bytes(&mut self, count: usize) -> &[u8] {
+    /// Reads the next `count` bytes.
+    ///
+    /// For a reader created with [`Bytes::new_zero_filled`] the returned slice is shorter than
+    /// `count` if the bytes extend past the end of the data, the missing bytes are zeros.
+    pub fn get_bytes(&mut self, count: usize) -> &'a [u8] {
         self.check_remaining(count);
 
-        let bytes = &self.data[self.pos..(self.pos + count)];
+        let start = self.pos.min(self.data.len());
+        let end = (self.pos + count).min(self.data.len());
         self.pos += count;
-        bytes
+        &self.data[start..end]
+    }
+
+    /// Copies the next bytes into `dst`, bytes past the end of the data are left as they are
+    fn fill(&mut self, dst: &mut [u8]) {
+        let bytes = self.get_bytes(dst.len());
+        dst[..bytes.len()].copy_from_slice(bytes);
     }
 
     pub fn get_array<const SIZE: usize>(&mut self) -> [u8; SIZE] {
-        let bytes = self.get_bytes(SIZE);
         let mut arr = [0u8; SIZE];
-
-        arr.copy_from_slice(bytes);
-
-        debug_assert_eq!(arr.as_slice(), bytes);
-
+        self.fill(&mut arr);
         arr
     }
 
     pub fn get_u8(&mut self) -> u8 {
         self.check_remaining(1);
 
-        let val = self.data[self.pos];
+        let val = self.data.get(self.pos).copied().unwrap_or(0);
         self.pos += 1;
         val
     }
 
     pub fn get_i8(&mut self) -> i8 {
-        self.check_remaining(1);
-
-        let val = self.data[self.pos] as i8;
-        self.pos += 1;
-        val
+        self.get_u8() as i8
     }
 
     pub fn get_u16_le(&mut self) -> u16 {
@@ -71,11 +88,9 @@ impl<'a> Bytes<'a> {
 
     pub fn get_u24_le(&mut self) -> u32 {
         const SIZE: usize = 3;
-        self.check_remaining(SIZE);
 
         let mut val = [0u8; SIZE + 1];
-        val[..3].copy_from_slice(&self.data[self.pos..self.pos + SIZE]);
-        self.pos += SIZE;
+        self.fill(&mut val[..SIZE]);
 
         debug_assert_eq!(val[3], 0);
         u32::from_le_bytes(val)
@@ -83,11 +98,9 @@ impl<'a> Bytes<'a> {
 
     pub fn get_i24_le(&mut self) -> i32 {
         const SIZE: usize = 3;
-        self.check_remaining(SIZE);
 
         let mut val = [0u8; SIZE + 1];
-        val[..3].copy_from_slice(&self.data[self.pos..self.pos + SIZE]);
-        self.pos += SIZE;
+        self.fill(&mut val[..SIZE]);
 
         debug_assert_eq!(val[3], 0);
         i32::from_le_bytes(val)
@@ -0,0 +1,70 @@
+//! Text in the `char[N]` fields of the messages.
+//!
+//! Fields like the `text` of STATUSTEXT or the `param_id` of PARAM_VALUE are byte arrays holding
+//! a string padded with NUL bytes, or filling the whole array. [`CharArray`] shows them as that
+//! string, so the `Debug` output of messages reads `text: "PreArm: Compass not calibrated"`
+//! instead of fifty numbers.
+
+use core::fmt::{self, Write};
+
+/// Formatter printing a `char[N]` field as its text up to the first NUL byte, with invalid UTF-8
+/// replaced by U+FFFD. `{:?}` quotes and escapes the text like a `str`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CharArray<'a>(pub &'a [u8]);
+
+impl<'a> CharArray<'a> {
+    /// The bytes up to the first NUL byte
+    pub fn bytes(&self) -> &'a [u8] {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(self.0.len());
+        &self.0[..len]
+    }
+
+    /// The text, `None` if it is not valid UTF-8
+    pub fn to_str(&self) -> Option<&'a str> {
+        core::str::from_utf8(self.bytes()).ok()
+    }
+
+    /// Calls `write` with the valid parts of the text and U+FFFD for each invalid sequence
+    fn write_lossy(&self, mut write: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+        let mut bytes = self.bytes();
+        loop {
+            match core::str::from_utf8(bytes) {
+                Ok(text) => return write(text),
+                Err(error) => {
+                    let (valid, rest) = bytes.split_at(error.valid_up_to());
+                    // checked by from_utf8
+                    write(core::str::from_utf8(valid).unwrap())?;
+                    write("\u{FFFD}")?;
+                    bytes = &rest[error.error_len().unwrap_or(rest.len())..];
+                }
+            }
+        }
+    }
+}
+
+impl<'a, const N: usize> From<&'a [u8; N]> for CharArray<'a> {
+    fn from(bytes: &'a [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for CharArray<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_lossy(|text| f.write_str(text))
+    }
+}
+
+impl fmt::Debug for CharArray<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(text) = self.to_str() {
+            return fmt::Debug::fmt(text, f);
+        }
+        f.write_char('"')?;
+        self.write_lossy(|text| {
+            text.chars()
+                .flat_map(char::escape_debug)
+                .try_for_each(|c| f.write_char(c))
+        })?;
+        f.write_char('"')
+    }
+}
@@ -0,0 +1,247 @@
+//! Sender side of the [command protocol](https://mavlink.io/en/services/command.html).
+//!
+//! [`CommandTransaction`] tracks a single COMMAND_LONG or COMMAND_INT until the matching
+//! COMMAND_ACK arrives, and [`CommandClient`] drives it over a [`MavConnection`] of any message
+//! set.
+//!
+//! [`MavConnection`]: crate::MavConnection
+
+use std::time::Duration;
+
+use crate::common::{MavCmd, MavMessage, MavResult, COMMAND_INT_DATA, COMMAND_LONG_DATA};
+use crate::error::ProtocolError;
+use crate::request::{Exchange, Requester, Step};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Time to wait for an acknowledgement before resending
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Number of times a command is resent before giving up
+pub const DEFAULT_RETRIES: u8 = 3;
+
+/// Time to wait for the final acknowledgement once the command is reported in progress
+pub const DEFAULT_IN_PROGRESS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A command, sent as COMMAND_LONG or COMMAND_INT
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Long(COMMAND_LONG_DATA),
+    Int(COMMAND_INT_DATA),
+}
+
+impl Command {
+    pub fn command(&self) -> MavCmd {
+        match self {
+            Self::Long(data) => data.command,
+            Self::Int(data) => data.command,
+        }
+    }
+
+    pub fn target_system(&self) -> u8 {
+        match self {
+            Self::Long(data) => data.target_system,
+            Self::Int(data) => data.target_system,
+        }
+    }
+
+    pub fn target_component(&self) -> u8 {
+        match self {
+            Self::Long(data) => data.target_component,
+            Self::Int(data) => data.target_component,
+        }
+    }
+
+    fn to_message(&self) -> MavMessage {
+        match self {
+            Self::Long(data) => MavMessage::COMMAND_LONG(data.clone()),
+            Self::Int(data) => MavMessage::COMMAND_INT(data.clone()),
+        }
+    }
+}
+
+impl From<COMMAND_LONG_DATA> for Command {
+    fn from(data: COMMAND_LONG_DATA) -> Self {
+        Self::Long(data)
+    }
+}
+
+impl From<COMMAND_INT_DATA> for Command {
+    fn from(data: COMMAND_INT_DATA) -> Self {
+        Self::Int(data)
+    }
+}
+
+/// State of a command waiting for its acknowledgement
+#[derive(Debug, Clone)]
+pub struct CommandTransaction {
+    command: Command,
+    retries: u8,
+    attempts: u8,
+    in_progress_timeout: Duration,
+    progress: Option<u8>,
+    #[cfg_attr(not(feature = "emit-extensions"), allow(dead_code))]
+    sender: Option<(u8, u8)>,
+}
+
+impl CommandTransaction {
+    pub fn new(command: impl Into<Command>) -> Self {
+        Self {
+            command: command.into(),
+            retries: DEFAULT_RETRIES,
+            attempts: 0,
+            in_progress_timeout: DEFAULT_IN_PROGRESS_TIMEOUT,
+            progress: None,
+            sender: None,
+        }
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Time to wait for the final acknowledgement once the command is reported in progress
+    pub fn with_in_progress_timeout(mut self, timeout: Duration) -> Self {
+        self.in_progress_timeout = timeout;
+        self
+    }
+
+    /// Ids the command is sent with, acknowledgements addressed to another system or component
+    /// are ignored
+    pub fn with_sender(mut self, system_id: u8, component_id: u8) -> Self {
+        self.sender = Some((system_id, component_id));
+        self
+    }
+
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Whether the target reported the command as in progress, in which case it is not resent
+    pub fn in_progress(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    /// Last progress reported by the target, in percent (255 if unknown)
+    pub fn progress(&self) -> Option<u8> {
+        self.progress
+    }
+}
+
+impl Exchange for CommandTransaction {
+    type Message = MavMessage;
+    type Output = MavResult;
+    type Error = ProtocolError;
+
+    /// Message sending the command
+    fn start(&mut self) -> MavMessage {
+        self.command.to_message()
+    }
+
+    /// Feed a received message, the exchange is done once the command is finished
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<Step<MavMessage, MavResult>, ProtocolError> {
+        let ack = match msg {
+            MavMessage::COMMAND_ACK(ack) => ack,
+            _ => return Ok(Step::Wait),
+        };
+
+        let target_system = self.command.target_system();
+        let target_component = self.command.target_component();
+        if ack.command != self.command.command()
+            || (target_system != 0 && header.system_id != target_system)
+            || (target_component != 0 && header.component_id != target_component)
+        {
+            return Ok(Step::Wait);
+        }
+
+        // the ids are zero if the target does not fill them in
+        #[cfg(feature = "emit-extensions")]
+        if let Some((system_id, component_id)) = self.sender {
+            if (ack.target_system != 0 && ack.target_system != system_id)
+                || (ack.target_component != 0 && ack.target_component != component_id)
+            {
+                return Ok(Step::Wait);
+            }
+        }
+
+        match ack.result {
+            MavResult::MAV_RESULT_IN_PROGRESS => {
+                #[cfg(feature = "emit-extensions")]
+                let progress = ack.progress;
+                #[cfg(not(feature = "emit-extensions"))]
+                let progress = u8::MAX;
+                self.progress = Some(progress);
+                // every progress report extends the wait for the final acknowledgement
+                Ok(Step::Progress)
+            }
+            result => Ok(Step::Done(None, result)),
+        }
+    }
+
+    /// Called when no acknowledgement was received in time, resends the command
+    fn on_timeout(&mut self) -> Result<Step<MavMessage, MavResult>, ProtocolError> {
+        if self.in_progress() || self.attempts >= self.retries {
+            warn!(command = ?self.command.command(), attempts = self.attempts, "command timed out");
+            return Err(ProtocolError::Timeout);
+        }
+        self.attempts += 1;
+        debug!(command = ?self.command.command(), attempt = self.attempts, "resending command");
+
+        // COMMAND_LONG counts retransmissions so the target can detect duplicates
+        if let Command::Long(data) = &mut self.command {
+            data.confirmation = data.confirmation.wrapping_add(1);
+        }
+        Ok(Step::Send(self.command.to_message()))
+    }
+
+    fn timeout(&self, timeout: Duration) -> Duration {
+        if self.in_progress() {
+            self.in_progress_timeout
+        } else {
+            timeout
+        }
+    }
+}
+
+/// Sends commands over a connection and waits for their acknowledgement
+pub struct CommandClient<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    requester: Requester<'a, M, C>,
+    in_progress_timeout: Duration,
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> CommandClient<'a, M, C> {
+    pub fn new(connection: &'a C) -> Self {
+        Self::from_requester(
+            Requester::new(connection)
+                .with_timeout(DEFAULT_TIMEOUT)
+                .with_retries(DEFAULT_RETRIES),
+        )
+    }
+
+    /// Send the commands with the header, timeout and retries of `requester`
+    pub fn from_requester(requester: Requester<'a, M, C>) -> Self {
+        Self {
+            requester,
+            in_progress_timeout: DEFAULT_IN_PROGRESS_TIMEOUT,
+        }
+    }
+
+    pub fn with_in_progress_timeout(mut self, timeout: Duration) -> Self {
+        self.in_progress_timeout = timeout;
+        self
+    }
+
+    /// Send a command and wait for its final result
+    pub fn send_command(&self, command: impl Into<Command>) -> Result<MavResult, ProtocolError> {
+        let header = self.requester.header();
+        let mut transaction = CommandTransaction::new(command)
+            .with_sender(header.system_id, header.component_id)
+            .with_retries(self.requester.retries())
+            .with_in_progress_timeout(self.in_progress_timeout);
+        self.requester.run(&mut transaction)
+    }
+}
@@ -0,0 +1,221 @@
+//! The baseline behaviour expected from every component on a MAVLink network.
+//!
+//! [`Component`] sends the [HEARTBEAT](https://mavlink.io/en/services/heartbeat.html) of a
+//! component and answers the requests any component has to answer: PING, PROTOCOL_VERSION and
+//! AUTOPILOT_VERSION, asked for with MAV_CMD_REQUEST_MESSAGE or the older
+//! MAV_CMD_REQUEST_PROTOCOL_VERSION and MAV_CMD_REQUEST_AUTOPILOT_CAPABILITIES. Peripherals,
+//! companion computer services and simulators start from it and handle their own messages and
+//! commands next to it; other commands are left unanswered, so servers like
+//! [`ComponentMetadataServer`](crate::component_information::ComponentMetadataServer) can answer
+//! on the same connection.
+
+use std::time::{Duration, Instant};
+
+use crate::common::{
+    MavAutopilot, MavCmd, MavMessage, MavModeFlag, MavProtocolCapability, MavResult, MavState,
+    MavType, AUTOPILOT_VERSION_DATA, COMMAND_ACK_DATA, HEARTBEAT_DATA, PING_DATA,
+    PROTOCOL_VERSION_DATA,
+};
+use crate::error::MessageWriteError;
+use crate::{MavConnection, MavHeader, Message, MessageData};
+
+/// Nominal heartbeat interval
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Identity, state and heartbeat schedule of a component
+#[derive(Debug, Clone)]
+pub struct Component {
+    system_id: u8,
+    component_id: u8,
+    heartbeat: HEARTBEAT_DATA,
+    version: AUTOPILOT_VERSION_DATA,
+    next_heartbeat: Option<Instant>,
+}
+
+impl Component {
+    /// A component that is no autopilot, in standby and capable of MAVLink 2
+    pub fn new(system_id: u8, component_id: u8, mavtype: MavType) -> Self {
+        Self {
+            system_id,
+            component_id,
+            heartbeat: HEARTBEAT_DATA {
+                custom_mode: 0,
+                mavtype,
+                autopilot: MavAutopilot::MAV_AUTOPILOT_INVALID,
+                base_mode: MavModeFlag::empty(),
+                system_status: MavState::MAV_STATE_STANDBY,
+                mavlink_version: 3,
+            },
+            version: AUTOPILOT_VERSION_DATA {
+                capabilities: MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_MAVLINK2,
+                ..Default::default()
+            },
+            next_heartbeat: None,
+        }
+    }
+
+    pub fn with_autopilot(mut self, autopilot: MavAutopilot) -> Self {
+        self.heartbeat.autopilot = autopilot;
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: MavProtocolCapability) -> Self {
+        self.version.capabilities = capabilities;
+        self
+    }
+
+    /// Versions, board and ids sent as AUTOPILOT_VERSION, including the capabilities
+    pub fn with_autopilot_version(mut self, version: AUTOPILOT_VERSION_DATA) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn system_id(&self) -> u8 {
+        self.system_id
+    }
+
+    pub fn component_id(&self) -> u8 {
+        self.component_id
+    }
+
+    /// Header for messages sent by this component, the sequence number is set by the connection
+    pub fn header(&self) -> MavHeader {
+        MavHeader {
+            system_id: self.system_id,
+            component_id: self.component_id,
+            sequence: 0,
+        }
+    }
+
+    pub fn set_mode(&mut self, base_mode: MavModeFlag, custom_mode: u32) {
+        self.heartbeat.base_mode = base_mode;
+        self.heartbeat.custom_mode = custom_mode;
+    }
+
+    pub fn set_system_status(&mut self, system_status: MavState) {
+        self.heartbeat.system_status = system_status;
+    }
+
+    pub fn heartbeat(&self) -> MavMessage {
+        MavMessage::HEARTBEAT(self.heartbeat.clone())
+    }
+
+    pub fn autopilot_version(&self) -> MavMessage {
+        MavMessage::AUTOPILOT_VERSION(self.version.clone())
+    }
+
+    /// PROTOCOL_VERSION, `None` if the component is not capable of MAVLink 2
+    pub fn protocol_version(&self) -> Option<MavMessage> {
+        if !self
+            .version
+            .capabilities
+            .contains(MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_MAVLINK2)
+        {
+            return None;
+        }
+        Some(MavMessage::PROTOCOL_VERSION(PROTOCOL_VERSION_DATA {
+            version: 200,
+            min_version: 100,
+            max_version: 200,
+            ..Default::default()
+        }))
+    }
+
+    /// The heartbeat, if it is due at `now`
+    pub fn poll(&mut self, now: Instant) -> Option<MavMessage> {
+        match self.next_heartbeat {
+            Some(next) if next > now => None,
+            next => {
+                // keep the nominal rate unless the heartbeat is more than an interval late
+                let next = next
+                    .map(|next| next + HEARTBEAT_INTERVAL)
+                    .filter(|next| *next > now)
+                    .unwrap_or(now + HEARTBEAT_INTERVAL);
+                self.next_heartbeat = Some(next);
+                Some(self.heartbeat())
+            }
+        }
+    }
+
+    /// Handle a received message and return the replies
+    pub fn handle(&self, header: &MavHeader, msg: &MavMessage) -> Vec<MavMessage> {
+        match msg {
+            // requests are broadcast, answers are addressed to the requester
+            MavMessage::PING(ping) if ping.target_system == 0 && ping.target_component == 0 => {
+                vec![MavMessage::PING(PING_DATA {
+                    time_usec: ping.time_usec,
+                    seq: ping.seq,
+                    target_system: header.system_id,
+                    target_component: header.component_id,
+                })]
+            }
+            MavMessage::COMMAND_LONG(command)
+                if command.target_system == self.system_id
+                    && (command.target_component == self.component_id
+                        || command.target_component == 0) =>
+            {
+                let reply = match command.command {
+                    MavCmd::MAV_CMD_REQUEST_MESSAGE => match command.param1 as u32 {
+                        id if id == HEARTBEAT_DATA::ID => Some(self.heartbeat()),
+                        id if id == AUTOPILOT_VERSION_DATA::ID => Some(self.autopilot_version()),
+                        id if id == PROTOCOL_VERSION_DATA::ID => self.protocol_version(),
+                        _ => return Vec::new(),
+                    },
+                    _ => return Vec::new(),
+                };
+
+                let result = if reply.is_some() {
+                    MavResult::MAV_RESULT_ACCEPTED
+                } else {
+                    MavResult::MAV_RESULT_UNSUPPORTED
+                };
+                let ack = MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+                    command: command.command,
+                    result,
+                    #[cfg(feature = "emit-extensions")]
+                    progress: 0,
+                    #[cfg(feature = "emit-extensions")]
+                    result_param2: 0,
+                    #[cfg(feature = "emit-extensions")]
+                    target_system: header.system_id,
+                    #[cfg(feature = "emit-extensions")]
+                    target_component: header.component_id,
+                });
+                Some(ack).into_iter().chain(reply).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Handle a message received on a connection of any message set and send the replies
+    pub fn handle_and_reply<M: Message, C: MavConnection<M> + ?Sized>(
+        &self,
+        connection: &C,
+        header: &MavHeader,
+        msg: &M,
+    ) -> Result<(), MessageWriteError> {
+        let msg = match msg.to_dialect::<MavMessage>() {
+            Some(msg) => msg,
+            None => return Ok(()),
+        };
+
+        for reply in self.handle(header, &msg) {
+            if let Some(reply) = reply.to_dialect::<M>() {
+                connection.send(&self.header(), &reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the heartbeat on a connection of any message set, if it is due at `now`
+    pub fn poll_and_send<M: Message, C: MavConnection<M> + ?Sized>(
+        &mut self,
+        connection: &C,
+        now: Instant,
+    ) -> Result<(), MessageWriteError> {
+        if let Some(heartbeat) = self.poll(now).and_then(|msg| msg.to_dialect::<M>()) {
+            connection.send(&self.header(), &heartbeat)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+//! [Component information](https://mavlink.io/en/services/component_information.html) support.
+//!
+//! A component advertises the location and CRC32 of its general metadata file (`general.json`)
+//! with COMPONENT_METADATA, or the deprecated COMPONENT_INFORMATION, when asked with
+//! MAV_CMD_REQUEST_MESSAGE. [`ComponentMetadataServer`] answers these requests and
+//! [`request_component_metadata`] sends them. Downloading the file itself is left to a
+//! [`MetadataFetcher`], as it is usually hosted on MAVLink FTP or on a web server.
+
+use core::fmt::{Display, Formatter};
+use std::error::Error;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crc_any::CRCu32;
+
+use crate::commands::CommandTransaction;
+use crate::common::{
+    MavCmd, MavMessage, MavResult, COMMAND_ACK_DATA, COMMAND_LONG_DATA, COMPONENT_INFORMATION_DATA,
+    COMPONENT_METADATA_DATA,
+};
+use crate::error::{MessageReadError, MessageWriteError, ProtocolError};
+use crate::request::{Exchange, Requester, Step};
+use crate::{MavConnection, MavHeader, Message, MessageData};
+
+/// Length of the URI fields
+pub const URI_LEN: usize = 100;
+
+#[derive(Debug)]
+pub enum ComponentInformationError {
+    /// The component refused to send its metadata
+    Rejected(MavResult),
+    /// The downloaded file does not match the advertised CRC
+    CrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// The metadata file could not be downloaded
+    Fetch(io::Error),
+    Protocol(ProtocolError),
+}
+
+impl Display for ComponentInformationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Rejected(result) => write!(f, "Metadata request rejected: {result:?}"),
+            Self::CrcMismatch { expected, actual } => write!(
+                f,
+                "Metadata file CRC mismatch: expected {expected:#010x}, got {actual:#010x}"
+            ),
+            Self::Fetch(e) => write!(f, "Could not download metadata file: {e}"),
+            Self::Protocol(e) => write!(f, "Metadata request failed: {e}"),
+        }
+    }
+}
+
+impl Error for ComponentInformationError {}
+
+impl From<ProtocolError> for ComponentInformationError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+impl From<MessageReadError> for ComponentInformationError {
+    fn from(e: MessageReadError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+impl From<MessageWriteError> for ComponentInformationError {
+    fn from(e: MessageWriteError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+/// CRC32 of a metadata file, as advertised in COMPONENT_METADATA
+pub fn metadata_file_crc(content: &[u8]) -> u32 {
+    let mut crc = CRCu32::crc32();
+    crc.digest(content);
+    crc.get_crc()
+}
+
+fn uri_field(uri: &str) -> [u8; URI_LEN] {
+    // the field must stay NUL terminated
+    let mut field = [0u8; URI_LEN];
+    let len = uri.len().min(URI_LEN - 1);
+    field[..len].copy_from_slice(&uri.as_bytes()[..len]);
+    field
+}
+
+fn uri_from_field(field: &[u8; URI_LEN]) -> Option<String> {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(URI_LEN);
+    core::str::from_utf8(&field[..len]).ok().map(str::to_string)
+}
+
+/// Downloads files referenced by metadata URIs (`mftp://...` or `https://...`)
+pub trait MetadataFetcher {
+    fn fetch(&mut self, uri: &str) -> io::Result<Vec<u8>>;
+}
+
+/// Location and CRC32 of the general metadata file of a component
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentMetadata {
+    pub uri: String,
+    pub file_crc: u32,
+}
+
+impl ComponentMetadata {
+    /// Metadata for a `general.json` served at `uri`, the file may be xz compressed
+    pub fn new(uri: &str, general_metadata: &[u8]) -> Self {
+        Self {
+            uri: uri.to_string(),
+            file_crc: metadata_file_crc(general_metadata),
+        }
+    }
+
+    /// Read COMPONENT_METADATA, or the general metadata part of COMPONENT_INFORMATION
+    pub fn from_message(msg: &MavMessage) -> Option<Self> {
+        match msg {
+            MavMessage::COMPONENT_METADATA(data) => Some(Self {
+                uri: uri_from_field(&data.uri)?,
+                file_crc: data.file_crc,
+            }),
+            MavMessage::COMPONENT_INFORMATION(data) => Some(Self {
+                uri: uri_from_field(&data.general_metadata_uri)?,
+                file_crc: data.general_metadata_file_crc,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_message(&self, time_boot_ms: u32) -> MavMessage {
+        MavMessage::COMPONENT_METADATA(COMPONENT_METADATA_DATA {
+            time_boot_ms,
+            file_crc: self.file_crc,
+            uri: uri_field(&self.uri),
+        })
+    }
+
+    /// Whether `content` is the advertised file
+    pub fn verify(&self, content: &[u8]) -> Result<(), ComponentInformationError> {
+        let actual = metadata_file_crc(content);
+        if actual == self.file_crc {
+            Ok(())
+        } else {
+            Err(ComponentInformationError::CrcMismatch {
+                expected: self.file_crc,
+                actual,
+            })
+        }
+    }
+
+    /// Download the general metadata file and check its CRC
+    pub fn fetch_general<F: MetadataFetcher + ?Sized>(
+        &self,
+        fetcher: &mut F,
+    ) -> Result<Vec<u8>, ComponentInformationError> {
+        let content = fetcher
+            .fetch(&self.uri)
+            .map_err(ComponentInformationError::Fetch)?;
+        self.verify(&content)?;
+        Ok(content)
+    }
+}
+
+/// Answers MAV_CMD_REQUEST_MESSAGE for COMPONENT_METADATA and COMPONENT_INFORMATION
+pub struct ComponentMetadataServer {
+    system_id: u8,
+    component_id: u8,
+    metadata: ComponentMetadata,
+    boot: Instant,
+}
+
+impl ComponentMetadataServer {
+    pub fn new(system_id: u8, component_id: u8, metadata: ComponentMetadata) -> Self {
+        Self {
+            system_id,
+            component_id,
+            metadata,
+            boot: Instant::now(),
+        }
+    }
+
+    pub fn metadata(&self) -> &ComponentMetadata {
+        &self.metadata
+    }
+
+    /// Header for messages sent by this server, the sequence number is set by the connection
+    pub fn header(&self) -> MavHeader {
+        MavHeader {
+            system_id: self.system_id,
+            component_id: self.component_id,
+            sequence: 0,
+        }
+    }
+
+    /// Handle a received message and return the replies
+    pub fn handle(&self, header: &MavHeader, msg: &MavMessage) -> Vec<MavMessage> {
+        let request = match msg {
+            MavMessage::COMMAND_LONG(request)
+                if request.command == MavCmd::MAV_CMD_REQUEST_MESSAGE
+                    && request.target_system == self.system_id
+                    && (request.target_component == self.component_id
+                        || request.target_component == 0) =>
+            {
+                request
+            }
+            _ => return Vec::new(),
+        };
+
+        let time_boot_ms = self.boot.elapsed().as_millis() as u32;
+        let reply = match request.param1 as u32 {
+            id if id == COMPONENT_METADATA_DATA::ID => self.metadata.to_message(time_boot_ms),
+            id if id == COMPONENT_INFORMATION_DATA::ID => {
+                MavMessage::COMPONENT_INFORMATION(COMPONENT_INFORMATION_DATA {
+                    time_boot_ms,
+                    general_metadata_file_crc: self.metadata.file_crc,
+                    general_metadata_uri: uri_field(&self.metadata.uri),
+                    ..Default::default()
+                })
+            }
+            _ => return Vec::new(),
+        };
+
+        let ack = MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+            command: request.command,
+            result: MavResult::MAV_RESULT_ACCEPTED,
+            #[cfg(feature = "emit-extensions")]
+            progress: 0,
+            #[cfg(feature = "emit-extensions")]
+            result_param2: 0,
+            #[cfg(feature = "emit-extensions")]
+            target_system: header.system_id,
+            #[cfg(feature = "emit-extensions")]
+            target_component: header.component_id,
+        });
+        #[cfg(not(feature = "emit-extensions"))]
+        let _ = header;
+        vec![ack, reply]
+    }
+
+    /// Handle a message received on a connection of any message set and send the replies
+    pub fn handle_and_reply<M: Message, C: MavConnection<M> + ?Sized>(
+        &self,
+        connection: &C,
+        header: &MavHeader,
+        msg: &M,
+    ) -> Result<(), MessageWriteError> {
+        let msg = match msg.to_dialect::<MavMessage>() {
+            Some(msg) => msg,
+            None => return Ok(()),
+        };
+
+        for reply in self.handle(header, &msg) {
+            if let Some(reply) = reply.to_dialect::<M>() {
+                connection.send(&self.header(), &reply)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ask a component for its COMPONENT_METADATA and wait for the answer.
+///
+/// The request is resent as described by the command protocol until the message arrives or
+/// the component rejects it.
+pub fn request_component_metadata<M: Message, C: MavConnection<M> + ?Sized>(
+    requester: &Requester<M, C>,
+    target_system: u8,
+    target_component: u8,
+) -> Result<ComponentMetadata, ComponentInformationError> {
+    let transaction = CommandTransaction::new(COMMAND_LONG_DATA {
+        param1: COMPONENT_METADATA_DATA::ID as f32,
+        command: MavCmd::MAV_CMD_REQUEST_MESSAGE,
+        target_system,
+        target_component,
+        ..Default::default()
+    })
+    .with_retries(requester.retries());
+    requester.run(&mut MetadataRequest {
+        transaction,
+        target_system,
+        target_component,
+        accepted: false,
+    })
+}
+
+/// MAV_CMD_REQUEST_MESSAGE for COMPONENT_METADATA, done once the message arrives
+struct MetadataRequest {
+    transaction: CommandTransaction,
+    target_system: u8,
+    target_component: u8,
+    accepted: bool,
+}
+
+impl MetadataRequest {
+    fn on_command_step(
+        &mut self,
+        step: Step<MavMessage, MavResult>,
+    ) -> Result<Step<MavMessage, ComponentMetadata>, ComponentInformationError> {
+        match step {
+            Step::Send(msg) => Ok(Step::Send(msg)),
+            Step::Progress => Ok(Step::Progress),
+            Step::Wait => Ok(Step::Wait),
+            Step::Done(_, MavResult::MAV_RESULT_ACCEPTED) if !self.accepted => {
+                // wait for the message itself without resending the request
+                self.accepted = true;
+                Ok(Step::Progress)
+            }
+            Step::Done(_, MavResult::MAV_RESULT_ACCEPTED) => Ok(Step::Wait),
+            Step::Done(_, result) => Err(ComponentInformationError::Rejected(result)),
+        }
+    }
+}
+
+impl Exchange for MetadataRequest {
+    type Message = MavMessage;
+    type Output = ComponentMetadata;
+    type Error = ComponentInformationError;
+
+    fn start(&mut self) -> MavMessage {
+        self.transaction.start()
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<Step<MavMessage, ComponentMetadata>, ComponentInformationError> {
+        if header.system_id == self.target_system
+            && (self.target_component == 0 || header.component_id == self.target_component)
+        {
+            if let Some(metadata) = ComponentMetadata::from_message(msg) {
+                return Ok(Step::Done(None, metadata));
+            }
+        }
+        let step = self.transaction.handle(header, msg)?;
+        self.on_command_step(step)
+    }
+
+    fn on_timeout(
+        &mut self,
+    ) -> Result<Step<MavMessage, ComponentMetadata>, ComponentInformationError> {
+        // the acknowledgement may arrive without the message if that got lost
+        let step = self.transaction.on_timeout()?;
+        self.on_command_step(step)
+    }
+
+    fn timeout(&self, timeout: Duration) -> Duration {
+        self.transaction.timeout(timeout)
+    }
+}
@@ -1,43 +1,149 @@
 use crate::connection::MavConnection;
+use crate::parser::PushParser;
 use crate::{read_versioned_msg, write_versioned_msg, MavHeader, MavlinkVersion, Message};
-use std::io;
+use std::io::{self, Read};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use crate::error::{MessageReadError, MessageWriteError};
 use serial::prelude::*;
 
-/// Serial MAVLINK connection
+/// Baud rates probed by `serial:<port>:auto`, most common for telemetry radios and autopilots first
+pub const AUTO_BAUD_RATES: [usize; 10] = [
+    57600, 115200, 921600, 460800, 230400, 500000, 1500000, 38400, 19200, 9600,
+];
+
+/// Time listened for frames at each baud rate, long enough for two 1 Hz heartbeats
+pub const BAUD_PROBE_TIME: Duration = Duration::from_millis(2500);
+
+/// Valid frames needed to accept a baud rate, noise at a wrong rate can pass a checksum once
+const MIN_VALID_FRAMES: usize = 2;
+
+/// Counts the frames with a valid checksum in bytes fed as they arrive.
+///
+/// Each protocol version is searched by its own [`PushParser`], which keeps a partial frame
+/// between the reads and resynchronizes after a false start of a frame, so it doesn't hide the
+/// frames it overlaps.
+struct FrameCounter {
+    parsers: [PushParser; 2],
+    count: usize,
+}
 
-pub fn open(settings: &str) -> io::Result<SerialConnection> {
-    let settings_toks: Vec<&str> = settings.split(':').collect();
-    if settings_toks.len() < 2 {
-        return Err(io::Error::new(
-            io::ErrorKind::AddrNotAvailable,
-            "Incomplete port settings",
-        ));
+impl FrameCounter {
+    const fn new() -> Self {
+        Self {
+            parsers: [
+                PushParser::new(MavlinkVersion::V1),
+                PushParser::new(MavlinkVersion::V2),
+            ],
+            count: 0,
+        }
     }
 
-    let baud_opt = settings_toks[1].parse::<usize>();
-    if baud_opt.is_err() {
-        return Err(io::Error::new(
-            io::ErrorKind::AddrNotAvailable,
-            "Invalid baud rate",
-        ));
+    /// Feed the next received bytes, frames of messages that cannot be parsed count as well
+    fn push<M: Message>(&mut self, bytes: &[u8]) {
+        for parser in &mut self.parsers {
+            self.count += bytes
+                .iter()
+                .filter(|&&byte| parser.push::<M>(byte).is_some())
+                .count();
+        }
     }
+}
 
-    let baud = serial::core::BaudRate::from_speed(baud_opt.unwrap());
+/// Number of frames with a valid checksum for message set `M` in `bytes`.
+///
+/// Every start marker is tried, so a false start of a frame doesn't hide the frames it overlaps.
+pub fn count_valid_frames<M: Message>(bytes: &[u8]) -> usize {
+    let mut frames = FrameCounter::new();
+    frames.push::<M>(bytes);
+    frames.count
+}
 
+/// Configure the port for 8N1 at `baud`
+fn configure(port: &mut serial::SystemPort, baud: usize) -> io::Result<()> {
     let settings = serial::core::PortSettings {
-        baud_rate: baud,
+        baud_rate: serial::core::BaudRate::from_speed(baud),
         char_size: serial::Bits8,
         parity: serial::ParityNone,
         stop_bits: serial::Stop1,
         flow_control: serial::FlowNone,
     };
+    port.configure(&settings)?;
+    Ok(())
+}
+
+/// Listen at every baud rate of [`AUTO_BAUD_RATES`] until valid frames of message set `M` are
+/// received and return that rate, leaving the port configured for it
+pub fn detect_baud_rate<M: Message>(port: &mut serial::SystemPort) -> io::Result<usize> {
+    let timeout = port.timeout();
+    port.set_timeout(Duration::from_millis(100))?;
+
+    // the timeout is restored on errors too
+    let detected = probe_baud_rates::<M>(port);
+    let restored = port.set_timeout(timeout);
+    let detected = detected?;
+    restored?;
+    detected.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "No MAVLink frames received at any baud rate",
+        )
+    })
+}
+
+/// First baud rate of [`AUTO_BAUD_RATES`] at which [`MIN_VALID_FRAMES`] frames are received
+fn probe_baud_rates<M: Message>(port: &mut serial::SystemPort) -> io::Result<Option<usize>> {
+    for &baud in AUTO_BAUD_RATES.iter() {
+        configure(port, baud)?;
+        let start = Instant::now();
+        let mut frames = FrameCounter::new();
+        let mut buf = [0; 512];
+        while start.elapsed() < BAUD_PROBE_TIME {
+            match port.read(&mut buf) {
+                Ok(len) => frames.push::<M>(&buf[..len]),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+            if frames.count >= MIN_VALID_FRAMES {
+                return Ok(Some(baud));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Serial MAVLINK connection, `serial:<port>:auto` detects the baud rate
+pub fn open<M: Message>(settings: &str) -> io::Result<SerialConnection> {
+    let settings_toks: Vec<&str> = settings.split(':').collect();
+    if settings_toks.len() < 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            "Incomplete port settings",
+        ));
+    }
+
+    let baud_opt = match settings_toks[1] {
+        "auto" => None,
+        baud => match baud.parse::<usize>() {
+            Ok(baud) => Some(baud),
+            Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrNotAvailable,
+                    "Invalid baud rate",
+                ))
+            }
+        },
+    };
 
     let port_name = settings_toks[0];
     let mut port = serial::open(port_name)?;
-    port.configure(&settings)?;
+    match baud_opt {
+        Some(baud) => configure(&mut port, baud)?,
+        None => {
+            detect_baud_rate::<M>(&mut port)?;
+        }
+    }
 
     Ok(SerialConnection {
         port: Mutex::new(port),
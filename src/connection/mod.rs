@@ -1,18 +1,31 @@
-use crate::{MavFrame, MavHeader, MavlinkVersion, Message};
+//! Connections to MAVLink endpoints, opened with [`connect`].
+
+use crate::error::MessageReadError;
+use crate::{MavFrame, MavHeader, MavlinkVersion, Message, MessageData};
 
 use std::io::{self};
+use std::time::Instant;
 
-#[cfg(feature = "tcp")]
+#[cfg(all(feature = "tcp", not(target_arch = "wasm32")))]
 mod tcp;
 
-#[cfg(feature = "udp")]
+#[cfg(all(feature = "udp", not(target_arch = "wasm32")))]
 mod udp;
 
-#[cfg(feature = "direct-serial")]
+#[cfg(all(feature = "direct-serial", not(target_arch = "wasm32")))]
 mod direct_serial;
+#[cfg(all(feature = "direct-serial", not(target_arch = "wasm32")))]
+pub use self::direct_serial::{
+    count_valid_frames, detect_baud_rate, AUTO_BAUD_RATES, BAUD_PROBE_TIME,
+};
 
 mod file;
 
+#[cfg(all(feature = "websocket", target_arch = "wasm32"))]
+mod websocket;
+#[cfg(all(feature = "websocket", target_arch = "wasm32"))]
+pub use self::websocket::WebSocketConnection;
+
 /// A MAVLink connection
 pub trait MavConnection<M: Message> {
     /// Receive a mavlink message.
@@ -49,6 +62,34 @@ pub trait MavConnection<M: Message> {
     }
 }
 
+/// Typed receiving on any [`MavConnection`], including `dyn MavConnection`
+pub trait MavConnectionExt<M: Message>: MavConnection<M> {
+    /// Receive the next message of type `D`, e.g. `conn.recv_as::<HEARTBEAT_DATA>()`.
+    ///
+    /// Other messages received before it are discarded, errors are returned like by
+    /// [`MavConnection::recv`].
+    fn recv_as<D: MessageData<Message = M>>(&self) -> Result<(MavHeader, D), MessageReadError> {
+        loop {
+            let (header, msg) = self.recv()?;
+            if let Ok(data) = D::from_message(msg) {
+                return Ok((header, data));
+            }
+        }
+    }
+
+    /// Receive the first message for which `predicate` holds before `deadline`, e.g. the
+    /// COMMAND_ACK of a command, see [`crate::request::recv_matching`]
+    fn recv_matching<F: FnMut(&MavHeader, &M) -> bool>(
+        &self,
+        deadline: Instant,
+        predicate: F,
+    ) -> Result<Option<(MavHeader, M)>, MessageReadError> {
+        crate::request::recv_matching(self, deadline, predicate)
+    }
+}
+
+impl<M: Message, C: MavConnection<M> + ?Sized> MavConnectionExt<M> for C {}
+
 /// Connect to a MAVLink node by address string.
 ///
 /// The address must be in one of the following formats:
@@ -56,43 +97,64 @@ pub trait MavConnection<M: Message> {
 ///  * `tcpin:<addr>:<port>` to create a TCP server, listening for incoming connections
 ///  * `tcpout:<addr>:<port>` to create a TCP client
 ///  * `udpin:<addr>:<port>` to create a UDP server, listening for incoming packets
-///  * `udpout:<addr>:<port>` to create a UDP client
+///  * `udpout:<addr>:<port>` to create a UDP client, `udpout:<addr>:<port>,<addr>:<port>,...`
+///    to fail over to backup destinations when the heartbeats of the first one stop
 ///  * `udpbcast:<addr>:<port>` to create a UDP broadcast
-///  * `serial:<port>:<baudrate>` to create a serial connection
+///  * `serial:<port>:<baudrate>` to create a serial connection, `serial:<port>:auto` to try the
+///    common baud rates until MAVLink frames are received
 ///  * `file:<path>` to extract file data
 ///
 /// The type of the connection is determined at runtime based on the address type, so the
 /// connection is returned as a trait object.
+///
+/// On `wasm32` targets there are no sockets or serial ports, only `file:` is available: use
+/// `WebSocketConnection` in the browser.
 pub fn connect<M: Message>(address: &str) -> io::Result<Box<dyn MavConnection<M> + Sync + Send>> {
+    debug!(address, "connecting");
+    let connection = open(address);
+    match &connection {
+        Ok(_) => info!(address, "connected"),
+        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+        Err(e) => warn!(address, error = %e, "connecting failed"),
+    }
+    connection
+}
+
+fn open<M: Message>(address: &str) -> io::Result<Box<dyn MavConnection<M> + Sync + Send>> {
     let protocol_err = Err(io::Error::new(
         io::ErrorKind::AddrNotAvailable,
         "Protocol unsupported",
     ));
 
-    if cfg!(feature = "tcp") && address.starts_with("tcp") {
-        #[cfg(feature = "tcp")]
+    if cfg!(all(feature = "tcp", not(target_arch = "wasm32"))) && address.starts_with("tcp") {
+        #[cfg(all(feature = "tcp", not(target_arch = "wasm32")))]
         {
             tcp::select_protocol(address)
         }
-        #[cfg(not(feature = "tcp"))]
+        #[cfg(not(all(feature = "tcp", not(target_arch = "wasm32"))))]
         {
             protocol_err
         }
-    } else if cfg!(feature = "udp") && address.starts_with("udp") {
-        #[cfg(feature = "udp")]
+    } else if cfg!(all(feature = "udp", not(target_arch = "wasm32"))) && address.starts_with("udp")
+    {
+        #[cfg(all(feature = "udp", not(target_arch = "wasm32")))]
         {
             udp::select_protocol(address)
         }
-        #[cfg(not(feature = "udp"))]
+        #[cfg(not(all(feature = "udp", not(target_arch = "wasm32"))))]
         {
             protocol_err
         }
-    } else if cfg!(feature = "direct-serial") && address.starts_with("serial:") {
-        #[cfg(feature = "direct-serial")]
+    } else if cfg!(all(feature = "direct-serial", not(target_arch = "wasm32")))
+        && address.starts_with("serial:")
+    {
+        #[cfg(all(feature = "direct-serial", not(target_arch = "wasm32")))]
         {
-            Ok(Box::new(direct_serial::open(&address["serial:".len()..])?))
+            Ok(Box::new(direct_serial::open::<M>(
+                &address["serial:".len()..],
+            )?))
         }
-        #[cfg(not(feature = "direct-serial"))]
+        #[cfg(not(all(feature = "direct-serial", not(target_arch = "wasm32"))))]
         {
             protocol_err
         }
@@ -5,6 +5,13 @@ use std::io::{self};
 use std::net::ToSocketAddrs;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Message id of HEARTBEAT, the same in every message set
+const HEARTBEAT_ID: u32 = 0;
+
+/// A `udpout` destination is considered down after not sending a heartbeat for this long
+const FAILOVER_TIMEOUT: Duration = Duration::from_secs(3);
 
 /// UDP MAVLink connection
 
@@ -14,7 +21,11 @@ pub fn select_protocol<M: Message>(
     let connection = if let Some(address) = address.strip_prefix("udpin:") {
         udpin(address)
     } else if let Some(address) = address.strip_prefix("udpout:") {
-        udpout(address)
+        if address.contains(',') {
+            udpout_failover(&address.split(',').collect::<Vec<_>>())
+        } else {
+            udpout(address)
+        }
     } else if let Some(address) = address.strip_prefix("udpbcast:") {
         udpbcast(address)
     } else {
@@ -37,7 +48,7 @@ pub fn udpbcast<T: ToSocketAddrs>(address: T) -> io::Result<UdpConnection> {
     socket
         .set_broadcast(true)
         .expect("Couldn't bind to broadcast address.");
-    UdpConnection::new(socket, false, Some(addr))
+    UdpConnection::new(socket, false, vec![addr])
 }
 
 pub fn udpout<T: ToSocketAddrs>(address: T) -> io::Result<UdpConnection> {
@@ -47,7 +58,26 @@ pub fn udpout<T: ToSocketAddrs>(address: T) -> io::Result<UdpConnection> {
         .next()
         .expect("Invalid address");
     let socket = UdpSocket::bind("0.0.0.0:0")?;
-    UdpConnection::new(socket, false, Some(addr))
+    UdpConnection::new(socket, false, vec![addr])
+}
+
+/// Connect to several destinations, in order of priority.
+///
+/// Messages go to the first destination still sending heartbeats, falling back to the next one
+/// when its heartbeats stop and back to it when they resume. Sent heartbeats go to all
+/// destinations, so that the backups keep answering.
+pub fn udpout_failover(addresses: &[&str]) -> io::Result<UdpConnection> {
+    let mut addrs = Vec::new();
+    for address in addresses {
+        addrs.push(
+            address
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid address"))?,
+        );
+    }
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    UdpConnection::new(socket, false, addrs)
 }
 
 pub fn udpin<T: ToSocketAddrs>(address: T) -> io::Result<UdpConnection> {
@@ -57,15 +87,44 @@ pub fn udpin<T: ToSocketAddrs>(address: T) -> io::Result<UdpConnection> {
         .next()
         .expect("Invalid address");
     let socket = UdpSocket::bind(addr)?;
-    UdpConnection::new(socket, true, None)
+    UdpConnection::new(socket, true, Vec::new())
+}
+
+struct Destination {
+    addr: SocketAddr,
+    last_heartbeat: Instant,
 }
 
 struct UdpWrite {
     socket: UdpSocket,
-    dest: Option<SocketAddr>,
+    /// Destinations in order of priority
+    dests: Vec<Destination>,
+    /// Destination of everything but heartbeats
+    active: usize,
     sequence: u8,
 }
 
+impl UdpWrite {
+    /// Switch to the first destination whose heartbeats still arrive, if any
+    fn select_destination(&mut self, now: Instant) {
+        if let Some(index) = self
+            .dests
+            .iter()
+            .position(|dest| now.saturating_duration_since(dest.last_heartbeat) < FAILOVER_TIMEOUT)
+        {
+            #[cfg(feature = "tracing")]
+            if index != self.active {
+                info!(
+                    from = %self.dests[self.active].addr,
+                    to = %self.dests[index].addr,
+                    "switching udpout destination"
+                );
+            }
+            self.active = index;
+        }
+    }
+}
+
 struct PacketBuf {
     buf: Vec<u8>,
     start: usize,
@@ -113,6 +172,8 @@ impl Read for PacketBuf {
 struct UdpRead {
     socket: UdpSocket,
     recv_buf: PacketBuf,
+    /// Sender of the datagram in the buffer
+    src: Option<SocketAddr>,
 }
 
 pub struct UdpConnection {
@@ -123,16 +184,26 @@ pub struct UdpConnection {
 }
 
 impl UdpConnection {
-    fn new(socket: UdpSocket, server: bool, dest: Option<SocketAddr>) -> io::Result<Self> {
+    fn new(socket: UdpSocket, server: bool, dests: Vec<SocketAddr>) -> io::Result<Self> {
+        let now = Instant::now();
         Ok(Self {
             server,
             reader: Mutex::new(UdpRead {
                 socket: socket.try_clone()?,
                 recv_buf: PacketBuf::new(),
+                src: None,
             }),
             writer: Mutex::new(UdpWrite {
                 socket,
-                dest,
+                dests: dests
+                    .into_iter()
+                    .map(|addr| Destination {
+                        addr,
+                        // give every destination the time to answer
+                        last_heartbeat: now,
+                    })
+                    .collect(),
+                active: 0,
                 sequence: 0,
             }),
             protocol_version: MavlinkVersion::V2,
@@ -148,14 +219,37 @@ impl<M: Message> MavConnection<M> for UdpConnection {
             if state.recv_buf.len() == 0 {
                 let (len, src) = state.socket.recv_from(state.recv_buf.reset())?;
                 state.recv_buf.set_len(len);
+                state.src = Some(src);
 
+                // answer the last sender
                 if self.server {
-                    self.writer.lock().unwrap().dest = Some(src);
+                    let mut writer = self.writer.lock().unwrap();
+                    if writer.dests.first().map(|dest| dest.addr) != Some(src) {
+                        writer.dests.clear();
+                        writer.dests.push(Destination {
+                            addr: src,
+                            last_heartbeat: Instant::now(),
+                        });
+                        writer.active = 0;
+                    }
                 }
             }
 
-            if let ok @ Ok(..) = read_versioned_msg(&mut state.recv_buf, self.protocol_version) {
-                return ok;
+            if let Ok((header, msg)) =
+                read_versioned_msg::<M, _>(&mut state.recv_buf, self.protocol_version)
+            {
+                // a heartbeat shows that its sender is still up
+                if msg.message_id() == HEARTBEAT_ID && !self.server {
+                    let mut writer = self.writer.lock().unwrap();
+                    if let Some(dest) = writer
+                        .dests
+                        .iter_mut()
+                        .find(|dest| Some(dest.addr) == state.src)
+                    {
+                        dest.last_heartbeat = Instant::now();
+                    }
+                }
+                return Ok((header, msg));
             }
         }
     }
@@ -172,14 +266,27 @@ impl<M: Message> MavConnection<M> for UdpConnection {
 
         state.sequence = state.sequence.wrapping_add(1);
 
-        let len = if let Some(addr) = state.dest {
-            let mut buf = Vec::new();
-            write_versioned_msg(&mut buf, self.protocol_version, header, data)?;
-            state.socket.send_to(&buf, addr)?
-        } else {
-            0
+        state.select_destination(Instant::now());
+        let active = match state.dests.get(state.active) {
+            Some(dest) => dest.addr,
+            None => return Ok(0),
         };
 
+        let mut buf = Vec::new();
+        write_versioned_msg(&mut buf, self.protocol_version, header, data)?;
+        let len = state.socket.send_to(&buf, active)?;
+        // heartbeats go to the backups as well, one being unreachable doesn't fail the send
+        if data.message_id() == HEARTBEAT_ID {
+            for dest in &state.dests {
+                if dest.addr != active {
+                    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                    if let Err(e) = state.socket.send_to(&buf, dest.addr) {
+                        warn!(to = %dest.addr, error = %e, "sending heartbeat to backup failed");
+                    }
+                }
+            }
+        }
+
         Ok(len)
     }
 
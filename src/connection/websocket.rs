@@ -0,0 +1,144 @@
+use crate::connection::MavConnection;
+use crate::error::{MessageReadError, MessageWriteError};
+use crate::{read_versioned_msg, write_versioned_msg, MavHeader, MavlinkVersion, Message};
+use std::cell::{Cell, RefCell};
+use std::io::{self};
+use std::rc::Rc;
+
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+/// Browser WebSocket MAVLink connection, exchanging one or more frames per binary message.
+///
+/// The browser delivers messages from its event loop, so `recv` cannot block: it returns an
+/// error of kind `WouldBlock` until a whole frame has been received, and of kind
+/// `ConnectionAborted` once the socket is closed. Sending before the socket is open fails.
+pub struct WebSocketConnection {
+    socket: WebSocket,
+    received: Rc<RefCell<Vec<u8>>>,
+    closed: Rc<Cell<bool>>,
+    sequence: Cell<u8>,
+    protocol_version: MavlinkVersion,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(JsValue)>,
+}
+
+fn js_error(kind: io::ErrorKind, value: JsValue) -> io::Error {
+    let message = value
+        .as_string()
+        .or_else(|| js_sys::Error::from(value).message().as_string())
+        .unwrap_or_else(|| "WebSocket error".to_string());
+    io::Error::new(kind, message)
+}
+
+impl WebSocketConnection {
+    /// Open a WebSocket to `url` (`ws://` or `wss://`)
+    pub fn open(url: &str) -> io::Result<Self> {
+        let socket =
+            WebSocket::new(url).map_err(|e| js_error(io::ErrorKind::AddrNotAvailable, e))?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let on_message = {
+            let received = received.clone();
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                // text messages are not MAVLink frames
+                if let Ok(data) = event.data().dyn_into::<ArrayBuffer>() {
+                    let data = Uint8Array::new(&data);
+                    let mut received = received.borrow_mut();
+                    let start = received.len();
+                    received.resize(start + data.length() as usize, 0);
+                    data.copy_to(&mut received[start..]);
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let closed = Rc::new(Cell::new(false));
+        let on_close = {
+            let closed = closed.clone();
+            Closure::wrap(Box::new(move |_: JsValue| closed.set(true)) as Box<dyn FnMut(JsValue)>)
+        };
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            received,
+            closed,
+            sequence: Cell::new(0),
+            protocol_version: MavlinkVersion::V2,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    /// Whether the socket is open, i.e. messages can be sent
+    pub fn is_open(&self) -> bool {
+        self.socket.ready_state() == WebSocket::OPEN
+    }
+
+    /// Close the socket
+    pub fn close(&self) -> io::Result<()> {
+        self.socket
+            .close()
+            .map_err(|e| js_error(io::ErrorKind::Other, e))
+    }
+}
+
+impl Drop for WebSocketConnection {
+    fn drop(&mut self) {
+        // the handlers are freed with the connection
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+impl<M: Message> MavConnection<M> for WebSocketConnection {
+    fn recv(&self) -> Result<(MavHeader, M), MessageReadError> {
+        let mut received = self.received.borrow_mut();
+        let mut reader = &received[..];
+        let result = read_versioned_msg(&mut reader, self.protocol_version);
+        match result {
+            Err(MessageReadError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                // keep the start of the frame until the rest is received
+                Err(MessageReadError::Io(if self.closed.get() {
+                    io::ErrorKind::ConnectionAborted.into()
+                } else {
+                    io::ErrorKind::WouldBlock.into()
+                }))
+            }
+            result => {
+                let consumed = received.len() - reader.len();
+                received.drain(..consumed);
+                result
+            }
+        }
+    }
+
+    fn send(&self, header: &MavHeader, data: &M) -> Result<usize, MessageWriteError> {
+        let header = MavHeader {
+            sequence: self.sequence.get(),
+            system_id: header.system_id,
+            component_id: header.component_id,
+        };
+        self.sequence.set(header.sequence.wrapping_add(1));
+
+        let mut frame = Vec::new();
+        let len = write_versioned_msg(&mut frame, self.protocol_version, header, data)?;
+        self.socket
+            .send_with_u8_array(&frame)
+            .map_err(|e| js_error(io::ErrorKind::NotConnected, e))?;
+        Ok(len)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.protocol_version = version;
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.protocol_version
+    }
+}
@@ -0,0 +1,158 @@
+//! Conversions of the scaled integer positions of the messages.
+//!
+//! Most messages send latitude and longitude in degE7 (degrees * 10^7) and altitudes in mm as
+//! integers. [`LatLon`] and [`AltitudeMm`] keep the integer values of the messages and convert
+//! them from and to degrees and meters, and [`GlobalPosition`] is GLOBAL_POSITION_INT with all
+//! fields in degrees, meters and m/s.
+
+use crate::common::{GLOBAL_POSITION_INT_DATA, GPS_RAW_INT_DATA, HOME_POSITION_DATA};
+
+/// Mean earth radius in m
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A latitude and longitude in degE7, as sent in the `lat` and `lon` fields
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LatLon {
+    pub lat: i32,
+    pub lon: i32,
+}
+
+impl LatLon {
+    pub const fn new(lat: i32, lon: i32) -> Self {
+        Self { lat, lon }
+    }
+
+    /// The position of a latitude and longitude in degrees, rounded to the nearest degE7
+    pub fn from_degrees(lat: f64, lon: f64) -> Self {
+        Self {
+            lat: (lat * 1e7).round() as i32,
+            lon: (lon * 1e7).round() as i32,
+        }
+    }
+
+    /// Latitude in degrees
+    pub fn lat_degrees(&self) -> f64 {
+        f64::from(self.lat) / 1e7
+    }
+
+    /// Longitude in degrees
+    pub fn lon_degrees(&self) -> f64 {
+        f64::from(self.lon) / 1e7
+    }
+
+    /// Latitude and longitude in degrees
+    pub fn to_degrees(&self) -> (f64, f64) {
+        (self.lat_degrees(), self.lon_degrees())
+    }
+
+    /// Great circle distance to another position in m
+    pub fn distance_to(&self, other: &Self) -> f64 {
+        let (lat1, lat2) = (
+            self.lat_degrees().to_radians(),
+            other.lat_degrees().to_radians(),
+        );
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon_degrees() - self.lon_degrees()).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+}
+
+impl From<&GLOBAL_POSITION_INT_DATA> for LatLon {
+    fn from(position: &GLOBAL_POSITION_INT_DATA) -> Self {
+        Self::new(position.lat, position.lon)
+    }
+}
+
+impl From<&GPS_RAW_INT_DATA> for LatLon {
+    fn from(gps: &GPS_RAW_INT_DATA) -> Self {
+        Self::new(gps.lat, gps.lon)
+    }
+}
+
+impl From<&HOME_POSITION_DATA> for LatLon {
+    fn from(home: &HOME_POSITION_DATA) -> Self {
+        Self::new(home.latitude, home.longitude)
+    }
+}
+
+/// An altitude in mm, as sent in the `alt` fields
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AltitudeMm(pub i32);
+
+impl AltitudeMm {
+    /// The altitude of a height in m, rounded to the nearest mm
+    pub fn from_meters(meters: f64) -> Self {
+        Self((meters * 1000.0).round() as i32)
+    }
+
+    /// Altitude in m
+    pub fn meters(&self) -> f64 {
+        f64::from(self.0) / 1000.0
+    }
+}
+
+/// GLOBAL_POSITION_INT in degrees, meters and m/s
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct GlobalPosition {
+    /// Latitude in degrees
+    pub lat: f64,
+    /// Longitude in degrees
+    pub lon: f64,
+    /// Altitude above mean sea level in m
+    pub alt: f64,
+    /// Altitude above home in m
+    pub relative_alt: f64,
+    /// Velocity north in m/s
+    pub vx: f64,
+    /// Velocity east in m/s
+    pub vy: f64,
+    /// Velocity down in m/s
+    pub vz: f64,
+    /// Heading in degrees 0..360, `None` if unknown
+    pub heading: Option<f64>,
+}
+
+impl GlobalPosition {
+    /// Latitude and longitude in degE7
+    pub fn lat_lon(&self) -> LatLon {
+        LatLon::from_degrees(self.lat, self.lon)
+    }
+
+    /// GLOBAL_POSITION_INT of the position at a time since boot, with every field rounded to the
+    /// resolution of the message
+    pub fn to_message(&self, time_boot_ms: u32) -> GLOBAL_POSITION_INT_DATA {
+        let lat_lon = self.lat_lon();
+        GLOBAL_POSITION_INT_DATA {
+            time_boot_ms,
+            lat: lat_lon.lat,
+            lon: lat_lon.lon,
+            alt: AltitudeMm::from_meters(self.alt).0,
+            relative_alt: AltitudeMm::from_meters(self.relative_alt).0,
+            vx: (self.vx * 100.0).round() as i16,
+            vy: (self.vy * 100.0).round() as i16,
+            vz: (self.vz * 100.0).round() as i16,
+            hdg: self.heading.map_or(u16::MAX, |heading| {
+                ((heading.rem_euclid(360.0) * 100.0).round() as u16) % 36000
+            }),
+        }
+    }
+}
+
+impl From<&GLOBAL_POSITION_INT_DATA> for GlobalPosition {
+    fn from(position: &GLOBAL_POSITION_INT_DATA) -> Self {
+        let (lat, lon) = LatLon::from(position).to_degrees();
+        Self {
+            lat,
+            lon,
+            alt: AltitudeMm(position.alt).meters(),
+            relative_alt: AltitudeMm(position.relative_alt).meters(),
+            vx: f64::from(position.vx) / 100.0,
+            vy: f64::from(position.vy) / 100.0,
+            vz: f64::from(position.vz) / 100.0,
+            heading: Some(position.hdg)
+                .filter(|hdg| *hdg != u16::MAX)
+                .map(|hdg| f64::from(hdg) / 100.0),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Annotated hex dumps of raw frames.
+//!
+//! [`DebugFrame`] lays out the bytes of frames field by field, so framing bugs can be read off a
+//! log line: where the start marker was found, what the header claims, where the payload ends and
+//! whether the checksum matches. It takes arbitrary bytes, so garbage, truncated frames and
+//! several frames in a row are shown as such:
+//! ```text
+//! MAVLink 2 frame, 21 bytes
+//!     0  fd                       STX, MAVLink 2
+//!     1  09                       payload length 9
+//!     2  00                       incompatibility flags 0x00
+//!     3  00                       compatibility flags 0x00
+//!     4  4e                       sequence 78
+//!     5  01                       system id 1
+//!     6  01                       component id 1
+//!     7  00 00 00                 message id 0 (HEARTBEAT)
+//!    10  05 00 00 00 02 03 51 04  payload, 9 bytes
+//!    18  03
+//!    19  1c 7f                    checksum 0x7f1c, valid
+//! ```
+
+use core::fmt;
+
+use crate::{calculate_crc, Message, MAVLINK_IFLAG_SIGNED, MAV_STX, MAV_STX_V2};
+
+const BYTES_PER_ROW: usize = 8;
+
+/// Formatter printing bytes as an annotated hex dump of the frames in them, with both `{}` and
+/// `{:?}`
+#[derive(Clone, Copy)]
+pub struct DebugFrame<'a> {
+    bytes: &'a [u8],
+    extra_crc: Option<fn(u32) -> u8>,
+    message_name: Option<fn(u32) -> Option<&'static str>>,
+}
+
+impl<'a> DebugFrame<'a> {
+    /// Dump of `bytes`, without checking the checksums
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            extra_crc: None,
+            message_name: None,
+        }
+    }
+
+    /// Name the messages and check the checksums with the message set `M`
+    pub fn with_message_set<M: Message>(mut self) -> Self {
+        self.extra_crc = Some(M::extra_crc);
+        self.message_name = Some(|id| {
+            M::default_message_from_id(id)
+                .ok()
+                .map(|msg| msg.message_name())
+        });
+        self
+    }
+
+    fn message_name(&self, id: u32) -> Option<&'static str> {
+        self.message_name.and_then(|name| name(id))
+    }
+
+    /// Dump the frame at `start`, returning where the next one starts if this one is complete
+    fn fmt_frame(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        start: usize,
+    ) -> Result<Option<usize>, fmt::Error> {
+        let bytes = self.bytes;
+        // the message id takes the last byte of a MAVLink 1 header and 3 of a MAVLink 2 one
+        let (version, header_size, id_size) = match bytes[start] {
+            MAV_STX => (1, 5, 1),
+            _ => (2, 9, 3),
+        };
+        let signed = version == 2
+            && bytes
+                .get(start + 2)
+                .map_or(false, |flags| flags & MAVLINK_IFLAG_SIGNED != 0);
+        match bytes.get(start + 1) {
+            Some(&payload_length) => {
+                let size =
+                    1 + header_size + usize::from(payload_length) + 2 + if signed { 13 } else { 0 };
+                write!(f, "MAVLink {version} frame, {size} bytes")?;
+                if bytes.len() - start < size {
+                    write!(f, ", truncated to {}", bytes.len() - start)?;
+                }
+                writeln!(f)?;
+            }
+            None => writeln!(f, "MAVLink {version} frame, truncated to 1 byte")?,
+        }
+
+        let mut fields = Fields {
+            bytes,
+            offset: start,
+        };
+        macro_rules! field {
+            ($name:expr, $len:expr) => {
+                match fields.take(f, $name, $len)? {
+                    Some(field) => field,
+                    None => return Ok(None),
+                }
+            };
+        }
+
+        let stx = field!("STX", 1);
+        row(f, start, stx, format_args!("STX, MAVLink {version}"))?;
+
+        let offset = fields.offset;
+        let payload_length = field!("payload length", 1)[0];
+        row(
+            f,
+            offset,
+            &[payload_length],
+            format_args!("payload length {payload_length}"),
+        )?;
+
+        if version == 2 {
+            let offset = fields.offset;
+            let flags = field!("incompatibility flags", 1)[0];
+            write_row(f, offset, &[flags])?;
+            write!(f, "incompatibility flags {flags:#04x}")?;
+            if flags & MAVLINK_IFLAG_SIGNED != 0 {
+                write!(f, ", signed")?;
+            }
+            if flags & !MAVLINK_IFLAG_SIGNED != 0 {
+                write!(f, ", unknown {:#04x}", flags & !MAVLINK_IFLAG_SIGNED)?;
+            }
+            writeln!(f)?;
+
+            let offset = fields.offset;
+            let flags = field!("compatibility flags", 1)[0];
+            row(
+                f,
+                offset,
+                &[flags],
+                format_args!("compatibility flags {flags:#04x}"),
+            )?;
+        }
+
+        let offset = fields.offset;
+        let sequence = field!("sequence", 1)[0];
+        row(f, offset, &[sequence], format_args!("sequence {sequence}"))?;
+        let offset = fields.offset;
+        let system_id = field!("system id", 1)[0];
+        row(
+            f,
+            offset,
+            &[system_id],
+            format_args!("system id {system_id}"),
+        )?;
+        let offset = fields.offset;
+        let component_id = field!("component id", 1)[0];
+        row(
+            f,
+            offset,
+            &[component_id],
+            format_args!("component id {component_id}"),
+        )?;
+
+        let offset = fields.offset;
+        let id_bytes = field!("message id", id_size);
+        let mut id = [0; 4];
+        id[..id_bytes.len()].copy_from_slice(id_bytes);
+        let message_id = u32::from_le_bytes(id);
+        write_row(f, offset, id_bytes)?;
+        write!(f, "message id {message_id}")?;
+        if self.message_name.is_some() {
+            write!(
+                f,
+                " ({})",
+                self.message_name(message_id).unwrap_or("unknown")
+            )?;
+        }
+        writeln!(f)?;
+
+        let offset = fields.offset;
+        let payload = field!("payload", usize::from(payload_length));
+        row(
+            f,
+            offset,
+            payload,
+            format_args!("payload, {} bytes", payload.len()),
+        )?;
+
+        let offset = fields.offset;
+        let checksum = field!("checksum", 2);
+        let checksum = u16::from_le_bytes([checksum[0], checksum[1]]);
+        write_row(f, offset, &checksum.to_le_bytes())?;
+        write!(f, "checksum {checksum:#06x}")?;
+        if let (Some(extra_crc), Some(_)) = (self.extra_crc, self.message_name(message_id)) {
+            let expected = calculate_crc(&bytes[(start + 1)..offset], extra_crc(message_id));
+            if checksum == expected {
+                write!(f, ", valid")?;
+            } else {
+                write!(f, ", invalid, expected {expected:#06x}")?;
+            }
+        }
+        writeln!(f)?;
+
+        if signed {
+            let offset = fields.offset;
+            let link_id = field!("link id", 1)[0];
+            row(f, offset, &[link_id], format_args!("link id {link_id}"))?;
+
+            let offset = fields.offset;
+            let timestamp_bytes = field!("timestamp", 6);
+            let mut timestamp = [0; 8];
+            timestamp[..6].copy_from_slice(timestamp_bytes);
+            row(
+                f,
+                offset,
+                timestamp_bytes,
+                format_args!(
+                    "timestamp {}, 10 us units since 2015",
+                    u64::from_le_bytes(timestamp)
+                ),
+            )?;
+
+            let offset = fields.offset;
+            let signature = field!("signature", 6);
+            row(f, offset, signature, format_args!("signature"))?;
+        }
+        Ok(Some(fields.offset))
+    }
+}
+
+impl fmt::Display for DebugFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bytes.is_empty() {
+            return writeln!(f, "no bytes");
+        }
+
+        let mut offset = 0;
+        while offset < self.bytes.len() {
+            let rest = &self.bytes[offset..];
+            let garbage = rest
+                .iter()
+                .position(|byte| *byte == MAV_STX || *byte == MAV_STX_V2)
+                .unwrap_or(rest.len());
+            if garbage > 0 {
+                writeln!(f, "{garbage} bytes without start marker")?;
+                row(f, offset, &rest[..garbage], format_args!("skipped"))?;
+                offset += garbage;
+                continue;
+            }
+            match self.fmt_frame(f, offset)? {
+                Some(next) => offset = next,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DebugFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Fields of a frame, in order
+struct Fields<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Fields<'a> {
+    /// Next `len` bytes, or `None` after dumping what is left if the bytes end before
+    fn take(
+        &mut self,
+        f: &mut fmt::Formatter<'_>,
+        name: &str,
+        len: usize,
+    ) -> Result<Option<&'a [u8]>, fmt::Error> {
+        let offset = self.offset;
+        let available = len.min(self.bytes.len() - offset);
+        let field = &self.bytes[offset..(offset + available)];
+        self.offset += available;
+        if available == len {
+            return Ok(Some(field));
+        }
+        if available == 0 {
+            write_row(f, offset, &[])?;
+            writeln!(f, "{name} missing")?;
+        } else {
+            row(
+                f,
+                offset,
+                field,
+                format_args!("{name}, truncated to {available} of {len} bytes"),
+            )?;
+        }
+        Ok(None)
+    }
+}
+
+/// Write `bytes` and their description, wrapping the bytes into rows below it
+fn row(
+    f: &mut fmt::Formatter<'_>,
+    offset: usize,
+    bytes: &[u8],
+    description: fmt::Arguments<'_>,
+) -> fmt::Result {
+    let first = bytes.len().min(BYTES_PER_ROW);
+    write_row(f, offset, &bytes[..first])?;
+    writeln!(f, "{description}")?;
+    for (index, chunk) in bytes[first..].chunks(BYTES_PER_ROW).enumerate() {
+        write!(f, "{:>5} ", offset + (index + 1) * BYTES_PER_ROW)?;
+        for byte in chunk {
+            write!(f, " {byte:02x}")?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// Write the offset and at most a row of `bytes`, padded to where the description starts
+fn write_row(f: &mut fmt::Formatter<'_>, offset: usize, bytes: &[u8]) -> fmt::Result {
+    debug_assert!(bytes.len() <= BYTES_PER_ROW);
+    write!(f, "{offset:>5} ")?;
+    for byte in bytes {
+        write!(f, " {byte:02x}")?;
+    }
+    write!(f, "{:w$}  ", "", w = 3 * (BYTES_PER_ROW - bytes.len()))
+}
@@ -0,0 +1,405 @@
+//! Differences between two recorded telemetry streams.
+//!
+//! [`Differ`] aligns two [`Recording`]s, e.g. of the same mission flown before and after a
+//! firmware change, by the time since their first message and by the sender and id of the
+//! messages. It reports how the rate of every message changed and how far the values of its
+//! fields moved apart between messages that passed at about the same time. The fields are taken
+//! from the serde representation of the messages, array elements and nested values flattened like
+//! the CSV columns of `mavlink-convert`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::recording::Recording;
+use crate::Message;
+
+/// Differences of one field between aligned messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub name: String,
+    /// Pairs of aligned messages compared
+    pub compared: u64,
+    /// Pairs whose values differ by more than the tolerance
+    pub differing: u64,
+    /// Largest absolute difference of a numeric field, 0 for the others
+    pub max_difference: f64,
+    /// Mean absolute difference of a numeric field over all compared pairs, 0 for the others
+    pub mean_difference: f64,
+}
+
+/// Differences of the messages of one id from one sender
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageDiff {
+    pub system_id: u8,
+    pub component_id: u8,
+    pub message_id: u32,
+    pub name: &'static str,
+    /// Number of messages in the first and the second stream
+    pub before: u64,
+    pub after: u64,
+    /// Messages per second over the first and the second stream
+    pub before_rate: f64,
+    pub after_rate: f64,
+    /// The message is missing from one stream or its rate changed by more than the tolerance
+    pub rate_changed: bool,
+    /// Fields differing in at least one pair of aligned messages
+    pub fields: Vec<FieldDiff>,
+}
+
+impl MessageDiff {
+    /// Relative change of the rate, `None` if the message is missing from the first stream
+    pub fn rate_change(&self) -> Option<f64> {
+        if self.before_rate > 0.0 {
+            Some(self.after_rate / self.before_rate - 1.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.rate_changed || !self.fields.is_empty()
+    }
+}
+
+/// Differences between two streams, by sender and message id
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamDiff {
+    /// Time from the first to the last message of each stream
+    pub before_duration: Duration,
+    pub after_duration: Duration,
+    pub messages: Vec<MessageDiff>,
+}
+
+impl StreamDiff {
+    /// Messages missing from a stream, with a changed rate or with differing fields
+    pub fn changed(&self) -> impl Iterator<Item = &MessageDiff> {
+        self.messages.iter().filter(|message| message.is_changed())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed().next().is_none()
+    }
+}
+
+impl fmt::Display for StreamDiff {
+    /// Report of the changed messages
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "before {:.1} s, after {:.1} s",
+            self.before_duration.as_secs_f64(),
+            self.after_duration.as_secs_f64()
+        )?;
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for message in self.changed() {
+            write!(
+                f,
+                "{}:{} {}: ",
+                message.system_id, message.component_id, message.name
+            )?;
+            match (message.before, message.after) {
+                (_, 0) => writeln!(f, "only before, {:.1} Hz", message.before_rate)?,
+                (0, _) => writeln!(f, "only after, {:.1} Hz", message.after_rate)?,
+                _ => {
+                    write!(
+                        f,
+                        "{:.1} Hz -> {:.1} Hz",
+                        message.before_rate, message.after_rate
+                    )?;
+                    if let Some(change) = message.rate_change() {
+                        write!(f, " ({:+.1}%)", change * 100.0)?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+            for field in &message.fields {
+                write!(
+                    f,
+                    "  {}: {} of {} differ",
+                    field.name, field.differing, field.compared
+                )?;
+                if field.max_difference > 0.0 {
+                    write!(
+                        f,
+                        ", max {}, mean {}",
+                        field.max_difference, field.mean_difference
+                    )?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare two streams
+pub fn diff<M: Message + Serialize>(before: &Recording<M>, after: &Recording<M>) -> StreamDiff {
+    Differ::new().diff(before, after)
+}
+
+/// Value of a flattened field
+#[derive(Debug, Clone, PartialEq)]
+enum FieldValue {
+    Number(f64),
+    /// Enum entries by name, `char` arrays
+    Text(String),
+}
+
+/// Messages of one sender and id, by time since the start of the stream in microseconds
+type Series = Vec<(u64, Vec<(String, FieldValue)>)>;
+
+struct Stream {
+    duration: Duration,
+    series: BTreeMap<(u8, u8, u32), (&'static str, Series)>,
+}
+
+#[derive(Debug, Default)]
+struct FieldStats {
+    compared: u64,
+    differing: u64,
+    max_difference: f64,
+    sum_difference: f64,
+}
+
+/// Settings of the comparison
+#[derive(Debug, Clone, PartialEq)]
+pub struct Differ {
+    max_skew: Duration,
+    tolerance: f64,
+    rate_tolerance: f64,
+    ignored: Vec<String>,
+}
+
+impl Default for Differ {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Differ {
+    /// Align messages up to 100 ms apart, report any difference of their values and rate changes
+    /// above 10%, and ignore the `time_*` fields
+    pub fn new() -> Self {
+        Self {
+            max_skew: Duration::from_millis(100),
+            tolerance: 0.0,
+            rate_tolerance: 0.1,
+            ignored: vec!["time_".to_string()],
+        }
+    }
+
+    /// Largest time between two messages to compare, relative to the start of their streams
+    pub fn with_max_skew(mut self, max_skew: Duration) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    /// Largest absolute difference of numeric values not reported
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Largest relative change of a rate not reported, e.g. 0.1 for 10%
+    pub fn with_rate_tolerance(mut self, rate_tolerance: f64) -> Self {
+        self.rate_tolerance = rate_tolerance;
+        self
+    }
+
+    /// Prefixes of the names of the fields not to compare, replacing the default `time_`
+    pub fn with_ignored_fields(mut self, prefixes: &[&str]) -> Self {
+        self.ignored = prefixes.iter().map(|prefix| prefix.to_string()).collect();
+        self
+    }
+
+    pub fn diff<M: Message + Serialize>(
+        &self,
+        before: &Recording<M>,
+        after: &Recording<M>,
+    ) -> StreamDiff {
+        let before = self.stream(before);
+        let after = self.stream(after);
+
+        let mut keys: Vec<_> = before.series.keys().chain(after.series.keys()).collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let empty = Series::new();
+        let messages = keys
+            .into_iter()
+            .map(|key| {
+                let (name, before_series) = before
+                    .series
+                    .get(key)
+                    .map_or(("", &empty), |(name, series)| (*name, series));
+                let (name, after_series) = after
+                    .series
+                    .get(key)
+                    .map_or((name, &empty), |(name, series)| (*name, series));
+
+                let before_rate = rate(before_series.len(), before.duration);
+                let after_rate = rate(after_series.len(), after.duration);
+                let rate_changed = before_series.is_empty()
+                    || after_series.is_empty()
+                    || (after_rate - before_rate).abs() > before_rate * self.rate_tolerance;
+
+                MessageDiff {
+                    system_id: key.0,
+                    component_id: key.1,
+                    message_id: key.2,
+                    name,
+                    before: before_series.len() as u64,
+                    after: after_series.len() as u64,
+                    before_rate,
+                    after_rate,
+                    rate_changed,
+                    fields: self.compare(before_series, after_series),
+                }
+            })
+            .collect();
+
+        StreamDiff {
+            before_duration: before.duration,
+            after_duration: after.duration,
+            messages,
+        }
+    }
+
+    fn stream<M: Message + Serialize>(&self, recording: &Recording<M>) -> Stream {
+        let start = recording.messages.iter().map(|m| m.timestamp).min();
+        let end = recording.messages.iter().map(|m| m.timestamp).max();
+        let duration = match (start, end) {
+            (Some(start), Some(end)) => Duration::from_micros(end - start),
+            _ => Duration::ZERO,
+        };
+
+        let mut series: BTreeMap<_, (&'static str, Series)> = BTreeMap::new();
+        for message in &recording.messages {
+            let key = (
+                message.header.system_id,
+                message.header.component_id,
+                message.msg.message_id(),
+            );
+            let time = message.timestamp - start.unwrap_or_default();
+            series
+                .entry(key)
+                .or_insert_with(|| (message.msg.message_name(), Series::new()))
+                .1
+                .push((time, self.fields(&message.msg)));
+        }
+        for (_, series) in series.values_mut() {
+            series.sort_by_key(|(time, _)| *time);
+        }
+        Stream { duration, series }
+    }
+
+    /// Compared fields of a message
+    fn fields<M: Serialize>(&self, msg: &M) -> Vec<(String, FieldValue)> {
+        let mut fields = Vec::new();
+        if let Ok(Value::Object(map)) = serde_json::to_value(msg) {
+            for (name, value) in map {
+                // the message name
+                if name == "type" || self.ignored.iter().any(|prefix| name.starts_with(prefix)) {
+                    continue;
+                }
+                flatten(name, &value, &mut fields);
+            }
+        }
+        fields
+    }
+
+    /// Compare every message of `before` with the one of `after` nearest in time
+    fn compare(&self, before: &Series, after: &Series) -> Vec<FieldDiff> {
+        let max_skew = self.max_skew.as_micros() as u64;
+        let mut stats: BTreeMap<&str, FieldStats> = BTreeMap::new();
+        for (time, fields) in before {
+            let index = after.partition_point(|(other, _)| other < time);
+            let nearest = [index.checked_sub(1), Some(index)]
+                .iter()
+                .flatten()
+                .filter_map(|index| after.get(*index))
+                .min_by_key(|(other, _)| other.abs_diff(*time));
+            let other_fields = match nearest {
+                Some((other, fields)) if other.abs_diff(*time) <= max_skew => fields,
+                _ => continue,
+            };
+
+            for (name, value) in fields {
+                let other = match other_fields.iter().find(|(other, _)| other == name) {
+                    Some((_, other)) => other,
+                    None => continue,
+                };
+                let stats = stats.entry(name).or_default();
+                stats.compared += 1;
+                let differs = match (value, other) {
+                    (FieldValue::Number(value), FieldValue::Number(other)) => {
+                        let difference = (value - other).abs();
+                        stats.max_difference = stats.max_difference.max(difference);
+                        stats.sum_difference += difference;
+                        difference > self.tolerance
+                    }
+                    (value, other) => value != other,
+                };
+                if differs {
+                    stats.differing += 1;
+                }
+            }
+        }
+
+        stats
+            .into_iter()
+            .filter(|(_, stats)| stats.differing > 0)
+            .map(|(name, stats)| FieldDiff {
+                name: name.to_string(),
+                compared: stats.compared,
+                differing: stats.differing,
+                max_difference: stats.max_difference,
+                mean_difference: stats.sum_difference / stats.compared as f64,
+            })
+            .collect()
+    }
+}
+
+fn rate(count: usize, duration: Duration) -> f64 {
+    if duration.is_zero() {
+        0.0
+    } else {
+        count as f64 / duration.as_secs_f64()
+    }
+}
+
+/// Flatten a value of the serde representation into named fields
+fn flatten(name: String, value: &Value, fields: &mut Vec<(String, FieldValue)>) {
+    match value {
+        Value::Null => {}
+        Value::Bool(value) => fields.push((name, FieldValue::Number(f64::from(u8::from(*value))))),
+        Value::Number(value) => {
+            fields.push((name, FieldValue::Number(value.as_f64().unwrap_or_default())))
+        }
+        Value::String(value) => fields.push((name, FieldValue::Text(value.clone()))),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten(format!("{name}[{index}]"), item, fields);
+            }
+        }
+        // enum entries are tagged with their name
+        Value::Object(map) if map.len() == 1 && map.contains_key("type") => {
+            flatten(name, &map["type"], fields)
+        }
+        // bitflags are wrapped in their bits
+        Value::Object(map) if map.len() == 1 && map.contains_key("bits") => {
+            flatten(name, &map["bits"], fields)
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(format!("{name}.{key}"), value, fields);
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Discovery of the systems and components on a network from their heartbeats.
+//!
+//! [`ComponentRegistry`] records every component that sends a HEARTBEAT together with its type,
+//! autopilot, mode and, once AUTOPILOT_VERSION was received, its capabilities. Changes are
+//! reported as [`DiscoveryEvent`]s so the application can react to components appearing or
+//! disappearing.
+//!
+//! Before there is a connection, [`NetworkDiscovery`] listens on the UDP ports vehicles and
+//! simulators usually send to and reports the systems heard there with their source address, so
+//! tools can offer to connect to a detected vehicle.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "udp")]
+use std::io;
+#[cfg(feature = "udp")]
+use std::net::{SocketAddr, UdpSocket};
+#[cfg(feature = "udp")]
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::{
+    MavAutopilot, MavMessage, MavModeFlag, MavProtocolCapability, MavState, MavType,
+};
+#[cfg(feature = "udp")]
+use crate::error::MessageReadError;
+use crate::MavHeader;
+#[cfg(feature = "udp")]
+use crate::{read_versioned_msg, MavlinkVersion};
+
+/// A component is considered lost after missing this many heartbeats at the nominal 1 Hz
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What is known about a component
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentInfo {
+    pub system_id: u8,
+    pub component_id: u8,
+    pub mavtype: MavType,
+    pub autopilot: MavAutopilot,
+    pub base_mode: MavModeFlag,
+    pub custom_mode: u32,
+    pub system_status: MavState,
+    /// Capabilities from AUTOPILOT_VERSION, if the component sent it
+    pub capabilities: Option<MavProtocolCapability>,
+    pub first_seen: Instant,
+    pub last_seen: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscoveryEvent {
+    /// First heartbeat of a component, or first one after it was lost
+    Discovered(ComponentInfo),
+    /// The type, mode, state or capabilities of a known component changed
+    Changed(ComponentInfo),
+    /// No heartbeat was received within the timeout
+    Lost(ComponentInfo),
+}
+
+/// Registry of the components seen on a connection
+#[derive(Debug, Clone)]
+pub struct ComponentRegistry {
+    timeout: Duration,
+    components: BTreeMap<(u8, u8), ComponentInfo>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            components: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Record a received message
+    pub fn update(
+        &mut self,
+        now: Instant,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Option<DiscoveryEvent> {
+        let key = (header.system_id, header.component_id);
+        match msg {
+            MavMessage::HEARTBEAT(heartbeat) => match self.components.get_mut(&key) {
+                Some(info) => {
+                    info.last_seen = now;
+                    let changed = info.mavtype != heartbeat.mavtype
+                        || info.autopilot != heartbeat.autopilot
+                        || info.base_mode != heartbeat.base_mode
+                        || info.custom_mode != heartbeat.custom_mode
+                        || info.system_status != heartbeat.system_status;
+                    if !changed {
+                        return None;
+                    }
+                    info.mavtype = heartbeat.mavtype;
+                    info.autopilot = heartbeat.autopilot;
+                    info.base_mode = heartbeat.base_mode;
+                    info.custom_mode = heartbeat.custom_mode;
+                    info.system_status = heartbeat.system_status;
+                    Some(DiscoveryEvent::Changed(info.clone()))
+                }
+                None => {
+                    let info = ComponentInfo {
+                        system_id: header.system_id,
+                        component_id: header.component_id,
+                        mavtype: heartbeat.mavtype,
+                        autopilot: heartbeat.autopilot,
+                        base_mode: heartbeat.base_mode,
+                        custom_mode: heartbeat.custom_mode,
+                        system_status: heartbeat.system_status,
+                        capabilities: None,
+                        first_seen: now,
+                        last_seen: now,
+                    };
+                    self.components.insert(key, info.clone());
+                    Some(DiscoveryEvent::Discovered(info))
+                }
+            },
+            MavMessage::AUTOPILOT_VERSION(version) => {
+                let info = self.components.get_mut(&key)?;
+                if info.capabilities == Some(version.capabilities) {
+                    return None;
+                }
+                info.capabilities = Some(version.capabilities);
+                Some(DiscoveryEvent::Changed(info.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Remove the components whose heartbeat timed out
+    pub fn expire(&mut self, now: Instant) -> Vec<DiscoveryEvent> {
+        let timeout = self.timeout;
+        let lost: Vec<_> = self
+            .components
+            .iter()
+            .filter(|(_, info)| now.saturating_duration_since(info.last_seen) > timeout)
+            .map(|(key, _)| *key)
+            .collect();
+
+        lost.into_iter()
+            .filter_map(|key| self.components.remove(&key))
+            .map(DiscoveryEvent::Lost)
+            .collect()
+    }
+
+    pub fn get(&self, system_id: u8, component_id: u8) -> Option<&ComponentInfo> {
+        self.components.get(&(system_id, component_id))
+    }
+
+    /// All known components, ordered by system and component id
+    pub fn components(&self) -> impl Iterator<Item = &ComponentInfo> {
+        self.components.values()
+    }
+
+    /// Components of one system
+    pub fn system(&self, system_id: u8) -> impl Iterator<Item = &ComponentInfo> {
+        self.components
+            .range((system_id, 0)..=(system_id, u8::MAX))
+            .map(|(_, info)| info)
+    }
+
+    /// The flight controller of a system, i.e. the component with an actual autopilot
+    pub fn autopilot(&self, system_id: u8) -> Option<&ComponentInfo> {
+        self.system(system_id)
+            .find(|info| info.autopilot != MavAutopilot::MAV_AUTOPILOT_INVALID)
+    }
+}
+
+/// UDP ports MAVLink endpoints commonly send to: ground stations and offboard APIs
+#[cfg(feature = "udp")]
+pub const DISCOVERY_PORTS: [u16; 2] = [14550, 14540];
+
+/// A component whose heartbeats were received on the network
+#[cfg(feature = "udp")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedEndpoint {
+    pub system_id: u8,
+    pub component_id: u8,
+    pub mavtype: MavType,
+    pub autopilot: MavAutopilot,
+    pub version: MavlinkVersion,
+    /// Address the heartbeats were sent from
+    pub source: SocketAddr,
+    /// Local port the heartbeats were received on
+    pub port: u16,
+    pub last_seen: Instant,
+}
+
+#[cfg(feature = "udp")]
+impl DetectedEndpoint {
+    /// Address for [`connect`](crate::connect) to receive from and reply to the endpoint
+    pub fn connection_address(&self) -> String {
+        format!("udpin:0.0.0.0:{}", self.port)
+    }
+}
+
+/// Listener for heartbeats on UDP ports, including broadcasts to them
+#[cfg(feature = "udp")]
+#[derive(Debug)]
+pub struct NetworkDiscovery {
+    sockets: Vec<UdpSocket>,
+    endpoints: Vec<DetectedEndpoint>,
+}
+
+#[cfg(feature = "udp")]
+impl NetworkDiscovery {
+    /// Listen on [`DISCOVERY_PORTS`]
+    pub fn new() -> io::Result<Self> {
+        Self::with_ports(&DISCOVERY_PORTS)
+    }
+
+    /// Listen on the given ports of all interfaces, skipping the ones already in use, e.g. by a
+    /// running ground station
+    pub fn with_ports(ports: &[u16]) -> io::Result<Self> {
+        let mut sockets = Vec::new();
+        let mut error = None;
+        for &port in ports {
+            match UdpSocket::bind(("0.0.0.0", port)).and_then(|socket| {
+                socket.set_nonblocking(true)?;
+                Ok(socket)
+            }) {
+                Ok(socket) => sockets.push(socket),
+                Err(e) => error = Some(e),
+            }
+        }
+        match error {
+            Some(e) if sockets.is_empty() => Err(e),
+            _ => Ok(Self {
+                sockets,
+                endpoints: Vec::new(),
+            }),
+        }
+    }
+
+    /// The ports listened on
+    pub fn ports(&self) -> Vec<u16> {
+        self.sockets
+            .iter()
+            .filter_map(|socket| socket.local_addr().ok())
+            .map(|addr| addr.port())
+            .collect()
+    }
+
+    /// Handle the datagrams received so far without blocking and return the endpoints detected
+    /// for the first time
+    pub fn poll(&mut self, now: Instant) -> io::Result<Vec<DetectedEndpoint>> {
+        let mut detected = Vec::new();
+        let mut buf = [0; 65536];
+        for i in 0..self.sockets.len() {
+            let port = self.sockets[i].local_addr()?.port();
+            loop {
+                let (len, source) = match self.sockets[i].recv_from(&mut buf) {
+                    Ok(received) => received,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e),
+                };
+                detected.extend(self.handle_datagram(now, port, source, &buf[..len]));
+            }
+        }
+        Ok(detected)
+    }
+
+    fn handle_datagram(
+        &mut self,
+        now: Instant,
+        port: u16,
+        source: SocketAddr,
+        datagram: &[u8],
+    ) -> Vec<DetectedEndpoint> {
+        let mut detected = Vec::new();
+        for &version in [MavlinkVersion::V2, MavlinkVersion::V1].iter() {
+            let mut reader = datagram;
+            loop {
+                let (header, heartbeat) =
+                    match read_versioned_msg::<MavMessage, _>(&mut reader, version) {
+                        Ok((header, MavMessage::HEARTBEAT(heartbeat))) => (header, heartbeat),
+                        Ok(_) | Err(MessageReadError::Parse(_)) => continue,
+                        Err(MessageReadError::Io(_)) => break,
+                    };
+
+                let known = self.endpoints.iter_mut().find(|endpoint| {
+                    endpoint.source == source
+                        && endpoint.system_id == header.system_id
+                        && endpoint.component_id == header.component_id
+                });
+                match known {
+                    Some(endpoint) => {
+                        endpoint.mavtype = heartbeat.mavtype;
+                        endpoint.autopilot = heartbeat.autopilot;
+                        endpoint.version = version;
+                        endpoint.last_seen = now;
+                    }
+                    None => {
+                        let endpoint = DetectedEndpoint {
+                            system_id: header.system_id,
+                            component_id: header.component_id,
+                            mavtype: heartbeat.mavtype,
+                            autopilot: heartbeat.autopilot,
+                            version,
+                            source,
+                            port,
+                            last_seen: now,
+                        };
+                        self.endpoints.push(endpoint.clone());
+                        detected.push(endpoint);
+                    }
+                }
+            }
+        }
+        detected
+    }
+
+    /// All endpoints detected so far, in the order they were detected
+    pub fn endpoints(&self) -> &[DetectedEndpoint] {
+        &self.endpoints
+    }
+}
+
+/// Listen on [`DISCOVERY_PORTS`] for `duration` and return the detected endpoints
+#[cfg(feature = "udp")]
+pub fn discover(duration: Duration) -> io::Result<Vec<DetectedEndpoint>> {
+    let mut discovery = NetworkDiscovery::new()?;
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        discovery.poll(Instant::now())?;
+        thread::sleep(Duration::from_millis(10));
+    }
+    Ok(discovery.endpoints)
+}
@@ -2,11 +2,24 @@ use core::fmt::{Display, Formatter};
 #[cfg(feature = "std")]
 use std::error::Error;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ParserError {
-    InvalidFlag { flag_type: &'static str, value: u32 },
-    InvalidEnum { enum_type: &'static str, value: u32 },
-    UnknownMessage { id: u32 },
+    InvalidFlag {
+        flag_type: &'static str,
+        value: u32,
+    },
+    InvalidEnum {
+        enum_type: &'static str,
+        value: u32,
+    },
+    UnknownMessage {
+        id: u32,
+    },
+    /// A frame was parsed as a specific message but holds another one
+    UnexpectedMessage {
+        expected: u32,
+        id: u32,
+    },
 }
 
 impl Display for ParserError {
@@ -21,6 +34,9 @@ impl Display for ParserError {
                 "Invalid enum value for enum type {enum_type:?}, got {value:?}"
             ),
             Self::UnknownMessage { id } => write!(f, "Unknown message with ID {id:?}"),
+            Self::UnexpectedMessage { expected, id } => {
+                write!(f, "Expected message with ID {expected:?}, got {id:?}")
+            }
         }
     }
 }
@@ -71,6 +87,8 @@ pub enum MessageWriteError {
     Io(std::io::Error),
     #[cfg(feature = "embedded")]
     Io,
+    /// The message id does not fit in a MAVLink 1 frame
+    MAVLink2Only,
 }
 
 impl Display for MessageWriteError {
@@ -80,6 +98,7 @@ impl Display for MessageWriteError {
             Self::Io(e) => write!(f, "Failed to write message: {e:#?}"),
             #[cfg(feature = "embedded")]
             Self::Io => write!(f, "Failed to write message"),
+            Self::MAVLink2Only => write!(f, "Message is not supported in MAVLink 1"),
         }
     }
 }
@@ -93,3 +112,43 @@ impl From<std::io::Error> for MessageWriteError {
         Self::Io(e)
     }
 }
+
+/// Failure of an exchange with another component, like a command, a parameter or mission
+/// transfer, or a request for a message
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// No answer was received, even after resending
+    Timeout,
+    /// The message with this id is not part of the message set of the connection
+    Unsupported(u32),
+    Read(MessageReadError),
+    Write(MessageWriteError),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "No answer was received"),
+            Self::Unsupported(id) => {
+                write!(f, "Message with ID {id} is not supported by the connection")
+            }
+            Self::Read(e) => write!(f, "{e}"),
+            Self::Write(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ProtocolError {}
+
+impl From<MessageReadError> for ProtocolError {
+    fn from(e: MessageReadError) -> Self {
+        Self::Read(e)
+    }
+}
+
+impl From<MessageWriteError> for ProtocolError {
+    fn from(e: MessageWriteError) -> Self {
+        Self::Write(e)
+    }
+}
@@ -0,0 +1,209 @@
+//! Failsafe conditions of a vehicle as seen from a ground station or companion computer.
+//!
+//! [`FailsafeMonitor`] watches the heartbeat of one component, the RADIO_STATUS reports of the
+//! telemetry radios and the main battery, and turns them into [`FailsafeEvent`]s. Thresholds on
+//! measured values apply a hysteresis so a value hovering around the threshold does not raise a
+//! burst of events.
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use crate::battery::PowerMonitor;
+use crate::common::MavMessage;
+use crate::MavHeader;
+
+/// RSSI value of RADIO_STATUS for an unknown signal strength
+const RSSI_UNKNOWN: u8 = u8::MAX;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailsafeKind {
+    /// No heartbeat was received within the timeout
+    HeartbeatLost,
+    /// The signal strength reported by RADIO_STATUS is below the threshold
+    LinkDegraded,
+    /// The remaining capacity of the main battery is below the low threshold
+    BatteryLow,
+    /// The remaining capacity of the main battery is below the critical threshold
+    BatteryCritical,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FailsafeEvent {
+    Triggered(FailsafeKind),
+    Cleared(FailsafeKind),
+}
+
+/// Thresholds of the failsafe conditions
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FailsafeConfig {
+    pub heartbeat_timeout: Duration,
+    /// Lowest acceptable RSSI of either end of the radio link, in the 0-254 RADIO_STATUS scale
+    pub min_rssi: u8,
+    /// Amount the RSSI has to rise above `min_rssi` to clear [`FailsafeKind::LinkDegraded`]
+    pub rssi_hysteresis: u8,
+    /// Remaining capacity in percent below which the battery is low
+    pub battery_low: u8,
+    /// Remaining capacity in percent below which the battery is critical
+    pub battery_critical: u8,
+    /// Amount in percent the capacity has to rise above a threshold to clear it
+    pub battery_hysteresis: u8,
+}
+
+impl Default for FailsafeConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_timeout: Duration::from_secs(3),
+            min_rssi: 50,
+            rssi_hysteresis: 10,
+            battery_low: 30,
+            battery_critical: 15,
+            battery_hysteresis: 5,
+        }
+    }
+}
+
+/// Whether a value is below a threshold, once below it has to rise above the threshold plus the
+/// hysteresis to count as above again
+fn below(active: bool, value: u8, threshold: u8, hysteresis: u8) -> bool {
+    if active {
+        u16::from(value) < u16::from(threshold) + u16::from(hysteresis)
+    } else {
+        value < threshold
+    }
+}
+
+/// Failsafe state of one vehicle.
+///
+/// The heartbeat and battery are taken from the monitored component only. RADIO_STATUS is
+/// accepted from any sender, as radios report with their own system id.
+#[derive(Debug, Clone)]
+pub struct FailsafeMonitor {
+    system_id: u8,
+    component_id: u8,
+    config: FailsafeConfig,
+    power: PowerMonitor,
+    last_heartbeat: Option<Instant>,
+    active: BTreeSet<FailsafeKind>,
+}
+
+impl FailsafeMonitor {
+    /// Monitor the component, usually the autopilot with component id 1
+    pub fn new(system_id: u8, component_id: u8) -> Self {
+        Self {
+            system_id,
+            component_id,
+            config: FailsafeConfig::default(),
+            power: PowerMonitor::new(),
+            last_heartbeat: None,
+            active: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_config(mut self, config: FailsafeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &FailsafeConfig {
+        &self.config
+    }
+
+    /// Record a received message
+    pub fn update(
+        &mut self,
+        now: Instant,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Vec<FailsafeEvent> {
+        let mut events = Vec::new();
+
+        if let MavMessage::RADIO_STATUS(status) = msg {
+            let rssi = [status.rssi, status.remrssi]
+                .iter()
+                .copied()
+                .filter(|rssi| *rssi != RSSI_UNKNOWN)
+                .min();
+            if let Some(rssi) = rssi {
+                let degraded = below(
+                    self.is_active(FailsafeKind::LinkDegraded),
+                    rssi,
+                    self.config.min_rssi,
+                    self.config.rssi_hysteresis,
+                );
+                self.set(FailsafeKind::LinkDegraded, degraded, &mut events);
+            }
+            return events;
+        }
+
+        if header.system_id != self.system_id || header.component_id != self.component_id {
+            return events;
+        }
+
+        match msg {
+            MavMessage::HEARTBEAT(_) => {
+                self.last_heartbeat = Some(now);
+                self.set(FailsafeKind::HeartbeatLost, false, &mut events);
+            }
+            MavMessage::SYS_STATUS(_) | MavMessage::BATTERY_STATUS(_) => {
+                self.power.update(msg);
+                let remaining = self.power.battery(0).and_then(|battery| battery.remaining);
+                if let Some(remaining) = remaining {
+                    let hysteresis = self.config.battery_hysteresis;
+                    let low = below(
+                        self.is_active(FailsafeKind::BatteryLow),
+                        remaining,
+                        self.config.battery_low,
+                        hysteresis,
+                    );
+                    let critical = below(
+                        self.is_active(FailsafeKind::BatteryCritical),
+                        remaining,
+                        self.config.battery_critical,
+                        hysteresis,
+                    );
+                    self.set(FailsafeKind::BatteryLow, low, &mut events);
+                    self.set(FailsafeKind::BatteryCritical, critical, &mut events);
+                }
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// Check for a heartbeat timeout, to be called periodically.
+    ///
+    /// The heartbeat is only considered lost once a first heartbeat was received.
+    pub fn check(&mut self, now: Instant) -> Vec<FailsafeEvent> {
+        let mut events = Vec::new();
+        if let Some(last_heartbeat) = self.last_heartbeat {
+            let lost =
+                now.saturating_duration_since(last_heartbeat) > self.config.heartbeat_timeout;
+            if lost {
+                self.set(FailsafeKind::HeartbeatLost, true, &mut events);
+            }
+        }
+        events
+    }
+
+    fn set(&mut self, kind: FailsafeKind, active: bool, events: &mut Vec<FailsafeEvent>) {
+        if active && self.active.insert(kind) {
+            events.push(FailsafeEvent::Triggered(kind));
+        } else if !active && self.active.remove(&kind) {
+            events.push(FailsafeEvent::Cleared(kind));
+        }
+    }
+
+    pub fn is_active(&self, kind: FailsafeKind) -> bool {
+        self.active.contains(&kind)
+    }
+
+    /// The currently active failsafes
+    pub fn active(&self) -> impl Iterator<Item = FailsafeKind> + '_ {
+        self.active.iter().copied()
+    }
+
+    /// Battery state of the monitored vehicle
+    pub fn power(&self) -> &PowerMonitor {
+        &self.power
+    }
+}
@@ -0,0 +1,114 @@
+//! Parsing with several message sets on one connection.
+//!
+//! A fleet mixing firmwares may send messages only one of their message sets defines.
+//! [`Fallback`] is a [`Message`] trying the message sets in order, so a single connection like
+//! `connect::<Fallback<ardupilotmega::MavMessage, development::MavMessage>>(address)` parses the
+//! messages of both, and longer lists nest: `Fallback<A, Fallback<B, C>>`.
+//!
+//! A message id is always handled by the first message set defining it, also when a later one
+//! defines it differently.
+
+use crate::error::ParserError;
+use crate::{MavlinkVersion, Message};
+
+/// Message of the first message set `A`, or of the fallback `B` for the ids `A` does not define
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fallback<A: Message, B: Message> {
+    First(A),
+    Second(B),
+}
+
+impl<A: Message, B: Message> Fallback<A, B> {
+    /// Name of the message set which matched this message, e.g. `"ardupilotmega"`
+    pub fn dialect(&self) -> &'static str {
+        match self {
+            Self::First(_) => A::dialect_name(),
+            Self::Second(_) => B::dialect_name(),
+        }
+    }
+
+    /// Whether `A` defines the message id
+    fn is_first(id: u32) -> bool {
+        A::default_message_from_id(id).is_ok()
+    }
+}
+
+impl<A: Message, B: Message> Message for Fallback<A, B> {
+    fn message_id(&self) -> u32 {
+        match self {
+            Self::First(msg) => msg.message_id(),
+            Self::Second(msg) => msg.message_id(),
+        }
+    }
+
+    fn message_name(&self) -> &'static str {
+        match self {
+            Self::First(msg) => msg.message_name(),
+            Self::Second(msg) => msg.message_name(),
+        }
+    }
+
+    fn ser(&self, version: MavlinkVersion, bytes: &mut [u8]) -> usize {
+        match self {
+            Self::First(msg) => msg.ser(version, bytes),
+            Self::Second(msg) => msg.ser(version, bytes),
+        }
+    }
+
+    fn parse(version: MavlinkVersion, msgid: u32, payload: &[u8]) -> Result<Self, ParserError> {
+        match A::parse(version, msgid, payload) {
+            Err(ParserError::UnknownMessage { .. }) => {
+                B::parse(version, msgid, payload).map(Self::Second)
+            }
+            parsed => parsed.map(Self::First),
+        }
+    }
+
+    fn message_id_from_name(name: &str) -> Result<u32, &'static str> {
+        A::message_id_from_name(name).or_else(|_| B::message_id_from_name(name))
+    }
+
+    fn default_message_from_id(id: u32) -> Result<Self, &'static str> {
+        match A::default_message_from_id(id) {
+            Ok(msg) => Ok(Self::First(msg)),
+            Err(_) => B::default_message_from_id(id).map(Self::Second),
+        }
+    }
+
+    fn extra_crc(id: u32) -> u8 {
+        if Self::is_first(id) {
+            A::extra_crc(id)
+        } else {
+            B::extra_crc(id)
+        }
+    }
+
+    /// Name of the first message set
+    fn dialect_name() -> &'static str {
+        A::dialect_name()
+    }
+
+    /// Version of the first message set
+    fn dialect_version() -> Option<u8> {
+        A::dialect_version()
+    }
+
+    /// Dialect number of the first message set
+    fn dialect_number() -> Option<u8> {
+        A::dialect_number()
+    }
+
+    fn target_system_id(&self) -> Option<u8> {
+        match self {
+            Self::First(msg) => msg.target_system_id(),
+            Self::Second(msg) => msg.target_system_id(),
+        }
+    }
+
+    fn target_component_id(&self) -> Option<u8> {
+        match self {
+            Self::First(msg) => msg.target_component_id(),
+            Self::Second(msg) => msg.target_component_id(),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Link faults for testing.
+//!
+//! [`FaultInjector`] wraps any [`MavConnection`] and drops, duplicates, reorders, corrupts and
+//! delays the messages passing through it as described by a [`FaultModel`]. The faults are drawn
+//! from a seeded generator, so a test exercising the retry logic of e.g. the mission, parameter
+//! or command helpers sees the same faults on every run.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{MessageReadError, MessageWriteError, ParserError};
+use crate::{MavConnection, MavHeader, MavlinkVersion, Message, MAX_FRAME_SIZE};
+
+/// Probabilities and delays of the faults of one direction
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FaultModel {
+    drop: f64,
+    duplicate: f64,
+    reorder: f64,
+    corrupt: f64,
+    delay: Duration,
+    jitter: Duration,
+}
+
+impl FaultModel {
+    /// A model without any fault
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Probability that a message is lost
+    pub fn with_drop(mut self, probability: f64) -> Self {
+        self.drop = probability;
+        self
+    }
+
+    /// Probability that a message arrives twice
+    pub fn with_duplicate(mut self, probability: f64) -> Self {
+        self.duplicate = probability;
+        self
+    }
+
+    /// Probability that a message is held back and arrives after the next one
+    pub fn with_reorder(mut self, probability: f64) -> Self {
+        self.reorder = probability;
+        self
+    }
+
+    /// Probability that a bit of the payload flips without the checksum noticing
+    pub fn with_corrupt(mut self, probability: f64) -> Self {
+        self.corrupt = probability;
+        self
+    }
+
+    /// Delay every message by `delay` plus up to `jitter`
+    pub fn with_delay(mut self, delay: Duration, jitter: Duration) -> Self {
+        self.delay = delay;
+        self.jitter = jitter;
+        self
+    }
+}
+
+/// Number of faults injected so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub corrupted: u64,
+}
+
+/// xorshift64*, good enough to draw faults and small enough to not need a dependency
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(crate) fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.next_f64() < probability
+    }
+
+    /// Uniform in `[0, bound)`, 0 if `bound` is 0
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next_u64() % bound
+        }
+    }
+}
+
+type Delivery<M> = Result<(MavHeader, M), ParserError>;
+
+/// Faults of one direction, turning every message into the deliveries it causes
+struct Faults<M> {
+    model: FaultModel,
+    /// Message held back to arrive after the next one
+    held: Option<Delivery<M>>,
+}
+
+impl<M: Message + Clone> Faults<M> {
+    fn new(model: FaultModel) -> Self {
+        Self { model, held: None }
+    }
+
+    fn apply(
+        &mut self,
+        rng: &mut Rng,
+        stats: &mut FaultStats,
+        version: MavlinkVersion,
+        header: MavHeader,
+        msg: M,
+    ) -> Vec<Delivery<M>> {
+        if rng.chance(self.model.drop) {
+            stats.dropped += 1;
+            return Vec::new();
+        }
+
+        let delivery = if rng.chance(self.model.corrupt) {
+            stats.corrupted += 1;
+            corrupt(rng, version, &msg).map(|msg| (header, msg))
+        } else {
+            Ok((header, msg))
+        };
+
+        let mut deliveries = Vec::new();
+        if rng.chance(self.model.duplicate) {
+            stats.duplicated += 1;
+            deliveries.push(delivery.clone());
+        }
+        deliveries.push(delivery);
+
+        if self.held.is_none() && rng.chance(self.model.reorder) {
+            stats.reordered += 1;
+            self.held = deliveries.pop();
+        } else if let Some(held) = self.held.take() {
+            deliveries.push(held);
+        }
+        deliveries
+    }
+
+    fn delay(&self, rng: &mut Rng) -> Duration {
+        let jitter = rng.below(self.model.jitter.as_nanos() as u64);
+        self.model.delay + Duration::from_nanos(jitter)
+    }
+}
+
+/// Flip one bit of the serialized payload and parse it again
+fn corrupt<M: Message>(rng: &mut Rng, version: MavlinkVersion, msg: &M) -> Result<M, ParserError> {
+    let mut payload = [0; MAX_FRAME_SIZE];
+    let len = msg.ser(version, &mut payload);
+    let bit = rng.below(len as u64 * 8) as usize;
+    payload[bit / 8] ^= 1 << (bit % 8);
+    M::parse(version, msg.message_id(), &payload[..len])
+}
+
+struct InjectorState<M> {
+    rng: Rng,
+    stats: FaultStats,
+    recv_faults: Faults<M>,
+    send_faults: Faults<M>,
+    /// Received messages waiting for their delay to pass, by delivery time
+    pending: VecDeque<(Instant, Delivery<M>)>,
+}
+
+/// Connection injecting faults into the traffic of the connection it wraps.
+///
+/// Received messages are delayed by holding them back in `recv`, which keeps reading the wrapped
+/// connection meanwhile, so that connection should have a read timeout. Sent messages are delayed
+/// by sleeping before sending them. Corrupted messages that no longer parse are returned from
+/// `recv` as parse errors and not sent at all. Dropped sent messages are reported as 0 bytes
+/// written.
+pub struct FaultInjector<M, C> {
+    connection: C,
+    state: Mutex<InjectorState<M>>,
+}
+
+impl<M: Message + Clone, C: MavConnection<M>> FaultInjector<M, C> {
+    /// Wrap `connection` without faults, `seed` selects the sequence of faults
+    pub fn new(connection: C, seed: u64) -> Self {
+        Self {
+            connection,
+            state: Mutex::new(InjectorState {
+                rng: Rng::new(seed),
+                stats: FaultStats::default(),
+                recv_faults: Faults::new(FaultModel::new()),
+                send_faults: Faults::new(FaultModel::new()),
+                pending: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Faults of the received messages
+    pub fn with_recv_faults(self, model: FaultModel) -> Self {
+        self.state.lock().unwrap().recv_faults = Faults::new(model);
+        self
+    }
+
+    /// Faults of the sent messages
+    pub fn with_send_faults(self, model: FaultModel) -> Self {
+        self.state.lock().unwrap().send_faults = Faults::new(model);
+        self
+    }
+
+    /// The same faults in both directions
+    pub fn with_faults(self, model: FaultModel) -> Self {
+        self.with_recv_faults(model).with_send_faults(model)
+    }
+
+    pub fn connection(&self) -> &C {
+        &self.connection
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// Next received message whose delay has passed
+    fn next_due(&self) -> Option<Delivery<M>> {
+        let mut state = self.state.lock().unwrap();
+        match state.pending.front() {
+            Some((due, _)) if *due <= Instant::now() => state.pending.pop_front().map(|(_, d)| d),
+            _ => None,
+        }
+    }
+}
+
+impl<M: Message + Clone, C: MavConnection<M>> MavConnection<M> for FaultInjector<M, C> {
+    fn recv(&self) -> Result<(MavHeader, M), MessageReadError> {
+        loop {
+            if let Some(delivery) = self.next_due() {
+                return delivery.map_err(MessageReadError::Parse);
+            }
+
+            let (header, msg) = match self.connection.recv() {
+                Ok(received) => received,
+                Err(e) => {
+                    // a silent link must not hold back what already arrived
+                    let next = self
+                        .state
+                        .lock()
+                        .unwrap()
+                        .pending
+                        .front()
+                        .map(|(due, _)| *due);
+                    match next {
+                        Some(due) => {
+                            thread::sleep(due.saturating_duration_since(Instant::now()));
+                            continue;
+                        }
+                        None => return Err(e),
+                    }
+                }
+            };
+
+            let version = self.connection.get_protocol_version();
+            let mut state = self.state.lock().unwrap();
+            let state = &mut *state;
+            let deliveries =
+                state
+                    .recv_faults
+                    .apply(&mut state.rng, &mut state.stats, version, header, msg);
+            for delivery in deliveries {
+                let due = Instant::now() + state.recv_faults.delay(&mut state.rng);
+                // keep the queue ordered by delivery time, jitter may overtake earlier messages
+                let index = state.pending.partition_point(|(other, _)| *other <= due);
+                state.pending.insert(index, (due, delivery));
+            }
+        }
+    }
+
+    fn send(&self, header: &MavHeader, data: &M) -> Result<usize, MessageWriteError> {
+        let version = self.connection.get_protocol_version();
+        let (deliveries, delay) = {
+            let mut state = self.state.lock().unwrap();
+            let state = &mut *state;
+            let deliveries = state.send_faults.apply(
+                &mut state.rng,
+                &mut state.stats,
+                version,
+                *header,
+                data.clone(),
+            );
+            let delay = state.send_faults.delay(&mut state.rng);
+            (deliveries, delay)
+        };
+
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        let mut written = 0;
+        for (header, msg) in deliveries.into_iter().flatten() {
+            written += self.connection.send(&header, &msg)?;
+        }
+        Ok(written)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.connection.set_protocol_version(version);
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.connection.get_protocol_version()
+    }
+}
@@ -0,0 +1,474 @@
+//! C interface to parse, inspect, build and serialize messages of the `ardupilotmega` dialect,
+//! which includes `common`, so that C and C++ flight software can reuse this implementation.
+//!
+//! The header declaring it, with the ids of the messages and the values of the enums, is
+//! generated from the definitions as [`HEADER`]. The library is built with
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ffi --crate-type cdylib   # or staticlib
+//! ```
+//!
+//! Fields are addressed by name, elements of arrays as `name[index]`. Enum fields are read and
+//! written by the name of their value as strings, bitmasks as integers, and `char` arrays as
+//! strings. Functions returning an `int` give `MAVLINK_OK` or one of the negative
+//! `MAVLINK_ERROR_*` codes.
+
+use std::ffi::CStr;
+use std::io;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use serde_json::Value;
+
+use crate::ardupilotmega::MavMessage;
+use crate::error::MessageReadError;
+use crate::{MavHeader, MavlinkVersion, Message, MAV_STX, MAV_STX_V2};
+
+/// Source of the C header
+pub const HEADER: &str = include_str!(concat!(env!("OUT_DIR"), "/mavlink.h"));
+
+pub const MAVLINK_OK: c_int = 0;
+/// A pointer is null or a string is not valid UTF-8
+pub const MAVLINK_ERROR_INVALID_ARGUMENT: c_int = -1;
+pub const MAVLINK_ERROR_UNKNOWN_FIELD: c_int = -2;
+/// The value does not fit the type of the field
+pub const MAVLINK_ERROR_INVALID_VALUE: c_int = -3;
+pub const MAVLINK_ERROR_BUFFER_TOO_SMALL: c_int = -4;
+/// The message cannot be sent in the requested protocol version
+pub const MAVLINK_ERROR_SERIALIZE: c_int = -5;
+
+/// A message with its header, opaque to C
+pub struct FfiMessage {
+    header: MavHeader,
+    msg: MavMessage,
+    // C string of the message name, kept for `mavlink_message_name`
+    name: Box<CStr>,
+}
+
+impl FfiMessage {
+    fn new(header: MavHeader, msg: MavMessage) -> Box<Self> {
+        let name = std::ffi::CString::new(msg.message_name())
+            .unwrap()
+            .into_boxed_c_str();
+        Box::new(Self { header, msg, name })
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, c_int> {
+    if s.is_null() {
+        return Err(MAVLINK_ERROR_INVALID_ARGUMENT);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| MAVLINK_ERROR_INVALID_ARGUMENT)
+}
+
+/// Split `name[index]`
+fn field_path(field: &str) -> Result<(&str, Option<usize>), c_int> {
+    match field.strip_suffix(']').and_then(|f| f.split_once('[')) {
+        Some((name, index)) => index
+            .parse()
+            .map(|index| (name, Some(index)))
+            .map_err(|_| MAVLINK_ERROR_UNKNOWN_FIELD),
+        None => Ok((field, None)),
+    }
+}
+
+/// Value of a field in the serde representation of a message
+fn field_mut<'a>(message: &'a mut Value, field: &str) -> Result<&'a mut Value, c_int> {
+    let (name, index) = field_path(field)?;
+    // the generated rust code renames the `type` fields
+    let name = if name == "type" { "mavtype" } else { name };
+    let value = message.get_mut(name).ok_or(MAVLINK_ERROR_UNKNOWN_FIELD)?;
+    match index {
+        Some(index) => value.get_mut(index).ok_or(MAVLINK_ERROR_UNKNOWN_FIELD),
+        None => Ok(value),
+    }
+}
+
+unsafe fn read_field<T>(
+    msg: *const FfiMessage,
+    field: *const c_char,
+    read: impl FnOnce(&Value) -> Option<T>,
+) -> Result<T, c_int> {
+    let msg = msg.as_ref().ok_or(MAVLINK_ERROR_INVALID_ARGUMENT)?;
+    let field = to_str(field)?;
+    let mut message = serde_json::to_value(&msg.msg).map_err(|_| MAVLINK_ERROR_INVALID_VALUE)?;
+    read(field_mut(&mut message, field)?).ok_or(MAVLINK_ERROR_INVALID_VALUE)
+}
+
+unsafe fn write_field(
+    msg: *mut FfiMessage,
+    field: *const c_char,
+    write: impl FnOnce(&mut Value) -> Option<()>,
+) -> c_int {
+    let result = (|| {
+        let msg = msg.as_mut().ok_or(MAVLINK_ERROR_INVALID_ARGUMENT)?;
+        let field = to_str(field)?;
+        let mut message =
+            serde_json::to_value(&msg.msg).map_err(|_| MAVLINK_ERROR_INVALID_VALUE)?;
+        write(field_mut(&mut message, field)?).ok_or(MAVLINK_ERROR_INVALID_VALUE)?;
+        // deserializing checks the value against the type of the field
+        msg.msg = serde_json::from_value(message).map_err(|_| MAVLINK_ERROR_INVALID_VALUE)?;
+        Ok(())
+    })();
+    result.err().unwrap_or(MAVLINK_OK)
+}
+
+/// Create a message with default fields from its name, e.g. `"HEARTBEAT"`, or return null if
+/// there is no such message.
+///
+/// # Safety
+///
+/// `name` must be null or a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_new(name: *const c_char) -> *mut FfiMessage {
+    let msg = to_str(name)
+        .ok()
+        .and_then(|name| MavMessage::message_id_from_name(name).ok())
+        .and_then(|id| MavMessage::default_message_from_id(id).ok());
+    match msg {
+        Some(msg) => Box::into_raw(FfiMessage::new(MavHeader::default(), msg)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Free a message created by `mavlink_message_new` or `mavlink_parse`.
+///
+/// # Safety
+///
+/// `msg` must be null or a message which was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_free(msg: *mut FfiMessage) {
+    if !msg.is_null() {
+        drop(Box::from_raw(msg));
+    }
+}
+
+/// Parse the first frame of `buf`.
+///
+/// Returns the number of bytes consumed, and sets `*msg` to the parsed message or to null if
+/// bytes which are not a valid frame were skipped. Returns 0 if more data is needed.
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes and `msg` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_parse(
+    buf: *const u8,
+    len: usize,
+    msg: *mut *mut FfiMessage,
+) -> isize {
+    if buf.is_null() || msg.is_null() {
+        return MAVLINK_ERROR_INVALID_ARGUMENT as isize;
+    }
+    *msg = ptr::null_mut();
+    let data = std::slice::from_raw_parts(buf, len);
+
+    // skip to the next start of frame
+    let start = match data.iter().position(|b| *b == MAV_STX || *b == MAV_STX_V2) {
+        Some(start) => start,
+        None => return len as isize,
+    };
+    if start > 0 {
+        return start as isize;
+    }
+
+    let mut reader = data;
+    let parsed = if data[0] == MAV_STX {
+        crate::read_v1_raw_message(&mut reader).map(|raw| {
+            let header = MavHeader {
+                sequence: raw.sequence(),
+                system_id: raw.system_id(),
+                component_id: raw.component_id(),
+            };
+            let id = u32::from(raw.message_id());
+            raw.has_valid_crc::<MavMessage>()
+                .then(|| MavMessage::parse(MavlinkVersion::V1, id, raw.payload()).ok())
+                .flatten()
+                .map(|parsed| (header, parsed))
+        })
+    } else {
+        crate::read_v2_raw_message(&mut reader).map(|raw| {
+            let header = MavHeader {
+                sequence: raw.sequence(),
+                system_id: raw.system_id(),
+                component_id: raw.component_id(),
+            };
+            raw.has_valid_crc::<MavMessage>()
+                .then(|| {
+                    MavMessage::parse(MavlinkVersion::V2, raw.message_id(), raw.payload()).ok()
+                })
+                .flatten()
+                .map(|parsed| (header, parsed))
+        })
+    };
+
+    match parsed {
+        Ok(Some((header, parsed))) => {
+            *msg = Box::into_raw(FfiMessage::new(header, parsed));
+            (len - reader.len()) as isize
+        }
+        Err(MessageReadError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => 0,
+        // not a valid frame, skip its start marker
+        _ => 1,
+    }
+}
+
+/// Serialize a message as a frame of the protocol `version`, 1 or 2, into `buf`.
+///
+/// Returns the length of the frame or an error code.
+///
+/// # Safety
+///
+/// `msg` must be a valid message and `buf` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_serialize(
+    msg: *const FfiMessage,
+    version: u8,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    let msg = match msg.as_ref() {
+        Some(msg) if !buf.is_null() => msg,
+        _ => return MAVLINK_ERROR_INVALID_ARGUMENT as isize,
+    };
+    let version = match version {
+        1 => MavlinkVersion::V1,
+        2 => MavlinkVersion::V2,
+        _ => return MAVLINK_ERROR_INVALID_ARGUMENT as isize,
+    };
+    let mut frame = Vec::new();
+    if crate::write_versioned_msg(&mut frame, version, msg.header, &msg.msg).is_err() {
+        return MAVLINK_ERROR_SERIALIZE as isize;
+    }
+    if frame.len() > len {
+        return MAVLINK_ERROR_BUFFER_TOO_SMALL as isize;
+    }
+    ptr::copy_nonoverlapping(frame.as_ptr(), buf, frame.len());
+    frame.len() as isize
+}
+
+/// Id of a message.
+///
+/// # Safety
+///
+/// `msg` must be a valid message.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_id(msg: *const FfiMessage) -> u32 {
+    (*msg).msg.message_id()
+}
+
+/// Name of a message, valid as long as the message.
+///
+/// # Safety
+///
+/// `msg` must be a valid message.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_name(msg: *const FfiMessage) -> *const c_char {
+    (*msg).name.as_ptr()
+}
+
+/// Header of a message.
+///
+/// # Safety
+///
+/// `msg` must be a valid message, the other pointers may be null.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_get_header(
+    msg: *const FfiMessage,
+    system_id: *mut u8,
+    component_id: *mut u8,
+    sequence: *mut u8,
+) {
+    let header = (*msg).header;
+    for (target, value) in [
+        (system_id, header.system_id),
+        (component_id, header.component_id),
+        (sequence, header.sequence),
+    ] {
+        if !target.is_null() {
+            *target = value;
+        }
+    }
+}
+
+/// Set the header a message is serialized with.
+///
+/// # Safety
+///
+/// `msg` must be a valid message.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_set_header(
+    msg: *mut FfiMessage,
+    system_id: u8,
+    component_id: u8,
+    sequence: u8,
+) {
+    (*msg).header = MavHeader {
+        system_id,
+        component_id,
+        sequence,
+    };
+}
+
+/// Read an integer field or bitmask.
+///
+/// # Safety
+///
+/// `msg` must be a valid message, `field` a valid C string and `value` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_get_int(
+    msg: *const FfiMessage,
+    field: *const c_char,
+    value: *mut i64,
+) -> c_int {
+    if value.is_null() {
+        return MAVLINK_ERROR_INVALID_ARGUMENT;
+    }
+    let read = read_field(msg, field, |field| {
+        // bitmasks are serialized as their bits
+        let field = field.get("bits").unwrap_or(field);
+        field
+            .as_i64()
+            .or_else(|| field.as_u64().map(|value| value as i64))
+    });
+    match read {
+        Ok(read) => {
+            *value = read;
+            MAVLINK_OK
+        }
+        Err(e) => e,
+    }
+}
+
+/// Read a floating point or integer field.
+///
+/// # Safety
+///
+/// `msg` must be a valid message, `field` a valid C string and `value` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_get_float(
+    msg: *const FfiMessage,
+    field: *const c_char,
+    value: *mut f64,
+) -> c_int {
+    if value.is_null() {
+        return MAVLINK_ERROR_INVALID_ARGUMENT;
+    }
+    match read_field(msg, field, Value::as_f64) {
+        Ok(read) => {
+            *value = read;
+            MAVLINK_OK
+        }
+        Err(e) => e,
+    }
+}
+
+/// Read a `char` array, or the name of the value of an enum field, into `buf` as a C string.
+///
+/// Returns the length of the string or an error code.
+///
+/// # Safety
+///
+/// `msg` must be a valid message, `field` a valid C string and `buf` must point to `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_get_string(
+    msg: *const FfiMessage,
+    field: *const c_char,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    if buf.is_null() {
+        return MAVLINK_ERROR_INVALID_ARGUMENT;
+    }
+    let read = read_field(msg, field, |field| match field {
+        Value::Object(map) => map.get("type")?.as_str().map(|s| s.as_bytes().to_vec()),
+        Value::Array(chars) => chars
+            .iter()
+            .map(|c| c.as_u64().map(|c| c as u8))
+            .take_while(|c| *c != Some(0))
+            .collect(),
+        _ => None,
+    });
+    match read {
+        Ok(s) if s.len() < len => {
+            ptr::copy_nonoverlapping(s.as_ptr(), buf as *mut u8, s.len());
+            *buf.add(s.len()) = 0;
+            s.len() as c_int
+        }
+        Ok(_) => MAVLINK_ERROR_BUFFER_TOO_SMALL,
+        Err(e) => e,
+    }
+}
+
+/// Write an integer field or bitmask.
+///
+/// # Safety
+///
+/// `msg` must be a valid message and `field` a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_set_int(
+    msg: *mut FfiMessage,
+    field: *const c_char,
+    value: i64,
+) -> c_int {
+    write_field(msg, field, |field| {
+        let field = match field {
+            Value::Object(map) => map.get_mut("bits")?,
+            field => field,
+        };
+        *field = value.into();
+        Some(())
+    })
+}
+
+/// Write a floating point field.
+///
+/// # Safety
+///
+/// `msg` must be a valid message and `field` a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_set_float(
+    msg: *mut FfiMessage,
+    field: *const c_char,
+    value: f64,
+) -> c_int {
+    write_field(msg, field, |field| {
+        *field = serde_json::Number::from_f64(value)?.into();
+        Some(())
+    })
+}
+
+/// Write a `char` array, or an enum field by the name of its value.
+///
+/// # Safety
+///
+/// `msg` must be a valid message and `field` and `value` valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mavlink_message_set_string(
+    msg: *mut FfiMessage,
+    field: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    let value = match to_str(value) {
+        Ok(value) => value,
+        Err(e) => return e,
+    };
+    write_field(msg, field, |field| {
+        match field {
+            Value::Object(map) if map.contains_key("type") => {
+                map.insert("type".to_string(), value.into());
+            }
+            Value::Array(chars) if value.len() <= chars.len() => {
+                let mut bytes = value.bytes();
+                for c in chars.iter_mut() {
+                    *c = bytes.next().unwrap_or(0).into();
+                }
+            }
+            _ => return None,
+        }
+        Some(())
+    })
+}
@@ -0,0 +1,373 @@
+//! Client side of the [file transfer protocol](https://mavlink.io/en/services/ftp.html),
+//! limited to reading files.
+//!
+//! FTP requests and replies travel in the payload of FILE_TRANSFER_PROTOCOL, which
+//! [`FtpPayload`] encodes and decodes. [`FtpRead`] reads one file as a state machine on `common`
+//! messages and [`FtpClient`] drives it over a [`MavConnection`].
+
+use core::fmt::{Display, Formatter};
+use std::error::Error;
+use std::time::Duration;
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+use crate::common::{MavMessage, FILE_TRANSFER_PROTOCOL_DATA};
+use crate::error::{MessageReadError, MessageWriteError, ProtocolError};
+use crate::request::{Exchange, Requester, Step};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Time to wait for a reply before resending the request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Number of times a request is resent before giving up
+pub const DEFAULT_RETRIES: u8 = 5;
+
+/// Size of the `payload` field of FILE_TRANSFER_PROTOCOL
+pub const PAYLOAD_LEN: usize = 251;
+
+/// Bytes of data in one FTP payload, after the 12 byte header
+pub const DATA_LEN: usize = PAYLOAD_LEN - 12;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+pub enum FtpOpcode {
+    None = 0,
+    TerminateSession = 1,
+    ResetSessions = 2,
+    ListDirectory = 3,
+    OpenFileRO = 4,
+    ReadFile = 5,
+    CreateFile = 6,
+    WriteFile = 7,
+    RemoveFile = 8,
+    CreateDirectory = 9,
+    RemoveDirectory = 10,
+    OpenFileWO = 11,
+    TruncateFile = 12,
+    Rename = 13,
+    CalcFileCRC32 = 14,
+    BurstReadFile = 15,
+    Ack = 128,
+    Nak = 129,
+}
+
+/// Error code in the first data byte of a NAK
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+pub enum FtpNak {
+    None = 0,
+    Fail = 1,
+    FailErrno = 2,
+    InvalidDataSize = 3,
+    InvalidSession = 4,
+    NoSessionsAvailable = 5,
+    Eof = 6,
+    UnknownCommand = 7,
+    FileExists = 8,
+    FileProtected = 9,
+    FileNotFound = 10,
+}
+
+/// The payload of a FILE_TRANSFER_PROTOCOL message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtpPayload {
+    pub seq_number: u16,
+    pub session: u8,
+    pub opcode: FtpOpcode,
+    /// Length of `data`, or the number of bytes to read for read requests
+    pub size: u8,
+    /// Opcode of the request an ACK or NAK answers
+    pub req_opcode: FtpOpcode,
+    pub burst_complete: bool,
+    pub offset: u32,
+    /// At most [`DATA_LEN`] bytes
+    pub data: Vec<u8>,
+}
+
+impl FtpPayload {
+    pub fn new(seq_number: u16, session: u8, opcode: FtpOpcode) -> Self {
+        Self {
+            seq_number,
+            session,
+            opcode,
+            size: 0,
+            req_opcode: FtpOpcode::None,
+            burst_complete: false,
+            offset: 0,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; PAYLOAD_LEN] {
+        let len = self.data.len().min(DATA_LEN);
+        let mut bytes = [0; PAYLOAD_LEN];
+        bytes[0..2].copy_from_slice(&self.seq_number.to_le_bytes());
+        bytes[2] = self.session;
+        bytes[3] = self.opcode as u8;
+        bytes[4] = self.size;
+        bytes[5] = self.req_opcode as u8;
+        bytes[6] = u8::from(self.burst_complete);
+        bytes[8..12].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[12..12 + len].copy_from_slice(&self.data[..len]);
+        bytes
+    }
+
+    /// Decode a payload, `None` if an opcode is unknown
+    pub fn from_bytes(bytes: &[u8; PAYLOAD_LEN]) -> Option<Self> {
+        let size = usize::from(bytes[4]).min(DATA_LEN);
+        Some(Self {
+            seq_number: u16::from_le_bytes([bytes[0], bytes[1]]),
+            session: bytes[2],
+            opcode: FtpOpcode::from_u8(bytes[3])?,
+            size: bytes[4],
+            req_opcode: FtpOpcode::from_u8(bytes[5])?,
+            burst_complete: bytes[6] != 0,
+            offset: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            data: bytes[12..12 + size].to_vec(),
+        })
+    }
+
+    /// The error code of a NAK
+    pub fn nak(&self) -> Option<FtpNak> {
+        match self.opcode {
+            FtpOpcode::Nak => self.data.first().and_then(|code| FtpNak::from_u8(*code)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FtpError {
+    /// The component refused the request
+    Nak(FtpNak),
+    Protocol(ProtocolError),
+}
+
+impl Display for FtpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Nak(nak) => write!(f, "File transfer refused: {nak:?}"),
+            Self::Protocol(e) => write!(f, "File transfer failed: {e}"),
+        }
+    }
+}
+
+impl Error for FtpError {}
+
+impl From<ProtocolError> for FtpError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+impl From<MessageReadError> for FtpError {
+    fn from(e: MessageReadError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+impl From<MessageWriteError> for FtpError {
+    fn from(e: MessageWriteError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+/// What a transfer wants to happen next
+pub type FtpStep<T> = Step<MavMessage, T>;
+
+/// Reading of one file: OpenFileRO, ReadFile until the end, TerminateSession
+#[derive(Debug, Clone)]
+pub struct FtpRead {
+    target_system: u8,
+    target_component: u8,
+    path: String,
+    retries: u8,
+    attempts: u8,
+    seq_number: u16,
+    last_sent: Option<FtpPayload>,
+    session: Option<u8>,
+    size: Option<u32>,
+    data: Vec<u8>,
+}
+
+impl FtpRead {
+    pub fn new(target_system: u8, target_component: u8, path: &str) -> Self {
+        Self {
+            target_system,
+            target_component,
+            path: path.to_string(),
+            retries: DEFAULT_RETRIES,
+            attempts: 0,
+            seq_number: 0,
+            last_sent: None,
+            session: None,
+            size: None,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    fn message(&self, payload: &FtpPayload) -> MavMessage {
+        MavMessage::FILE_TRANSFER_PROTOCOL(FILE_TRANSFER_PROTOCOL_DATA {
+            target_network: 0,
+            target_system: self.target_system,
+            target_component: self.target_component,
+            payload: payload.to_bytes(),
+        })
+    }
+
+    fn send(&mut self, opcode: FtpOpcode, offset: u32, size: u8, data: Vec<u8>) -> MavMessage {
+        self.seq_number = self.seq_number.wrapping_add(1);
+        self.attempts = 0;
+        let mut payload =
+            FtpPayload::new(self.seq_number, self.session.unwrap_or_default(), opcode);
+        payload.size = size;
+        payload.offset = offset;
+        payload.data = data;
+        let msg = self.message(&payload);
+        self.last_sent = Some(payload);
+        msg
+    }
+
+    fn read_next(&mut self) -> FtpStep<Vec<u8>> {
+        if self
+            .size
+            .map_or(false, |size| self.data.len() >= size as usize)
+        {
+            return self.finish();
+        }
+        let offset = self.data.len() as u32;
+        FtpStep::Send(self.send(FtpOpcode::ReadFile, offset, DATA_LEN as u8, vec![]))
+    }
+
+    fn finish(&mut self) -> FtpStep<Vec<u8>> {
+        let terminate = self.send(FtpOpcode::TerminateSession, 0, 0, vec![]);
+        FtpStep::Done(Some(terminate), core::mem::take(&mut self.data))
+    }
+}
+
+impl Exchange for FtpRead {
+    type Message = MavMessage;
+    type Output = Vec<u8>;
+    type Error = FtpError;
+
+    /// Message opening the transfer
+    fn start(&mut self) -> MavMessage {
+        let path = self.path.clone().into_bytes();
+        self.send(FtpOpcode::OpenFileRO, 0, path.len() as u8, path)
+    }
+
+    /// Feed a message received from the component
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<FtpStep<Vec<u8>>, FtpError> {
+        if header.system_id != self.target_system
+            || (self.target_component != 0 && header.component_id != self.target_component)
+        {
+            return Ok(FtpStep::Wait);
+        }
+        let reply = match msg {
+            MavMessage::FILE_TRANSFER_PROTOCOL(ftp) => match FtpPayload::from_bytes(&ftp.payload) {
+                Some(reply) => reply,
+                None => return Ok(FtpStep::Wait),
+            },
+            _ => return Ok(FtpStep::Wait),
+        };
+        let request = match &self.last_sent {
+            Some(request) => request.opcode,
+            None => return Ok(FtpStep::Wait),
+        };
+        // replies carry the sequence number of the request plus one
+        if reply.seq_number != self.seq_number.wrapping_add(1) || reply.req_opcode != request {
+            return Ok(FtpStep::Wait);
+        }
+
+        match (reply.opcode, request) {
+            (FtpOpcode::Ack, FtpOpcode::OpenFileRO) => {
+                self.session = Some(reply.session);
+                if let [a, b, c, d, ..] = reply.data[..] {
+                    self.size = Some(u32::from_le_bytes([a, b, c, d]));
+                }
+                Ok(self.read_next())
+            }
+            (FtpOpcode::Ack, FtpOpcode::ReadFile) => {
+                if reply.offset as usize != self.data.len() {
+                    return Ok(FtpStep::Wait);
+                }
+                if reply.data.is_empty() {
+                    return Ok(self.finish());
+                }
+                self.data.extend_from_slice(&reply.data);
+                Ok(self.read_next())
+            }
+            (FtpOpcode::Nak, FtpOpcode::ReadFile) if reply.nak() == Some(FtpNak::Eof) => {
+                Ok(self.finish())
+            }
+            (FtpOpcode::Nak, _) => Err(FtpError::Nak(reply.nak().unwrap_or(FtpNak::Fail))),
+            _ => Ok(FtpStep::Wait),
+        }
+    }
+
+    /// Called when no reply was received within the timeout
+    fn on_timeout(&mut self) -> Result<FtpStep<Vec<u8>>, FtpError> {
+        if self.attempts >= self.retries {
+            warn!(
+                sysid = self.target_system,
+                attempts = self.attempts,
+                "FTP request timed out"
+            );
+            return Err(ProtocolError::Timeout.into());
+        }
+        self.attempts += 1;
+        debug!(
+            sysid = self.target_system,
+            attempt = self.attempts,
+            "resending FTP request"
+        );
+        Ok(match &self.last_sent {
+            Some(payload) => FtpStep::Send(self.message(payload)),
+            None => FtpStep::Wait,
+        })
+    }
+}
+
+/// Reads files from a component over a connection
+pub struct FtpClient<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    requester: Requester<'a, M, C>,
+    target_system: u8,
+    target_component: u8,
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> FtpClient<'a, M, C> {
+    pub fn new(connection: &'a C, target_system: u8, target_component: u8) -> Self {
+        let requester = Requester::new(connection)
+            .with_timeout(DEFAULT_TIMEOUT)
+            .with_retries(DEFAULT_RETRIES);
+        Self::from_requester(requester, target_system, target_component)
+    }
+
+    /// Read files with the header, timeout and retries of `requester`
+    pub fn from_requester(
+        requester: Requester<'a, M, C>,
+        target_system: u8,
+        target_component: u8,
+    ) -> Self {
+        Self {
+            requester,
+            target_system,
+            target_component,
+        }
+    }
+
+    /// Read a whole file
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, FtpError> {
+        let mut transfer = FtpRead::new(self.target_system, self.target_component, path)
+            .with_retries(self.requester.retries());
+        self.requester.run(&mut transfer)
+    }
+}
@@ -0,0 +1,220 @@
+//! Conversion between regular telemetry and [HIGH_LATENCY2](https://mavlink.io/en/services/high_latency.html).
+//!
+//! On satellite or LTE links only one HIGH_LATENCY2 message is sent every few seconds instead of
+//! the regular telemetry stream. [`HighLatencySummary`] collects the latest values from the
+//! standard messages and packs them into HIGH_LATENCY2, [`expand`] turns a received
+//! HIGH_LATENCY2 back into the standard messages it can represent.
+
+use crate::common::{
+    HlFailureFlag, MavMessage, MavSysStatusSensor, GLOBAL_POSITION_INT_DATA, HEARTBEAT_DATA,
+    HIGH_LATENCY2_DATA, MISSION_CURRENT_DATA, NAV_CONTROLLER_OUTPUT_DATA, VFR_HUD_DATA,
+};
+
+/// Sensors whose failure is reported in HIGH_LATENCY2
+const FAILURE_FLAGS: [(MavSysStatusSensor, HlFailureFlag); 11] = [
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_GPS,
+        HlFailureFlag::HL_FAILURE_FLAG_GPS,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_DIFFERENTIAL_PRESSURE,
+        HlFailureFlag::HL_FAILURE_FLAG_DIFFERENTIAL_PRESSURE,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_ABSOLUTE_PRESSURE,
+        HlFailureFlag::HL_FAILURE_FLAG_ABSOLUTE_PRESSURE,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_ACCEL,
+        HlFailureFlag::HL_FAILURE_FLAG_3D_ACCEL,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_GYRO,
+        HlFailureFlag::HL_FAILURE_FLAG_3D_GYRO,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_MAG,
+        HlFailureFlag::HL_FAILURE_FLAG_3D_MAG,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_TERRAIN,
+        HlFailureFlag::HL_FAILURE_FLAG_TERRAIN,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_BATTERY,
+        HlFailureFlag::HL_FAILURE_FLAG_BATTERY,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_RC_RECEIVER,
+        HlFailureFlag::HL_FAILURE_FLAG_RC_RECEIVER,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_GEOFENCE,
+        HlFailureFlag::HL_FAILURE_FLAG_GEOFENCE,
+    ),
+    (
+        MavSysStatusSensor::MAV_SYS_STATUS_AHRS,
+        HlFailureFlag::HL_FAILURE_FLAG_ESTIMATOR,
+    ),
+];
+
+fn saturate_u8(value: f32) -> u8 {
+    value.round().clamp(0.0, f32::from(u8::MAX)) as u8
+}
+
+fn saturate_i8(value: f32) -> i8 {
+    value.round().clamp(f32::from(i8::MIN), f32::from(i8::MAX)) as i8
+}
+
+fn saturate_i16(value: f32) -> i16 {
+    value
+        .round()
+        .clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16
+}
+
+/// Heading in degrees to the 2 degree resolution of HIGH_LATENCY2
+fn half_degrees(degrees: f32) -> u8 {
+    (degrees.rem_euclid(360.0) / 2.0) as u8
+}
+
+/// Latest state of a vehicle, summarized as HIGH_LATENCY2.
+///
+/// Feed every message of the vehicle to [`HighLatencySummary::update`] and call
+/// [`HighLatencySummary::message`] whenever a HIGH_LATENCY2 should be sent. Values the vehicle
+/// did not report are left at zero.
+#[derive(Debug, Clone, Default)]
+pub struct HighLatencySummary {
+    data: HIGH_LATENCY2_DATA,
+}
+
+impl HighLatencySummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the values of a telemetry message into account, returns `false` if the message
+    /// does not contribute to HIGH_LATENCY2
+    pub fn update(&mut self, msg: &MavMessage) -> bool {
+        let data = &mut self.data;
+        match msg {
+            MavMessage::HEARTBEAT(heartbeat) => {
+                data.mavtype = heartbeat.mavtype;
+                data.autopilot = heartbeat.autopilot;
+                data.custom_mode = heartbeat.custom_mode as u16;
+            }
+            MavMessage::GLOBAL_POSITION_INT(position) => {
+                data.timestamp = position.time_boot_ms;
+                data.latitude = position.lat;
+                data.longitude = position.lon;
+                data.altitude = saturate_i16(position.alt as f32 / 1000.0);
+                if position.hdg != u16::MAX {
+                    data.heading = half_degrees(f32::from(position.hdg) / 100.0);
+                }
+            }
+            MavMessage::VFR_HUD(hud) => {
+                data.airspeed = saturate_u8(hud.airspeed * 5.0);
+                data.groundspeed = saturate_u8(hud.groundspeed * 5.0);
+                data.climb_rate = saturate_i8(hud.climb * 10.0);
+                data.throttle = hud.throttle.min(100) as u8;
+            }
+            MavMessage::SYS_STATUS(status) => {
+                data.battery = status.battery_remaining;
+                let failed = status.onboard_control_sensors_present
+                    & status.onboard_control_sensors_enabled
+                    & !status.onboard_control_sensors_health;
+                data.failure_flags = FAILURE_FLAGS
+                    .iter()
+                    .filter(|(sensor, _)| failed.contains(*sensor))
+                    .fold(HlFailureFlag::empty(), |flags, (_, flag)| flags | *flag);
+            }
+            MavMessage::GPS_RAW_INT(gps) => {
+                // cm to dm, UINT16_MAX means unknown
+                data.eph = saturate_u8(f32::from(gps.eph) / 10.0);
+                data.epv = saturate_u8(f32::from(gps.epv) / 10.0);
+            }
+            MavMessage::MISSION_CURRENT(current) => data.wp_num = current.seq,
+            MavMessage::NAV_CONTROLLER_OUTPUT(nav) => {
+                data.target_heading = half_degrees(f32::from(nav.nav_bearing));
+                data.target_distance = nav.wp_dist / 10;
+                data.target_altitude = data.altitude.saturating_add(saturate_i16(nav.alt_error));
+            }
+            MavMessage::WIND_COV(wind) => {
+                let speed = wind.wind_x.hypot(wind.wind_y);
+                data.windspeed = saturate_u8(speed * 5.0);
+                // direction the wind is coming from
+                let from = (-wind.wind_y).atan2(-wind.wind_x).to_degrees();
+                data.wind_heading = half_degrees(from);
+            }
+            MavMessage::SCALED_PRESSURE(pressure) => {
+                data.temperature_air = saturate_i8(f32::from(pressure.temperature) / 100.0);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Custom values, their meaning is defined by the autopilot
+    pub fn set_custom(&mut self, custom0: i8, custom1: i8, custom2: i8) {
+        self.data.custom0 = custom0;
+        self.data.custom1 = custom1;
+        self.data.custom2 = custom2;
+    }
+
+    pub fn data(&self) -> &HIGH_LATENCY2_DATA {
+        &self.data
+    }
+
+    pub fn message(&self) -> MavMessage {
+        MavMessage::HIGH_LATENCY2(self.data.clone())
+    }
+}
+
+/// Standard messages carrying the values of a HIGH_LATENCY2.
+///
+/// Only fields HIGH_LATENCY2 reports are set, at its reduced resolution; values it does not
+/// carry, such as the base mode or velocities, are left at their defaults.
+pub fn expand(data: &HIGH_LATENCY2_DATA) -> Vec<MavMessage> {
+    let heading = u16::from(data.heading) * 2;
+    let altitude = f32::from(data.altitude);
+
+    vec![
+        MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode: u32::from(data.custom_mode),
+            mavtype: data.mavtype,
+            autopilot: data.autopilot,
+            ..Default::default()
+        }),
+        MavMessage::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+            time_boot_ms: data.timestamp,
+            lat: data.latitude,
+            lon: data.longitude,
+            alt: i32::from(data.altitude) * 1000,
+            hdg: heading * 100,
+            ..Default::default()
+        }),
+        MavMessage::VFR_HUD(VFR_HUD_DATA {
+            airspeed: f32::from(data.airspeed) / 5.0,
+            groundspeed: f32::from(data.groundspeed) / 5.0,
+            alt: altitude,
+            climb: f32::from(data.climb_rate) / 10.0,
+            heading: heading as i16,
+            throttle: u16::from(data.throttle),
+        }),
+        MavMessage::MISSION_CURRENT(MISSION_CURRENT_DATA {
+            seq: data.wp_num,
+            #[cfg(feature = "emit-extensions")]
+            total: u16::MAX,
+            #[cfg(feature = "emit-extensions")]
+            mission_state: Default::default(),
+            #[cfg(feature = "emit-extensions")]
+            mission_mode: 0,
+        }),
+        MavMessage::NAV_CONTROLLER_OUTPUT(NAV_CONTROLLER_OUTPUT_DATA {
+            alt_error: f32::from(data.target_altitude) - altitude,
+            nav_bearing: i16::from(data.target_heading) * 2,
+            target_bearing: i16::from(data.target_heading) * 2,
+            wp_dist: data.target_distance.saturating_mul(10),
+            ..Default::default()
+        }),
+    ]
+}
@@ -0,0 +1,296 @@
+//! Access to the messages of a connection over HTTP and JSON, using the paths of
+//! [mavlink2rest](https://github.com/mavlink/mavlink2rest).
+//!
+//! | Request                           | Response                                              |
+//! |-----------------------------------|-------------------------------------------------------|
+//! | `GET /mavlink`                    | last message of every type, by system and component   |
+//! | `GET /mavlink/<path>`             | part of it, e.g. `/mavlink/vehicles/1/components/1/messages/ATTITUDE` |
+//! | `POST /mavlink`                   | sends `{"header": {..}, "message": {"type": "..", ..}}` |
+//! | `GET /helper/mavlink?name=<NAME>` | template of a message to post                         |
+//!
+//! Times are given in seconds since the UNIX epoch.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::MessageReadError;
+use crate::{MavConnection, MavHeader, Message};
+
+/// Last received message of one type from one component
+#[derive(Debug, Clone)]
+struct StoredMessage {
+    message: Value,
+    first_update: SystemTime,
+    last_update: SystemTime,
+    counter: u64,
+}
+
+fn seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+impl StoredMessage {
+    fn to_json(&self) -> Value {
+        let elapsed = self
+            .last_update
+            .duration_since(self.first_update)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let frequency = if elapsed > 0.0 {
+            (self.counter - 1) as f64 / elapsed
+        } else {
+            0.0
+        };
+        json!({
+            "message": self.message,
+            "status": {
+                "time": {
+                    "first_update": seconds(self.first_update),
+                    "last_update": seconds(self.last_update),
+                    "counter": self.counter,
+                    "frequency": frequency,
+                }
+            }
+        })
+    }
+}
+
+/// The last message of every type received from every component
+#[derive(Debug, Clone, Default)]
+pub struct MessageStore {
+    vehicles: BTreeMap<u8, BTreeMap<u8, BTreeMap<&'static str, StoredMessage>>>,
+}
+
+impl MessageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a received message
+    pub fn update<M: Message + Serialize>(&mut self, now: SystemTime, header: &MavHeader, msg: &M) {
+        let message = match serde_json::to_value(msg) {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        let messages = self
+            .vehicles
+            .entry(header.system_id)
+            .or_default()
+            .entry(header.component_id)
+            .or_default();
+        match messages.get_mut(msg.message_name()) {
+            Some(stored) => {
+                stored.message = message;
+                stored.last_update = now;
+                stored.counter += 1;
+            }
+            None => {
+                messages.insert(
+                    msg.message_name(),
+                    StoredMessage {
+                        message,
+                        first_update: now,
+                        last_update: now,
+                        counter: 1,
+                    },
+                );
+            }
+        }
+    }
+
+    /// All stored messages as served at `/mavlink`
+    pub fn to_json(&self) -> Value {
+        let vehicles: serde_json::Map<String, Value> = self
+            .vehicles
+            .iter()
+            .map(|(system_id, components)| {
+                let components: serde_json::Map<String, Value> = components
+                    .iter()
+                    .map(|(component_id, messages)| {
+                        let messages: serde_json::Map<String, Value> = messages
+                            .iter()
+                            .map(|(name, stored)| (name.to_string(), stored.to_json()))
+                            .collect();
+                        (component_id.to_string(), json!({ "messages": messages }))
+                    })
+                    .collect();
+                (system_id.to_string(), json!({ "components": components }))
+            })
+            .collect();
+        json!({ "vehicles": vehicles })
+    }
+}
+
+/// Response to an HTTP request
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl HttpResponse {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, error: impl ToString) -> Self {
+        Self {
+            status,
+            body: json!({ "error": error.to_string() }),
+        }
+    }
+}
+
+/// Message posted to `/mavlink`, the header defaults to the one of the bridge
+#[derive(Deserialize)]
+#[serde(bound = "M: DeserializeOwned")]
+struct PostedMessage<M> {
+    header: Option<MavHeader>,
+    message: M,
+}
+
+/// Serves the messages of a connection over HTTP.
+///
+/// [`HttpBridge::handle`] answers a single request and can be used with any HTTP server,
+/// [`HttpBridge::run`] receives from the connection and serves requests itself.
+pub struct HttpBridge<M: Message> {
+    connection: Box<dyn MavConnection<M> + Send + Sync>,
+    header: MavHeader,
+    store: Mutex<MessageStore>,
+}
+
+impl<M: Message + Serialize + DeserializeOwned> HttpBridge<M> {
+    pub fn new(connection: Box<dyn MavConnection<M> + Send + Sync>) -> Self {
+        Self {
+            connection,
+            header: MavHeader::default(),
+            store: Mutex::new(MessageStore::new()),
+        }
+    }
+
+    /// Header of posted messages which do not bring their own
+    pub fn with_header(mut self, header: MavHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn connection(&self) -> &(dyn MavConnection<M> + Send + Sync) {
+        &*self.connection
+    }
+
+    /// Record a message received on the connection
+    pub fn record(&self, header: &MavHeader, msg: &M) {
+        self.store
+            .lock()
+            .unwrap()
+            .update(SystemTime::now(), header, msg);
+    }
+
+    /// Answer a request, `url` being the path with the query string
+    pub fn handle(&self, method: &str, url: &str, body: &str) -> HttpResponse {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments: Vec<&str> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        match (method, &segments[..]) {
+            ("GET", ["mavlink", keys @ ..]) => {
+                let mut value = self.store.lock().unwrap().to_json();
+                for segment in keys {
+                    let child = match &mut value {
+                        Value::Object(map) => map.remove(*segment),
+                        Value::Array(values) => segment
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|index| *index < values.len())
+                            .map(|index| values.swap_remove(index)),
+                        _ => None,
+                    };
+                    value = match child {
+                        Some(child) => child,
+                        None => return HttpResponse::error(404, format!("{path} not found")),
+                    };
+                }
+                HttpResponse::ok(value)
+            }
+            ("POST", ["mavlink"]) => {
+                let posted: PostedMessage<M> = match serde_json::from_str(body) {
+                    Ok(posted) => posted,
+                    Err(e) => return HttpResponse::error(400, e),
+                };
+                let header = posted.header.unwrap_or(self.header);
+                match self.connection.send(&header, &posted.message) {
+                    Ok(_) => HttpResponse::ok(json!({ "sent": posted.message.message_name() })),
+                    Err(e) => HttpResponse::error(500, e),
+                }
+            }
+            ("GET", ["helper", "mavlink"]) => {
+                let name = query
+                    .split('&')
+                    .find_map(|parameter| parameter.strip_prefix("name="));
+                let msg = name
+                    .ok_or("missing name parameter")
+                    .and_then(M::message_id_from_name)
+                    .and_then(M::default_message_from_id);
+                match msg {
+                    Ok(msg) => HttpResponse::ok(json!({ "header": self.header, "message": msg })),
+                    Err(e) => HttpResponse::error(404, e),
+                }
+            }
+            _ => HttpResponse::error(404, format!("{method} {path} not found")),
+        }
+    }
+
+    /// Receive from the connection on a separate thread and serve requests on `address`, e.g.
+    /// `0.0.0.0:8088`, until the server fails
+    pub fn run(self: Arc<Self>, address: &str) -> io::Result<()>
+    where
+        M: Send + Sync + 'static,
+    {
+        let server = tiny_http::Server::http(address)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        thread::spawn({
+            let bridge = self.clone();
+            move || loop {
+                match bridge.connection.recv() {
+                    Ok((header, msg)) => bridge.record(&header, &msg),
+                    Err(MessageReadError::Io(e)) => {
+                        if e.kind() != io::ErrorKind::WouldBlock {
+                            return;
+                        }
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                    // messages that could not be parsed are not served
+                    Err(_) => {}
+                }
+            }
+        });
+
+        let content_type =
+            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            let response = match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => self.handle(request.method().as_str(), request.url(), &body),
+                Err(e) => HttpResponse::error(400, e),
+            };
+            request.respond(
+                tiny_http::Response::from_string(response.body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(content_type.clone()),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+//! Round trip time of a link, measured with TIMESYNC or PING exchanges.
+//!
+//! [`LatencyProbe`] builds the requests and matches the answers to them, taking the local time
+//! explicitly like [`TimeSync`](crate::timesync::TimeSync). [`LatencyMeter`] sends a series of
+//! probes over a [`MavConnection`] and collects the round trips into [`LatencyStats`].
+
+use core::fmt::{Display, Formatter};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::{MavMessage, PING_DATA, TIMESYNC_DATA};
+use crate::error::ProtocolError;
+use crate::request::recv_message;
+use crate::{MavConnection, MavHeader, Message};
+
+/// Time between two probes
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Time to wait for an answer before the probe is counted as lost
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Number of unanswered probes remembered, answers to older ones are ignored
+const MAX_PENDING: usize = 16;
+
+/// Message used to measure the round trip
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProbeKind {
+    /// TIMESYNC request, answered by most autopilots
+    Timesync,
+    /// PING request, deprecated but still answered by some components
+    Ping,
+}
+
+/// Builds latency probes and matches the answers to them
+#[derive(Debug, Clone)]
+pub struct LatencyProbe {
+    kind: ProbeKind,
+    target_system: u8,
+    target_component: u8,
+    sequence: u32,
+    /// Sequence number and send time of the unanswered probes
+    pending: VecDeque<(u32, i64)>,
+}
+
+impl LatencyProbe {
+    pub fn new(kind: ProbeKind) -> Self {
+        Self {
+            kind,
+            target_system: 0,
+            target_component: 0,
+            sequence: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Only accept answers of this system and component, 0 accepts any
+    pub fn with_target(mut self, target_system: u8, target_component: u8) -> Self {
+        self.target_system = target_system;
+        self.target_component = target_component;
+        self
+    }
+
+    pub fn kind(&self) -> ProbeKind {
+        self.kind
+    }
+
+    /// Probe to send at the local time `now_ns`
+    pub fn request(&mut self, now_ns: i64) -> MavMessage {
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        if self.pending.len() == MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((sequence, now_ns));
+
+        match self.kind {
+            ProbeKind::Timesync => MavMessage::TIMESYNC(TIMESYNC_DATA {
+                tc1: 0,
+                ts1: now_ns,
+                #[cfg(feature = "emit-extensions")]
+                target_system: self.target_system,
+                #[cfg(feature = "emit-extensions")]
+                target_component: self.target_component,
+            }),
+            ProbeKind::Ping => MavMessage::PING(PING_DATA {
+                time_usec: (now_ns / 1000) as u64,
+                seq: sequence,
+                target_system: 0,
+                target_component: 0,
+            }),
+        }
+    }
+
+    /// Feed a received message, returning the round trip time if it answers a pending probe
+    pub fn handle(
+        &mut self,
+        now_ns: i64,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Option<Duration> {
+        if (self.target_system != 0 && header.system_id != self.target_system)
+            || (self.target_component != 0 && header.component_id != self.target_component)
+        {
+            return None;
+        }
+
+        let index = match (self.kind, msg) {
+            // requests have tc1 0, answers echo our timestamp in ts1
+            (ProbeKind::Timesync, MavMessage::TIMESYNC(data)) if data.tc1 != 0 => self
+                .pending
+                .iter()
+                .position(|(_, sent_ns)| *sent_ns == data.ts1)?,
+            // requests are broadcast, answers are addressed to the requester
+            (ProbeKind::Ping, MavMessage::PING(data)) if data.target_system != 0 => {
+                self.pending.iter().position(|(sequence, sent_ns)| {
+                    *sequence == data.seq && (*sent_ns / 1000) as u64 == data.time_usec
+                })?
+            }
+            _ => return None,
+        };
+        let (_, sent_ns) = self.pending.remove(index)?;
+        Some(Duration::from_nanos((now_ns - sent_ns).max(0) as u64))
+    }
+}
+
+/// Round trips of a series of probes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyStats {
+    /// Number of probes sent
+    pub sent: u32,
+    /// Round trip times of the answered probes, in the order they were sent
+    pub samples: Vec<Duration>,
+}
+
+impl LatencyStats {
+    pub fn add_sample(&mut self, rtt: Duration) {
+        self.samples.push(rtt);
+    }
+
+    /// Share of the probes that were not answered, between 0 and 1
+    pub fn loss(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        1.0 - self.samples.len() as f64 / f64::from(self.sent)
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.iter().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<Duration>() / self.samples.len() as u32)
+    }
+
+    pub fn median(&self) -> Option<Duration> {
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        match sorted.len() {
+            0 => None,
+            len if len % 2 == 1 => Some(sorted[len / 2]),
+            len => Some((sorted[len / 2 - 1] + sorted[len / 2]) / 2),
+        }
+    }
+
+    pub fn std_dev(&self) -> Option<Duration> {
+        let mean = self.mean()?.as_secs_f64();
+        let variance = self
+            .samples
+            .iter()
+            .map(|rtt| (rtt.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / self.samples.len() as f64;
+        Some(Duration::from_secs_f64(variance.sqrt()))
+    }
+
+    /// Mean difference between consecutive round trips, as in RFC 3550 without its smoothing
+    pub fn jitter(&self) -> Option<Duration> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+        let total: Duration = self
+            .samples
+            .windows(2)
+            .map(|pair| {
+                if pair[1] > pair[0] {
+                    pair[1] - pair[0]
+                } else {
+                    pair[0] - pair[1]
+                }
+            })
+            .sum();
+        Some(total / (self.samples.len() - 1) as u32)
+    }
+}
+
+impl Display for LatencyStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let ms = |rtt: Option<Duration>| rtt.map_or(0.0, |rtt| rtt.as_secs_f64() * 1000.0);
+        write!(
+            f,
+            "{} sent, {} answered, {:.1}% loss, rtt min/mean/median/max = {:.3}/{:.3}/{:.3}/{:.3} ms, std dev {:.3} ms, jitter {:.3} ms",
+            self.sent,
+            self.samples.len(),
+            self.loss() * 100.0,
+            ms(self.min()),
+            ms(self.mean()),
+            ms(self.median()),
+            ms(self.max()),
+            ms(self.std_dev()),
+            ms(self.jitter()),
+        )
+    }
+}
+
+/// Measures the round trip time over a connection.
+///
+/// Timeouts are checked whenever the connection returns from `recv`, so on a silent link the
+/// connection should have a read timeout.
+pub struct LatencyMeter<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    connection: &'a C,
+    header: MavHeader,
+    kind: ProbeKind,
+    target: (u8, u8),
+    interval: Duration,
+    timeout: Duration,
+    _message: PhantomData<M>,
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> LatencyMeter<'a, M, C> {
+    pub fn new(connection: &'a C) -> Self {
+        Self {
+            connection,
+            header: MavHeader::default(),
+            kind: ProbeKind::Timesync,
+            target: (0, 0),
+            interval: DEFAULT_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            _message: PhantomData,
+        }
+    }
+
+    /// Header used for outgoing messages, the sequence number is set by the connection
+    pub fn with_header(mut self, header: MavHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: ProbeKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Only measure the round trip to this system and component, 0 accepts any
+    pub fn with_target(mut self, target_system: u8, target_component: u8) -> Self {
+        self.target = (target_system, target_component);
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send `count` probes, each once the previous one was answered or timed out and the
+    /// interval passed
+    pub fn measure(&self, count: u32) -> Result<LatencyStats, ProtocolError> {
+        let epoch = Instant::now();
+        let now_ns = || epoch.elapsed().as_nanos() as i64;
+        let mut probe = LatencyProbe::new(self.kind).with_target(self.target.0, self.target.1);
+        let mut stats = LatencyStats::default();
+
+        for _ in 0..count {
+            let sent = Instant::now();
+            self.send(&probe.request(now_ns()))?;
+            stats.sent += 1;
+
+            let deadline = sent + self.timeout;
+            while Instant::now() < deadline {
+                let (header, msg) = match recv_message(self.connection)? {
+                    Some(received) => received,
+                    None => continue,
+                };
+                let msg = match msg.to_dialect::<MavMessage>() {
+                    Some(msg) => msg,
+                    None => continue,
+                };
+                if let Some(rtt) = probe.handle(now_ns(), &header, &msg) {
+                    stats.add_sample(rtt);
+                    break;
+                }
+            }
+
+            let next = sent + self.interval;
+            let now = Instant::now();
+            if next > now {
+                thread::sleep(next - now);
+            }
+        }
+        Ok(stats)
+    }
+
+    fn send(&self, msg: &MavMessage) -> Result<(), ProtocolError> {
+        // TIMESYNC and PING are part of every message set including common
+        if let Some(msg) = msg.to_dialect::<M>() {
+            self.connection.send(&self.header, &msg)?;
+        }
+        Ok(())
+    }
+}
@@ -18,12 +18,39 @@
 //! feature for the message sets that it includes. For example, you cannot use the `ardupilotmega`
 //! feature without also using the `uavionix` and `icarous` features.
 //!
+//! # Strict and lenient decoding
+//! By default a message carrying an enum value unknown to the message set is rejected with
+//! [`error::ParserError::InvalidEnum`]. With the `lenient-decoding` feature the generated
+//! deserializers instead substitute the enum default, so messages from newer or vendor firmware
+//! are still delivered.
+//!
+//! # Invalid values
+//! Fields that mark an unknown value with a sentinel (e.g. `UINT16_MAX` or `NaN`) get a getter
+//! named after the field with a `_checked` suffix that returns `None` for the sentinel, e.g.
+//! `GLOBAL_POSITION_INT_DATA::hdg_checked()`.
+//!
+//! # Units
+//! With the `uom` feature, the fields whose unit is a length, velocity or angle get accessors
+//! returning [`uom`](https://docs.rs/uom) quantities, named after the field with a `_quantity`
+//! suffix, e.g. `GLOBAL_POSITION_INT_DATA::lat_quantity()`. Scaled units like `degE7`, `cm` or
+//! `cdeg` are converted, so the quantities can be compared and combined whatever the unit of the
+//! field.
+//!
+//! # Diagnostics
+//! With the `tracing` feature, connection setup, sent and received frames, dropped frames and the
+//! retries of the protocol clients are reported as [`tracing`](https://docs.rs/tracing) events
+//! with the message, system and component ids as fields. Frames are traced at the `TRACE` level,
+//! dropped frames and retries at `DEBUG`.
+//!
 #![cfg_attr(not(feature = "std"), no_std)]
 #![deny(clippy::all)]
 #![warn(clippy::use_self)]
 
 use core::result::Result;
 
+#[macro_use]
+mod trace;
+
 #[cfg(feature = "std")]
 use std::io::{Read, Write};
 
@@ -31,13 +58,82 @@ use std::io::{Read, Write};
 use byteorder::ReadBytesExt;
 
 #[cfg(feature = "std")]
-mod connection;
+pub mod connection;
+#[cfg(all(feature = "websocket", target_arch = "wasm32"))]
+pub use self::connection::WebSocketConnection;
+#[cfg(feature = "std")]
+pub use self::connection::{connect, MavConnection, MavConnectionExt};
+
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod battery;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod commands;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod component;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod component_information;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod coordinates;
+#[cfg(all(feature = "std", feature = "json"))]
+pub mod diff;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod discovery;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod failsafe;
+#[cfg(feature = "std")]
+pub mod faults;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod ftp;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod high_latency;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod latency;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod logs;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod missions;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod params;
+#[cfg(feature = "qgc-plan")]
+pub mod plan;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod recording;
 #[cfg(feature = "std")]
-pub use self::connection::{connect, MavConnection};
+pub mod request;
+#[cfg(feature = "std")]
+pub mod router;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod signing;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod subscription;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod synthetic;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod telemetry;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod timestamps;
+#[cfg(all(feature = "std", feature = "common"))]
+pub mod timesync;
+#[cfg(feature = "wireshark")]
+pub mod wireshark;
+#[cfg(feature = "zmq")]
+pub mod zmq;
 
 mod utils;
 #[allow(unused_imports)]
-use utils::{remove_trailing_zeroes, RustDefault};
+use utils::remove_trailing_zeroes;
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
+use utils::RustDefault;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -51,7 +147,11 @@ include!(concat!(env!("OUT_DIR"), "/mod.rs"));
 
 pub mod bytes;
 pub mod bytes_mut;
+pub mod chars;
+pub mod debug;
 pub mod error;
+pub mod fallback;
+pub mod parser;
 
 #[cfg(feature = "embedded")]
 mod embedded;
@@ -79,6 +179,48 @@ where
     fn message_id_from_name(name: &str) -> Result<u32, &'static str>;
     fn default_message_from_id(id: u32) -> Result<Self, &'static str>;
     fn extra_crc(id: u32) -> u8;
+
+    /// Name of the message set this message type was generated from, e.g. `"ardupilotmega"`,
+    /// `"unknown"` for message types not generated from a message set definition
+    fn dialect_name() -> &'static str {
+        "unknown"
+    }
+
+    /// Value of the `<version>` element of the message set definition, if any
+    fn dialect_version() -> Option<u8> {
+        None
+    }
+
+    /// Value of the `<dialect>` element of the message set definition, if any
+    fn dialect_number() -> Option<u8> {
+        None
+    }
+
+    /// Value of the `target_system` field, `None` if the message has none
+    fn target_system_id(&self) -> Option<u8> {
+        None
+    }
+
+    /// Value of the `target_component` field, `None` if the message has none
+    fn target_component_id(&self) -> Option<u8> {
+        None
+    }
+
+    /// Convert this message into the `MavMessage` of another message set.
+    ///
+    /// The conversion goes through the wire representation, so a `common` message can be
+    /// handed to an `ardupilotmega` connection and vice versa. Returns `None` if the other
+    /// message set does not define this message with the same layout.
+    fn to_dialect<N: Message>(&self) -> Option<N> {
+        let id = self.message_id();
+        if N::extra_crc(id) != Self::extra_crc(id) {
+            return None;
+        }
+
+        let mut payload = [0u8; 255];
+        let len = self.ser(MavlinkVersion::V2, &mut payload);
+        N::parse(MavlinkVersion::V2, id, &payload[..len]).ok()
+    }
 }
 
 pub trait MessageData: Sized {
@@ -91,6 +233,12 @@ pub trait MessageData: Sized {
 
     fn ser(&self, version: MavlinkVersion, payload: &mut [u8]) -> usize;
     fn deser(version: MavlinkVersion, payload: &[u8]) -> Result<Self, ParserError>;
+
+    /// The data of `msg` if it is this message, otherwise `msg` is returned as error
+    fn from_message(msg: Self::Message) -> Result<Self, Self::Message>;
+
+    /// The data of `msg` if it is this message
+    fn from_message_ref(msg: &Self::Message) -> Option<&Self>;
 }
 
 /// Metadata from a MAVLink packet header
@@ -208,6 +356,11 @@ impl<M: Message> MavFrame<M> {
     pub fn header(&self) -> MavHeader {
         self.header
     }
+
+    /// Return the message data if the message of this frame is `D`
+    pub fn get<D: MessageData<Message = M>>(&self) -> Option<&D> {
+        D::from_message_ref(&self.msg)
+    }
 }
 
 fn calculate_crc(data: &[u8], extra_crc: u8) -> u16 {
@@ -316,6 +469,33 @@ impl MAVLinkV1MessageRaw {
         &self.0[..(1 + Self::HEADER_SIZE + payload_length + 2)]
     }
 
+    /// Annotated hex dump of the frame, checked against the message set `M`
+    pub fn debug<M: Message>(&self) -> debug::DebugFrame<'_> {
+        debug::DebugFrame::new(self.raw_bytes()).with_message_set::<M>()
+    }
+
+    /// Parse the payload as a message of the message set `M`, without checking the checksum
+    pub fn parse<M: Message>(&self) -> Result<M, ParserError> {
+        M::parse(
+            MavlinkVersion::V1,
+            u32::from(self.message_id()),
+            self.payload(),
+        )
+    }
+
+    /// Parse the payload as a message of type `D`, without checking the checksum. Fails with
+    /// [`ParserError::UnexpectedMessage`] if the frame holds another message.
+    pub fn parse_data<D: MessageData>(&self) -> Result<D, ParserError> {
+        let id = u32::from(self.message_id());
+        if id != D::ID {
+            return Err(ParserError::UnexpectedMessage {
+                expected: D::ID,
+                id,
+            });
+        }
+        D::deser(MavlinkVersion::V1, self.payload())
+    }
+
     fn serialize_stx_and_header_and_crc(
         &mut self,
         header: MavHeader,
@@ -364,6 +544,58 @@ impl MAVLinkV1MessageRaw {
     }
 }
 
+/// Bytes of dropped frames that are searched for the next frame before reading on, like
+/// [`parser::PushParser`] does, so a start marker in garbage or a frame cut short does not take
+/// the following frames with it.
+///
+/// The bytes are only kept while looking for one frame, so bytes after a frame found among them
+/// are dropped.
+struct Resync {
+    buffer: [u8; MAX_FRAME_SIZE],
+    pos: usize,
+    len: usize,
+}
+
+impl Resync {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; MAX_FRAME_SIZE],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    fn read_u8<R: Read>(&mut self, reader: &mut R) -> Result<u8, error::MessageReadError> {
+        if self.pos < self.len {
+            self.pos += 1;
+            return Ok(self.buffer[self.pos - 1]);
+        }
+        Ok(reader.read_u8()?)
+    }
+
+    fn read_exact<R: Read>(
+        &mut self,
+        reader: &mut R,
+        buf: &mut [u8],
+    ) -> Result<(), error::MessageReadError> {
+        let buffered = buf.len().min(self.len - self.pos);
+        buf[..buffered].copy_from_slice(&self.buffer[self.pos..self.pos + buffered]);
+        self.pos += buffered;
+        reader.read_exact(&mut buf[buffered..])?;
+        Ok(())
+    }
+
+    /// Search the bytes of `frame` after its start marker again, before the bytes not read yet
+    fn drop_frame(&mut self, frame: &[u8]) {
+        let rest = self.len - self.pos;
+        // the frame was read from the buffer first, so both fit into it
+        self.buffer.copy_within(self.pos..self.len, frame.len() - 1);
+        self.buffer[..frame.len() - 1].copy_from_slice(&frame[1..]);
+        self.pos = 0;
+        self.len = frame.len() - 1 + rest;
+    }
+}
+
 /// Return a raw buffer with the mavlink message
 /// V1 maximum size is 263 bytes: `<https://mavlink.io/en/guide/serialization.html>`
 pub fn read_v1_raw_message<R: Read>(
@@ -385,35 +617,78 @@ pub fn read_v1_raw_message<R: Read>(
     Ok(message)
 }
 
-/// Read a MAVLink v1  message from a Read stream.
-pub fn read_v1_msg<M: Message, R: Read>(
-    r: &mut R,
-) -> Result<(MavHeader, M), error::MessageReadError> {
+/// Return a raw buffer with the next mavlink message with a valid checksum for message set `M`,
+/// searching the bytes of the frames with an invalid checksum for the next frame.
+/// V1 maximum size is 263 bytes: `<https://mavlink.io/en/guide/serialization.html>`
+pub fn read_v1_raw_message_checked<M: Message, R: Read>(
+    reader: &mut R,
+) -> Result<MAVLinkV1MessageRaw, error::MessageReadError> {
+    let mut resync = Resync::new();
     loop {
-        let message = read_v1_raw_message(r)?;
+        // search for the magic framing value indicating start of mavlink message
+        while resync.read_u8(reader)? != MAV_STX {}
+
+        let mut message = MAVLinkV1MessageRaw::new();
+
+        message.0[0] = MAV_STX;
+        resync.read_exact(reader, message.mut_header())?;
+        resync.read_exact(reader, message.mut_payload_and_checksum())?;
+
         if !message.has_valid_crc::<M>() {
+            debug!(
+                msgid = message.message_id(),
+                sysid = message.system_id(),
+                compid = message.component_id(),
+                "dropped MAVLink 1 frame with invalid checksum"
+            );
+            resync.drop_frame(message.raw_bytes());
             continue;
         }
 
-        return M::parse(
-            MavlinkVersion::V1,
-            u32::from(message.message_id()),
-            message.payload(),
-        )
-        .map(|msg| {
-            (
-                MavHeader {
-                    sequence: message.sequence(),
-                    system_id: message.system_id(),
-                    component_id: message.component_id(),
-                },
-                msg,
-            )
-        })
-        .map_err(|err| err.into());
+        return Ok(message);
     }
 }
 
+/// Read a MAVLink v1  message from a Read stream.
+pub fn read_v1_msg<M: Message, R: Read>(
+    r: &mut R,
+) -> Result<(MavHeader, M), error::MessageReadError> {
+    let message = read_v1_raw_message_checked::<M, _>(r)?;
+
+    M::parse(
+        MavlinkVersion::V1,
+        u32::from(message.message_id()),
+        message.payload(),
+    )
+    .map(|msg| {
+        trace!(
+            msgid = message.message_id(),
+            sysid = message.system_id(),
+            compid = message.component_id(),
+            seq = message.sequence(),
+            "received MAVLink 1 frame"
+        );
+        (
+            MavHeader {
+                sequence: message.sequence(),
+                system_id: message.system_id(),
+                component_id: message.component_id(),
+            },
+            msg,
+        )
+    })
+    .map_err(|err| {
+        debug!(
+            msgid = message.message_id(),
+            sysid = message.system_id(),
+            compid = message.component_id(),
+            error = %err,
+            "failed to parse MAVLink 1 frame"
+        );
+        err.into()
+    })
+}
+
 const MAVLINK_IFLAG_SIGNED: u8 = 0x01;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -530,6 +805,29 @@ impl MAVLinkV2MessageRaw {
         &self.0[..(1 + Self::HEADER_SIZE + payload_length + signature_size + 2)]
     }
 
+    /// Annotated hex dump of the frame, checked against the message set `M`
+    pub fn debug<M: Message>(&self) -> debug::DebugFrame<'_> {
+        debug::DebugFrame::new(self.raw_bytes()).with_message_set::<M>()
+    }
+
+    /// Parse the payload as a message of the message set `M`, without checking the checksum
+    pub fn parse<M: Message>(&self) -> Result<M, ParserError> {
+        M::parse(MavlinkVersion::V2, self.message_id(), self.payload())
+    }
+
+    /// Parse the payload as a message of type `D`, without checking the checksum. Fails with
+    /// [`ParserError::UnexpectedMessage`] if the frame holds another message.
+    pub fn parse_data<D: MessageData>(&self) -> Result<D, ParserError> {
+        let id = self.message_id();
+        if id != D::ID {
+            return Err(ParserError::UnexpectedMessage {
+                expected: D::ID,
+                id,
+            });
+        }
+        D::deser(MavlinkVersion::V2, self.payload())
+    }
+
     fn serialize_stx_and_header_and_crc(
         &mut self,
         header: MavHeader,
@@ -604,32 +902,88 @@ pub fn read_v2_raw_message<R: Read>(
     Ok(message)
 }
 
-/// Read a MAVLink v2  message from a Read stream.
-pub fn read_v2_msg<M: Message, R: Read>(
-    read: &mut R,
-) -> Result<(MavHeader, M), error::MessageReadError> {
+/// Return a raw buffer with the next mavlink message with a valid checksum for message set `M`,
+/// searching the bytes of the dropped frames for the next frame. Frames with incompatibility
+/// flags other than signing are dropped as well, as their layout is unknown.
+/// V2 maximum size is 280 bytes: `<https://mavlink.io/en/guide/serialization.html>`
+pub fn read_v2_raw_message_checked<M: Message, R: Read>(
+    reader: &mut R,
+) -> Result<MAVLinkV2MessageRaw, error::MessageReadError> {
+    let mut resync = Resync::new();
     loop {
-        let message = read_v2_raw_message(read)?;
+        // search for the magic framing value indicating start of mavlink message
+        while resync.read_u8(reader)? != MAV_STX_V2 {}
+
+        let mut message = MAVLinkV2MessageRaw::new();
+
+        message.0[0] = MAV_STX_V2;
+        resync.read_exact(reader, message.mut_header())?;
+        if message.incompatibility_flags() & !MAVLINK_IFLAG_SIGNED != 0 {
+            // the frame layout is unknown with other incompatibility flags
+            debug!(
+                msgid = message.message_id(),
+                sysid = message.system_id(),
+                compid = message.component_id(),
+                flags = message.incompatibility_flags(),
+                "dropped MAVLink 2 frame with unknown incompatibility flags"
+            );
+            resync.drop_frame(&message.0[..=MAVLinkV2MessageRaw::HEADER_SIZE]);
+            continue;
+        }
+        resync.read_exact(reader, message.mut_payload_and_checksum_and_sign())?;
+
         if !message.has_valid_crc::<M>() {
             // bad crc: ignore message
+            debug!(
+                msgid = message.message_id(),
+                sysid = message.system_id(),
+                compid = message.component_id(),
+                "dropped MAVLink 2 frame with invalid checksum"
+            );
+            resync.drop_frame(message.raw_bytes());
             continue;
         }
 
-        return M::parse(MavlinkVersion::V2, message.message_id(), message.payload())
-            .map(|msg| {
-                (
-                    MavHeader {
-                        sequence: message.sequence(),
-                        system_id: message.system_id(),
-                        component_id: message.component_id(),
-                    },
-                    msg,
-                )
-            })
-            .map_err(|err| err.into());
+        return Ok(message);
     }
 }
 
+/// Read a MAVLink v2  message from a Read stream.
+pub fn read_v2_msg<M: Message, R: Read>(
+    read: &mut R,
+) -> Result<(MavHeader, M), error::MessageReadError> {
+    let message = read_v2_raw_message_checked::<M, _>(read)?;
+
+    M::parse(MavlinkVersion::V2, message.message_id(), message.payload())
+        .map(|msg| {
+            trace!(
+                msgid = message.message_id(),
+                sysid = message.system_id(),
+                compid = message.component_id(),
+                seq = message.sequence(),
+                "received MAVLink 2 frame"
+            );
+            (
+                MavHeader {
+                    sequence: message.sequence(),
+                    system_id: message.system_id(),
+                    component_id: message.component_id(),
+                },
+                msg,
+            )
+        })
+        .map_err(|err| {
+            debug!(
+                msgid = message.message_id(),
+                sysid = message.system_id(),
+                compid = message.component_id(),
+                error = %err,
+                "failed to parse MAVLink 2 frame"
+            );
+            err.into()
+        })
+}
+
 /// Write a message using the given mavlink version
 pub fn write_versioned_msg<M: Message, W: Write>(
     w: &mut W,
@@ -656,6 +1010,14 @@ pub fn write_v2_msg<M: Message, W: Write>(
     let len = 1 + MAVLinkV2MessageRaw::HEADER_SIZE + payload_length + 2;
 
     w.write_all(&message_raw.0[..len])?;
+    trace!(
+        msgid = data.message_id(),
+        sysid = header.system_id,
+        compid = header.component_id,
+        seq = header.sequence,
+        len,
+        "sent MAVLink 2 frame"
+    );
 
     Ok(len)
 }
@@ -666,6 +1028,11 @@ pub fn write_v1_msg<M: Message, W: Write>(
     header: MavHeader,
     data: &M,
 ) -> Result<usize, error::MessageWriteError> {
+    // the id would be truncated to its lowest byte
+    if data.message_id() > u32::from(u8::MAX) {
+        return Err(error::MessageWriteError::MAVLink2Only);
+    }
+
     let mut message_raw = MAVLinkV1MessageRaw::new();
     message_raw.serialize_message(header, data);
 
@@ -673,6 +1040,14 @@ pub fn write_v1_msg<M: Message, W: Write>(
     let len = 1 + MAVLinkV1MessageRaw::HEADER_SIZE + payload_length + 2;
 
     w.write_all(&message_raw.0[..len])?;
+    trace!(
+        msgid = data.message_id(),
+        sysid = header.system_id,
+        compid = header.component_id,
+        seq = header.sequence,
+        len,
+        "sent MAVLink 1 frame"
+    );
 
     Ok(len)
 }
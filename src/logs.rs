@@ -0,0 +1,427 @@
+//! Client side of the [log download protocol](https://mavlink.io/en/services/log_download.html).
+//!
+//! As in the `missions` module, the transfers ([`LogList`], [`LogDownload`]) are state machines
+//! working on `common` messages, and [`LogClient`] drives them over a [`MavConnection`]. An
+//! interrupted download can be resumed from the data received so far, and the request size and
+//! download rate can be limited to leave room for other traffic on slow links.
+
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::{
+    MavMessage, LOG_ENTRY_DATA, LOG_ERASE_DATA, LOG_REQUEST_DATA_DATA, LOG_REQUEST_END_DATA,
+    LOG_REQUEST_LIST_DATA,
+};
+use crate::error::ProtocolError;
+use crate::request::{Exchange, Requester, Step};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Time to wait for data before requesting it again
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Number of times a request is resent before giving up
+pub const DEFAULT_RETRIES: u8 = 5;
+
+/// Size of the data field of LOG_DATA
+pub const LOG_DATA_LEN: usize = 90;
+
+/// Bytes asked for by one LOG_REQUEST_DATA
+pub const DEFAULT_CHUNK_SIZE: u32 = 100 * LOG_DATA_LEN as u32;
+
+/// What a transfer wants to happen next
+pub type LogStep<T> = Step<MavMessage, T>;
+
+/// A log transfer state machine
+pub trait LogTransfer: Exchange<Message = MavMessage, Error = ProtocolError> {
+    /// Number of log bytes received so far
+    fn received(&self) -> usize {
+        0
+    }
+}
+
+/// Progress of a download, as reported to the callback of [`LogClient::download`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LogProgress {
+    pub received: usize,
+    /// Size announced in LOG_ENTRY, which may be approximate
+    pub size: usize,
+}
+
+/// Listing of the logs on the vehicle: LOG_REQUEST_LIST answered by one LOG_ENTRY per log
+#[derive(Debug, Clone)]
+pub struct LogList {
+    target_system: u8,
+    target_component: u8,
+    retries: u8,
+    attempts: u8,
+    num_logs: Option<u16>,
+    entries: BTreeMap<u16, LOG_ENTRY_DATA>,
+}
+
+impl LogList {
+    pub fn new(target_system: u8, target_component: u8) -> Self {
+        Self {
+            target_system,
+            target_component,
+            retries: DEFAULT_RETRIES,
+            attempts: 0,
+            num_logs: None,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    fn request(&self) -> MavMessage {
+        MavMessage::LOG_REQUEST_LIST(LOG_REQUEST_LIST_DATA {
+            target_system: self.target_system,
+            target_component: self.target_component,
+            start: 0,
+            end: u16::MAX,
+        })
+    }
+
+    fn finish(&mut self) -> LogStep<Vec<LOG_ENTRY_DATA>> {
+        let entries = core::mem::take(&mut self.entries);
+        LogStep::Done(None, entries.into_values().collect())
+    }
+}
+
+impl Exchange for LogList {
+    type Message = MavMessage;
+    type Output = Vec<LOG_ENTRY_DATA>;
+    type Error = ProtocolError;
+
+    fn start(&mut self) -> MavMessage {
+        self.request()
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<LogStep<Self::Output>, ProtocolError> {
+        if !is_from(header, self.target_system, self.target_component) {
+            return Ok(LogStep::Wait);
+        }
+
+        Ok(match msg {
+            MavMessage::LOG_ENTRY(entry) => {
+                self.attempts = 0;
+                self.num_logs = Some(entry.num_logs);
+                // an empty log list is reported with a single entry with id 0
+                if entry.num_logs > 0 {
+                    self.entries.insert(entry.id, entry.clone());
+                }
+                if self.entries.len() >= usize::from(entry.num_logs) {
+                    self.finish()
+                } else {
+                    LogStep::Progress
+                }
+            }
+            _ => LogStep::Wait,
+        })
+    }
+
+    fn on_timeout(&mut self) -> Result<LogStep<Self::Output>, ProtocolError> {
+        if self.attempts >= self.retries {
+            warn!(
+                sysid = self.target_system,
+                attempts = self.attempts,
+                "log list timed out, returning the received entries"
+            );
+            // entries may be lost on a lossy link, return what could be listed
+            return match self.num_logs {
+                Some(_) => Ok(self.finish()),
+                None => Err(ProtocolError::Timeout),
+            };
+        }
+        self.attempts += 1;
+        debug!(
+            sysid = self.target_system,
+            attempt = self.attempts,
+            "resending log request"
+        );
+        Ok(LogStep::Send(self.request()))
+    }
+}
+
+impl LogTransfer for LogList {}
+
+/// Download of one log with LOG_REQUEST_DATA, one chunk of data at a time
+#[derive(Debug, Clone)]
+pub struct LogDownload {
+    target_system: u8,
+    target_component: u8,
+    id: u16,
+    chunk_size: u32,
+    retries: u8,
+    attempts: u8,
+    /// End of the data asked for by the last request
+    requested_end: usize,
+    data: Vec<u8>,
+}
+
+impl LogDownload {
+    pub fn new(target_system: u8, target_component: u8, id: u16) -> Self {
+        Self::resume(target_system, target_component, id, Vec::new())
+    }
+
+    /// Continue an interrupted download, `data` being the start of the log received before
+    pub fn resume(target_system: u8, target_component: u8, id: u16, data: Vec<u8>) -> Self {
+        Self {
+            target_system,
+            target_component,
+            id,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            retries: DEFAULT_RETRIES,
+            attempts: 0,
+            requested_end: data.len(),
+            data,
+        }
+    }
+
+    /// Number of bytes asked for at once, smaller chunks leave more room for other traffic
+    pub fn with_chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size.max(LOG_DATA_LEN as u32);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// The data received so far, which can be used to resume the download
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn request(&mut self) -> MavMessage {
+        self.requested_end = self.data.len() + self.chunk_size as usize;
+        MavMessage::LOG_REQUEST_DATA(LOG_REQUEST_DATA_DATA {
+            ofs: self.data.len() as u32,
+            count: self.chunk_size,
+            id: self.id,
+            target_system: self.target_system,
+            target_component: self.target_component,
+        })
+    }
+
+    fn end(&self) -> MavMessage {
+        MavMessage::LOG_REQUEST_END(LOG_REQUEST_END_DATA {
+            target_system: self.target_system,
+            target_component: self.target_component,
+        })
+    }
+}
+
+impl Exchange for LogDownload {
+    type Message = MavMessage;
+    type Output = Vec<u8>;
+    type Error = ProtocolError;
+
+    fn start(&mut self) -> MavMessage {
+        self.request()
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<LogStep<Self::Output>, ProtocolError> {
+        if !is_from(header, self.target_system, self.target_component) {
+            return Ok(LogStep::Wait);
+        }
+
+        Ok(match msg {
+            // data after a gap is dropped and requested again once the chunk timed out
+            MavMessage::LOG_DATA(data)
+                if data.id == self.id && data.ofs as usize == self.data.len() =>
+            {
+                self.attempts = 0;
+                let count = usize::from(data.count).min(LOG_DATA_LEN);
+                self.data.extend_from_slice(&data.data[..count]);
+
+                // a short packet marks the end of the log
+                if count < LOG_DATA_LEN {
+                    LogStep::Done(Some(self.end()), core::mem::take(&mut self.data))
+                } else if self.data.len() >= self.requested_end {
+                    LogStep::Send(self.request())
+                } else {
+                    LogStep::Progress
+                }
+            }
+            _ => LogStep::Wait,
+        })
+    }
+
+    fn on_timeout(&mut self) -> Result<LogStep<Self::Output>, ProtocolError> {
+        if self.attempts >= self.retries {
+            warn!(
+                sysid = self.target_system,
+                attempts = self.attempts,
+                "log download timed out"
+            );
+            return Err(ProtocolError::Timeout);
+        }
+        self.attempts += 1;
+        debug!(
+            sysid = self.target_system,
+            attempt = self.attempts,
+            "resending log request"
+        );
+        Ok(LogStep::Send(self.request()))
+    }
+}
+
+impl LogTransfer for LogDownload {
+    fn received(&self) -> usize {
+        self.data.len()
+    }
+}
+
+fn is_from(header: &MavHeader, target_system: u8, target_component: u8) -> bool {
+    header.system_id == target_system
+        && (target_component == 0 || header.component_id == target_component)
+}
+
+/// Lists, downloads and erases the logs of a vehicle over a connection
+pub struct LogClient<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    requester: Requester<'a, M, C>,
+    target_system: u8,
+    target_component: u8,
+    chunk_size: u32,
+    max_rate: Option<u32>,
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> LogClient<'a, M, C> {
+    pub fn new(connection: &'a C, target_system: u8, target_component: u8) -> Self {
+        let requester = Requester::new(connection)
+            .with_timeout(DEFAULT_TIMEOUT)
+            .with_retries(DEFAULT_RETRIES);
+        Self::from_requester(requester, target_system, target_component)
+    }
+
+    /// Run the transfers with the header, timeout and retries of `requester`
+    pub fn from_requester(
+        requester: Requester<'a, M, C>,
+        target_system: u8,
+        target_component: u8,
+    ) -> Self {
+        Self {
+            requester,
+            target_system,
+            target_component,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_rate: None,
+        }
+    }
+
+    /// Number of bytes asked for by each LOG_REQUEST_DATA
+    pub fn with_chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Limit the download rate in bytes per second by delaying the requests for more data
+    pub fn with_max_rate(mut self, bytes_per_second: u32) -> Self {
+        self.max_rate = Some(bytes_per_second);
+        self
+    }
+
+    /// The logs on the vehicle, ordered by id
+    pub fn list(&self) -> Result<Vec<LOG_ENTRY_DATA>, ProtocolError> {
+        let mut transfer = LogList::new(self.target_system, self.target_component)
+            .with_retries(self.requester.retries());
+        self.run(&mut transfer, |_| {})
+    }
+
+    /// Download a log, reporting the progress after every received packet
+    pub fn download<F: FnMut(LogProgress)>(
+        &self,
+        entry: &LOG_ENTRY_DATA,
+        progress: F,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let mut data = Vec::new();
+        self.resume(entry, &mut data, progress)?;
+        Ok(data)
+    }
+
+    /// Download the rest of a log of which `data` was already received. If the download fails,
+    /// `data` holds everything received so it can be resumed later.
+    pub fn resume<F: FnMut(LogProgress)>(
+        &self,
+        entry: &LOG_ENTRY_DATA,
+        data: &mut Vec<u8>,
+        mut progress: F,
+    ) -> Result<(), ProtocolError> {
+        let mut transfer = LogDownload::resume(
+            self.target_system,
+            self.target_component,
+            entry.id,
+            core::mem::take(data),
+        )
+        .with_chunk_size(self.chunk_size)
+        .with_retries(self.requester.retries());
+        let size = entry.size as usize;
+
+        match self.run(&mut transfer, |received| {
+            progress(LogProgress { received, size })
+        }) {
+            Ok(log) => {
+                *data = log;
+                progress(LogProgress {
+                    received: data.len(),
+                    size,
+                });
+                Ok(())
+            }
+            Err(e) => {
+                *data = transfer.into_data();
+                Err(e)
+            }
+        }
+    }
+
+    /// Erase all logs. The protocol has no acknowledgement, list the logs to check the result.
+    pub fn erase(&self) -> Result<(), ProtocolError> {
+        self.requester.send(&MavMessage::LOG_ERASE(LOG_ERASE_DATA {
+            target_system: self.target_system,
+            target_component: self.target_component,
+        }))
+    }
+
+    /// Drive a transfer until it completes or fails, `on_progress` gets the number of bytes
+    /// received whenever data arrives
+    pub fn run<T: LogTransfer>(
+        &self,
+        transfer: &mut T,
+        mut on_progress: impl FnMut(usize),
+    ) -> Result<T::Output, ProtocolError> {
+        let started = Instant::now();
+        let initial = transfer.received();
+        self.requester.run_with(transfer, |transfer| {
+            on_progress(transfer.received());
+            self.throttle(started, transfer.received() - initial);
+        })
+    }
+
+    /// Wait until downloading `received` bytes since `started` is within the rate limit
+    fn throttle(&self, started: Instant, received: usize) {
+        if let Some(rate) = self.max_rate.filter(|rate| *rate > 0) {
+            let due = Duration::from_secs_f64(received as f64 / f64::from(rate));
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Client side of the [mission microservice](https://mavlink.io/en/services/mission.html).
+//!
+//! The transfers are implemented as state machines ([`MissionUpload`], [`MissionDownload`],
+//! [`MissionClear`]) that only consume and produce `common` messages, so they can be driven by
+//! any transport. [`MissionClient`] drives them over a [`MavConnection`] of any message set.
+
+use core::fmt::{Display, Formatter};
+use std::error::Error;
+use std::time::Duration;
+
+#[cfg(feature = "emit-extensions")]
+use crate::common::MavMissionType;
+use crate::common::{
+    MavMessage, MavMissionResult, MISSION_ACK_DATA, MISSION_CLEAR_ALL_DATA, MISSION_COUNT_DATA,
+    MISSION_ITEM_INT_DATA, MISSION_REQUEST_INT_DATA, MISSION_REQUEST_LIST_DATA,
+};
+use crate::error::{MessageReadError, MessageWriteError, ProtocolError};
+use crate::request::{Exchange, Requester, Step};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Time to wait for an answer before resending, as recommended by the protocol
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Number of times a message is resent before giving up
+pub const DEFAULT_RETRIES: u8 = 5;
+
+#[derive(Debug)]
+pub enum MissionError {
+    /// The vehicle ended the transfer with an error
+    Rejected(MavMissionResult),
+    Protocol(ProtocolError),
+}
+
+impl Display for MissionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Rejected(result) => write!(f, "Mission transfer rejected: {result:?}"),
+            Self::Protocol(e) => write!(f, "Mission transfer failed: {e}"),
+        }
+    }
+}
+
+impl Error for MissionError {}
+
+impl From<ProtocolError> for MissionError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+impl From<MessageReadError> for MissionError {
+    fn from(e: MessageReadError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+impl From<MessageWriteError> for MissionError {
+    fn from(e: MessageWriteError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+/// What a transfer wants to happen next
+pub type MissionStep<T> = Step<MavMessage, T>;
+
+/// Addressing and retry bookkeeping shared by all transfers
+#[derive(Debug, Clone)]
+struct TransferState {
+    target_system: u8,
+    target_component: u8,
+    retries: u8,
+    attempts: u8,
+    last_sent: Option<MavMessage>,
+    #[cfg(feature = "emit-extensions")]
+    mission_type: MavMissionType,
+}
+
+impl TransferState {
+    fn new(target_system: u8, target_component: u8) -> Self {
+        Self {
+            target_system,
+            target_component,
+            retries: DEFAULT_RETRIES,
+            attempts: 0,
+            last_sent: None,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: MavMissionType::DEFAULT,
+        }
+    }
+
+    /// Whether the message comes from the vehicle we are talking to
+    fn is_from_target(&self, header: &MavHeader) -> bool {
+        header.system_id == self.target_system
+            && (self.target_component == 0 || header.component_id == self.target_component)
+    }
+
+    fn send<T>(&mut self, msg: MavMessage) -> MissionStep<T> {
+        self.attempts = 0;
+        self.last_sent = Some(msg.clone());
+        MissionStep::Send(msg)
+    }
+
+    fn resend<T>(&mut self) -> Result<MissionStep<T>, MissionError> {
+        if self.attempts >= self.retries {
+            warn!(
+                sysid = self.target_system,
+                attempts = self.attempts,
+                "mission transfer timed out"
+            );
+            return Err(ProtocolError::Timeout.into());
+        }
+        self.attempts += 1;
+        debug!(
+            sysid = self.target_system,
+            attempt = self.attempts,
+            "resending mission message"
+        );
+        Ok(match &self.last_sent {
+            Some(msg) => MissionStep::Send(msg.clone()),
+            None => MissionStep::Wait,
+        })
+    }
+
+    fn ack(&self, result: MavMissionResult) -> MavMessage {
+        MavMessage::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: self.target_system,
+            target_component: self.target_component,
+            mavtype: result,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: self.mission_type,
+        })
+    }
+}
+
+/// Upload of a mission: MISSION_COUNT, then one MISSION_ITEM_INT per request
+#[derive(Debug, Clone)]
+pub struct MissionUpload {
+    state: TransferState,
+    items: Vec<MISSION_ITEM_INT_DATA>,
+}
+
+impl MissionUpload {
+    pub fn new(target_system: u8, target_component: u8, items: &[MISSION_ITEM_INT_DATA]) -> Self {
+        let items = items
+            .iter()
+            .enumerate()
+            .map(|(seq, item)| MISSION_ITEM_INT_DATA {
+                target_system,
+                target_component,
+                seq: seq as u16,
+                ..item.clone()
+            })
+            .collect();
+
+        Self {
+            state: TransferState::new(target_system, target_component),
+            items,
+        }
+    }
+
+    /// Upload a fence or rally point list instead of the mission
+    #[cfg(feature = "emit-extensions")]
+    pub fn with_mission_type(mut self, mission_type: MavMissionType) -> Self {
+        self.state.mission_type = mission_type;
+        for item in &mut self.items {
+            item.mission_type = mission_type;
+        }
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.state.retries = retries;
+        self
+    }
+
+    fn item(&mut self, seq: u16) -> MissionStep<()> {
+        match self.items.get(usize::from(seq)) {
+            Some(item) => {
+                let item = MavMessage::MISSION_ITEM_INT(item.clone());
+                self.state.send(item)
+            }
+            // the vehicle asked for something we don't have, it will time out and retry
+            None => MissionStep::Wait,
+        }
+    }
+}
+
+impl Exchange for MissionUpload {
+    type Message = MavMessage;
+    type Output = ();
+    type Error = MissionError;
+
+    fn start(&mut self) -> MavMessage {
+        let count = MavMessage::MISSION_COUNT(MISSION_COUNT_DATA {
+            target_system: self.state.target_system,
+            target_component: self.state.target_component,
+            count: self.items.len() as u16,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: self.state.mission_type,
+        });
+        self.state.last_sent = Some(count.clone());
+        count
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<MissionStep<()>, MissionError> {
+        if !self.state.is_from_target(header) {
+            return Ok(MissionStep::Wait);
+        }
+
+        match msg {
+            MavMessage::MISSION_REQUEST_INT(request) => Ok(self.item(request.seq)),
+            // deprecated, but still sent by older autopilots
+            MavMessage::MISSION_REQUEST(request) => Ok(self.item(request.seq)),
+            MavMessage::MISSION_ACK(ack) => match ack.mavtype {
+                MavMissionResult::MAV_MISSION_ACCEPTED => Ok(MissionStep::Done(None, ())),
+                result => Err(MissionError::Rejected(result)),
+            },
+            _ => Ok(MissionStep::Wait),
+        }
+    }
+
+    fn on_timeout(&mut self) -> Result<MissionStep<()>, MissionError> {
+        self.state.resend()
+    }
+}
+
+/// Download of a mission: MISSION_REQUEST_LIST, then one MISSION_REQUEST_INT per item
+#[derive(Debug, Clone)]
+pub struct MissionDownload {
+    state: TransferState,
+    count: Option<u16>,
+    items: Vec<MISSION_ITEM_INT_DATA>,
+}
+
+impl MissionDownload {
+    pub fn new(target_system: u8, target_component: u8) -> Self {
+        Self {
+            state: TransferState::new(target_system, target_component),
+            count: None,
+            items: vec![],
+        }
+    }
+
+    /// Download the fence or rally point list instead of the mission
+    #[cfg(feature = "emit-extensions")]
+    pub fn with_mission_type(mut self, mission_type: MavMissionType) -> Self {
+        self.state.mission_type = mission_type;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.state.retries = retries;
+        self
+    }
+
+    fn request_next(&mut self) -> MissionStep<Vec<MISSION_ITEM_INT_DATA>> {
+        if Some(self.items.len() as u16) == self.count {
+            let ack = self.state.ack(MavMissionResult::MAV_MISSION_ACCEPTED);
+            return MissionStep::Done(Some(ack), core::mem::take(&mut self.items));
+        }
+
+        let request = MavMessage::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+            target_system: self.state.target_system,
+            target_component: self.state.target_component,
+            seq: self.items.len() as u16,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: self.state.mission_type,
+        });
+        self.state.send(request)
+    }
+}
+
+impl Exchange for MissionDownload {
+    type Message = MavMessage;
+    type Output = Vec<MISSION_ITEM_INT_DATA>;
+    type Error = MissionError;
+
+    fn start(&mut self) -> MavMessage {
+        let request = MavMessage::MISSION_REQUEST_LIST(MISSION_REQUEST_LIST_DATA {
+            target_system: self.state.target_system,
+            target_component: self.state.target_component,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: self.state.mission_type,
+        });
+        self.state.last_sent = Some(request.clone());
+        request
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<MissionStep<Self::Output>, MissionError> {
+        if !self.state.is_from_target(header) {
+            return Ok(MissionStep::Wait);
+        }
+
+        match msg {
+            MavMessage::MISSION_COUNT(count) if self.count.is_none() => {
+                self.count = Some(count.count);
+                Ok(self.request_next())
+            }
+            MavMessage::MISSION_ITEM_INT(item)
+                if self.count.is_some() && usize::from(item.seq) == self.items.len() =>
+            {
+                self.items.push(item.clone());
+                Ok(self.request_next())
+            }
+            MavMessage::MISSION_ACK(ack)
+                if ack.mavtype != MavMissionResult::MAV_MISSION_ACCEPTED =>
+            {
+                Err(MissionError::Rejected(ack.mavtype))
+            }
+            _ => Ok(MissionStep::Wait),
+        }
+    }
+
+    fn on_timeout(&mut self) -> Result<MissionStep<Self::Output>, MissionError> {
+        self.state.resend()
+    }
+}
+
+/// Removal of the mission from the vehicle with MISSION_CLEAR_ALL
+#[derive(Debug, Clone)]
+pub struct MissionClear {
+    state: TransferState,
+}
+
+impl MissionClear {
+    pub fn new(target_system: u8, target_component: u8) -> Self {
+        Self {
+            state: TransferState::new(target_system, target_component),
+        }
+    }
+
+    /// Clear the fence or rally point list instead of the mission
+    #[cfg(feature = "emit-extensions")]
+    pub fn with_mission_type(mut self, mission_type: MavMissionType) -> Self {
+        self.state.mission_type = mission_type;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.state.retries = retries;
+        self
+    }
+}
+
+impl Exchange for MissionClear {
+    type Message = MavMessage;
+    type Output = ();
+    type Error = MissionError;
+
+    fn start(&mut self) -> MavMessage {
+        let clear = MavMessage::MISSION_CLEAR_ALL(MISSION_CLEAR_ALL_DATA {
+            target_system: self.state.target_system,
+            target_component: self.state.target_component,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: self.state.mission_type,
+        });
+        self.state.last_sent = Some(clear.clone());
+        clear
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Result<MissionStep<()>, MissionError> {
+        if !self.state.is_from_target(header) {
+            return Ok(MissionStep::Wait);
+        }
+
+        match msg {
+            MavMessage::MISSION_ACK(ack) => match ack.mavtype {
+                MavMissionResult::MAV_MISSION_ACCEPTED => Ok(MissionStep::Done(None, ())),
+                result => Err(MissionError::Rejected(result)),
+            },
+            _ => Ok(MissionStep::Wait),
+        }
+    }
+
+    fn on_timeout(&mut self) -> Result<MissionStep<()>, MissionError> {
+        self.state.resend()
+    }
+}
+
+/// Runs mission transfers with a vehicle over a connection
+pub struct MissionClient<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    requester: Requester<'a, M, C>,
+    target_system: u8,
+    target_component: u8,
+    #[cfg(feature = "emit-extensions")]
+    mission_type: MavMissionType,
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> MissionClient<'a, M, C> {
+    pub fn new(connection: &'a C, target_system: u8, target_component: u8) -> Self {
+        let requester = Requester::new(connection)
+            .with_timeout(DEFAULT_TIMEOUT)
+            .with_retries(DEFAULT_RETRIES);
+        Self::from_requester(requester, target_system, target_component)
+    }
+
+    /// Run the transfers with the header, timeout and retries of `requester`
+    pub fn from_requester(
+        requester: Requester<'a, M, C>,
+        target_system: u8,
+        target_component: u8,
+    ) -> Self {
+        Self {
+            requester,
+            target_system,
+            target_component,
+            #[cfg(feature = "emit-extensions")]
+            mission_type: MavMissionType::DEFAULT,
+        }
+    }
+
+    /// Work on the fence or rally point list instead of the mission
+    #[cfg(feature = "emit-extensions")]
+    pub fn with_mission_type(mut self, mission_type: MavMissionType) -> Self {
+        self.mission_type = mission_type;
+        self
+    }
+
+    /// Replace the mission on the vehicle
+    pub fn upload(&self, items: &[MISSION_ITEM_INT_DATA]) -> Result<(), MissionError> {
+        let transfer = MissionUpload::new(self.target_system, self.target_component, items)
+            .with_retries(self.requester.retries());
+        #[cfg(feature = "emit-extensions")]
+        let transfer = transfer.with_mission_type(self.mission_type);
+        self.run(transfer)
+    }
+
+    /// Read the mission from the vehicle
+    pub fn download(&self) -> Result<Vec<MISSION_ITEM_INT_DATA>, MissionError> {
+        let transfer = MissionDownload::new(self.target_system, self.target_component)
+            .with_retries(self.requester.retries());
+        #[cfg(feature = "emit-extensions")]
+        let transfer = transfer.with_mission_type(self.mission_type);
+        self.run(transfer)
+    }
+
+    /// Remove the mission from the vehicle
+    pub fn clear(&self) -> Result<(), MissionError> {
+        let transfer = MissionClear::new(self.target_system, self.target_component)
+            .with_retries(self.requester.retries());
+        #[cfg(feature = "emit-extensions")]
+        let transfer = transfer.with_mission_type(self.mission_type);
+        self.run(transfer)
+    }
+
+    fn run<T: Exchange<Message = MavMessage, Error = MissionError>>(
+        &self,
+        mut transfer: T,
+    ) -> Result<T::Output, MissionError> {
+        self.requester.run(&mut transfer)
+    }
+}
@@ -0,0 +1,635 @@
+//! Both sides of the [parameter microservice](https://mavlink.io/en/services/parameter.html).
+//!
+//! [`ParamServer`] answers PARAM_REQUEST_LIST, PARAM_REQUEST_READ and PARAM_SET from a
+//! [`ParamTable`] supplied by the application, so a companion component shows up with its
+//! parameters in a ground control station.
+//!
+//! [`ParamClient`] reads all parameters of a component, either with the parameter protocol or,
+//! for ArduPilot, by downloading the packed `@PARAM/param.pck` file over MAVLink FTP.
+
+use core::fmt::{Display, Formatter};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::Duration;
+
+use crate::common::{
+    MavMessage, MavParamType, PARAM_REQUEST_LIST_DATA, PARAM_REQUEST_READ_DATA, PARAM_VALUE_DATA,
+};
+use crate::error::{MessageWriteError, ProtocolError};
+use crate::ftp::{FtpClient, FtpError};
+use crate::request::{Exchange, Requester, Step};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Length of the `param_id` field
+pub const PARAM_ID_LEN: usize = 16;
+
+/// How integer values are packed into the `f32` field of the parameter messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParamEncoding {
+    /// The bytes of the value are stored in the float (`MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_BYTEWISE`)
+    Bytewise,
+    /// The value is converted to a float (`MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_C_CAST`), as done by ArduPilot
+    CCast,
+}
+
+/// A typed parameter value
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParamValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+}
+
+impl ParamValue {
+    pub fn param_type(&self) -> MavParamType {
+        match self {
+            Self::U8(_) => MavParamType::MAV_PARAM_TYPE_UINT8,
+            Self::I8(_) => MavParamType::MAV_PARAM_TYPE_INT8,
+            Self::U16(_) => MavParamType::MAV_PARAM_TYPE_UINT16,
+            Self::I16(_) => MavParamType::MAV_PARAM_TYPE_INT16,
+            Self::U32(_) => MavParamType::MAV_PARAM_TYPE_UINT32,
+            Self::I32(_) => MavParamType::MAV_PARAM_TYPE_INT32,
+            Self::F32(_) => MavParamType::MAV_PARAM_TYPE_REAL32,
+        }
+    }
+
+    /// Value as carried in the `param_value` field
+    pub fn to_wire(&self, encoding: ParamEncoding) -> f32 {
+        match encoding {
+            ParamEncoding::Bytewise => {
+                let bytes = match *self {
+                    Self::U8(v) => [v, 0, 0, 0],
+                    Self::I8(v) => [v as u8, 0, 0, 0],
+                    Self::U16(v) => {
+                        let [b0, b1] = v.to_le_bytes();
+                        [b0, b1, 0, 0]
+                    }
+                    Self::I16(v) => {
+                        let [b0, b1] = v.to_le_bytes();
+                        [b0, b1, 0, 0]
+                    }
+                    Self::U32(v) => v.to_le_bytes(),
+                    Self::I32(v) => v.to_le_bytes(),
+                    Self::F32(v) => return v,
+                };
+                f32::from_le_bytes(bytes)
+            }
+            ParamEncoding::CCast => match *self {
+                Self::U8(v) => f32::from(v),
+                Self::I8(v) => f32::from(v),
+                Self::U16(v) => f32::from(v),
+                Self::I16(v) => f32::from(v),
+                Self::U32(v) => v as f32,
+                Self::I32(v) => v as f32,
+                Self::F32(v) => v,
+            },
+        }
+    }
+
+    /// Decode a `param_value` field, `None` for the 64 bit types which cannot be carried
+    pub fn from_wire(
+        value: f32,
+        param_type: MavParamType,
+        encoding: ParamEncoding,
+    ) -> Option<Self> {
+        let value = match encoding {
+            ParamEncoding::Bytewise => {
+                let bytes = value.to_le_bytes();
+                match param_type {
+                    MavParamType::MAV_PARAM_TYPE_UINT8 => Self::U8(bytes[0]),
+                    MavParamType::MAV_PARAM_TYPE_INT8 => Self::I8(bytes[0] as i8),
+                    MavParamType::MAV_PARAM_TYPE_UINT16 => {
+                        Self::U16(u16::from_le_bytes([bytes[0], bytes[1]]))
+                    }
+                    MavParamType::MAV_PARAM_TYPE_INT16 => {
+                        Self::I16(i16::from_le_bytes([bytes[0], bytes[1]]))
+                    }
+                    MavParamType::MAV_PARAM_TYPE_UINT32 => Self::U32(u32::from_le_bytes(bytes)),
+                    MavParamType::MAV_PARAM_TYPE_INT32 => Self::I32(i32::from_le_bytes(bytes)),
+                    MavParamType::MAV_PARAM_TYPE_REAL32 => Self::F32(value),
+                    _ => return None,
+                }
+            }
+            ParamEncoding::CCast => match param_type {
+                MavParamType::MAV_PARAM_TYPE_UINT8 => Self::U8(value as u8),
+                MavParamType::MAV_PARAM_TYPE_INT8 => Self::I8(value as i8),
+                MavParamType::MAV_PARAM_TYPE_UINT16 => Self::U16(value as u16),
+                MavParamType::MAV_PARAM_TYPE_INT16 => Self::I16(value as i16),
+                MavParamType::MAV_PARAM_TYPE_UINT32 => Self::U32(value as u32),
+                MavParamType::MAV_PARAM_TYPE_INT32 => Self::I32(value as i32),
+                MavParamType::MAV_PARAM_TYPE_REAL32 => Self::F32(value),
+                _ => return None,
+            },
+        };
+        Some(value)
+    }
+}
+
+/// Encode a parameter name into a `param_id` field, longer names are truncated
+pub fn param_id(name: &str) -> [u8; PARAM_ID_LEN] {
+    let mut id = [0u8; PARAM_ID_LEN];
+    let len = name.len().min(PARAM_ID_LEN);
+    id[..len].copy_from_slice(&name.as_bytes()[..len]);
+    id
+}
+
+/// Decode a `param_id` field, which is only NUL terminated if shorter than 16 bytes
+pub fn param_name(id: &[u8; PARAM_ID_LEN]) -> Option<&str> {
+    let len = id.iter().position(|&b| b == 0).unwrap_or(PARAM_ID_LEN);
+    core::str::from_utf8(&id[..len]).ok()
+}
+
+/// Parameters exposed by a [`ParamServer`]
+pub trait ParamTable {
+    /// Number of parameters, indices run from 0 to `count() - 1`
+    fn count(&self) -> usize;
+
+    /// Name and value of the parameter at `index`
+    fn get(&self, index: usize) -> Option<(&str, ParamValue)>;
+
+    /// Store a new value, returns `false` if the value is not acceptable
+    fn set(&mut self, index: usize, value: ParamValue) -> bool;
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        (0..self.count()).find(|&index| matches!(self.get(index), Some((n, _)) if n == name))
+    }
+}
+
+/// A fixed list of parameters, values may be changed but not their type
+impl ParamTable for Vec<(String, ParamValue)> {
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, index: usize) -> Option<(&str, ParamValue)> {
+        self.as_slice()
+            .get(index)
+            .map(|(name, value)| (name.as_str(), *value))
+    }
+
+    fn set(&mut self, index: usize, value: ParamValue) -> bool {
+        match self.get_mut(index) {
+            Some((_, current)) if current.param_type() == value.param_type() => {
+                *current = value;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Answers parameter requests addressed to one component.
+///
+/// All parameters are returned at once for PARAM_REQUEST_LIST; the caller is expected to pace
+/// sending them if the link is slow.
+pub struct ParamServer<T: ParamTable> {
+    table: T,
+    system_id: u8,
+    component_id: u8,
+    encoding: ParamEncoding,
+}
+
+impl<T: ParamTable> ParamServer<T> {
+    pub fn new(system_id: u8, component_id: u8, table: T) -> Self {
+        Self {
+            table,
+            system_id,
+            component_id,
+            encoding: ParamEncoding::Bytewise,
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: ParamEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Access the parameters, send [`ParamServer::param_value`] afterwards for values changed
+    /// locally so the ground station is notified
+    pub fn table_mut(&mut self) -> &mut T {
+        &mut self.table
+    }
+
+    pub fn into_table(self) -> T {
+        self.table
+    }
+
+    /// Header for messages sent by this server, the sequence number is set by the connection
+    pub fn header(&self) -> MavHeader {
+        MavHeader {
+            system_id: self.system_id,
+            component_id: self.component_id,
+            sequence: 0,
+        }
+    }
+
+    /// PARAM_VALUE message for the parameter at `index`
+    pub fn param_value(&self, index: usize) -> Option<MavMessage> {
+        let (name, value) = self.table.get(index)?;
+        Some(MavMessage::PARAM_VALUE(PARAM_VALUE_DATA {
+            param_value: value.to_wire(self.encoding),
+            param_count: self.table.count() as u16,
+            param_index: index as u16,
+            param_id: param_id(name),
+            param_type: value.param_type(),
+        }))
+    }
+
+    fn is_addressed(&self, target_system: u8, target_component: u8) -> bool {
+        target_system == self.system_id
+            && (target_component == self.component_id || target_component == 0)
+    }
+
+    /// Handle a received message and return the replies
+    pub fn handle(&mut self, msg: &MavMessage) -> Vec<MavMessage> {
+        match msg {
+            MavMessage::PARAM_REQUEST_LIST(request)
+                if self.is_addressed(request.target_system, request.target_component) =>
+            {
+                (0..self.table.count())
+                    .filter_map(|index| self.param_value(index))
+                    .collect()
+            }
+            MavMessage::PARAM_REQUEST_READ(request)
+                if self.is_addressed(request.target_system, request.target_component) =>
+            {
+                let index = if request.param_index < 0 {
+                    param_name(&request.param_id).and_then(|name| self.table.index_of(name))
+                } else {
+                    Some(request.param_index as usize)
+                };
+                index
+                    .and_then(|index| self.param_value(index))
+                    .into_iter()
+                    .collect()
+            }
+            MavMessage::PARAM_SET(set)
+                if self.is_addressed(set.target_system, set.target_component) =>
+            {
+                let index =
+                    match param_name(&set.param_id).and_then(|name| self.table.index_of(name)) {
+                        Some(index) => index,
+                        None => return Vec::new(),
+                    };
+                if let Some(value) =
+                    ParamValue::from_wire(set.param_value, set.param_type, self.encoding)
+                {
+                    self.table.set(index, value);
+                }
+                // the current value is reported whether or not the new one was accepted
+                self.param_value(index).into_iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Handle a message received on a connection of any message set and send the replies
+    pub fn handle_and_reply<M: Message, C: MavConnection<M> + ?Sized>(
+        &mut self,
+        connection: &C,
+        msg: &M,
+    ) -> Result<(), MessageWriteError> {
+        let msg = match msg.to_dialect::<MavMessage>() {
+            Some(msg) => msg,
+            None => return Ok(()),
+        };
+
+        let header = self.header();
+        for reply in self.handle(&msg) {
+            if let Some(reply) = reply.to_dialect::<M>() {
+                connection.send(&header, &reply)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Path of the packed parameter file served over FTP by ArduPilot
+pub const PARAM_PCK_PATH: &str = "@PARAM/param.pck";
+
+const PCK_MAGIC: u16 = 0x671b;
+const PCK_MAGIC_WITH_DEFAULTS: u16 = 0x671c;
+
+/// Error decoding ArduPilot's packed parameter file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackedParamError {
+    BadMagic(u16),
+    UnknownType(u8),
+    Truncated,
+    /// The header announced another number of parameters than the file contains
+    CountMismatch {
+        expected: u16,
+        actual: usize,
+    },
+}
+
+impl Display for PackedParamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadMagic(magic) => write!(f, "Not a packed parameter file (magic {magic:#06x})"),
+            Self::UnknownType(ptype) => write!(f, "Unknown parameter type {ptype}"),
+            Self::Truncated => write!(f, "Packed parameter file is truncated"),
+            Self::CountMismatch { expected, actual } => {
+                write!(f, "Expected {expected} parameters, found {actual}")
+            }
+        }
+    }
+}
+
+impl Error for PackedParamError {}
+
+/// Decode the `@PARAM/param.pck` file of ArduPilot.
+///
+/// The file holds a header (magic, number of parameters, total number of parameters) followed by
+/// one entry per parameter: type and flags, the lengths of the name and of the prefix it shares
+/// with the previous name, the rest of the name and the little endian value, optionally followed
+/// by the default value. Zero bytes between entries are padding.
+pub fn decode_param_pck(data: &[u8]) -> Result<Vec<(String, ParamValue)>, PackedParamError> {
+    if data.len() < 6 {
+        return Err(PackedParamError::Truncated);
+    }
+    let magic = u16::from_le_bytes([data[0], data[1]]);
+    let with_defaults = match magic {
+        PCK_MAGIC => false,
+        PCK_MAGIC_WITH_DEFAULTS => true,
+        magic => return Err(PackedParamError::BadMagic(magic)),
+    };
+    let num_params = u16::from_le_bytes([data[2], data[3]]);
+
+    let mut params = Vec::with_capacity(usize::from(num_params));
+    let mut name = Vec::new();
+    let mut data = &data[6..];
+    loop {
+        while let [0, rest @ ..] = data {
+            data = rest;
+        }
+        let (ptype, lengths) = match data {
+            [] => break,
+            [ptype, lengths, ..] => (*ptype, *lengths),
+            _ => return Err(PackedParamError::Truncated),
+        };
+        let has_default = with_defaults && (ptype >> 4) & 1 != 0;
+        let value_len = match ptype & 0x0f {
+            1 => 1,
+            2 => 2,
+            3 | 4 => 4,
+            ptype => return Err(PackedParamError::UnknownType(ptype)),
+        };
+        let common_len = usize::from(lengths & 0x0f);
+        let name_len = usize::from(lengths >> 4) + 1;
+        let entry_len = 2 + name_len + value_len * if has_default { 2 } else { 1 };
+        if data.len() < entry_len || name.len() < common_len {
+            return Err(PackedParamError::Truncated);
+        }
+
+        name.truncate(common_len);
+        name.extend_from_slice(&data[2..2 + name_len]);
+        let bytes = &data[2 + name_len..2 + name_len + value_len];
+        let value = match ptype & 0x0f {
+            1 => ParamValue::I8(bytes[0] as i8),
+            2 => ParamValue::I16(i16::from_le_bytes([bytes[0], bytes[1]])),
+            3 => ParamValue::I32(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            _ => ParamValue::F32(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        };
+        params.push((String::from_utf8_lossy(&name).into_owned(), value));
+        data = &data[entry_len..];
+    }
+
+    if params.len() != usize::from(num_params) {
+        return Err(PackedParamError::CountMismatch {
+            expected: num_params,
+            actual: params.len(),
+        });
+    }
+    Ok(params)
+}
+
+/// Time to wait for PARAM_VALUE before requesting the missing parameters
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Number of timeouts without receiving a parameter before giving up
+pub const DEFAULT_RETRIES: u8 = 5;
+
+/// What a [`ParamDownload`] wants to happen next, it is done once all parameters were received,
+/// ordered by index
+pub type ParamStep = Step<MavMessage, Vec<(String, ParamValue)>>;
+
+/// Download of all parameters with PARAM_REQUEST_LIST, requesting the ones that got lost with
+/// PARAM_REQUEST_READ
+#[derive(Debug, Clone)]
+pub struct ParamDownload {
+    target_system: u8,
+    target_component: u8,
+    encoding: ParamEncoding,
+    retries: u8,
+    attempts: u8,
+    count: Option<u16>,
+    /// Received parameters by index, `None` for values of 64 bit types
+    received: BTreeMap<u16, Option<(String, ParamValue)>>,
+    /// Missing parameters are being requested one by one
+    requesting_missing: bool,
+}
+
+impl ParamDownload {
+    pub fn new(target_system: u8, target_component: u8) -> Self {
+        Self {
+            target_system,
+            target_component,
+            encoding: ParamEncoding::Bytewise,
+            retries: DEFAULT_RETRIES,
+            attempts: 0,
+            count: None,
+            received: BTreeMap::new(),
+            requesting_missing: false,
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: ParamEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Number of parameters received and announced so far
+    pub fn progress(&self) -> (usize, Option<u16>) {
+        (self.received.len(), self.count)
+    }
+
+    fn first_missing(&self) -> Option<u16> {
+        let count = self.count?;
+        (0..count).find(|index| !self.received.contains_key(index))
+    }
+
+    fn request_read(&self, index: u16) -> MavMessage {
+        MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            param_index: index as i16,
+            target_system: self.target_system,
+            target_component: self.target_component,
+            param_id: [0; PARAM_ID_LEN],
+        })
+    }
+}
+
+impl Exchange for ParamDownload {
+    type Message = MavMessage;
+    type Output = Vec<(String, ParamValue)>;
+    type Error = ProtocolError;
+
+    /// Message opening the transfer
+    fn start(&mut self) -> MavMessage {
+        MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: self.target_system,
+            target_component: self.target_component,
+        })
+    }
+
+    /// Feed a message received from the component
+    fn handle(&mut self, header: &MavHeader, msg: &MavMessage) -> Result<ParamStep, ProtocolError> {
+        let value = match msg {
+            MavMessage::PARAM_VALUE(value)
+                if header.system_id == self.target_system
+                    && (self.target_component == 0
+                        || header.component_id == self.target_component) =>
+            {
+                value
+            }
+            _ => return Ok(ParamStep::Wait),
+        };
+        // parameters sent after a PARAM_SET of another client have no valid index
+        if value.param_index == u16::MAX || value.param_index >= value.param_count {
+            return Ok(ParamStep::Wait);
+        }
+
+        self.attempts = 0;
+        self.count = Some(value.param_count);
+        let param = param_name(&value.param_id).and_then(|name| {
+            let value = ParamValue::from_wire(value.param_value, value.param_type, self.encoding)?;
+            Some((name.to_string(), value))
+        });
+        self.received.insert(value.param_index, param);
+
+        Ok(match self.first_missing() {
+            None => ParamStep::Done(
+                None,
+                core::mem::take(&mut self.received)
+                    .into_values()
+                    .flatten()
+                    .collect(),
+            ),
+            Some(index) if self.requesting_missing => ParamStep::Send(self.request_read(index)),
+            Some(_) => ParamStep::Progress,
+        })
+    }
+
+    /// Called when no parameter was received within the timeout
+    fn on_timeout(&mut self) -> Result<ParamStep, ProtocolError> {
+        if self.attempts >= self.retries {
+            warn!(
+                sysid = self.target_system,
+                attempts = self.attempts,
+                "parameter download timed out"
+            );
+            return Err(ProtocolError::Timeout);
+        }
+        self.attempts += 1;
+        debug!(
+            sysid = self.target_system,
+            attempt = self.attempts,
+            "requesting parameters again"
+        );
+        Ok(match self.first_missing() {
+            Some(index) => {
+                self.requesting_missing = true;
+                ParamStep::Send(self.request_read(index))
+            }
+            // nothing was received yet
+            None => ParamStep::Send(self.start()),
+        })
+    }
+}
+
+/// Reads the parameters of a component over a connection
+pub struct ParamClient<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    requester: Requester<'a, M, C>,
+    target_system: u8,
+    target_component: u8,
+    encoding: ParamEncoding,
+    use_ftp: bool,
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> ParamClient<'a, M, C> {
+    pub fn new(connection: &'a C, target_system: u8, target_component: u8) -> Self {
+        let requester = Requester::new(connection)
+            .with_timeout(DEFAULT_TIMEOUT)
+            .with_retries(DEFAULT_RETRIES);
+        Self::from_requester(requester, target_system, target_component)
+    }
+
+    /// Read the parameters with the header, timeout and retries of `requester`
+    pub fn from_requester(
+        requester: Requester<'a, M, C>,
+        target_system: u8,
+        target_component: u8,
+    ) -> Self {
+        Self {
+            requester,
+            target_system,
+            target_component,
+            encoding: ParamEncoding::Bytewise,
+            use_ftp: false,
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: ParamEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// First try to read all parameters at once from the `@PARAM/param.pck` file over FTP, as
+    /// supported by ArduPilot
+    pub fn with_ftp(mut self, use_ftp: bool) -> Self {
+        self.use_ftp = use_ftp;
+        self
+    }
+
+    /// Read all parameters of the component.
+    ///
+    /// With FTP enabled, the standard parameter protocol is used if the component does not
+    /// answer the FTP request, refuses it or sends a file which cannot be decoded.
+    pub fn fetch_all(&self) -> Result<Vec<(String, ParamValue)>, ProtocolError> {
+        if self.use_ftp {
+            let ftp = FtpClient::from_requester(
+                self.requester.clone().with_retries(1),
+                self.target_system,
+                self.target_component,
+            );
+            match ftp.read_file(PARAM_PCK_PATH) {
+                Ok(file) => {
+                    // a file which cannot be decoded is treated like a missing one
+                    if let Ok(params) = decode_param_pck(&file) {
+                        return Ok(params);
+                    }
+                }
+                Err(FtpError::Protocol(ProtocolError::Timeout)) | Err(FtpError::Nak(_)) => {}
+                Err(FtpError::Protocol(e)) => return Err(e),
+            }
+        }
+
+        let mut transfer = ParamDownload::new(self.target_system, self.target_component)
+            .with_encoding(self.encoding)
+            .with_retries(self.requester.retries());
+        self.requester.run(&mut transfer)
+    }
+}
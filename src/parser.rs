@@ -0,0 +1,155 @@
+//! Byte-at-a-time frame parser.
+//!
+//! The `read_*` functions pull bytes from a blocking reader. [`PushParser`] is fed the bytes as
+//! they arrive instead, e.g. from a UART interrupt or an async read, and needs neither `std` nor
+//! an allocator.
+
+use crate::error::ParserError;
+use crate::{
+    calculate_crc, MavHeader, MavlinkVersion, Message, MAVLINK_IFLAG_SIGNED, MAV_STX, MAV_STX_V2,
+    MAX_FRAME_SIZE,
+};
+
+const V1_HEADER_SIZE: usize = 5;
+const V2_HEADER_SIZE: usize = 9;
+const SIGNATURE_SIZE: usize = 13;
+
+/// Assembles frames of one protocol version from single bytes.
+///
+/// Bytes before a start marker are skipped, and so are frames with an invalid checksum, MAVLink 2
+/// frames with incompatibility flags other than signing, and frames of a message unknown to the
+/// message set. After a dropped frame the parser resynchronizes on the next start marker within
+/// the bytes of that frame, so a start marker in garbage or a frame cut short does not take the
+/// following frame with it. At most one frame is buffered.
+#[derive(Debug, Clone)]
+pub struct PushParser {
+    version: MavlinkVersion,
+    buffer: [u8; MAX_FRAME_SIZE],
+    len: usize,
+}
+
+impl PushParser {
+    pub const fn new(version: MavlinkVersion) -> Self {
+        Self {
+            version,
+            buffer: [0; MAX_FRAME_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn version(&self) -> MavlinkVersion {
+        self.version
+    }
+
+    /// Drop the partially received frame
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    fn header_size(&self) -> usize {
+        match self.version {
+            MavlinkVersion::V1 => V1_HEADER_SIZE,
+            MavlinkVersion::V2 => V2_HEADER_SIZE,
+        }
+    }
+
+    /// Size of the frame in the buffer, known once its header is complete
+    fn frame_size(&self) -> usize {
+        let payload_length = usize::from(self.buffer[1]);
+        let signature_size = match self.version {
+            MavlinkVersion::V2 if self.buffer[2] & MAVLINK_IFLAG_SIGNED != 0 => SIGNATURE_SIZE,
+            _ => 0,
+        };
+        1 + self.header_size() + payload_length + 2 + signature_size
+    }
+
+    fn stx(&self) -> u8 {
+        match self.version {
+            MavlinkVersion::V1 => MAV_STX,
+            MavlinkVersion::V2 => MAV_STX_V2,
+        }
+    }
+
+    /// Feed the next byte, returning the message once a valid frame is complete
+    pub fn push<M: Message>(&mut self, byte: u8) -> Option<Result<(MavHeader, M), ParserError>> {
+        // search for the magic framing value indicating start of mavlink message
+        if self.len == 0 && byte != self.stx() {
+            return None;
+        }
+        self.buffer[self.len] = byte;
+        self.len += 1;
+
+        loop {
+            if self.len <= self.header_size() {
+                return None;
+            }
+            if self.version == MavlinkVersion::V2 && self.buffer[2] & !MAVLINK_IFLAG_SIGNED != 0 {
+                // the frame layout is unknown with other incompatibility flags
+                self.resync(1);
+                continue;
+            }
+            let frame_size = self.frame_size();
+            if self.len < frame_size {
+                return None;
+            }
+            match self.parse() {
+                Some(result) => {
+                    self.resync(frame_size);
+                    return Some(result);
+                }
+                None => self.resync(1),
+            }
+        }
+    }
+
+    /// Drop the first `skip` bytes of the buffer and the bytes up to the next start marker after
+    /// them
+    fn resync(&mut self, skip: usize) {
+        let stx = self.stx();
+        let start = self.buffer[skip..self.len]
+            .iter()
+            .position(|&byte| byte == stx)
+            .map_or(self.len, |position| skip + position);
+        self.buffer.copy_within(start..self.len, 0);
+        self.len -= start;
+    }
+
+    /// Parse the complete frame at the start of the buffer, `None` if its checksum is invalid
+    fn parse<M: Message>(&self) -> Option<Result<(MavHeader, M), ParserError>> {
+        let header_size = self.header_size();
+        let payload_length = usize::from(self.buffer[1]);
+        let (sequence, system_id, component_id, message_id) = match self.version {
+            MavlinkVersion::V1 => (
+                self.buffer[2],
+                self.buffer[3],
+                self.buffer[4],
+                u32::from(self.buffer[5]),
+            ),
+            MavlinkVersion::V2 => (
+                self.buffer[4],
+                self.buffer[5],
+                self.buffer[6],
+                u32::from_le_bytes([self.buffer[7], self.buffer[8], self.buffer[9], 0]),
+            ),
+        };
+
+        let crc_end = 1 + header_size + payload_length;
+        let checksum = u16::from_le_bytes([self.buffer[crc_end], self.buffer[crc_end + 1]]);
+        if checksum != calculate_crc(&self.buffer[1..crc_end], M::extra_crc(message_id)) {
+            // bad crc: ignore message
+            return None;
+        }
+
+        let payload = &self.buffer[(1 + header_size)..crc_end];
+        Some(M::parse(self.version, message_id, payload).map(|msg| {
+            (
+                MavHeader {
+                    sequence,
+                    system_id,
+                    component_id,
+                },
+                msg,
+            )
+        }))
+    }
+}
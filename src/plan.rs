@@ -0,0 +1,249 @@
+//! Reading and writing QGroundControl `.plan` files.
+//!
+//! A [`Plan`] is the JSON document written by QGroundControl. Its mission part converts to and
+//! from the MISSION_ITEM_INT sequence used by the `missions` client, so missions can be prepared
+//! and archived offline. Geofence and rally points are kept as they are but not interpreted.
+
+use core::fmt::{Display, Formatter};
+use std::error::Error;
+use std::io::{Read, Write};
+
+use num_traits::{FromPrimitive, ToPrimitive};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::common::{MavCmd, MavFrame, MISSION_ITEM_INT_DATA};
+
+#[derive(Debug)]
+pub enum PlanError {
+    Json(serde_json::Error),
+    /// Complex items (surveys, corridor scans, ...) need to be converted to simple items by
+    /// QGroundControl first
+    ComplexItem(String),
+    UnknownCommand(u16),
+    UnknownFrame(u8),
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "Invalid plan file: {}", e),
+            Self::ComplexItem(kind) => write!(f, "Unsupported complex item {:?}", kind),
+            Self::UnknownCommand(command) => write!(f, "Unknown command {}", command),
+            Self::UnknownFrame(frame) => write!(f, "Unknown frame {}", frame),
+        }
+    }
+}
+
+impl Error for PlanError {}
+
+impl From<serde_json::Error> for PlanError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// A `.plan` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Plan {
+    pub file_type: String,
+    pub ground_station: String,
+    pub mission: PlanMission,
+    #[serde(default)]
+    pub geo_fence: Value,
+    #[serde(default)]
+    pub rally_points: Value,
+    pub version: u32,
+}
+
+/// The `mission` section of a plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanMission {
+    #[serde(default)]
+    pub cruise_speed: f64,
+    /// MAV_AUTOPILOT of the vehicle the plan was made for
+    #[serde(default)]
+    pub firmware_type: u32,
+    #[serde(default)]
+    pub hover_speed: f64,
+    pub items: Vec<PlanItem>,
+    /// Latitude, longitude and AMSL altitude of the home position
+    pub planned_home_position: [f64; 3],
+    /// MAV_TYPE of the vehicle the plan was made for
+    #[serde(default)]
+    pub vehicle_type: u32,
+    pub version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PlanItem {
+    SimpleItem(SimpleItem),
+    /// Item generating several simple items, kept as it is
+    ComplexItem(Value),
+}
+
+/// A single mission item, parameters are `null` where not used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimpleItem {
+    #[serde(rename = "autoContinue")]
+    pub auto_continue: bool,
+    pub command: u16,
+    #[serde(rename = "doJumpId")]
+    pub do_jump_id: u32,
+    pub frame: u8,
+    pub params: [Option<f64>; 7],
+    #[serde(rename = "Altitude", default, skip_serializing_if = "Option::is_none")]
+    pub altitude: Option<f64>,
+    #[serde(
+        rename = "AltitudeMode",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub altitude_mode: Option<u8>,
+    #[serde(
+        rename = "AMSLAltAboveTerrain",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub amsl_alt_above_terrain: Option<f64>,
+}
+
+/// Scale of `x` and `y` in MISSION_ITEM_INT, depending on the frame
+fn position_scale(frame: MavFrame) -> f64 {
+    match frame {
+        MavFrame::MAV_FRAME_GLOBAL
+        | MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT
+        | MavFrame::MAV_FRAME_GLOBAL_INT
+        | MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT
+        | MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT
+        | MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT_INT => 1e7,
+        MavFrame::MAV_FRAME_MISSION => 1.0,
+        _ => 1e4,
+    }
+}
+
+impl SimpleItem {
+    fn to_mission_item(
+        &self,
+        seq: u16,
+        target_system: u8,
+        target_component: u8,
+    ) -> Result<MISSION_ITEM_INT_DATA, PlanError> {
+        let command =
+            MavCmd::from_u16(self.command).ok_or(PlanError::UnknownCommand(self.command))?;
+        let frame = MavFrame::from_u8(self.frame).ok_or(PlanError::UnknownFrame(self.frame))?;
+        let param = |index: usize| self.params[index].unwrap_or(f64::NAN);
+        let scale = position_scale(frame);
+
+        Ok(MISSION_ITEM_INT_DATA {
+            param1: param(0) as f32,
+            param2: param(1) as f32,
+            param3: param(2) as f32,
+            param4: param(3) as f32,
+            x: self.params[4].map_or(0, |x| (x * scale).round() as i32),
+            y: self.params[5].map_or(0, |y| (y * scale).round() as i32),
+            z: param(6) as f32,
+            seq,
+            command,
+            target_system,
+            target_component,
+            frame,
+            current: 0,
+            autocontinue: u8::from(self.auto_continue),
+            #[cfg(feature = "emit-extensions")]
+            mission_type: crate::common::MavMissionType::MAV_MISSION_TYPE_MISSION,
+        })
+    }
+
+    fn from_mission_item(item: &MISSION_ITEM_INT_DATA) -> Self {
+        let param = |value: f32| Some(f64::from(value)).filter(|value| !value.is_nan());
+        let scale = position_scale(item.frame);
+
+        Self {
+            auto_continue: item.autocontinue != 0,
+            command: item.command.to_u16().unwrap_or_default(),
+            do_jump_id: u32::from(item.seq) + 1,
+            frame: item.frame.to_u8().unwrap_or_default(),
+            params: [
+                param(item.param1),
+                param(item.param2),
+                param(item.param3),
+                param(item.param4),
+                Some(f64::from(item.x) / scale),
+                Some(f64::from(item.y) / scale),
+                param(item.z),
+            ],
+            altitude: None,
+            altitude_mode: None,
+            amsl_alt_above_terrain: None,
+        }
+    }
+}
+
+impl Plan {
+    /// Plan for a mission, `home` being latitude, longitude and AMSL altitude
+    pub fn from_mission_items(items: &[MISSION_ITEM_INT_DATA], home: [f64; 3]) -> Self {
+        Self {
+            file_type: "Plan".to_string(),
+            ground_station: "QGroundControl".to_string(),
+            mission: PlanMission {
+                cruise_speed: 15.0,
+                firmware_type: 0,
+                hover_speed: 5.0,
+                items: items
+                    .iter()
+                    .map(|item| PlanItem::SimpleItem(SimpleItem::from_mission_item(item)))
+                    .collect(),
+                planned_home_position: home,
+                vehicle_type: 0,
+                version: 2,
+            },
+            geo_fence: serde_json::json!({"circles": [], "polygons": [], "version": 2}),
+            rally_points: serde_json::json!({"points": [], "version": 2}),
+            version: 1,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PlanError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, PlanError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, PlanError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), PlanError> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// Mission items ready to be uploaded, numbered from 0
+    pub fn mission_items(
+        &self,
+        target_system: u8,
+        target_component: u8,
+    ) -> Result<Vec<MISSION_ITEM_INT_DATA>, PlanError> {
+        self.mission
+            .items
+            .iter()
+            .enumerate()
+            .map(|(seq, item)| match item {
+                PlanItem::SimpleItem(item) => {
+                    item.to_mission_item(seq as u16, target_system, target_component)
+                }
+                PlanItem::ComplexItem(item) => Err(PlanError::ComplexItem(
+                    item.get("complexItemType")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                )),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,397 @@
+//! Protocol Buffers schema and encoding of the messages of the enabled dialects, to feed
+//! MAVLink traffic into gRPC or streaming systems with typed schemas.
+//!
+//! [`SCHEMA`] is a proto3 schema with one protobuf message per MAVLink message, with the
+//! fields in wire order, and a `MavlinkMessage` envelope holding the header and one of them.
+//! [`encode`] and [`decode`] convert between MAVLink messages and serialized envelopes:
+//!
+//! ```
+//! # #[cfg(feature = "common")]
+//! # {
+//! use mavlink::common::{MavMessage, HEARTBEAT_DATA};
+//! use mavlink::MavHeader;
+//!
+//! let msg = MavMessage::HEARTBEAT(HEARTBEAT_DATA::default());
+//! let bytes = mavlink::protobuf::encode(&MavHeader::default(), &msg).unwrap();
+//! let (_, decoded) = mavlink::protobuf::decode::<MavMessage>(&bytes).unwrap();
+//! assert_eq!(decoded, msg);
+//! # }
+//! ```
+//!
+//! Strings which are not valid UTF-8 are converted lossily. Enums which cannot be protobuf
+//! enums, like bitmasks, are given as integers.
+
+use crate::error::ParserError;
+use crate::{MavHeader, MavlinkVersion, Message};
+
+/// Source of the schema
+pub const SCHEMA: &str = include_str!(concat!(env!("OUT_DIR"), "/mavlink.proto"));
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum FieldKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    Char,
+    F32,
+    F64,
+}
+
+impl FieldKind {
+    fn len(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 | Self::Char => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+        }
+    }
+
+    fn wire_type(self) -> u8 {
+        match self {
+            Self::F32 => WIRE_FIXED32,
+            Self::F64 => WIRE_FIXED64,
+            _ => WIRE_VARINT,
+        }
+    }
+}
+
+/// Position of a field in the MAVLink payload
+#[derive(Debug)]
+struct FieldLayout {
+    number: u32,
+    kind: FieldKind,
+    offset: usize,
+    count: usize,
+    repeated: bool,
+}
+
+impl FieldLayout {
+    /// Encoded as `string` or `bytes` rather than a repeated field
+    fn is_bytes(&self) -> bool {
+        self.repeated && matches!(self.kind, FieldKind::Char | FieldKind::U8)
+    }
+}
+
+#[derive(Debug)]
+struct MessageLayout {
+    id: u32,
+    /// Field number in the `oneof` of the envelope
+    envelope_number: u32,
+    payload_len: usize,
+    fields: &'static [FieldLayout],
+}
+
+/// Layouts of all the messages, sorted by id
+static MESSAGES: &[MessageLayout] = include!(concat!(env!("OUT_DIR"), "/protobuf.rs"));
+
+fn layout(id: u32) -> Option<&'static MessageLayout> {
+    MESSAGES
+        .binary_search_by_key(&id, |layout| layout.id)
+        .ok()
+        .map(|index| &MESSAGES[index])
+}
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+const SYSTEM_ID: u32 = 1;
+const COMPONENT_ID: u32 = 2;
+const SEQUENCE: u32 = 3;
+
+#[derive(Debug)]
+pub enum ProtobufError {
+    /// The message is not part of the schema
+    UnknownMessage {
+        id: u32,
+    },
+    /// The input ends in the middle of a field
+    Truncated,
+    /// A field is not encoded as its type requires
+    InvalidField {
+        number: u32,
+    },
+    /// The envelope holds no message
+    MissingMessage,
+    Parse(ParserError),
+}
+
+impl std::fmt::Display for ProtobufError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownMessage { id } => write!(f, "Message {id} is not in the schema"),
+            Self::Truncated => write!(f, "Protobuf message is truncated"),
+            Self::InvalidField { number } => write!(f, "Field {number} has an invalid encoding"),
+            Self::MissingMessage => write!(f, "Protobuf envelope holds no message"),
+            Self::Parse(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ProtobufError {}
+
+impl From<ParserError> for ProtobufError {
+    fn from(e: ParserError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_tag(out: &mut Vec<u8>, number: u32, wire_type: u8) {
+    put_varint(out, u64::from(number) << 3 | u64::from(wire_type));
+}
+
+fn put_len_delimited(out: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    put_tag(out, number, WIRE_LEN);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Append the protobuf encoding of one element read from the little endian payload bytes,
+/// returns if it is not the default value
+fn put_element(out: &mut Vec<u8>, kind: FieldKind, bytes: &[u8]) -> bool {
+    let mut raw = [0u8; 8];
+    raw[..bytes.len()].copy_from_slice(bytes);
+    let unsigned = u64::from_le_bytes(raw);
+    match kind {
+        FieldKind::F32 | FieldKind::F64 => out.extend_from_slice(bytes),
+        FieldKind::I8 => put_varint(out, i64::from(bytes[0] as i8) as u64),
+        FieldKind::I16 => put_varint(
+            out,
+            i64::from(i16::from_le_bytes([bytes[0], bytes[1]])) as u64,
+        ),
+        FieldKind::I32 => put_varint(out, i64::from(unsigned as u32 as i32) as u64),
+        _ => put_varint(out, unsigned),
+    }
+    unsigned != 0
+}
+
+/// Serialize a message and its header as a `MavlinkMessage` of the [`SCHEMA`]
+pub fn encode<M: Message>(header: &MavHeader, msg: &M) -> Result<Vec<u8>, ProtobufError> {
+    let id = msg.message_id();
+    let layout = layout(id).ok_or(ProtobufError::UnknownMessage { id })?;
+    let mut payload = [0u8; 255];
+    msg.ser(MavlinkVersion::V2, &mut payload);
+
+    let mut fields = Vec::new();
+    for field in layout.fields {
+        let len = field.kind.len();
+        let bytes = &payload[field.offset..field.offset + len * field.count];
+        if field.kind == FieldKind::Char && field.repeated {
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+            if end > 0 {
+                let text = String::from_utf8_lossy(&bytes[..end]);
+                put_len_delimited(&mut fields, field.number, text.as_bytes());
+            }
+        } else if field.is_bytes() {
+            put_len_delimited(&mut fields, field.number, bytes);
+        } else if field.repeated {
+            // packed
+            let mut packed = Vec::new();
+            for element in bytes.chunks(len) {
+                put_element(&mut packed, field.kind, element);
+            }
+            put_len_delimited(&mut fields, field.number, &packed);
+        } else {
+            // proto3 leaves out default values
+            let mut element = Vec::new();
+            if put_element(&mut element, field.kind, bytes) {
+                put_tag(&mut fields, field.number, field.kind.wire_type());
+                fields.extend_from_slice(&element);
+            }
+        }
+    }
+
+    let mut out = Vec::with_capacity(fields.len() + 16);
+    for (number, value) in [
+        (SYSTEM_ID, header.system_id),
+        (COMPONENT_ID, header.component_id),
+        (SEQUENCE, header.sequence),
+    ] {
+        if value != 0 {
+            put_tag(&mut out, number, WIRE_VARINT);
+            put_varint(&mut out, u64::from(value));
+        }
+    }
+    put_len_delimited(&mut out, layout.envelope_number, &fields);
+    Ok(out)
+}
+
+/// Reader over protobuf fields
+struct Fields<'a> {
+    bytes: &'a [u8],
+}
+
+/// Value of a field
+enum Value<'a> {
+    Varint(u64),
+    Fixed(&'a [u8]),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Fields<'a> {
+    fn varint(&mut self) -> Result<u64, ProtobufError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first().ok_or(ProtobufError::Truncated)?;
+            self.bytes = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ProtobufError::Truncated)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProtobufError> {
+        if self.bytes.len() < len {
+            return Err(ProtobufError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u32, Value<'a>)>, ProtobufError> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+        let tag = self.varint()?;
+        let number = (tag >> 3) as u32;
+        let value = match (tag & 0x7) as u8 {
+            WIRE_VARINT => Value::Varint(self.varint()?),
+            WIRE_FIXED64 => Value::Fixed(self.take(8)?),
+            WIRE_LEN => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            WIRE_FIXED32 => Value::Fixed(self.take(4)?),
+            _ => return Err(ProtobufError::InvalidField { number }),
+        };
+        Ok(Some((number, value)))
+    }
+}
+
+/// Write one element into the little endian payload, returns if it has the wire type of the
+/// field
+fn set_element(payload: &mut [u8], kind: FieldKind, value: &Value) -> bool {
+    let len = kind.len();
+    match (kind.wire_type(), value) {
+        (WIRE_VARINT, Value::Varint(value)) => {
+            payload[..len].copy_from_slice(&value.to_le_bytes()[..len]);
+            true
+        }
+        (WIRE_FIXED32 | WIRE_FIXED64, Value::Fixed(bytes)) if bytes.len() == len => {
+            payload[..len].copy_from_slice(bytes);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn decode_fields(layout: &MessageLayout, bytes: &[u8]) -> Result<Vec<u8>, ProtobufError> {
+    let mut payload = vec![0u8; layout.payload_len];
+    // elements already set of every repeated field
+    let mut counts = vec![0usize; layout.fields.len()];
+    let mut fields = Fields { bytes };
+
+    while let Some((number, value)) = fields.next_field()? {
+        let index = match layout
+            .fields
+            .iter()
+            .position(|field| field.number == number)
+        {
+            Some(index) => index,
+            // unknown fields are skipped
+            None => continue,
+        };
+        let field = &layout.fields[index];
+        let len = field.kind.len();
+        let target = &mut payload[field.offset..field.offset + len * field.count];
+        let invalid = ProtobufError::InvalidField { number };
+        let mut valid = true;
+
+        match value {
+            Value::Bytes(bytes) if field.is_bytes() => {
+                let end = bytes.len().min(target.len());
+                target[..end].copy_from_slice(&bytes[..end]);
+            }
+            Value::Bytes(packed) if field.repeated => {
+                let mut elements = Fields { bytes: packed };
+                while !elements.bytes.is_empty() {
+                    let element = match field.kind.wire_type() {
+                        WIRE_VARINT => Value::Varint(elements.varint()?),
+                        _ => Value::Fixed(elements.take(len)?),
+                    };
+                    if counts[index] < field.count {
+                        let offset = counts[index] * len;
+                        valid &= set_element(&mut target[offset..], field.kind, &element);
+                        counts[index] += 1;
+                    }
+                }
+            }
+            value if field.repeated && !field.is_bytes() => {
+                // not packed
+                if counts[index] < field.count {
+                    let offset = counts[index] * len;
+                    valid = set_element(&mut target[offset..], field.kind, &value);
+                    counts[index] += 1;
+                }
+            }
+            value if !field.repeated => valid = set_element(target, field.kind, &value),
+            _ => valid = false,
+        }
+        if !valid {
+            return Err(invalid);
+        }
+    }
+    Ok(payload)
+}
+
+/// Deserialize a `MavlinkMessage` of the [`SCHEMA`]
+pub fn decode<M: Message>(bytes: &[u8]) -> Result<(MavHeader, M), ProtobufError> {
+    let mut header = MavHeader {
+        system_id: 0,
+        component_id: 0,
+        sequence: 0,
+    };
+    let mut message = None;
+    let mut fields = Fields { bytes };
+
+    while let Some((number, value)) = fields.next_field()? {
+        match (number, value) {
+            (SYSTEM_ID, Value::Varint(value)) => header.system_id = value as u8,
+            (COMPONENT_ID, Value::Varint(value)) => header.component_id = value as u8,
+            (SEQUENCE, Value::Varint(value)) => header.sequence = value as u8,
+            (SYSTEM_ID | COMPONENT_ID | SEQUENCE, _) => {
+                return Err(ProtobufError::InvalidField { number })
+            }
+            (number, Value::Bytes(bytes)) => {
+                // the last one wins, as for any oneof
+                if let Some(layout) = number.checked_sub(16).and_then(layout) {
+                    message = Some((layout, bytes));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (layout, bytes) = message.ok_or(ProtobufError::MissingMessage)?;
+    let payload = decode_fields(layout, bytes)?;
+    let msg = M::parse(MavlinkVersion::V2, layout.id, &payload)?;
+    Ok((header, msg))
+}
@@ -0,0 +1,467 @@
+//! Python bindings for the `ardupilotmega` dialect, which includes `common`, covering the
+//! common uses of pymavlink: connecting, waiting for and filtering messages, building, sending,
+//! packing and parsing them.
+//!
+//! The extension module is built with
+//!
+//! ```sh
+//! cargo rustc --release --lib --features python-extension --crate-type cdylib
+//! cp target/release/libmavlink.so mavlink.so
+//! ```
+//!
+//! and used as
+//!
+//! ```python
+//! import mavlink
+//!
+//! master = mavlink.mavlink_connection("udpin:0.0.0.0:14550")
+//! master.wait_heartbeat()
+//! master.send(mavlink.Message("COMMAND_LONG", target_system=master.target_system,
+//!                             command="MAV_CMD_COMPONENT_ARM_DISARM", param1=1.0))
+//! ack = master.recv_match(type="COMMAND_ACK", timeout=3)
+//! print(ack.result, ack.to_dict())
+//! ```
+//!
+//! Fields are attributes of the messages, with `type` for the fields the generated rust code
+//! names `mavtype`. Enum fields are read and written by the name of their value, bitmasks as
+//! integers and `char` arrays as strings. Errors of connections raise `MavlinkError`, invalid
+//! field values `ValueError`.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyAttributeError, PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList, PyString};
+use pythonize::{depythonize_bound, pythonize};
+
+use crate::ardupilotmega::MavMessage;
+use crate::error::MessageReadError;
+use crate::request::recv_message;
+use crate::{MavConnection, MavHeader, MavlinkVersion, Message};
+
+create_exception!(
+    mavlink,
+    MavlinkError,
+    PyException,
+    "Error of a MAVLink connection"
+);
+
+/// `char` array fields of each message, sorted by message name
+static CHAR_FIELDS: &[(&str, &[&str])] = include!(concat!(env!("OUT_DIR"), "/python.rs"));
+
+fn is_char_field(message: &str, field: &str) -> bool {
+    CHAR_FIELDS
+        .binary_search_by_key(&message, |(name, _)| name)
+        .map_or(false, |index| CHAR_FIELDS[index].1.contains(&field))
+}
+
+/// The generated rust code renames the `type` fields
+fn rust_field_name(name: &str) -> &str {
+    if name == "type" {
+        "mavtype"
+    } else {
+        name
+    }
+}
+
+fn version(version: u8) -> PyResult<MavlinkVersion> {
+    match version {
+        1 => Ok(MavlinkVersion::V1),
+        2 => Ok(MavlinkVersion::V2),
+        _ => Err(PyValueError::new_err("the protocol version must be 1 or 2")),
+    }
+}
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn duration(seconds: f64) -> PyResult<Duration> {
+    if seconds.is_finite() && seconds >= 0.0 {
+        Ok(Duration::from_secs_f64(seconds))
+    } else {
+        Err(value_error(
+            "the timeout must be a positive number of seconds",
+        ))
+    }
+}
+
+/// Python value of a field from its serde representation
+fn field_value(py: Python, value: &Bound<PyAny>, is_char: bool) -> PyResult<PyObject> {
+    if let Ok(map) = value.downcast::<PyDict>() {
+        // enums are tagged with the name of their value, bitmasks hold their bits
+        for key in ["type", "bits"] {
+            if let Some(inner) = map.get_item(key)? {
+                return Ok(inner.into_py(py));
+            }
+        }
+    }
+    if is_char {
+        let chars: Vec<u8> = value.extract()?;
+        let end = chars.iter().position(|c| *c == 0).unwrap_or(chars.len());
+        return Ok(String::from_utf8_lossy(&chars[..end]).into_py(py));
+    }
+    Ok(value.into_py(py))
+}
+
+/// Serde representation of a new value of a field, given its current one
+fn serde_value(
+    py: Python,
+    current: &Bound<PyAny>,
+    value: &Bound<PyAny>,
+    is_char: bool,
+) -> PyResult<PyObject> {
+    if let Ok(map) = current.downcast::<PyDict>() {
+        let new = PyDict::new_bound(py);
+        if map.contains("type")? {
+            new.set_item("type", value.downcast::<PyString>()?)?;
+        } else {
+            new.set_item("bits", value)?;
+        }
+        return Ok(new.into_py(py));
+    }
+    if is_char {
+        let len = current.len()?;
+        let mut chars = match value.downcast::<PyBytes>() {
+            Ok(bytes) => bytes.as_bytes().to_vec(),
+            Err(_) => value.extract::<String>()?.into_bytes(),
+        };
+        if chars.len() > len {
+            return Err(value_error(format!("string longer than {len} bytes")));
+        }
+        chars.resize(len, 0);
+        return Ok(PyList::new_bound(py, chars).into_py(py));
+    }
+    Ok(value.into_py(py))
+}
+
+/// A message with its header
+#[pyclass(name = "Message", module = "mavlink")]
+#[derive(Clone)]
+pub struct PyMessage {
+    header: MavHeader,
+    msg: MavMessage,
+}
+
+impl PyMessage {
+    /// Serde representation of the message
+    fn serde_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        Ok(pythonize(py, &self.msg)?
+            .into_bound(py)
+            .downcast_into::<PyDict>()?)
+    }
+
+    fn set_field(&mut self, py: Python, name: &str, value: &Bound<PyAny>) -> PyResult<()> {
+        let name = rust_field_name(name);
+        let message = self.serde_dict(py)?;
+        let current = message
+            .get_item(name)?
+            .ok_or_else(|| PyAttributeError::new_err(name.to_string()))?;
+        let is_char = is_char_field(self.msg.message_name(), name);
+        message.set_item(name, serde_value(py, &current, value, is_char)?)?;
+        // deserializing checks the value against the type of the field
+        self.msg = depythonize_bound(message.into_any()).map_err(value_error)?;
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyMessage {
+    /// Create a message with default fields from its name, e.g. `"HEARTBEAT"`, setting the
+    /// given fields
+    #[new]
+    #[pyo3(signature = (name, **fields))]
+    fn new(py: Python, name: &str, fields: Option<&Bound<PyDict>>) -> PyResult<Self> {
+        let msg = MavMessage::message_id_from_name(name)
+            .and_then(MavMessage::default_message_from_id)
+            .map_err(|_| value_error(format!("unknown message {name}")))?;
+        let mut message = Self {
+            header: MavHeader::default(),
+            msg,
+        };
+        for (field, value) in fields.iter().flat_map(|fields| fields.iter()) {
+            message.set_field(py, &field.extract::<String>()?, &value)?;
+        }
+        Ok(message)
+    }
+
+    fn get_type(&self) -> &'static str {
+        self.msg.message_name()
+    }
+
+    #[allow(non_snake_case)]
+    fn get_msgId(&self) -> u32 {
+        self.msg.message_id()
+    }
+
+    #[allow(non_snake_case)]
+    fn get_srcSystem(&self) -> u8 {
+        self.header.system_id
+    }
+
+    #[allow(non_snake_case)]
+    fn get_srcComponent(&self) -> u8 {
+        self.header.component_id
+    }
+
+    fn get_seq(&self) -> u8 {
+        self.header.sequence
+    }
+
+    /// Set the header the message is packed with
+    #[pyo3(signature = (system_id, component_id, sequence = 0))]
+    fn set_header(&mut self, system_id: u8, component_id: u8, sequence: u8) {
+        self.header = MavHeader {
+            system_id,
+            component_id,
+            sequence,
+        };
+    }
+
+    /// Fields by name, and the name of the message as `mavpackettype`
+    fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let name = self.msg.message_name();
+        let dict = PyDict::new_bound(py);
+        dict.set_item("mavpackettype", name)?;
+        for (field, value) in self.serde_dict(py)? {
+            let field: String = field.extract()?;
+            if field == "type" {
+                continue;
+            }
+            let key = if field == "mavtype" { "type" } else { &field };
+            dict.set_item(key, field_value(py, &value, is_char_field(name, &field))?)?;
+        }
+        Ok(dict.into_py(py))
+    }
+
+    /// Serialize the message as a frame of the protocol `version`, 1 or 2
+    #[pyo3(signature = (version = 2))]
+    fn pack(&self, py: Python, version: u8) -> PyResult<PyObject> {
+        let mut frame = Vec::new();
+        crate::write_versioned_msg(&mut frame, self::version(version)?, self.header, &self.msg)
+            .map_err(|e| MavlinkError::new_err(e.to_string()))?;
+        Ok(PyBytes::new_bound(py, &frame).into_py(py))
+    }
+
+    fn __getattr__(&self, py: Python, name: &str) -> PyResult<PyObject> {
+        let name = rust_field_name(name);
+        match self.serde_dict(py)?.get_item(name)? {
+            Some(value) => field_value(py, &value, is_char_field(self.msg.message_name(), name)),
+            None => Err(PyAttributeError::new_err(name.to_string())),
+        }
+    }
+
+    fn __setattr__(&mut self, py: Python, name: &str, value: &Bound<PyAny>) -> PyResult<()> {
+        self.set_field(py, name, value)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.header == other.header && self.msg == other.msg
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let dict = self.to_dict(py)?.into_bound(py).downcast_into::<PyDict>()?;
+        let mut fields = Vec::new();
+        for (field, value) in dict {
+            let field: String = field.extract()?;
+            if field != "mavpackettype" {
+                fields.push(format!("{field} : {}", value.repr()?));
+            }
+        }
+        Ok(format!(
+            "{} {{{}}}",
+            self.msg.message_name(),
+            fields.join(", ")
+        ))
+    }
+}
+
+/// Parser of a stream of frames, fed with the received bytes
+#[pyclass(name = "Parser", module = "mavlink")]
+pub struct PyParser {
+    buffer: Vec<u8>,
+    version: MavlinkVersion,
+}
+
+#[pymethods]
+impl PyParser {
+    /// Parse frames of the protocol `version`, 1 or 2
+    #[new]
+    #[pyo3(signature = (version = 2))]
+    fn new(version: u8) -> PyResult<Self> {
+        Ok(Self {
+            buffer: Vec::new(),
+            version: self::version(version)?,
+        })
+    }
+
+    /// Add `data` to the stream and return the messages of the frames it completes, skipping
+    /// invalid ones
+    fn parse_buffer(&mut self, data: &[u8]) -> Vec<PyMessage> {
+        self.buffer.extend_from_slice(data);
+        let mut messages = Vec::new();
+        loop {
+            let mut reader = &self.buffer[..];
+            let result = crate::read_versioned_msg(&mut reader, self.version);
+            if let Err(MessageReadError::Io(e)) = &result {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    // keep the start of the frame until the rest is received
+                    return messages;
+                }
+            }
+            let consumed = self.buffer.len() - reader.len();
+            self.buffer.drain(..consumed);
+            if let Ok((header, msg)) = result {
+                messages.push(PyMessage { header, msg });
+            }
+        }
+    }
+}
+
+/// A connection, see `mavlink::connect` for the addresses
+#[pyclass(name = "Connection", module = "mavlink")]
+pub struct PyConnection {
+    connection: Box<dyn MavConnection<MavMessage> + Sync + Send>,
+    source_system: u8,
+    source_component: u8,
+    /// System and component of the last received heartbeat
+    #[pyo3(get, set)]
+    target_system: u8,
+    #[pyo3(get, set)]
+    target_component: u8,
+}
+
+impl PyConnection {
+    /// Receive a message for which `accept` holds, or `None` after `timeout`
+    fn recv_until(
+        &self,
+        py: Python,
+        timeout: Option<Duration>,
+        accept: impl Fn(&MavMessage) -> bool,
+    ) -> PyResult<Option<PyMessage>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // the connection blocks, let other Python threads run
+            let received = py
+                .allow_threads(|| recv_message(&*self.connection))
+                .map_err(|e| MavlinkError::new_err(e.to_string()))?;
+            if let Some((header, msg)) = received {
+                if accept(&msg) {
+                    return Ok(Some(PyMessage { header, msg }));
+                }
+            }
+            // let Ctrl-C interrupt waiting
+            py.check_signals()?;
+            if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl PyConnection {
+    /// Receive the next message, or `None` if the connection timed out
+    fn recv_msg(&self, py: Python) -> PyResult<Option<PyMessage>> {
+        self.recv_until(py, Some(Duration::ZERO), |_| true)
+    }
+
+    /// Receive a message of one of the given types, or any message, waiting at most `timeout`
+    /// seconds, or returning `None` at the first timeout of the connection if not `blocking`.
+    ///
+    /// The timeout is checked between received messages and timeouts of the connection.
+    #[pyo3(signature = (r#type = None, blocking = true, timeout = None))]
+    #[pyo3(text_signature = "(self, type=None, blocking=True, timeout=None)")]
+    fn recv_match(
+        &self,
+        py: Python,
+        r#type: Option<&Bound<PyAny>>,
+        blocking: bool,
+        timeout: Option<f64>,
+    ) -> PyResult<Option<PyMessage>> {
+        let types: Option<Vec<String>> = match r#type {
+            Some(name) if name.is_instance_of::<PyString>() => Some(vec![name.extract()?]),
+            Some(names) => Some(names.extract()?),
+            None => None,
+        };
+        let timeout = match timeout {
+            Some(timeout) => Some(duration(timeout)?),
+            None if blocking => None,
+            None => Some(Duration::ZERO),
+        };
+        self.recv_until(py, timeout, |msg| {
+            types.as_ref().map_or(true, |types| {
+                types.iter().any(|name| name == msg.message_name())
+            })
+        })
+    }
+
+    /// Wait for a heartbeat and take its sender as target, returning it or `None` after
+    /// `timeout` seconds
+    #[pyo3(signature = (timeout = None))]
+    fn wait_heartbeat(&mut self, py: Python, timeout: Option<f64>) -> PyResult<Option<PyMessage>> {
+        let timeout = timeout.map(duration).transpose()?;
+        let heartbeat =
+            self.recv_until(py, timeout, |msg| matches!(msg, MavMessage::HEARTBEAT(_)))?;
+        if let Some(heartbeat) = &heartbeat {
+            self.target_system = heartbeat.header.system_id;
+            self.target_component = heartbeat.header.component_id;
+        }
+        Ok(heartbeat)
+    }
+
+    /// Send a message from the source system and component of the connection, returning the
+    /// length of the frame
+    fn send(&self, py: Python, message: &PyMessage) -> PyResult<usize> {
+        let header = MavHeader {
+            system_id: self.source_system,
+            component_id: self.source_component,
+            sequence: 0,
+        };
+        py.allow_threads(|| self.connection.send(&header, &message.msg))
+            .map_err(|e| MavlinkError::new_err(e.to_string()))
+    }
+
+    /// Use the protocol `version`, 1 or 2
+    fn set_protocol_version(&mut self, version: u8) -> PyResult<()> {
+        self.connection
+            .set_protocol_version(self::version(version)?);
+        Ok(())
+    }
+}
+
+/// Connect to `address`, see `mavlink::connect` for the formats, sending as `source_system`
+/// and `source_component`
+#[pyfunction]
+#[pyo3(signature = (address, source_system = 255, source_component = 0))]
+fn mavlink_connection(
+    py: Python,
+    address: &str,
+    source_system: u8,
+    source_component: u8,
+) -> PyResult<PyConnection> {
+    let connection = py
+        .allow_threads(|| crate::connect::<MavMessage>(address))
+        .map_err(|e| MavlinkError::new_err(e.to_string()))?;
+    Ok(PyConnection {
+        connection,
+        source_system,
+        source_component,
+        target_system: 0,
+        target_component: 0,
+    })
+}
+
+/// The `mavlink` Python module
+#[pymodule]
+#[pyo3(name = "mavlink")]
+pub fn mavlink_module(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add("MavlinkError", m.py().get_type_bound::<MavlinkError>())?;
+    m.add_class::<PyMessage>()?;
+    m.add_class::<PyParser>()?;
+    m.add_class::<PyConnection>()?;
+    m.add_function(wrap_pyfunction!(mavlink_connection, m)?)?;
+    Ok(())
+}
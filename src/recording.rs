@@ -0,0 +1,265 @@
+//! Recording the traffic of a connection and replaying it later.
+//!
+//! [`Recorder`] wraps any [`MavConnection`] and keeps every message it sends or receives
+//! together with the time it passed. The resulting [`Recording`] can be saved as a tlog and
+//! loaded again, and [`ReplayConnection`] plays it back as a connection so that application logic
+//! can be tested deterministically against a captured session.
+
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{MessageReadError, MessageWriteError};
+use crate::{
+    read_versioned_msg, write_versioned_msg, MavConnection, MavHeader, MavlinkVersion, Message,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Received,
+    Sent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedMessage<M> {
+    /// Time the message passed, in microseconds since the Unix epoch
+    pub timestamp: u64,
+    pub direction: Direction,
+    pub header: MavHeader,
+    pub msg: M,
+}
+
+/// Messages of a session in the order they passed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording<M> {
+    pub messages: Vec<RecordedMessage<M>>,
+}
+
+impl<M> Default for Recording<M> {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+        }
+    }
+}
+
+impl<M: Message> Recording<M> {
+    /// Read a tlog, where every frame is preceded by its reception time as big endian
+    /// microseconds since the Unix epoch.
+    ///
+    /// A tlog does not tell in which direction a message went, all of them are taken as
+    /// received.
+    pub fn read_tlog<R: Read>(reader: &mut R, version: MavlinkVersion) -> io::Result<Self> {
+        let mut messages = Vec::new();
+        loop {
+            let mut timestamp = [0; 8];
+            match reader.read_exact(&mut timestamp) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            match read_versioned_msg(reader, version) {
+                Ok((header, msg)) => messages.push(RecordedMessage {
+                    timestamp: u64::from_be_bytes(timestamp),
+                    direction: Direction::Received,
+                    header,
+                    msg,
+                }),
+                Err(MessageReadError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(MessageReadError::Io(e)) => return Err(e),
+                // the frame has been consumed, go on with the next one
+                Err(MessageReadError::Parse(_)) => {}
+            }
+        }
+        Ok(Self { messages })
+    }
+
+    /// Write the messages of both directions as a tlog
+    pub fn write_tlog<W: Write>(
+        &self,
+        writer: &mut W,
+        version: MavlinkVersion,
+    ) -> Result<(), MessageWriteError> {
+        for message in &self.messages {
+            writer.write_all(&message.timestamp.to_be_bytes())?;
+            write_versioned_msg(writer, version, message.header, &message.msg)?;
+        }
+        Ok(())
+    }
+
+    pub fn received(&self) -> impl Iterator<Item = &RecordedMessage<M>> {
+        self.messages
+            .iter()
+            .filter(|message| message.direction == Direction::Received)
+    }
+
+    pub fn sent(&self) -> impl Iterator<Item = &RecordedMessage<M>> {
+        self.messages
+            .iter()
+            .filter(|message| message.direction == Direction::Sent)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_micros() as u64)
+}
+
+/// Connection recording the traffic of the connection it wraps
+pub struct Recorder<M, C> {
+    connection: C,
+    recording: Mutex<Recording<M>>,
+}
+
+impl<M: Message + Clone, C: MavConnection<M>> Recorder<M, C> {
+    pub fn new(connection: C) -> Self {
+        Self {
+            connection,
+            recording: Mutex::new(Recording::default()),
+        }
+    }
+
+    pub fn connection(&self) -> &C {
+        &self.connection
+    }
+
+    /// Messages recorded so far
+    pub fn recording(&self) -> Recording<M> {
+        self.recording.lock().unwrap().clone()
+    }
+
+    /// Take the messages recorded so far, starting a new recording
+    pub fn take_recording(&self) -> Recording<M> {
+        core::mem::take(&mut *self.recording.lock().unwrap())
+    }
+
+    fn record(&self, direction: Direction, header: MavHeader, msg: &M) {
+        self.recording
+            .lock()
+            .unwrap()
+            .messages
+            .push(RecordedMessage {
+                timestamp: now(),
+                direction,
+                header,
+                msg: msg.clone(),
+            });
+    }
+}
+
+impl<M: Message + Clone, C: MavConnection<M>> MavConnection<M> for Recorder<M, C> {
+    fn recv(&self) -> Result<(MavHeader, M), MessageReadError> {
+        let (header, msg) = self.connection.recv()?;
+        self.record(Direction::Received, header, &msg);
+        Ok((header, msg))
+    }
+
+    fn send(&self, header: &MavHeader, data: &M) -> Result<usize, MessageWriteError> {
+        let written = self.connection.send(header, data)?;
+        self.record(Direction::Sent, *header, data);
+        Ok(written)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.connection.set_protocol_version(version);
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.connection.get_protocol_version()
+    }
+}
+
+struct ReplayState<M> {
+    /// Received messages not replayed yet, in reverse order
+    pending: Vec<RecordedMessage<M>>,
+    /// Timestamp of the previously replayed message
+    last_timestamp: Option<u64>,
+    sent: Vec<(MavHeader, M)>,
+}
+
+/// Connection replaying the received messages of a recording.
+///
+/// `recv` returns the received messages in their recorded order and an `UnexpectedEof` error
+/// once all of them have been replayed. Sent messages are not forwarded anywhere but kept, so
+/// that tests can check what the application sent.
+pub struct ReplayConnection<M> {
+    state: Mutex<ReplayState<M>>,
+    realtime: bool,
+    protocol_version: MavlinkVersion,
+}
+
+impl<M: Message + Clone> ReplayConnection<M> {
+    pub fn new(recording: &Recording<M>) -> Self {
+        let mut pending: Vec<_> = recording.received().cloned().collect();
+        pending.reverse();
+        Self {
+            state: Mutex::new(ReplayState {
+                pending,
+                last_timestamp: None,
+                sent: Vec::new(),
+            }),
+            realtime: false,
+            protocol_version: MavlinkVersion::V2,
+        }
+    }
+
+    /// Wait between the messages as long as they were apart when recorded, instead of
+    /// replaying them at once
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Number of received messages not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+
+    /// Messages sent through the connection so far
+    pub fn sent(&self) -> Vec<(MavHeader, M)> {
+        self.state.lock().unwrap().sent.clone()
+    }
+}
+
+impl<M: Message + Clone> MavConnection<M> for ReplayConnection<M> {
+    fn recv(&self) -> Result<(MavHeader, M), MessageReadError> {
+        let (message, delay) = {
+            let mut state = self.state.lock().unwrap();
+            let message = state
+                .pending
+                .pop()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            let delay = state
+                .last_timestamp
+                .map_or(0, |last| message.timestamp.saturating_sub(last));
+            state.last_timestamp = Some(message.timestamp);
+            (message, delay)
+        };
+        if self.realtime && delay > 0 {
+            thread::sleep(Duration::from_micros(delay));
+        }
+        Ok((message.header, message.msg))
+    }
+
+    fn send(&self, header: &MavHeader, data: &M) -> Result<usize, MessageWriteError> {
+        if self.protocol_version == MavlinkVersion::V1 && data.message_id() > 255 {
+            return Err(MessageWriteError::MAVLink2Only);
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .sent
+            .push((*header, data.clone()));
+        Ok(0)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.protocol_version = version;
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.protocol_version
+    }
+}
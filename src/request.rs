@@ -0,0 +1,359 @@
+//! Sending a message and waiting for the matching reply.
+//!
+//! Many MAVLink exchanges are a single request answered by a single message, e.g.
+//! PARAM_REQUEST_READ and PARAM_VALUE or MISSION_REQUEST_LIST and MISSION_COUNT. A [`Request`]
+//! describes the message to send and which reply it expects, and [`Requester`] sends it over a
+//! [`MavConnection`] and resends it until the reply arrives or the retries are used up.
+//!
+//! Longer exchanges, like commands or mission and parameter transfers, are state machines
+//! implementing [`Exchange`], which do no I/O themselves. [`Requester::run`] drives them over a
+//! connection, the clients of the other protocols are built on it.
+
+use std::io;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+use crate::error::{MessageReadError, ProtocolError};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Time to wait for a reply before resending
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Number of times a request is resent before giving up
+pub const DEFAULT_RETRIES: u8 = 3;
+
+/// Receive the next message.
+///
+/// Returns `None` if the connection timed out or the received frame could not be parsed, as
+/// such frames cannot be the awaited message, so that the caller can check its deadline.
+pub fn recv_message<M: Message, C: MavConnection<M> + ?Sized>(
+    connection: &C,
+) -> Result<Option<(MavHeader, M)>, MessageReadError> {
+    match connection.recv() {
+        Ok(received) => Ok(Some(received)),
+        Err(MessageReadError::Io(e))
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(MessageReadError::Io(e)) => Err(MessageReadError::Io(e)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Receive the first message for which `predicate` holds, discarding the messages before it.
+///
+/// Returns `None` if no message matched before `deadline`, which is checked whenever the
+/// connection returns from `recv`, so on a silent link the connection should have a read
+/// timeout.
+pub fn recv_matching<M, C, F>(
+    connection: &C,
+    deadline: Instant,
+    mut predicate: F,
+) -> Result<Option<(MavHeader, M)>, MessageReadError>
+where
+    M: Message,
+    C: MavConnection<M> + ?Sized,
+    F: FnMut(&MavHeader, &M) -> bool,
+{
+    while Instant::now() < deadline {
+        if let Some((header, msg)) = recv_message(connection)? {
+            if predicate(&header, &msg) {
+                return Ok(Some((header, msg)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// What an [`Exchange`] wants to happen next
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step<M, T> {
+    /// Send this message and restart the timeout
+    Send(M),
+    /// The exchange made progress, restart the timeout
+    Progress,
+    /// Nothing to send, keep waiting
+    Wait,
+    /// The exchange is complete, after sending the final message if there is one
+    Done(Option<M>, T),
+}
+
+/// A message exchange with another component, such as a command or a mission transfer.
+///
+/// The exchange only decides what to send in reply to the received messages and timeouts, so it
+/// can be driven by [`Requester::run`] as well as by an async runtime.
+pub trait Exchange {
+    /// Message set of the exchanged messages
+    type Message: Message;
+    type Output;
+    type Error: From<ProtocolError>;
+
+    /// Message opening the exchange
+    fn start(&mut self) -> Self::Message;
+
+    /// Feed a received message
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &Self::Message,
+    ) -> Result<Step<Self::Message, Self::Output>, Self::Error>;
+
+    /// Called when nothing useful was received within the timeout
+    fn on_timeout(&mut self) -> Result<Step<Self::Message, Self::Output>, Self::Error>;
+
+    /// Time to wait for the next message, given the timeout of the requester
+    fn timeout(&self, timeout: Duration) -> Duration {
+        timeout
+    }
+}
+
+/// Check of a reply beyond its id and sender
+type ReplyPredicate<'a, M> = Box<dyn Fn(&M) -> bool + 'a>;
+
+/// A message to send together with the reply it expects
+pub struct Request<'a, M: Message> {
+    msg: M,
+    reply_id: u32,
+    system_id: Option<u8>,
+    component_id: Option<u8>,
+    predicate: Option<ReplyPredicate<'a, M>>,
+}
+
+impl<'a, M: Message> Request<'a, M> {
+    /// Send `msg` and wait for a message with the id `reply_id`
+    pub fn new(msg: M, reply_id: u32) -> Self {
+        Self {
+            msg,
+            reply_id,
+            system_id: None,
+            component_id: None,
+            predicate: None,
+        }
+    }
+
+    /// Only accept replies sent by this system, 0 accepts any system
+    pub fn from_system(mut self, system_id: u8) -> Self {
+        self.system_id = Some(system_id).filter(|id| *id != 0);
+        self
+    }
+
+    /// Only accept replies sent by this component, 0 accepts any component
+    pub fn from_component(mut self, component_id: u8) -> Self {
+        self.component_id = Some(component_id).filter(|id| *id != 0);
+        self
+    }
+
+    /// Only accept replies for which the predicate holds, e.g. the ones with a matching index
+    pub fn matching(mut self, predicate: impl Fn(&M) -> bool + 'a) -> Self {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    pub fn message(&self) -> &M {
+        &self.msg
+    }
+
+    /// Whether a received message is the expected reply
+    pub fn is_reply(&self, header: &MavHeader, msg: &M) -> bool {
+        msg.message_id() == self.reply_id
+            && self.system_id.map_or(true, |id| id == header.system_id)
+            && self
+                .component_id
+                .map_or(true, |id| id == header.component_id)
+            && self
+                .predicate
+                .as_ref()
+                .map_or(true, |predicate| predicate(msg))
+    }
+}
+
+/// A [`Request`] as an [`Exchange`], resending it on every timeout
+struct RequestExchange<'r, 'a, M: Message> {
+    request: &'r Request<'a, M>,
+    /// Header of the requester, replies addressed to others are ignored
+    header: MavHeader,
+    retries: u8,
+    attempts: u8,
+}
+
+impl<M: Message + Clone> RequestExchange<'_, '_, M> {
+    /// Whether a message is targeted at the system and component of our header, or at everyone
+    fn is_addressed_to_us(&self, msg: &M) -> bool {
+        let system_id = msg.target_system_id().unwrap_or(0);
+        let component_id = msg.target_component_id().unwrap_or(0);
+        (system_id == 0 || system_id == self.header.system_id)
+            && (component_id == 0 || component_id == self.header.component_id)
+    }
+}
+
+impl<M: Message + Clone> Exchange for RequestExchange<'_, '_, M> {
+    type Message = M;
+    type Output = (MavHeader, M);
+    type Error = ProtocolError;
+
+    fn start(&mut self) -> M {
+        self.request.message().clone()
+    }
+
+    fn handle(
+        &mut self,
+        header: &MavHeader,
+        msg: &M,
+    ) -> Result<Step<M, Self::Output>, ProtocolError> {
+        if self.is_addressed_to_us(msg) && self.request.is_reply(header, msg) {
+            Ok(Step::Done(None, (*header, msg.clone())))
+        } else {
+            Ok(Step::Wait)
+        }
+    }
+
+    fn on_timeout(&mut self) -> Result<Step<M, Self::Output>, ProtocolError> {
+        if self.attempts >= self.retries {
+            return Err(ProtocolError::Timeout);
+        }
+        self.attempts += 1;
+        Ok(Step::Send(self.start()))
+    }
+}
+
+/// Sends requests over a connection and drives exchanges until they complete.
+///
+/// Timeouts are checked whenever the connection returns from `recv`, so on a silent link the
+/// connection should have a read timeout (as `tcpout` connections do).
+pub struct Requester<'a, M: Message, C: MavConnection<M> + ?Sized> {
+    connection: &'a C,
+    header: MavHeader,
+    timeout: Duration,
+    retries: u8,
+    _message: PhantomData<M>,
+}
+
+impl<M: Message, C: MavConnection<M> + ?Sized> Clone for Requester<'_, M, C> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection,
+            header: self.header,
+            timeout: self.timeout,
+            retries: self.retries,
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<'a, M: Message, C: MavConnection<M> + ?Sized> Requester<'a, M, C> {
+    pub fn new(connection: &'a C) -> Self {
+        Self {
+            connection,
+            header: MavHeader::default(),
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            _message: PhantomData,
+        }
+    }
+
+    /// Header used for outgoing messages, the sequence number is set by the connection
+    pub fn with_header(mut self, header: MavHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn header(&self) -> MavHeader {
+        self.header
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
+
+    /// Send a message of any message set, converted to the message set of the connection
+    pub fn send<N: Message>(&self, msg: &N) -> Result<(), ProtocolError> {
+        let msg = msg
+            .to_dialect::<M>()
+            .ok_or(ProtocolError::Unsupported(msg.message_id()))?;
+        self.connection.send(&self.header, &msg)?;
+        Ok(())
+    }
+
+    /// Send the request and wait for its reply, resending it on every timeout.
+    ///
+    /// Replies with a `target_system` or `target_component` field have to be addressed to the
+    /// header of the requester.
+    pub fn request(&self, request: &Request<M>) -> Result<(MavHeader, M), ProtocolError>
+    where
+        M: Clone,
+    {
+        self.run(&mut RequestExchange {
+            request,
+            header: self.header,
+            retries: self.retries,
+            attempts: 0,
+        })
+    }
+
+    /// Drive an exchange until it completes or fails
+    pub fn run<E: Exchange>(&self, exchange: &mut E) -> Result<E::Output, E::Error> {
+        self.run_with(exchange, |_| {})
+    }
+
+    /// Drive an exchange until it completes or fails, `on_progress` is called whenever it made
+    /// progress or is about to send a message
+    pub fn run_with<E: Exchange>(
+        &self,
+        exchange: &mut E,
+        mut on_progress: impl FnMut(&E),
+    ) -> Result<E::Output, E::Error> {
+        self.send(&exchange.start())?;
+        let mut deadline = Instant::now() + exchange.timeout(self.timeout);
+
+        loop {
+            let step = if Instant::now() >= deadline {
+                exchange.on_timeout()?
+            } else {
+                match recv_message(self.connection).map_err(ProtocolError::from)? {
+                    Some((header, msg)) => match msg.to_dialect::<E::Message>() {
+                        Some(msg) => exchange.handle(&header, &msg)?,
+                        None => Step::Wait,
+                    },
+                    None => Step::Wait,
+                }
+            };
+
+            match step {
+                Step::Send(msg) => {
+                    on_progress(exchange);
+                    self.send(&msg)?;
+                    deadline = Instant::now() + exchange.timeout(self.timeout);
+                }
+                Step::Progress => {
+                    on_progress(exchange);
+                    deadline = Instant::now() + exchange.timeout(self.timeout);
+                }
+                Step::Wait => {}
+                Step::Done(last, output) => {
+                    if let Some(msg) = last {
+                        self.send(&msg)?;
+                    }
+                    return Ok(output);
+                }
+            }
+        }
+    }
+}
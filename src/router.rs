@@ -0,0 +1,172 @@
+//! Forwarding of messages between links following the
+//! [routing rules](https://mavlink.io/en/guide/routing.html).
+//!
+//! [`Router`] learns on which link each system and component lives from the messages it
+//! receives, and forwards every message to the links where its target can be reached, or to all
+//! other links for broadcasts. Messages that cannot be represented on a link, such as messages
+//! with an id above 255 on a MAVLink 1 link, are not sent there and are reported instead.
+
+use core::fmt::{Display, Formatter};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::sync::Mutex;
+
+use crate::error::MessageWriteError;
+use crate::{MavConnection, MavHeader, MavlinkVersion, Message};
+
+/// Highest message id which can be sent in a MAVLink 1 frame
+pub const MAX_V1_MESSAGE_ID: u32 = 255;
+
+#[derive(Debug)]
+pub enum RouteError {
+    /// The message was not forwarded to a MAVLink 1 link because its id does not fit in the
+    /// frame
+    MAVLink2Only { link: usize, message_id: u32 },
+    Write {
+        link: usize,
+        error: MessageWriteError,
+    },
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MAVLink2Only { link, message_id } => write!(
+                f,
+                "Message {message_id} not forwarded to MAVLink 1 link {link}"
+            ),
+            Self::Write { link, error } => write!(f, "Forwarding to link {link} failed: {error}"),
+        }
+    }
+}
+
+impl Error for RouteError {}
+
+/// Decides whether a message is forwarded to a link
+pub type LinkFilter<M> = Box<dyn Fn(&MavHeader, &M) -> bool + Send + Sync>;
+
+/// Whether a message can be sent on a link using the given protocol version
+pub fn fits_version<M: Message>(msg: &M, version: MavlinkVersion) -> bool {
+    version == MavlinkVersion::V2 || msg.message_id() <= MAX_V1_MESSAGE_ID
+}
+
+/// Forwards messages between connections.
+///
+/// The router is shared between the threads reading the links, each of which hands the messages
+/// it receives to [`Router::handle`].
+pub struct Router<M: Message> {
+    links: Vec<Box<dyn MavConnection<M> + Send + Sync>>,
+    filters: Vec<Option<LinkFilter<M>>>,
+    /// Link each component was last seen on
+    routes: Mutex<HashMap<(u8, u8), usize>>,
+}
+
+impl<M: Message> Default for Router<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Message> Router<M> {
+    pub fn new() -> Self {
+        Self {
+            links: Vec::new(),
+            filters: Vec::new(),
+            routes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add a link, returning its index
+    pub fn add_link(&mut self, connection: Box<dyn MavConnection<M> + Send + Sync>) -> usize {
+        self.links.push(connection);
+        self.filters.push(None);
+        self.links.len() - 1
+    }
+
+    /// Add a link which only gets the messages accepted by the filter, returning its index
+    pub fn add_link_with_filter(
+        &mut self,
+        connection: Box<dyn MavConnection<M> + Send + Sync>,
+        filter: LinkFilter<M>,
+    ) -> usize {
+        let index = self.add_link(connection);
+        self.filters[index] = Some(filter);
+        index
+    }
+
+    pub fn link(&self, index: usize) -> Option<&(dyn MavConnection<M> + Send + Sync)> {
+        self.links.get(index).map(|link| &**link)
+    }
+
+    pub fn link_count(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Link the component was last seen on
+    pub fn route(&self, system_id: u8, component_id: u8) -> Option<usize> {
+        self.routes
+            .lock()
+            .unwrap()
+            .get(&(system_id, component_id))
+            .copied()
+    }
+
+    /// Remember that the sender of a message received on `link` can be reached through it
+    pub fn learn(&self, link: usize, header: &MavHeader) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert((header.system_id, header.component_id), link);
+    }
+
+    /// Links a message received on `from` has to be forwarded to
+    pub fn destinations(&self, from: usize, msg: &M) -> BTreeSet<usize> {
+        let target_system = msg.target_system_id().unwrap_or(0);
+        let target_component = msg.target_component_id().unwrap_or(0);
+        let others = (0..self.links.len()).filter(|link| *link != from);
+
+        if target_system == 0 {
+            return others.collect();
+        }
+
+        let routes = self.routes.lock().unwrap();
+        routes
+            .iter()
+            .filter(|((system_id, component_id), _)| {
+                *system_id == target_system
+                    && (target_component == 0 || *component_id == target_component)
+            })
+            .map(|(_, link)| *link)
+            .filter(|link| *link != from)
+            .collect()
+    }
+
+    /// Learn the route to the sender of a message received on `from` and forward the message.
+    ///
+    /// Messages rejected by the filter of a link are silently not sent there. Errors of individual links are returned, they do not keep the message from being
+    /// forwarded to the other links.
+    pub fn handle(&self, from: usize, header: &MavHeader, msg: &M) -> Vec<RouteError> {
+        self.learn(from, header);
+
+        let mut errors = Vec::new();
+        for link in self.destinations(from, msg) {
+            let connection = &self.links[link];
+            if let Some(filter) = &self.filters[link] {
+                if !filter(header, msg) {
+                    continue;
+                }
+            }
+            if !fits_version(msg, connection.get_protocol_version()) {
+                errors.push(RouteError::MAVLink2Only {
+                    link,
+                    message_id: msg.message_id(),
+                });
+                continue;
+            }
+            if let Err(error) = connection.send(header, msg) {
+                errors.push(RouteError::Write { link, error });
+            }
+        }
+        errors
+    }
+}
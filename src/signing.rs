@@ -0,0 +1,435 @@
+//! Provisioning of [message signing](https://mavlink.io/en/guide/message_signing.html) keys
+//! with SETUP_SIGNING.
+//!
+//! The sending side builds the message from a [`SigningKey`], the receiving component uses
+//! [`SigningSetupHandler`] to pick the key out of the incoming messages. With the `signing`
+//! feature, `FrameSigner` signs outgoing frames with the key and `SignatureVerifier` checks
+//! the signature and timestamp of incoming ones, `SignedConnection` does both on a stream.
+
+#[cfg(feature = "signing")]
+use core::fmt::{Display, Formatter};
+#[cfg(feature = "signing")]
+use std::collections::HashMap;
+#[cfg(feature = "signing")]
+use std::error::Error;
+#[cfg(feature = "signing")]
+use std::io::{Read, Write};
+#[cfg(feature = "signing")]
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::common::{MavMessage, SETUP_SIGNING_DATA};
+#[cfg(feature = "signing")]
+use crate::error::MessageReadError;
+use crate::error::MessageWriteError;
+#[cfg(feature = "signing")]
+use crate::{MAVLinkV2MessageRaw, MavlinkVersion, MAVLINK_IFLAG_SIGNED};
+use crate::{MavConnection, MavHeader, Message};
+
+/// Length of a signing secret key
+pub const SECRET_KEY_LEN: usize = 32;
+
+/// Start of the signing timestamp, 2015-01-01T00:00:00Z, in seconds since the UNIX epoch
+pub const SIGNING_EPOCH_SECS: u64 = 1_420_070_400;
+
+/// Signing timestamp of a point in time, in units of 10 microseconds since 2015-01-01
+pub fn signing_timestamp(time: SystemTime) -> u64 {
+    let epoch = UNIX_EPOCH + Duration::from_secs(SIGNING_EPOCH_SECS);
+    time.duration_since(epoch)
+        .map(|since_epoch| (since_epoch.as_micros() / 10) as u64)
+        .unwrap_or(0)
+}
+
+/// A secret key together with the timestamp signing starts from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    pub secret_key: [u8; SECRET_KEY_LEN],
+    pub initial_timestamp: u64,
+}
+
+impl SigningKey {
+    /// Key starting at the current time
+    pub fn new(secret_key: [u8; SECRET_KEY_LEN]) -> Self {
+        Self {
+            secret_key,
+            initial_timestamp: signing_timestamp(SystemTime::now()),
+        }
+    }
+
+    /// SETUP_SIGNING installing this key on a component
+    pub fn setup_message(&self, target_system: u8, target_component: u8) -> SETUP_SIGNING_DATA {
+        SETUP_SIGNING_DATA {
+            initial_timestamp: self.initial_timestamp,
+            target_system,
+            target_component,
+            secret_key: self.secret_key,
+        }
+    }
+}
+
+/// SETUP_SIGNING disabling signing on a component
+pub fn disable_message(target_system: u8, target_component: u8) -> SETUP_SIGNING_DATA {
+    SETUP_SIGNING_DATA {
+        initial_timestamp: 0,
+        target_system,
+        target_component,
+        secret_key: [0; SECRET_KEY_LEN],
+    }
+}
+
+/// Send a SETUP_SIGNING. The key travels in clear text, so this should only be done over a
+/// trusted link such as USB.
+pub fn send_setup_signing<M: Message, C: MavConnection<M> + ?Sized>(
+    connection: &C,
+    header: &MavHeader,
+    setup: SETUP_SIGNING_DATA,
+) -> Result<(), MessageWriteError> {
+    // SETUP_SIGNING is part of every message set including common
+    if let Some(msg) = MavMessage::SETUP_SIGNING(setup).to_dialect::<M>() {
+        connection.send(header, &msg)?;
+    }
+    Ok(())
+}
+
+/// What a received SETUP_SIGNING asks for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SigningSetup {
+    Enable(SigningKey),
+    Disable,
+}
+
+/// Receiving side of SETUP_SIGNING for a component
+#[derive(Debug, Clone)]
+pub struct SigningSetupHandler {
+    system_id: u8,
+    component_id: u8,
+    current: Option<SigningKey>,
+}
+
+impl SigningSetupHandler {
+    pub fn new(system_id: u8, component_id: u8) -> Self {
+        Self {
+            system_id,
+            component_id,
+            current: None,
+        }
+    }
+
+    /// Start with a key loaded from persistent storage
+    pub fn with_key(mut self, key: SigningKey) -> Self {
+        self.current = Some(key);
+        self
+    }
+
+    /// The key currently in use, if signing is enabled
+    pub fn key(&self) -> Option<&SigningKey> {
+        self.current.as_ref()
+    }
+
+    /// Feed a received message, returning the change to apply to the connection.
+    ///
+    /// The caller has to make sure the message arrived over a trusted link, the message itself
+    /// carries no proof of where it came from.
+    pub fn handle(&mut self, msg: &MavMessage) -> Option<SigningSetup> {
+        let setup = match msg {
+            MavMessage::SETUP_SIGNING(setup) => setup,
+            _ => return None,
+        };
+        if setup.target_system != self.system_id
+            || (setup.target_component != 0 && setup.target_component != self.component_id)
+        {
+            return None;
+        }
+
+        if setup.secret_key == [0; SECRET_KEY_LEN] && setup.initial_timestamp == 0 {
+            self.current = None;
+            return Some(SigningSetup::Disable);
+        }
+
+        let key = SigningKey {
+            secret_key: setup.secret_key,
+            // the timestamp must never go back, or old messages could be replayed
+            initial_timestamp: self.current.map_or(setup.initial_timestamp, |current| {
+                current.initial_timestamp.max(setup.initial_timestamp)
+            }),
+        };
+        self.current = Some(key);
+        Some(SigningSetup::Enable(key))
+    }
+}
+
+/// Length of the signature following the checksum: link id, timestamp and signature
+pub const SIGNATURE_BLOCK_LEN: usize = 13;
+
+/// Signing timestamps of new streams may lag behind the newest seen by this much, one minute
+pub const TIMESTAMP_WINDOW: u64 = 6_000_000;
+
+/// 48 bit signature of a frame: the first 6 bytes of the SHA-256 over the secret key and the
+/// frame from the magic byte up to and including the timestamp
+#[cfg(feature = "signing")]
+pub fn frame_signature(secret_key: &[u8; SECRET_KEY_LEN], frame: &[u8]) -> [u8; 6] {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(secret_key);
+    hasher.update(frame);
+    let mut signature = [0; 6];
+    signature.copy_from_slice(&hasher.finalize()[..6]);
+    signature
+}
+
+/// Compare two signatures in constant time, so the time taken does not reveal how many of the
+/// leading bytes of a forged signature are right
+#[cfg(feature = "signing")]
+fn signatures_equal(a: &[u8; 6], b: &[u8; 6]) -> bool {
+    a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Offset of the signature block in a frame
+#[cfg(feature = "signing")]
+fn signature_offset(frame: &MAVLinkV2MessageRaw) -> usize {
+    1 + MAVLinkV2MessageRaw::HEADER_SIZE + usize::from(frame.payload_length()) + 2
+}
+
+/// Link id, timestamp and signature of a signed frame
+#[cfg(feature = "signing")]
+pub fn frame_signature_block(frame: &MAVLinkV2MessageRaw) -> Option<(u8, u64, [u8; 6])> {
+    if frame.incompatibility_flags() & MAVLINK_IFLAG_SIGNED == 0 {
+        return None;
+    }
+    let block = &frame.0[signature_offset(frame)..][..SIGNATURE_BLOCK_LEN];
+    let mut timestamp = [0; 8];
+    timestamp[..6].copy_from_slice(&block[1..7]);
+    let mut signature = [0; 6];
+    signature.copy_from_slice(&block[7..]);
+    Some((block[0], u64::from_le_bytes(timestamp), signature))
+}
+
+/// Sign a frame.
+///
+/// The signed flag is part of the checksummed header, so the checksum is computed again with
+/// the extra CRC of the message set `M`.
+#[cfg(feature = "signing")]
+pub fn sign_frame<M: Message>(
+    frame: &mut MAVLinkV2MessageRaw,
+    secret_key: &[u8; SECRET_KEY_LEN],
+    link_id: u8,
+    timestamp: u64,
+) {
+    frame.0[2] |= MAVLINK_IFLAG_SIGNED;
+    let offset = signature_offset(frame);
+    let crc = crate::calculate_crc(&frame.0[1..(offset - 2)], M::extra_crc(frame.message_id()));
+    frame.0[(offset - 2)..offset].copy_from_slice(&crc.to_le_bytes());
+
+    frame.0[offset] = link_id;
+    // the timestamp is 48 bits long
+    frame.0[(offset + 1)..(offset + 7)].copy_from_slice(&timestamp.to_le_bytes()[..6]);
+    let signature = frame_signature(secret_key, &frame.0[..(offset + 7)]);
+    frame.0[(offset + 7)..(offset + SIGNATURE_BLOCK_LEN)].copy_from_slice(&signature);
+}
+
+/// Signs outgoing frames of one link with increasing timestamps
+#[cfg(feature = "signing")]
+#[derive(Debug, Clone)]
+pub struct FrameSigner {
+    key: SigningKey,
+    link_id: u8,
+    timestamp: u64,
+}
+
+#[cfg(feature = "signing")]
+impl FrameSigner {
+    pub fn new(key: SigningKey, link_id: u8) -> Self {
+        Self {
+            key,
+            link_id,
+            timestamp: key.initial_timestamp,
+        }
+    }
+
+    /// Timestamp of the last signed frame
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Sign a frame with a timestamp following the current time and the previous frame
+    pub fn sign<M: Message>(&mut self, frame: &mut MAVLinkV2MessageRaw) {
+        self.timestamp = (self.timestamp + 1).max(signing_timestamp(SystemTime::now()));
+        sign_frame::<M>(frame, &self.key.secret_key, self.link_id, self.timestamp);
+    }
+}
+
+#[cfg(feature = "signing")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The frame carries no signature
+    Unsigned,
+    /// The signature does not match the frame and key
+    InvalidSignature,
+    /// The timestamp is not newer than the last one of the same stream
+    Replayed,
+    /// The first frame of a stream is older than the timestamp window
+    TooOld,
+}
+
+#[cfg(feature = "signing")]
+impl Display for SignatureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unsigned => write!(f, "Frame is not signed"),
+            Self::InvalidSignature => write!(f, "Frame signature is invalid"),
+            Self::Replayed => write!(f, "Frame timestamp is not newer than the previous one"),
+            Self::TooOld => write!(f, "Frame timestamp is outside of the accepted window"),
+        }
+    }
+}
+
+#[cfg(feature = "signing")]
+impl Error for SignatureError {}
+
+/// Checks the signature and timestamp of incoming frames.
+///
+/// Every link id, system id and component id combination is a stream whose timestamps have to
+/// increase. A stream seen for the first time may start at most [`TIMESTAMP_WINDOW`] before the
+/// newest timestamp seen on any stream.
+#[cfg(feature = "signing")]
+#[derive(Debug, Clone)]
+pub struct SignatureVerifier {
+    secret_key: [u8; SECRET_KEY_LEN],
+    timestamp: u64,
+    streams: HashMap<(u8, u8, u8), u64>,
+}
+
+#[cfg(feature = "signing")]
+impl SignatureVerifier {
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            secret_key: key.secret_key,
+            timestamp: key.initial_timestamp,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Newest timestamp accepted so far
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Check a received frame, remembering its timestamp if it is accepted
+    pub fn check(&mut self, frame: &MAVLinkV2MessageRaw) -> Result<(), SignatureError> {
+        let (link_id, timestamp, signature) =
+            frame_signature_block(frame).ok_or(SignatureError::Unsigned)?;
+        let offset = signature_offset(frame);
+        let expected = frame_signature(&self.secret_key, &frame.0[..(offset + 7)]);
+        if !signatures_equal(&expected, &signature) {
+            return Err(SignatureError::InvalidSignature);
+        }
+
+        let stream = (link_id, frame.system_id(), frame.component_id());
+        match self.streams.get(&stream) {
+            Some(last) if timestamp <= *last => return Err(SignatureError::Replayed),
+            None if timestamp + TIMESTAMP_WINDOW < self.timestamp => {
+                return Err(SignatureError::TooOld)
+            }
+            _ => {}
+        }
+        self.streams.insert(stream, timestamp);
+        self.timestamp = self.timestamp.max(timestamp);
+        Ok(())
+    }
+}
+
+/// MAVLink 2 connection over a stream, such as a `TcpStream` or a serial port, signing the
+/// sent frames and dropping the received frames whose signature or timestamp is not accepted
+#[cfg(feature = "signing")]
+pub struct SignedConnection<C> {
+    reader: Mutex<SignedRead<C>>,
+    writer: Mutex<SignedWrite<C>>,
+}
+
+#[cfg(feature = "signing")]
+struct SignedRead<C> {
+    stream: C,
+    verifier: SignatureVerifier,
+}
+
+#[cfg(feature = "signing")]
+struct SignedWrite<C> {
+    stream: C,
+    signer: FrameSigner,
+    sequence: u8,
+}
+
+#[cfg(feature = "signing")]
+impl<C: Read + Write> SignedConnection<C> {
+    /// Connection reading from `reader` and writing to `writer`, two handles of the same stream
+    /// as given by `TcpStream::try_clone`. The frames are signed for link `link_id`.
+    pub fn new(reader: C, writer: C, key: SigningKey, link_id: u8) -> Self {
+        Self {
+            reader: Mutex::new(SignedRead {
+                stream: reader,
+                verifier: SignatureVerifier::new(key),
+            }),
+            writer: Mutex::new(SignedWrite {
+                stream: writer,
+                signer: FrameSigner::new(key, link_id),
+                sequence: 0,
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "signing")]
+impl<M: Message, C: Read + Write> MavConnection<M> for SignedConnection<C> {
+    fn recv(&self) -> Result<(MavHeader, M), MessageReadError> {
+        let mut guard = self.reader.lock().unwrap();
+        let state = &mut *guard;
+        loop {
+            let frame = crate::read_v2_raw_message_checked::<M, _>(&mut state.stream)?;
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            if let Err(e) = state.verifier.check(&frame) {
+                debug!(
+                    msgid = frame.message_id(),
+                    sysid = frame.system_id(),
+                    compid = frame.component_id(),
+                    error = %e,
+                    "dropped MAVLink 2 frame"
+                );
+                continue;
+            }
+
+            let msg = M::parse(MavlinkVersion::V2, frame.message_id(), frame.payload())?;
+            let header = MavHeader {
+                sequence: frame.sequence(),
+                system_id: frame.system_id(),
+                component_id: frame.component_id(),
+            };
+            return Ok((header, msg));
+        }
+    }
+
+    fn send(&self, header: &MavHeader, data: &M) -> Result<usize, MessageWriteError> {
+        let mut guard = self.writer.lock().unwrap();
+        let state = &mut *guard;
+
+        let header = MavHeader {
+            sequence: state.sequence,
+            system_id: header.system_id,
+            component_id: header.component_id,
+        };
+        state.sequence = state.sequence.wrapping_add(1);
+
+        let mut frame = MAVLinkV2MessageRaw::new();
+        frame.serialize_message(header, data);
+        state.signer.sign::<M>(&mut frame);
+        state.stream.write_all(frame.raw_bytes())?;
+        Ok(frame.raw_bytes().len())
+    }
+
+    /// Only MAVLink 2 frames can be signed, the version stays 2
+    fn set_protocol_version(&mut self, _version: MavlinkVersion) {}
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        MavlinkVersion::V2
+    }
+}
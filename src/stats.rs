@@ -0,0 +1,362 @@
+//! Traffic statistics: messages and bytes on the wire per message id and per sending component.
+//!
+//! [`TrafficStats`] counts what it is fed and turns the counts into a [`TrafficSnapshot`] of
+//! rates and bandwidths, e.g. to show them like `mavinspect` or to check the rates requested with
+//! SET_MESSAGE_INTERVAL against the link capacity. [`MeteredConnection`] counts the traffic of the
+//! connection it wraps, its byte counts are estimated payload bytes.
+//!
+//! Lost frames are counted from the gaps in the sequence numbers of each sending component,
+//! see [`sequence_gap`] for how the counter wrapping from 255 to 0 is handled.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{MessageReadError, MessageWriteError};
+use crate::{MavConnection, MavHeader, MavlinkVersion, Message, MAX_FRAME_SIZE};
+
+/// Size of a frame without signature carrying a payload of `payload_len` bytes
+pub fn frame_size(version: MavlinkVersion, payload_len: usize) -> usize {
+    match version {
+        // STX, 5 header bytes and CRC
+        MavlinkVersion::V1 => 1 + 5 + payload_len + 2,
+        // STX, 9 header bytes and CRC
+        MavlinkVersion::V2 => 1 + 9 + payload_len + 2,
+    }
+}
+
+/// Size of the payload of a message, MAVLink 2 payloads being truncated
+pub fn message_payload_size<M: Message>(version: MavlinkVersion, msg: &M) -> usize {
+    let mut payload = [0; MAX_FRAME_SIZE];
+    msg.ser(version, &mut payload)
+}
+
+/// Size of the frame of a message without signature, MAVLink 2 payloads being truncated
+pub fn message_frame_size<M: Message>(version: MavlinkVersion, msg: &M) -> usize {
+    frame_size(version, message_payload_size(version, msg))
+}
+
+/// Number of frames lost between two consecutive frames of a component with the sequence numbers
+/// `previous` and `current`.
+///
+/// The sequence number wraps from 255 to 0, so 255 followed by 0 is no loss and 250 followed by
+/// 2 are 7 lost frames. A repeated sequence number is taken for a duplicated frame rather than
+/// 255 lost frames, so there is no loss.
+pub fn sequence_gap(previous: u8, current: u8) -> u8 {
+    if current == previous {
+        return 0;
+    }
+    current.wrapping_sub(previous).wrapping_sub(1)
+}
+
+/// Received and lost frames of a component, counted from the sequence numbers of its frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SequenceCounter {
+    pub received: u64,
+    pub lost: u64,
+    last: Option<u8>,
+}
+
+impl SequenceCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a frame, returns the number of frames lost since the previous one
+    pub fn update(&mut self, sequence: u8) -> u8 {
+        let gap = self.last.map_or(0, |last| sequence_gap(last, sequence));
+        self.last = Some(sequence);
+        self.received += 1;
+        self.lost += u64::from(gap);
+        gap
+    }
+
+    /// Sequence number of the last frame counted
+    pub fn last(&self) -> Option<u8> {
+        self.last
+    }
+
+    /// Share of the frames sent by the component that were lost, 0 before the first loss
+    pub fn loss(&self) -> f64 {
+        let sent = self.received + self.lost;
+        if sent > 0 {
+            self.lost as f64 / sent as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Start counting again, keeping the last sequence number so that the frames lost before the
+    /// next frame are still counted
+    pub fn reset(&mut self) {
+        self.received = 0;
+        self.lost = 0;
+    }
+}
+
+/// Traffic of a message, a component or a whole link during a snapshot period
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Traffic {
+    pub messages: u64,
+    pub bytes: u64,
+    /// Messages per second
+    pub rate: f64,
+    /// Bytes per second
+    pub bandwidth: f64,
+}
+
+impl Traffic {
+    /// Average time between two messages, e.g. to compare with the interval set with
+    /// MAV_CMD_SET_MESSAGE_INTERVAL
+    pub fn interval(&self) -> Option<Duration> {
+        if self.rate > 0.0 {
+            Some(Duration::from_secs_f64(1.0 / self.rate))
+        } else {
+            None
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.messages += other.messages;
+        self.bytes += other.bytes;
+        self.rate += other.rate;
+        self.bandwidth += other.bandwidth;
+    }
+}
+
+/// Traffic keyed by system id, component id and message id
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrafficSnapshot {
+    /// Time the counts were collected over
+    pub elapsed: Duration,
+    pub entries: BTreeMap<(u8, u8, u32), Traffic>,
+    /// Received and lost frames keyed by system id and component id
+    pub sequences: BTreeMap<(u8, u8), SequenceCounter>,
+}
+
+impl TrafficSnapshot {
+    pub fn get(&self, system_id: u8, component_id: u8, message_id: u32) -> Option<&Traffic> {
+        self.entries.get(&(system_id, component_id, message_id))
+    }
+
+    /// Received and lost frames of a component
+    pub fn sequence(&self, system_id: u8, component_id: u8) -> Option<&SequenceCounter> {
+        self.sequences.get(&(system_id, component_id))
+    }
+
+    /// Traffic of a message id summed over all senders
+    pub fn message(&self, message_id: u32) -> Traffic {
+        self.sum(|(_, _, id)| *id == message_id)
+    }
+
+    /// Traffic of all messages of a component
+    pub fn component(&self, system_id: u8, component_id: u8) -> Traffic {
+        self.entries
+            .range((system_id, component_id, 0)..=(system_id, component_id, u32::MAX))
+            .fold(Traffic::default(), |mut sum, (_, traffic)| {
+                sum.add(traffic);
+                sum
+            })
+    }
+
+    /// Traffic of all messages of a system
+    pub fn system(&self, system_id: u8) -> Traffic {
+        self.sum(|(system, _, _)| *system == system_id)
+    }
+
+    pub fn total(&self) -> Traffic {
+        self.sum(|_| true)
+    }
+
+    /// Traffic per message id summed over all senders, by decreasing bandwidth
+    pub fn by_message(&self) -> Vec<(u32, Traffic)> {
+        let mut messages: BTreeMap<u32, Traffic> = BTreeMap::new();
+        for ((_, _, message_id), traffic) in &self.entries {
+            messages.entry(*message_id).or_default().add(traffic);
+        }
+        let mut messages: Vec<_> = messages.into_iter().collect();
+        messages.sort_by(|(_, a), (_, b)| {
+            b.bandwidth
+                .partial_cmp(&a.bandwidth)
+                .unwrap_or(core::cmp::Ordering::Equal)
+        });
+        messages
+    }
+
+    fn sum(&self, filter: impl Fn(&(u8, u8, u32)) -> bool) -> Traffic {
+        self.entries.iter().filter(|(key, _)| filter(key)).fold(
+            Traffic::default(),
+            |mut sum, (_, traffic)| {
+                sum.add(traffic);
+                sum
+            },
+        )
+    }
+}
+
+/// Counts of messages and bytes since the start of the current period
+#[derive(Debug, Clone)]
+pub struct TrafficStats {
+    since: Instant,
+    counts: BTreeMap<(u8, u8, u32), (u64, u64)>,
+    sequences: BTreeMap<(u8, u8), SequenceCounter>,
+}
+
+impl TrafficStats {
+    /// Start counting at `now`
+    pub fn new(now: Instant) -> Self {
+        Self {
+            since: now,
+            counts: BTreeMap::new(),
+            sequences: BTreeMap::new(),
+        }
+    }
+
+    /// Count a frame of `bytes` bytes
+    pub fn record(&mut self, header: &MavHeader, message_id: u32, bytes: usize) {
+        let (messages, total) = self
+            .counts
+            .entry((header.system_id, header.component_id, message_id))
+            .or_default();
+        *messages += 1;
+        *total += bytes as u64;
+    }
+
+    /// Count the sequence number of a received frame, returns the number of frames of its sender
+    /// lost since the previous one.
+    ///
+    /// Only received frames carry the sequence numbers of their sender, sent frames get theirs
+    /// from the connection.
+    pub fn record_sequence(&mut self, header: &MavHeader) -> u8 {
+        self.sequences
+            .entry((header.system_id, header.component_id))
+            .or_default()
+            .update(header.sequence)
+    }
+
+    /// Count a message with an estimate of its payload bytes: the size of the payload serialized
+    /// again, which differs from the received one if the sender didn't truncate it
+    pub fn record_estimated_payload<M: Message>(
+        &mut self,
+        version: MavlinkVersion,
+        header: &MavHeader,
+        msg: &M,
+    ) {
+        self.record(header, msg.message_id(), message_payload_size(version, msg));
+    }
+
+    /// Traffic since the start of the period
+    pub fn snapshot(&self, now: Instant) -> TrafficSnapshot {
+        let elapsed = now.saturating_duration_since(self.since);
+        let seconds = elapsed.as_secs_f64();
+        let per_second = |count: u64| {
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+        let entries = self
+            .counts
+            .iter()
+            .map(|(key, (messages, bytes))| {
+                let traffic = Traffic {
+                    messages: *messages,
+                    bytes: *bytes,
+                    rate: per_second(*messages),
+                    bandwidth: per_second(*bytes),
+                };
+                (*key, traffic)
+            })
+            .collect();
+        TrafficSnapshot {
+            elapsed,
+            entries,
+            sequences: self.sequences.clone(),
+        }
+    }
+
+    /// Traffic since the start of the period, starting a new one
+    pub fn take_snapshot(&mut self, now: Instant) -> TrafficSnapshot {
+        let snapshot = self.snapshot(now);
+        self.reset(now);
+        snapshot
+    }
+
+    /// Start a new period, the last sequence number of each component is kept
+    pub fn reset(&mut self, now: Instant) {
+        self.since = now;
+        self.counts.clear();
+        self.sequences.values_mut().for_each(SequenceCounter::reset);
+    }
+}
+
+/// Connection counting the traffic of the connection it wraps.
+///
+/// The wrapped connection gives messages rather than frames, so the byte counts of the snapshots
+/// are estimated payload bytes, see [`TrafficStats::record_estimated_payload`]. Headers,
+/// checksums, signatures and frames which could not be parsed are not counted.
+pub struct MeteredConnection<C> {
+    connection: C,
+    received: Mutex<TrafficStats>,
+    sent: Mutex<TrafficStats>,
+}
+
+impl<C> MeteredConnection<C> {
+    pub fn new(connection: C) -> Self {
+        let now = Instant::now();
+        Self {
+            connection,
+            received: Mutex::new(TrafficStats::new(now)),
+            sent: Mutex::new(TrafficStats::new(now)),
+        }
+    }
+
+    pub fn connection(&self) -> &C {
+        &self.connection
+    }
+
+    /// Received traffic since the connection was created or reset
+    pub fn received(&self) -> TrafficSnapshot {
+        self.received.lock().unwrap().snapshot(Instant::now())
+    }
+
+    /// Sent traffic since the connection was created or reset
+    pub fn sent(&self) -> TrafficSnapshot {
+        self.sent.lock().unwrap().snapshot(Instant::now())
+    }
+
+    pub fn reset(&self) {
+        let now = Instant::now();
+        self.received.lock().unwrap().reset(now);
+        self.sent.lock().unwrap().reset(now);
+    }
+}
+
+impl<M: Message, C: MavConnection<M>> MavConnection<M> for MeteredConnection<C> {
+    fn recv(&self) -> Result<(MavHeader, M), MessageReadError> {
+        let (header, msg) = self.connection.recv()?;
+        let mut received = self.received.lock().unwrap();
+        received.record_estimated_payload(self.connection.get_protocol_version(), &header, &msg);
+        received.record_sequence(&header);
+        Ok((header, msg))
+    }
+
+    fn send(&self, header: &MavHeader, data: &M) -> Result<usize, MessageWriteError> {
+        let written = self.connection.send(header, data)?;
+        self.sent.lock().unwrap().record_estimated_payload(
+            self.connection.get_protocol_version(),
+            header,
+            data,
+        );
+        Ok(written)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.connection.set_protocol_version(version);
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.connection.get_protocol_version()
+    }
+}
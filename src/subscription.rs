@@ -0,0 +1,146 @@
+//! Receiving each message type in its own stream.
+//!
+//! A [`Dispatcher`] reads a connection and hands every message to the [`Subscription`]s of its
+//! type, so independent consumers like a UI, a logger and a controller each iterate over the
+//! messages they need instead of sharing one central `match` over all received messages.
+//!
+//! ```no_run
+//! # use mavlink::common::{MavMessage, ATTITUDE_DATA};
+//! # use mavlink::subscription::Dispatcher;
+//! let connection = mavlink::connect::<MavMessage>("udpin:0.0.0.0:14550").unwrap();
+//! let dispatcher = Dispatcher::spawn(connection);
+//! for (header, attitude) in dispatcher.subscribe::<ATTITUDE_DATA>() {
+//!     println!("{}: roll {}", header.system_id, attitude.roll);
+//! }
+//! ```
+
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::MessageReadError;
+use crate::request::recv_message;
+use crate::{MavConnection, MavHeader, Message, MessageData};
+
+/// Hands a message to a subscription, returns `false` once the subscription is dropped
+type Forward<M> = Box<dyn Fn(&MavHeader, &M) -> bool + Send>;
+
+/// Fans the messages read from a connection out to the subscriptions of their type
+pub struct Dispatcher<M: Message> {
+    subscribers: Mutex<Vec<Forward<M>>>,
+}
+
+impl<M: Message> Default for Dispatcher<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Message> Dispatcher<M> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Start a thread running a new dispatcher on `connection`.
+    ///
+    /// The thread only holds a weak reference, so dropping the returned dispatcher ends all
+    /// subscriptions, and the thread stops with the connection after its next read. The thread
+    /// also stops when reading the connection fails.
+    pub fn spawn<C>(connection: Box<C>) -> Arc<Self>
+    where
+        C: MavConnection<M> + Send + ?Sized + 'static,
+        M: Send + 'static,
+    {
+        let dispatcher = Arc::new(Self::new());
+        let weak = Arc::downgrade(&dispatcher);
+        thread::spawn(move || Self::run_weak(&weak, &*connection));
+        dispatcher
+    }
+
+    /// Like [`Dispatcher::run`], until the dispatcher is dropped
+    fn run_weak<C: MavConnection<M> + ?Sized>(dispatcher: &Weak<Self>, connection: &C) {
+        loop {
+            let received = recv_message(connection);
+            let dispatcher = match dispatcher.upgrade() {
+                Some(dispatcher) => dispatcher,
+                None => return,
+            };
+            match received {
+                Ok(Some((header, msg))) => dispatcher.dispatch(&header, &msg),
+                Ok(None) => (),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Subscribe to the messages of type `D` dispatched from now on
+    pub fn subscribe<D>(&self) -> Subscription<D>
+    where
+        D: MessageData<Message = M> + Clone + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(Box::new(
+            move |header, msg| match D::from_message_ref(msg) {
+                Some(data) => tx.send((*header, data.clone())).is_ok(),
+                None => true,
+            },
+        ));
+        Subscription { rx }
+    }
+
+    /// Number of subscriptions, including dropped ones not yet noticed by [`Dispatcher::dispatch`]
+    pub fn subscription_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Hand a message to the subscriptions of its type, forgetting the dropped subscriptions
+    pub fn dispatch(&self, header: &MavHeader, msg: &M) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|forward| forward(header, msg));
+    }
+
+    /// Dispatch the messages received on `connection` until reading it fails.
+    ///
+    /// Timeouts and frames that cannot be parsed are skipped, the error ending the loop is
+    /// returned.
+    pub fn run<C: MavConnection<M> + ?Sized>(&self, connection: &C) -> MessageReadError {
+        loop {
+            match recv_message(connection) {
+                Ok(Some((header, msg))) => self.dispatch(&header, &msg),
+                Ok(None) => (),
+                Err(e) => return e,
+            }
+        }
+    }
+}
+
+/// Stream of the messages of type `D`, ending when its [`Dispatcher`] is dropped
+pub struct Subscription<D> {
+    rx: Receiver<(MavHeader, D)>,
+}
+
+impl<D> Subscription<D> {
+    /// The next message if one is already waiting, `None` if not or if the stream ended
+    pub fn try_recv(&self) -> Option<(MavHeader, D)> {
+        self.rx.try_recv().ok()
+    }
+
+    /// The next message, `None` if none arrived within `timeout` or if the stream ended
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<(MavHeader, D)> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+}
+
+impl<D> Iterator for Subscription<D> {
+    type Item = (MavHeader, D);
+
+    /// Blocks until the next message arrives
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
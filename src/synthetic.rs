@@ -0,0 +1,367 @@
+//! Synthetic telemetry for testing ground station software without a vehicle.
+//!
+//! [`TelemetryGenerator`] produces the HEARTBEAT, ATTITUDE, GPS_RAW_INT and GLOBAL_POSITION_INT
+//! messages of a vehicle following a [`FlightPath`], each at its own rate. The messages can be
+//! taken for a given time with [`TelemetryGenerator::poll`], which is deterministic and suits
+//! unit tests, or sent in real time over any connection with [`TelemetryGenerator::run`].
+
+use std::f64::consts::PI;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::{
+    GpsFixType, MavAutopilot, MavMessage, MavModeFlag, MavState, MavType, ATTITUDE_DATA,
+    GLOBAL_POSITION_INT_DATA, GPS_RAW_INT_DATA, HEARTBEAT_DATA,
+};
+use crate::error::MessageWriteError;
+use crate::{MavConnection, MavHeader, Message, MessageData};
+
+/// Mean radius of the Earth in meters
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Standard gravity in m/s²
+const GRAVITY: f64 = 9.806_65;
+
+/// Position, attitude and velocity of the simulated vehicle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VehicleState {
+    /// Latitude in degrees
+    pub lat: f64,
+    /// Longitude in degrees
+    pub lon: f64,
+    /// Altitude above mean sea level in meters
+    pub alt: f32,
+    /// Roll in radians
+    pub roll: f32,
+    /// Pitch in radians
+    pub pitch: f32,
+    /// Yaw in radians, clockwise from north
+    pub yaw: f32,
+    /// Yaw rate in rad/s
+    pub yaw_rate: f32,
+    /// Velocity towards north in m/s
+    pub vn: f32,
+    /// Velocity towards east in m/s
+    pub ve: f32,
+}
+
+/// Movement of the simulated vehicle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FlightPath {
+    /// Stay at one position, facing north
+    Hover { lat: f64, lon: f64, alt: f32 },
+    /// Fly clockwise around a center with a radius in meters at a speed in m/s
+    Circle {
+        lat: f64,
+        lon: f64,
+        alt: f32,
+        radius: f32,
+        speed: f32,
+    },
+    /// Fly back and forth between two positions, given as latitude and longitude in degrees, at a
+    /// speed in m/s
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        alt: f32,
+        speed: f32,
+    },
+}
+
+/// Position offset by a distance in meters towards north and east
+fn offset(lat: f64, lon: f64, north: f64, east: f64) -> (f64, f64) {
+    (
+        lat + (north / EARTH_RADIUS).to_degrees(),
+        lon + (east / (EARTH_RADIUS * lat.to_radians().cos())).to_degrees(),
+    )
+}
+
+/// Distance in meters towards north and east from one position to another
+fn distance(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    (
+        (to.0 - from.0).to_radians() * EARTH_RADIUS,
+        (to.1 - from.1).to_radians() * EARTH_RADIUS * from.0.to_radians().cos(),
+    )
+}
+
+impl FlightPath {
+    /// State of the vehicle at a time since the start of the flight
+    pub fn state(&self, time: Duration) -> VehicleState {
+        let t = time.as_secs_f64();
+        match *self {
+            Self::Hover { lat, lon, alt } => VehicleState {
+                lat,
+                lon,
+                alt,
+                roll: 0.0,
+                pitch: 0.0,
+                yaw: 0.0,
+                yaw_rate: 0.0,
+                vn: 0.0,
+                ve: 0.0,
+            },
+            Self::Circle {
+                lat,
+                lon,
+                alt,
+                radius,
+                speed,
+            } => {
+                let (radius, speed) = (f64::from(radius), f64::from(speed));
+                let rate = speed / radius;
+                // angle from the center, clockwise from north
+                let angle = rate * t;
+                let (lat, lon) = offset(lat, lon, radius * angle.cos(), radius * angle.sin());
+                let yaw = (angle + PI / 2.0).rem_euclid(2.0 * PI);
+                VehicleState {
+                    lat,
+                    lon,
+                    alt,
+                    // coordinated turn
+                    roll: (speed * speed / (radius * GRAVITY)).atan() as f32,
+                    pitch: 0.0,
+                    yaw: yaw as f32,
+                    yaw_rate: rate as f32,
+                    vn: (speed * yaw.cos()) as f32,
+                    ve: (speed * yaw.sin()) as f32,
+                }
+            }
+            Self::Line {
+                from,
+                to,
+                alt,
+                speed,
+            } => {
+                let (north, east) = distance(from, to);
+                let length = north.hypot(east);
+                let speed = f64::from(speed);
+                // position along the line, going back on odd legs
+                let travelled = if length > 0.0 {
+                    speed * t / length
+                } else {
+                    0.0
+                };
+                let leg = travelled.floor();
+                let backwards = leg as u64 % 2 == 1;
+                let fraction = if backwards {
+                    1.0 - (travelled - leg)
+                } else {
+                    travelled - leg
+                };
+                let (lat, lon) = offset(from.0, from.1, north * fraction, east * fraction);
+                let (north, east) = if backwards {
+                    (-north, -east)
+                } else {
+                    (north, east)
+                };
+                let (vn, ve) = if length > 0.0 {
+                    (speed * north / length, speed * east / length)
+                } else {
+                    (0.0, 0.0)
+                };
+                VehicleState {
+                    lat,
+                    lon,
+                    alt,
+                    roll: 0.0,
+                    pitch: 0.0,
+                    yaw: east.atan2(north).rem_euclid(2.0 * PI) as f32,
+                    yaw_rate: 0.0,
+                    vn: vn as f32,
+                    ve: ve as f32,
+                }
+            }
+        }
+    }
+}
+
+/// A message produced by the generator and the time it is due next
+#[derive(Debug, Copy, Clone)]
+struct Stream {
+    id: u32,
+    interval: Duration,
+    next: Duration,
+}
+
+/// Default message ids and their rate in Hz
+const DEFAULT_RATES: [(u32, f32); 4] = [
+    (HEARTBEAT_DATA::ID, 1.0),
+    (ATTITUDE_DATA::ID, 10.0),
+    (GPS_RAW_INT_DATA::ID, 5.0),
+    (GLOBAL_POSITION_INT_DATA::ID, 5.0),
+];
+
+/// Produces the telemetry of a simulated vehicle
+#[derive(Debug, Clone)]
+pub struct TelemetryGenerator {
+    path: FlightPath,
+    header: MavHeader,
+    streams: Vec<Stream>,
+}
+
+impl TelemetryGenerator {
+    /// Generator for a vehicle with the system id 1 and component id 1 sending the default rates:
+    /// HEARTBEAT at 1 Hz, ATTITUDE at 10 Hz, GPS_RAW_INT and GLOBAL_POSITION_INT at 5 Hz
+    pub fn new(path: FlightPath) -> Self {
+        let mut generator = Self {
+            path,
+            header: MavHeader {
+                system_id: 1,
+                component_id: 1,
+                sequence: 0,
+            },
+            streams: Vec::new(),
+        };
+        for (id, rate) in DEFAULT_RATES {
+            generator = generator.with_rate(id, rate);
+        }
+        generator
+    }
+
+    pub fn with_ids(mut self, system_id: u8, component_id: u8) -> Self {
+        self.header.system_id = system_id;
+        self.header.component_id = component_id;
+        self
+    }
+
+    /// Set the rate of a message in Hz, a rate of 0 stops it.
+    ///
+    /// Ids of messages the generator does not produce are ignored.
+    pub fn with_rate(mut self, id: u32, rate: f32) -> Self {
+        self.streams.retain(|stream| stream.id != id);
+        if rate > 0.0 && DEFAULT_RATES.iter().any(|(known, _)| *known == id) {
+            self.streams.push(Stream {
+                id,
+                interval: Duration::from_nanos((1e9 / f64::from(rate)).round() as u64),
+                next: Duration::ZERO,
+            });
+        }
+        self
+    }
+
+    pub fn path(&self) -> &FlightPath {
+        &self.path
+    }
+
+    /// Messages due at a time since the start, in the order they are due.
+    ///
+    /// Every message is produced at most once per call, a message which missed several intervals
+    /// is produced once for the latest.
+    pub fn poll(&mut self, time: Duration) -> Vec<MavMessage> {
+        let mut due: Vec<_> = self
+            .streams
+            .iter_mut()
+            .filter(|stream| stream.next <= time)
+            .map(|stream| {
+                let scheduled = stream.next;
+                while stream.next <= time {
+                    stream.next += stream.interval;
+                }
+                (scheduled, stream.id)
+            })
+            .collect();
+        due.sort_by_key(|(scheduled, _)| *scheduled);
+
+        let state = self.path.state(time);
+        due.into_iter()
+            .filter_map(|(_, id)| message(id, time, &state))
+            .collect()
+    }
+
+    /// Time since the start when the next message is due
+    pub fn next_due(&self) -> Option<Duration> {
+        self.streams.iter().map(|stream| stream.next).min()
+    }
+
+    /// Send the telemetry over a connection in real time for a duration, returning the number of
+    /// messages sent
+    pub fn run<M: Message, C: MavConnection<M> + ?Sized>(
+        &mut self,
+        connection: &C,
+        duration: Duration,
+    ) -> Result<usize, MessageWriteError> {
+        let start = Instant::now();
+        let mut sent = 0;
+        while let Some(next) = self.next_due() {
+            if next > duration {
+                break;
+            }
+            if let Some(wait) = next.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+            for msg in self.poll(start.elapsed().min(duration)) {
+                if let Some(msg) = msg.to_dialect::<M>() {
+                    connection.send(&self.header, &msg)?;
+                    self.header.sequence = self.header.sequence.wrapping_add(1);
+                    sent += 1;
+                }
+            }
+        }
+        Ok(sent)
+    }
+}
+
+/// Message with an id for the state of the vehicle at a time since boot
+// the defaults only fill the extension fields
+#[cfg_attr(not(feature = "emit-extensions"), allow(clippy::needless_update))]
+fn message(id: u32, time: Duration, state: &VehicleState) -> Option<MavMessage> {
+    let time_boot_ms = time.as_millis() as u32;
+    let speed = state.vn.hypot(state.ve);
+    let yaw_cdeg = (state.yaw.to_degrees() * 100.0) as u16;
+    let lat = (state.lat * 1e7) as i32;
+    let lon = (state.lon * 1e7) as i32;
+    let alt = (state.alt * 1000.0) as i32;
+    match id {
+        HEARTBEAT_DATA::ID => Some(MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode: 0,
+            mavtype: MavType::MAV_TYPE_QUADROTOR,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_GENERIC,
+            base_mode: MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED
+                | MavModeFlag::MAV_MODE_FLAG_GUIDED_ENABLED
+                | MavModeFlag::MAV_MODE_FLAG_STABILIZE_ENABLED,
+            system_status: MavState::MAV_STATE_ACTIVE,
+            mavlink_version: 3,
+        })),
+        ATTITUDE_DATA::ID => Some(MavMessage::ATTITUDE(ATTITUDE_DATA {
+            time_boot_ms,
+            roll: state.roll,
+            pitch: state.pitch,
+            // -pi..pi like autopilots report it
+            yaw: if state.yaw > core::f32::consts::PI {
+                state.yaw - 2.0 * core::f32::consts::PI
+            } else {
+                state.yaw
+            },
+            rollspeed: 0.0,
+            pitchspeed: 0.0,
+            yawspeed: state.yaw_rate,
+        })),
+        GPS_RAW_INT_DATA::ID => Some(MavMessage::GPS_RAW_INT(GPS_RAW_INT_DATA {
+            time_usec: time.as_micros() as u64,
+            fix_type: GpsFixType::GPS_FIX_TYPE_3D_FIX,
+            lat,
+            lon,
+            alt,
+            eph: 80,
+            epv: 120,
+            vel: (speed * 100.0) as u16,
+            cog: yaw_cdeg,
+            satellites_visible: 12,
+            ..Default::default()
+        })),
+        GLOBAL_POSITION_INT_DATA::ID => {
+            Some(MavMessage::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+                time_boot_ms,
+                lat,
+                lon,
+                alt,
+                relative_alt: alt,
+                vx: (state.vn * 100.0) as i16,
+                vy: (state.ve * 100.0) as i16,
+                vz: 0,
+                hdg: yaw_cdeg,
+            }))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,106 @@
+//! Requesting telemetry streams from a vehicle.
+//!
+//! Autopilots support two mechanisms: the legacy REQUEST_DATA_STREAM, still used by ArduPilot,
+//! and MAV_CMD_SET_MESSAGE_INTERVAL. [`request_telemetry`] sends both for a
+//! [`TelemetryProfile`], so the same call works whatever the vehicle runs.
+
+use crate::common::{
+    MavCmd, MavDataStream, MavMessage, ATTITUDE_DATA, BATTERY_STATUS_DATA, COMMAND_LONG_DATA,
+    GLOBAL_POSITION_INT_DATA, GPS_RAW_INT_DATA, LOCAL_POSITION_NED_DATA, REQUEST_DATA_STREAM_DATA,
+    SYS_STATUS_DATA, VFR_HUD_DATA,
+};
+use crate::error::MessageWriteError;
+use crate::{MavConnection, MavHeader, Message, MessageData};
+
+/// Predefined sets of telemetry messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TelemetryProfile {
+    /// Global and local position and GPS
+    Position,
+    /// Attitude
+    Attitude,
+    /// Position, attitude, status and HUD values
+    All,
+}
+
+impl TelemetryProfile {
+    /// Legacy data streams and their rate in Hz
+    pub fn streams(&self) -> &'static [(MavDataStream, u16)] {
+        match self {
+            Self::Position => &[(MavDataStream::MAV_DATA_STREAM_POSITION, 5)],
+            Self::Attitude => &[(MavDataStream::MAV_DATA_STREAM_EXTRA1, 10)],
+            Self::All => &[(MavDataStream::MAV_DATA_STREAM_ALL, 4)],
+        }
+    }
+
+    /// Message ids and their rate in Hz
+    pub fn messages(&self) -> &'static [(u32, f32)] {
+        match self {
+            Self::Position => &[
+                (GLOBAL_POSITION_INT_DATA::ID, 5.0),
+                (LOCAL_POSITION_NED_DATA::ID, 5.0),
+                (GPS_RAW_INT_DATA::ID, 1.0),
+            ],
+            Self::Attitude => &[(ATTITUDE_DATA::ID, 10.0)],
+            Self::All => &[
+                (GLOBAL_POSITION_INT_DATA::ID, 4.0),
+                (LOCAL_POSITION_NED_DATA::ID, 4.0),
+                (GPS_RAW_INT_DATA::ID, 1.0),
+                (ATTITUDE_DATA::ID, 10.0),
+                (VFR_HUD_DATA::ID, 4.0),
+                (SYS_STATUS_DATA::ID, 1.0),
+                (BATTERY_STATUS_DATA::ID, 1.0),
+            ],
+        }
+    }
+}
+
+/// REQUEST_DATA_STREAM and MAV_CMD_SET_MESSAGE_INTERVAL messages enabling a profile
+pub fn telemetry_requests(
+    profile: TelemetryProfile,
+    target_system: u8,
+    target_component: u8,
+) -> Vec<MavMessage> {
+    let streams = profile.streams().iter().map(|&(stream, rate)| {
+        MavMessage::REQUEST_DATA_STREAM(REQUEST_DATA_STREAM_DATA {
+            req_message_rate: rate,
+            target_system,
+            target_component,
+            req_stream_id: stream as u8,
+            start_stop: 1,
+        })
+    });
+
+    let intervals = profile.messages().iter().map(|&(id, rate)| {
+        MavMessage::COMMAND_LONG(COMMAND_LONG_DATA {
+            param1: id as f32,
+            param2: 1_000_000.0 / rate,
+            command: MavCmd::MAV_CMD_SET_MESSAGE_INTERVAL,
+            target_system,
+            target_component,
+            ..Default::default()
+        })
+    });
+
+    streams.chain(intervals).collect()
+}
+
+/// Ask a vehicle to stream the messages of a profile.
+///
+/// The requests are not acknowledged by every autopilot, so they are only sent; check that the
+/// messages arrive and repeat the call if needed.
+pub fn request_telemetry<M: Message, C: MavConnection<M> + ?Sized>(
+    connection: &C,
+    header: &MavHeader,
+    target_system: u8,
+    target_component: u8,
+    profile: TelemetryProfile,
+) -> Result<(), MessageWriteError> {
+    for msg in telemetry_requests(profile, target_system, target_component) {
+        // both messages are part of every message set including common
+        if let Some(msg) = msg.to_dialect::<M>() {
+            connection.send(header, &msg)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Conversions between MAVLink timestamps and host time.
+//!
+//! Messages carry either milliseconds since boot (`time_boot_ms`) or microseconds in a
+//! `time_usec` field which, according to the field documentation, holds either UNIX epoch time
+//! or time since boot, told apart by magnitude. [`BootClock`] relates the boot clock of a remote
+//! system to the host's `Instant` and `SystemTime` using its SYSTEM_TIME messages, and
+//! [`LocalClock`] produces timestamps for messages sent by the host.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::common::SYSTEM_TIME_DATA;
+
+/// `time_usec` values from this one on are UNIX epoch time (2001-09-09), smaller ones are time
+/// since boot
+pub const UNIX_USEC_THRESHOLD: u64 = 1_000_000_000_000_000;
+
+/// A `time_usec` value with its inferred format
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Timestamp {
+    SinceBoot(Duration),
+    Unix(SystemTime),
+}
+
+impl Timestamp {
+    /// Interpret a `time_usec` field, 0 means the sender has no time
+    pub fn from_usec(time_usec: u64) -> Option<Self> {
+        match time_usec {
+            0 => None,
+            usec if usec >= UNIX_USEC_THRESHOLD => Some(Self::Unix(usec_to_system_time(usec))),
+            usec => Some(Self::SinceBoot(Duration::from_micros(usec))),
+        }
+    }
+
+    pub fn from_boot_ms(time_boot_ms: u32) -> Self {
+        Self::SinceBoot(Duration::from_millis(u64::from(time_boot_ms)))
+    }
+}
+
+/// Microseconds since the UNIX epoch, 0 for times before it
+pub fn system_time_to_usec(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+pub fn usec_to_system_time(time_unix_usec: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(time_unix_usec)
+}
+
+/// Relation between the boot clock of a remote system and the host clocks.
+///
+/// The link latency is not compensated for, use the `timesync` module where better precision
+/// is needed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BootClock {
+    /// Host instant at which the remote system booted
+    boot_instant: Instant,
+    /// Wall clock time at which the remote system booted, if it knows the time
+    boot_time: Option<SystemTime>,
+}
+
+impl BootClock {
+    /// Clock from a message with a `time_boot_ms` received at `received`
+    pub fn from_boot_ms(time_boot_ms: u32, received: Instant) -> Self {
+        let since_boot = Duration::from_millis(u64::from(time_boot_ms));
+        Self {
+            boot_instant: received.checked_sub(since_boot).unwrap_or(received),
+            boot_time: None,
+        }
+    }
+
+    /// Clock from a SYSTEM_TIME received at `received`
+    pub fn from_system_time(system_time: &SYSTEM_TIME_DATA, received: Instant) -> Self {
+        let mut clock = Self::from_boot_ms(system_time.time_boot_ms, received);
+        clock.update(system_time, received);
+        clock
+    }
+
+    /// Refine the clock with a new SYSTEM_TIME
+    pub fn update(&mut self, system_time: &SYSTEM_TIME_DATA, received: Instant) {
+        let since_boot = Duration::from_millis(u64::from(system_time.time_boot_ms));
+        self.boot_instant = received.checked_sub(since_boot).unwrap_or(received);
+        if system_time.time_unix_usec != 0 {
+            self.boot_time =
+                usec_to_system_time(system_time.time_unix_usec).checked_sub(since_boot);
+        }
+    }
+
+    /// Host instant of a remote `time_boot_ms`
+    pub fn boot_ms_to_instant(&self, time_boot_ms: u32) -> Instant {
+        self.boot_instant + Duration::from_millis(u64::from(time_boot_ms))
+    }
+
+    /// Wall clock time of a remote `time_boot_ms`, if the remote system knows the time
+    pub fn boot_ms_to_system_time(&self, time_boot_ms: u32) -> Option<SystemTime> {
+        Some(self.boot_time? + Duration::from_millis(u64::from(time_boot_ms)))
+    }
+
+    /// Wall clock time of a remote timestamp
+    pub fn to_system_time(&self, timestamp: Timestamp) -> Option<SystemTime> {
+        match timestamp {
+            Timestamp::Unix(time) => Some(time),
+            Timestamp::SinceBoot(since_boot) => Some(self.boot_time? + since_boot),
+        }
+    }
+
+    /// Host instant of a remote timestamp
+    pub fn to_instant(&self, timestamp: Timestamp) -> Option<Instant> {
+        match timestamp {
+            Timestamp::SinceBoot(since_boot) => Some(self.boot_instant + since_boot),
+            Timestamp::Unix(time) => {
+                let since_boot = time.duration_since(self.boot_time?).ok()?;
+                Some(self.boot_instant + since_boot)
+            }
+        }
+    }
+
+    /// Remote `time_boot_ms` at a host instant
+    pub fn boot_ms_at(&self, instant: Instant) -> u32 {
+        instant
+            .saturating_duration_since(self.boot_instant)
+            .as_millis() as u32
+    }
+}
+
+/// Timestamps for messages sent by the host
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LocalClock {
+    boot: Instant,
+}
+
+impl Default for LocalClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalClock {
+    /// Clock counting from now
+    pub fn new() -> Self {
+        Self::with_boot(Instant::now())
+    }
+
+    pub fn with_boot(boot: Instant) -> Self {
+        Self { boot }
+    }
+
+    /// Value for `time_boot_ms` fields, wraps after 49 days
+    pub fn time_boot_ms(&self) -> u32 {
+        self.boot.elapsed().as_millis() as u32
+    }
+
+    /// Value for `time_usec` fields holding UNIX epoch time
+    pub fn time_unix_usec(&self) -> u64 {
+        system_time_to_usec(SystemTime::now())
+    }
+
+    /// SYSTEM_TIME announcing this clock
+    pub fn system_time(&self) -> SYSTEM_TIME_DATA {
+        SYSTEM_TIME_DATA {
+            time_unix_usec: self.time_unix_usec(),
+            time_boot_ms: self.time_boot_ms(),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Clock synchronization with the [TIMESYNC](https://mavlink.io/en/messages/common.html#TIMESYNC)
+//! exchange.
+//!
+//! [`TimeSync`] answers TIMESYNC requests from other systems and, from the answers to its own
+//! requests, keeps a filtered estimate of the clock offset of every system it talks to. All
+//! methods take the local time explicitly, so the estimator can be driven by any clock.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::common::{MavMessage, TIMESYNC_DATA};
+use crate::MavHeader;
+
+/// Round trips slower than this are too imprecise to be used
+pub const DEFAULT_MAX_RTT_NS: i64 = 50_000_000;
+
+/// Weight of a new sample once the estimate has converged
+pub const DEFAULT_ALPHA: f64 = 0.05;
+
+/// Filtered clock offset of a remote system
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OffsetEstimate {
+    /// Remote time minus local time, in nanoseconds
+    pub offset_ns: i64,
+    /// Round trip time of the last accepted sample, in nanoseconds
+    pub rtt_ns: i64,
+    /// Number of accepted samples
+    pub samples: u32,
+}
+
+/// Initiator and responder of the TIMESYNC exchange
+#[derive(Debug, Clone)]
+pub struct TimeSync {
+    epoch: Instant,
+    max_rtt_ns: i64,
+    alpha: f64,
+    /// Timestamp of the last request, answers to older requests are ignored
+    pending: Option<i64>,
+    estimates: HashMap<u8, OffsetEstimate>,
+}
+
+impl Default for TimeSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSync {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            max_rtt_ns: DEFAULT_MAX_RTT_NS,
+            alpha: DEFAULT_ALPHA,
+            pending: None,
+            estimates: HashMap::new(),
+        }
+    }
+
+    pub fn with_max_rtt_ns(mut self, max_rtt_ns: i64) -> Self {
+        self.max_rtt_ns = max_rtt_ns;
+        self
+    }
+
+    /// Weight of new samples in the exponential filter, between 0 and 1
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Local monotonic time in nanoseconds since this `TimeSync` was created
+    pub fn now_ns(&self) -> i64 {
+        self.epoch.elapsed().as_nanos() as i64
+    }
+
+    /// TIMESYNC request to send, `target_system` 0 asks every system
+    pub fn request(&mut self, now_ns: i64, target_system: u8, target_component: u8) -> MavMessage {
+        self.pending = Some(now_ns);
+        #[cfg(not(feature = "emit-extensions"))]
+        let _ = (target_system, target_component);
+        MavMessage::TIMESYNC(TIMESYNC_DATA {
+            tc1: 0,
+            ts1: now_ns,
+            #[cfg(feature = "emit-extensions")]
+            target_system,
+            #[cfg(feature = "emit-extensions")]
+            target_component,
+        })
+    }
+
+    /// Feed a received message.
+    ///
+    /// Requests are answered with the returned message, answers to our own requests update the
+    /// estimate of the sender.
+    pub fn handle(
+        &mut self,
+        now_ns: i64,
+        header: &MavHeader,
+        msg: &MavMessage,
+    ) -> Option<MavMessage> {
+        let data = match msg {
+            MavMessage::TIMESYNC(data) => data,
+            _ => return None,
+        };
+
+        if data.tc1 == 0 {
+            return Some(MavMessage::TIMESYNC(TIMESYNC_DATA {
+                tc1: now_ns,
+                ts1: data.ts1,
+                #[cfg(feature = "emit-extensions")]
+                target_system: header.system_id,
+                #[cfg(feature = "emit-extensions")]
+                target_component: header.component_id,
+            }));
+        }
+
+        if self.pending == Some(data.ts1) {
+            self.add_sample(header, now_ns, data.ts1, data.tc1);
+        }
+        None
+    }
+
+    fn add_sample(&mut self, header: &MavHeader, now_ns: i64, sent_ns: i64, remote_ns: i64) {
+        let rtt_ns = now_ns - sent_ns;
+        if rtt_ns < 0 || rtt_ns > self.max_rtt_ns {
+            return;
+        }
+        // assume the answer was produced half way through the round trip
+        let offset_ns = remote_ns - (sent_ns + rtt_ns / 2);
+
+        let alpha = self.alpha;
+        let estimate = self
+            .estimates
+            .entry(header.system_id)
+            .or_insert(OffsetEstimate {
+                offset_ns,
+                rtt_ns,
+                samples: 0,
+            });
+        estimate.samples += 1;
+        // plain average of the first samples, so the estimate converges quickly
+        let weight = alpha.max(1.0 / f64::from(estimate.samples));
+        estimate.offset_ns += ((offset_ns - estimate.offset_ns) as f64 * weight) as i64;
+        estimate.rtt_ns = rtt_ns;
+    }
+
+    /// Clock offset estimate of a system
+    pub fn estimate(&self, system_id: u8) -> Option<OffsetEstimate> {
+        self.estimates.get(&system_id).copied()
+    }
+
+    /// Convert a timestamp of a remote system into local time
+    pub fn to_local_ns(&self, system_id: u8, remote_ns: i64) -> Option<i64> {
+        self.estimate(system_id)
+            .map(|estimate| remote_ns - estimate.offset_ns)
+    }
+
+    /// Forget all estimates, e.g. after a remote system rebooted
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.estimates.clear();
+    }
+}
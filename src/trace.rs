@@ -0,0 +1,35 @@
+//! Events for the `tracing` crate, compiled out without the `tracing` feature.
+//!
+//! The macros take the arguments of their `tracing` counterparts. Without the feature the
+//! arguments aren't evaluated, so they must not have side effects.
+
+macro_rules! trace {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    }};
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    }};
+}
+
+// only used by the `std` clients and connections
+#[allow(unused_macros)]
+macro_rules! info {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::info!($($arg)*);
+    }};
+}
+
+#[allow(unused_macros)]
+macro_rules! warn {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    }};
+}
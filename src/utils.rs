@@ -20,12 +20,15 @@ pub(crate) fn remove_trailing_zeroes(data: &mut [u8]) -> usize {
 
 /// A trait very similar to `Default` but is only implemented for the equivalent Rust types to
 /// `MavType`s. This is only needed because rust doesn't currently implement `Default` for arrays
-/// of all sizes. In particular this trait is only ever used when the "serde" feature is enabled.
+/// of all sizes. In particular this trait is only ever used for the extension fields when the
+/// "serde" feature is enabled.
 /// For more information, check out [this issue](https://users.rust-lang.org/t/issue-for-derives-for-arrays-greater-than-size-32/59055/3).
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 pub(crate) trait RustDefault: Copy {
     fn rust_default() -> Self;
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl<T: RustDefault, const N: usize> RustDefault for [T; N] {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -34,6 +37,7 @@ impl<T: RustDefault, const N: usize> RustDefault for [T; N] {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for u8 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -41,6 +45,7 @@ impl RustDefault for u8 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for i8 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -48,6 +53,7 @@ impl RustDefault for i8 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for u16 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -55,6 +61,7 @@ impl RustDefault for u16 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for i16 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -62,6 +69,7 @@ impl RustDefault for i16 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for u32 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -69,6 +77,7 @@ impl RustDefault for u32 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for i32 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -76,6 +85,7 @@ impl RustDefault for i32 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for u64 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -83,6 +93,7 @@ impl RustDefault for u64 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for i64 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -90,6 +101,7 @@ impl RustDefault for i64 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for char {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -97,6 +109,7 @@ impl RustDefault for char {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for f32 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -104,6 +117,7 @@ impl RustDefault for f32 {
     }
 }
 
+#[cfg(all(feature = "serde", feature = "emit-extensions"))]
 impl RustDefault for f64 {
     #[inline(always)]
     fn rust_default() -> Self {
@@ -0,0 +1,15 @@
+//! Lua dissector for [Wireshark](https://www.wireshark.org), generated from the definitions of
+//! the enabled dialects.
+//!
+//! The dissector shows the header, the fields of every known message with their units and the
+//! names of enum values. To use it, write [`DISSECTOR`] into the Wireshark plugin directory, e.g.
+//!
+//! ```no_run
+//! std::fs::write("mavlink.lua", mavlink::wireshark::DISSECTOR).unwrap();
+//! ```
+//!
+//! UDP traffic on the ports 14550 to 14580 is decoded, other ports can be added in Wireshark
+//! with "Decode As".
+
+/// Source of the dissector
+pub const DISSECTOR: &str = include_str!(concat!(env!("OUT_DIR"), "/mavlink.lua"));
@@ -0,0 +1,286 @@
+//! Bridge between MAVLink connections and [ZeroMQ](https://zeromq.org) sockets.
+//!
+//! A [`ZmqPublisher`] republishes messages on a PUB socket, as raw frames or as JSON, with the
+//! message name as topic so that subscribers can filter on it. A [`ZmqReceiver`] accepts raw
+//! frames or JSON on a SUB or PULL socket. JSON messages have the format
+//! `{"header": {..}, "message": {"type": "..", ..}}`, the header may be left out.
+//!
+//! [`bridge`] connects both to a MAVLink connection:
+//!
+//! ```no_run
+//! use mavlink::zmq::{ZmqEncoding, ZmqPublisher, ZmqReceiver};
+//!
+//! let context = zmq::Context::new();
+//! let publisher = ZmqPublisher::bind(&context, "tcp://*:5600", ZmqEncoding::Json).unwrap();
+//! let receiver = ZmqReceiver::pull(&context, "tcp://*:5601").unwrap();
+//! let connection = mavlink::connect::<mavlink::common::MavMessage>("udpin:0.0.0.0:14550").unwrap();
+//! mavlink::zmq::bridge(connection.into(), publisher, receiver).unwrap();
+//! ```
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MessageReadError, MessageWriteError, ProtocolError};
+use crate::{
+    read_versioned_msg, write_versioned_msg, MavConnection, MavHeader, MavlinkVersion, Message,
+    MAV_STX,
+};
+
+/// Format of the published messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZmqEncoding {
+    /// MAVLink frames, as sent on the wire
+    Raw,
+    /// `{"header": {..}, "message": {..}}`
+    Json,
+}
+
+#[derive(Debug)]
+pub enum ZmqError {
+    Zmq(::zmq::Error),
+    Protocol(ProtocolError),
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for ZmqError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Zmq(e) => write!(f, "ZeroMQ error: {e}"),
+            Self::Protocol(e) => write!(f, "{e}"),
+            Self::Json(e) => write!(f, "Invalid JSON message: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ZmqError {}
+
+impl From<::zmq::Error> for ZmqError {
+    fn from(e: ::zmq::Error) -> Self {
+        Self::Zmq(e)
+    }
+}
+
+impl From<ProtocolError> for ZmqError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+impl From<MessageReadError> for ZmqError {
+    fn from(e: MessageReadError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+impl From<MessageWriteError> for ZmqError {
+    fn from(e: MessageWriteError) -> Self {
+        Self::Protocol(e.into())
+    }
+}
+
+impl From<serde_json::Error> for ZmqError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonMessage<'a, M> {
+    header: &'a MavHeader,
+    message: &'a M,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "M: DeserializeOwned")]
+struct ReceivedJsonMessage<M> {
+    header: Option<MavHeader>,
+    message: M,
+}
+
+/// Publishes messages on a PUB socket
+pub struct ZmqPublisher {
+    socket: ::zmq::Socket,
+    encoding: ZmqEncoding,
+    version: MavlinkVersion,
+}
+
+impl ZmqPublisher {
+    /// Bind a PUB socket to `endpoint`, e.g. `tcp://*:5600`
+    pub fn bind(
+        context: &::zmq::Context,
+        endpoint: &str,
+        encoding: ZmqEncoding,
+    ) -> Result<Self, ZmqError> {
+        let socket = context.socket(::zmq::PUB)?;
+        socket.bind(endpoint)?;
+        Ok(Self {
+            socket,
+            encoding,
+            version: MavlinkVersion::V2,
+        })
+    }
+
+    /// Protocol version of raw frames, MAVLink 2 by default
+    pub fn with_version(mut self, version: MavlinkVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Publish a message, the message name is sent as first part of a two part message
+    pub fn publish<M: Message + Serialize>(
+        &self,
+        header: &MavHeader,
+        msg: &M,
+    ) -> Result<(), ZmqError> {
+        let payload = match self.encoding {
+            ZmqEncoding::Raw => {
+                let mut frame = Vec::new();
+                write_versioned_msg(&mut frame, self.version, *header, msg)?;
+                frame
+            }
+            ZmqEncoding::Json => serde_json::to_vec(&JsonMessage {
+                header,
+                message: msg,
+            })?,
+        };
+        self.socket
+            .send_multipart([msg.message_name().as_bytes(), &payload[..]], 0)?;
+        Ok(())
+    }
+}
+
+/// Receives messages on a SUB or PULL socket
+pub struct ZmqReceiver {
+    socket: ::zmq::Socket,
+    header: MavHeader,
+}
+
+impl ZmqReceiver {
+    /// Connect a SUB socket to `endpoint`, subscribed to the messages named in `topics`, or
+    /// to all messages if it is empty
+    pub fn subscribe(
+        context: &::zmq::Context,
+        endpoint: &str,
+        topics: &[&str],
+    ) -> Result<Self, ZmqError> {
+        let socket = context.socket(::zmq::SUB)?;
+        socket.connect(endpoint)?;
+        if topics.is_empty() {
+            socket.set_subscribe(b"")?;
+        }
+        for topic in topics {
+            socket.set_subscribe(topic.as_bytes())?;
+        }
+        Ok(Self::new(socket))
+    }
+
+    /// Bind a PULL socket to `endpoint`, e.g. `tcp://*:5601`
+    pub fn pull(context: &::zmq::Context, endpoint: &str) -> Result<Self, ZmqError> {
+        let socket = context.socket(::zmq::PULL)?;
+        socket.bind(endpoint)?;
+        Ok(Self::new(socket))
+    }
+
+    fn new(socket: ::zmq::Socket) -> Self {
+        Self {
+            socket,
+            header: MavHeader::default(),
+        }
+    }
+
+    /// Header of JSON messages which do not bring their own
+    pub fn with_header(mut self, header: MavHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Wait up to `timeout` for a message, or indefinitely if it is `None`.
+    ///
+    /// The last part of a multipart message is taken as payload, so messages published by a
+    /// [`ZmqPublisher`] can be received as well as single part ones.
+    pub fn receive<M: Message + DeserializeOwned>(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(MavHeader, M)>, ZmqError> {
+        let timeout = timeout.map_or(-1, |timeout| timeout.as_millis() as i64);
+        if self.socket.poll(::zmq::POLLIN, timeout)? == 0 {
+            return Ok(None);
+        }
+        let mut parts = self.socket.recv_multipart(0)?;
+        let payload = parts.pop().unwrap_or_default();
+        match payload.first() {
+            Some(b'{') => {
+                let received: ReceivedJsonMessage<M> = serde_json::from_slice(&payload)?;
+                Ok(Some((
+                    received.header.unwrap_or(self.header),
+                    received.message,
+                )))
+            }
+            Some(&MAV_STX) => Ok(Some(read_versioned_msg(
+                &mut &payload[..],
+                MavlinkVersion::V1,
+            )?)),
+            _ => Ok(Some(read_versioned_msg(
+                &mut &payload[..],
+                MavlinkVersion::V2,
+            )?)),
+        }
+    }
+}
+
+/// Publish the messages received on `connection` on a separate thread, and send the messages
+/// of `receiver` to it, until the connection is closed or a socket fails
+pub fn bridge<M>(
+    connection: Arc<dyn MavConnection<M> + Send + Sync>,
+    publisher: ZmqPublisher,
+    receiver: ZmqReceiver,
+) -> Result<(), ZmqError>
+where
+    M: Message + Serialize + DeserializeOwned + Send + 'static,
+{
+    let running = Arc::new(AtomicBool::new(true));
+    let publishing = thread::spawn({
+        let connection = connection.clone();
+        let running = running.clone();
+        move || {
+            let result = loop {
+                match connection.recv() {
+                    Ok((header, msg)) => {
+                        if let Err(e) = publisher.publish(&header, &msg) {
+                            break Err(e);
+                        }
+                    }
+                    Err(MessageReadError::Io(e)) => {
+                        if e.kind() != io::ErrorKind::WouldBlock {
+                            break Ok(());
+                        }
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                    // messages that could not be parsed are not published
+                    Err(_) => {}
+                }
+            };
+            running.store(false, Ordering::SeqCst);
+            result
+        }
+    });
+
+    while running.load(Ordering::SeqCst) {
+        match receiver.receive::<M>(Some(Duration::from_millis(100))) {
+            Ok(Some((header, msg))) => {
+                connection.send(&header, &msg)?;
+            }
+            Ok(None) => {}
+            Err(ZmqError::Zmq(e)) => return Err(ZmqError::Zmq(e)),
+            // invalid messages are dropped
+            Err(_) => {}
+        }
+    }
+    publishing.join().unwrap_or(Ok(()))
+}
@@ -0,0 +1,132 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod battery_tests {
+    use mavlink::battery::{BatterySource, PowerEvent, PowerMonitor};
+    use mavlink::common::{
+        MavBatteryFunction, MavMessage, MavPowerStatus, BATTERY_STATUS_DATA, POWER_STATUS_DATA,
+        SYS_STATUS_DATA,
+    };
+
+    fn sys_status(voltage_battery: u16) -> MavMessage {
+        MavMessage::SYS_STATUS(SYS_STATUS_DATA {
+            voltage_battery,
+            current_battery: 1250,
+            battery_remaining: 80,
+            ..Default::default()
+        })
+    }
+
+    fn battery_status(id: u8, voltages: [u16; 10]) -> MavMessage {
+        MavMessage::BATTERY_STATUS(BATTERY_STATUS_DATA {
+            id,
+            voltages,
+            current_battery: -1,
+            current_consumed: 1500,
+            energy_consumed: -1,
+            temperature: i16::MAX,
+            battery_remaining: -1,
+            battery_function: MavBatteryFunction::MAV_BATTERY_FUNCTION_ALL,
+            ..Default::default()
+        })
+    }
+
+    fn approx(value: Option<f32>, expected: f32) -> bool {
+        value.map_or(false, |value| (value - expected).abs() < 1e-3)
+    }
+
+    #[test]
+    pub fn test_sys_status() {
+        let mut monitor = PowerMonitor::new();
+
+        match monitor.update(&sys_status(16_800)) {
+            Some(PowerEvent::BatteryAdded(battery)) => {
+                assert_eq!(battery.id, 0);
+                assert_eq!(battery.source, BatterySource::SysStatus);
+                assert!(approx(battery.voltage, 16.8));
+                assert!(approx(battery.current, 12.5));
+                assert_eq!(battery.remaining, Some(80));
+            }
+            event => panic!("expected a new battery, got {:?}", event),
+        }
+        assert_eq!(monitor.update(&sys_status(16_800)), None);
+        assert!(matches!(
+            monitor.update(&sys_status(16_700)),
+            Some(PowerEvent::BatteryChanged(_))
+        ));
+        assert!(monitor.update(&sys_status(u16::MAX)).is_some());
+        assert_eq!(monitor.battery(0).unwrap().voltage, None);
+    }
+
+    #[test]
+    pub fn test_battery_status() {
+        let mut monitor = PowerMonitor::new();
+        monitor.update(&sys_status(16_800));
+
+        let mut voltages = [u16::MAX; 10];
+        voltages[..4].copy_from_slice(&[4200, 4190, 4210, 4200]);
+        match monitor.update(&battery_status(0, voltages)) {
+            Some(PowerEvent::BatteryChanged(battery)) => {
+                assert_eq!(battery.source, BatterySource::BatteryStatus);
+                assert_eq!(battery.cell_voltages.len(), 4);
+                assert!(approx(battery.voltage, 16.8));
+                assert_eq!(battery.current, None);
+                assert_eq!(battery.remaining, None);
+                assert_eq!(battery.temperature, None);
+                assert!(approx(battery.consumed, 1500.0));
+            }
+            event => panic!("expected the battery to change, got {:?}", event),
+        }
+
+        // the detailed status is not overwritten by SYS_STATUS
+        assert_eq!(monitor.update(&sys_status(15_000)), None);
+
+        // a battery without cell monitoring reports the total voltage as first cell
+        let mut voltages = [u16::MAX; 10];
+        voltages[0] = 50_400;
+        match monitor.update(&battery_status(1, voltages)) {
+            Some(PowerEvent::BatteryAdded(battery)) => {
+                assert!(approx(battery.voltage, 50.4));
+                assert!(battery.cell_voltages.is_empty());
+            }
+            event => panic!("expected a new battery, got {:?}", event),
+        }
+        assert_eq!(monitor.batteries().count(), 2);
+    }
+
+    #[cfg(feature = "emit-extensions")]
+    #[test]
+    pub fn test_extension_cells() {
+        let mut monitor = PowerMonitor::new();
+        let msg = MavMessage::BATTERY_STATUS(BATTERY_STATUS_DATA {
+            voltages: [3700; 10],
+            voltages_ext: [3700, 3700, 0, 0],
+            ..Default::default()
+        });
+        monitor.update(&msg);
+        let battery = monitor.battery(0).unwrap();
+        assert_eq!(battery.cell_voltages.len(), 12);
+        assert!(approx(battery.voltage, 44.4));
+    }
+
+    #[test]
+    pub fn test_power_status() {
+        let mut monitor = PowerMonitor::new();
+        let msg = MavMessage::POWER_STATUS(POWER_STATUS_DATA {
+            Vcc: 5020,
+            Vservo: 0,
+            flags: MavPowerStatus::MAV_POWER_STATUS_USB_CONNECTED,
+        });
+        match monitor.update(&msg) {
+            Some(PowerEvent::PowerChanged(power)) => {
+                assert!((power.vcc - 5.02).abs() < 1e-3);
+                assert!(power
+                    .flags
+                    .contains(MavPowerStatus::MAV_POWER_STATUS_USB_CONNECTED));
+            }
+            event => panic!("expected a power change, got {:?}", event),
+        }
+        assert_eq!(monitor.update(&msg), None);
+        assert!(monitor.power().is_some());
+    }
+}
@@ -0,0 +1,41 @@
+#[cfg(all(feature = "std", feature = "common"))]
+mod builder_tests {
+    use mavlink::common::{
+        MavCmd, MavFrame, COMMAND_INT_DATA, GLOBAL_POSITION_INT_DATA, HEARTBEAT_DATA,
+    };
+
+    #[test]
+    pub fn test_builder_with_targets() {
+        let command = COMMAND_INT_DATA::builder(1, 190)
+            .with_command(MavCmd::MAV_CMD_DO_REPOSITION)
+            .with_frame(MavFrame::MAV_FRAME_GLOBAL_INT)
+            .with_param1(-1.0)
+            .with_x(473_977_418)
+            .with_y(85_455_939)
+            .with_z(500.0);
+
+        assert_eq!(command.target_system, 1);
+        assert_eq!(command.target_component, 190);
+        assert_eq!(command.command, MavCmd::MAV_CMD_DO_REPOSITION);
+        assert_eq!(command.x, 473_977_418);
+        // fields without setter call keep their default
+        assert_eq!(command.param2, 0.0);
+        assert_eq!(command.current, 0);
+    }
+
+    #[test]
+    pub fn test_builder_without_targets() {
+        let position = GLOBAL_POSITION_INT_DATA::builder()
+            .with_lat(473_977_418)
+            .with_hdg(u16::MAX);
+        assert_eq!(
+            position,
+            GLOBAL_POSITION_INT_DATA {
+                lat: 473_977_418,
+                hdg: u16::MAX,
+                ..Default::default()
+            }
+        );
+        assert_eq!(HEARTBEAT_DATA::builder(), HEARTBEAT_DATA::default());
+    }
+}
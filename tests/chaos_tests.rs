@@ -0,0 +1,276 @@
+//! Connection-level chaos harness: real TCP and UDP connections are fed a random mix of valid
+//! frames, corrupted and truncated frames and garbage, and the TCP peer disconnects at random
+//! points. The receiving connections must neither panic nor hang, and the heap must not grow
+//! with the amount of traffic.
+//!
+//! `MAVLINK_CHAOS_ROUNDS` scales the run and `MAVLINK_CHAOS_SEED` selects the traffic:
+//! ```sh
+//! MAVLINK_CHAOS_ROUNDS=1000 cargo test --release --test chaos_tests
+//! ```
+
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "tcp", feature = "udp", feature = "common"))]
+mod chaos_tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::env;
+    use std::io::Write;
+    use std::net::{TcpStream, UdpSocket};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::MavMessage;
+    use mavlink::error::MessageReadError;
+    use mavlink::{write_versioned_msg, MavHeader, MavlinkVersion, Message};
+
+    const TCP_ADDRESS: &str = "127.0.0.1:14590";
+    const UDP_ADDRESS: &str = "127.0.0.1:14591";
+
+    /// Header of the frame ending a UDP round, random frames never use system 255
+    const SENTINEL: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 255,
+        sequence: 0,
+    };
+
+    /// Allocator keeping track of the live and peak heap size
+    struct CountingAllocator;
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(live, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// xorshift64*, the traffic only has to be reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+        env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    struct Traffic {
+        rng: Rng,
+        /// Ids of all messages of the message set
+        ids: Vec<u32>,
+    }
+
+    impl Traffic {
+        fn new(seed: u64) -> Self {
+            let ids = (0..=u16::MAX.into())
+                .filter(|id| MavMessage::default_message_from_id(*id).is_ok())
+                .collect();
+            Self {
+                // the state must not be zero
+                rng: Rng(seed ^ 0x9e37_79b9_7f4a_7c15),
+                ids,
+            }
+        }
+
+        /// Random message, with a random payload where that parses
+        fn message(&mut self) -> MavMessage {
+            let id = self.ids[self.rng.below(self.ids.len())];
+            let payload = self.rng.bytes(255);
+            MavMessage::parse(MavlinkVersion::V2, id, &payload)
+                .or_else(|_| MavMessage::default_message_from_id(id))
+                .unwrap()
+        }
+
+        fn frame(&mut self, version: MavlinkVersion) -> Vec<u8> {
+            let header = MavHeader {
+                system_id: 1 + self.rng.below(254) as u8,
+                component_id: self.rng.next() as u8,
+                sequence: self.rng.next() as u8,
+            };
+            // MAVLink 1 only carries the first 256 ids
+            let msg = loop {
+                let msg = self.message();
+                if version == MavlinkVersion::V2 || msg.message_id() <= 255 {
+                    break msg;
+                }
+            };
+            let mut frame = Vec::new();
+            write_versioned_msg(&mut frame, version, header, &msg).unwrap();
+            frame
+        }
+
+        /// A valid MAVLink 2 frame or one of the ways a frame can go wrong
+        fn chunk(&mut self) -> Vec<u8> {
+            match self.rng.below(8) {
+                0..=2 => self.frame(MavlinkVersion::V2),
+                // frames of the other version are garbage to a MAVLink 2 connection
+                3 => self.frame(MavlinkVersion::V1),
+                4 => {
+                    let mut frame = self.frame(MavlinkVersion::V2);
+                    let index = self.rng.below(frame.len());
+                    frame[index] ^= 1 << self.rng.below(8);
+                    frame
+                }
+                5 => {
+                    let mut frame = self.frame(MavlinkVersion::V2);
+                    frame.truncate(self.rng.below(frame.len()));
+                    frame
+                }
+                // a start marker followed by an arbitrary header, e.g. the signed flag
+                6 => {
+                    let mut chunk = vec![mavlink::MAV_STX_V2];
+                    let len = self.rng.below(300);
+                    chunk.extend(self.rng.bytes(len));
+                    chunk
+                }
+                _ => {
+                    let len = self.rng.below(300);
+                    self.rng.bytes(len)
+                }
+            }
+        }
+    }
+
+    /// Receive until the peer disconnects, returning the number of valid messages
+    fn receive_tcp() -> usize {
+        let connection = mavlink::connect::<MavMessage>(&format!("tcpin:{TCP_ADDRESS}")).unwrap();
+        let mut received = 0;
+        loop {
+            match connection.recv() {
+                Ok(_) => received += 1,
+                Err(MessageReadError::Parse(_)) => {}
+                Err(MessageReadError::Io(_)) => return received,
+            }
+        }
+    }
+
+    fn tcp_round(traffic: &mut Traffic, chunks: usize) -> usize {
+        let receiver = thread::spawn(receive_tcp);
+        let mut stream = loop {
+            match TcpStream::connect(TCP_ADDRESS) {
+                Ok(stream) => break stream,
+                Err(_) => thread::sleep(Duration::from_millis(1)),
+            }
+        };
+
+        // disconnect after a random number of chunks, possibly in the middle of one
+        let disconnect_after = traffic.rng.below(chunks + 1);
+        for _ in 0..disconnect_after {
+            stream.write_all(&traffic.chunk()).unwrap();
+        }
+        let chunk = traffic.chunk();
+        let cut = traffic.rng.below(chunk.len() + 1);
+        stream.write_all(&chunk[..cut]).unwrap();
+        drop(stream);
+
+        receiver.join().expect("TCP receiver panicked")
+    }
+
+    fn udp_round(traffic: &mut Traffic, chunks: usize) -> usize {
+        let connection = mavlink::connect::<MavMessage>(&format!("udpin:{UDP_ADDRESS}")).unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+        let receiver = thread::spawn({
+            let done = done.clone();
+            move || {
+                let mut received = 0;
+                loop {
+                    match connection.recv() {
+                        Ok((header, _)) if header.system_id == SENTINEL.system_id => break,
+                        Ok(_) => received += 1,
+                        Err(MessageReadError::Parse(_)) => {}
+                        Err(MessageReadError::Io(e)) => panic!("UDP receive failed: {}", e),
+                    }
+                }
+                done.store(true, Ordering::Relaxed);
+                received
+            }
+        });
+
+        // every datagram stands on its own, so the socket may as well change between them
+        let mut socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..chunks {
+            if traffic.rng.below(16) == 0 {
+                socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            }
+            socket.send_to(&traffic.chunk(), UDP_ADDRESS).unwrap();
+        }
+
+        let mut sentinel = Vec::new();
+        write_versioned_msg(
+            &mut sentinel,
+            MavlinkVersion::V2,
+            SENTINEL,
+            &MavMessage::default_message_from_id(0).unwrap(),
+        )
+        .unwrap();
+        // a full socket buffer may drop datagrams
+        while !done.load(Ordering::Relaxed) {
+            socket.send_to(&sentinel, UDP_ADDRESS).unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        receiver.join().expect("UDP receiver panicked")
+    }
+
+    #[test]
+    pub fn test_chaos() {
+        let rounds: usize = env_or("MAVLINK_CHAOS_ROUNDS", 20);
+        let seed: u64 = env_or("MAVLINK_CHAOS_SEED", 0x5eed);
+        println!("chaos seed {seed:#x}, {rounds} rounds");
+        let mut traffic = Traffic::new(seed);
+
+        // the first round allocates what stays allocated, like the buffers of the test harness
+        tcp_round(&mut traffic, 50);
+        udp_round(&mut traffic, 50);
+        let baseline = LIVE.load(Ordering::Relaxed);
+        PEAK.store(baseline, Ordering::Relaxed);
+
+        let mut received = 0;
+        for _ in 0..rounds {
+            received += tcp_round(&mut traffic, 200);
+            received += udp_round(&mut traffic, 200);
+        }
+        assert!(received > 0);
+
+        let live = LIVE.load(Ordering::Relaxed);
+        let peak = PEAK.load(Ordering::Relaxed);
+        println!("{received} valid messages, heap {baseline} -> {live}, peak {peak}");
+        assert!(
+            live < baseline + 64 * 1024,
+            "heap grew from {} to {}",
+            baseline,
+            live
+        );
+        // a connection holds at most a 64kB datagram and a frame per direction
+        assert!(peak < baseline + 1024 * 1024, "heap peaked at {}", peak);
+    }
+}
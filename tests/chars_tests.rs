@@ -0,0 +1,43 @@
+mod chars_tests {
+    use mavlink::chars::CharArray;
+
+    #[test]
+    pub fn test_display() {
+        let mut text = [0u8; 10];
+        text[..5].copy_from_slice(b"Hello");
+        assert_eq!(CharArray::from(&text).to_string(), "Hello");
+        assert_eq!(CharArray(b"full array").to_string(), "full array");
+        assert_eq!(
+            CharArray(b"bad \xff byte\0\0").to_string(),
+            "bad \u{FFFD} byte"
+        );
+    }
+
+    #[test]
+    pub fn test_debug() {
+        assert_eq!(
+            format!("{:?}", CharArray(b"say \"hi\"\0\0")),
+            "\"say \\\"hi\\\"\""
+        );
+        assert_eq!(format!("{:?}", CharArray(b"\xffx")), "\"\u{FFFD}x\"");
+    }
+
+    #[cfg(feature = "common")]
+    #[test]
+    pub fn test_message_debug() {
+        use mavlink::common::{MavSeverity, STATUSTEXT_DATA};
+
+        let mut text = [0u8; 50];
+        text[..14].copy_from_slice(b"PreArm: no GPS");
+        // the extension fields are only generated with `emit-extensions`
+        #[allow(clippy::needless_update)]
+        let msg = STATUSTEXT_DATA {
+            severity: MavSeverity::MAV_SEVERITY_CRITICAL,
+            text,
+            ..Default::default()
+        };
+        let debug = format!("{msg:?}");
+        assert!(debug.starts_with("STATUSTEXT_DATA { severity: MAV_SEVERITY_CRITICAL,"));
+        assert!(debug.contains("text: \"PreArm: no GPS\""));
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(all(feature = "std", feature = "common"))]
+mod checked_getter_tests {
+    #[cfg(feature = "emit-extensions")]
+    use mavlink::common::{MavLandedState, AUTOPILOT_STATE_FOR_GIMBAL_DEVICE_DATA};
+    use mavlink::common::{
+        BATTERY_STATUS_DATA, COMMAND_INT_DATA, GLOBAL_POSITION_INT_DATA, SYS_STATUS_DATA,
+    };
+
+    #[test]
+    pub fn test_integer_sentinel() {
+        let mut position = GLOBAL_POSITION_INT_DATA {
+            hdg: u16::MAX,
+            ..Default::default()
+        };
+        assert_eq!(position.hdg_checked(), None);
+        position.hdg = 9_000;
+        assert_eq!(position.hdg_checked(), Some(9_000));
+
+        // -1 is the sentinel of the signed current
+        let sys_status = SYS_STATUS_DATA {
+            current_battery: -1,
+            ..Default::default()
+        };
+        assert_eq!(sys_status.current_battery_checked(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "emit-extensions")]
+    pub fn test_enum_sentinel() {
+        let mut state = AUTOPILOT_STATE_FOR_GIMBAL_DEVICE_DATA {
+            landed_state: MavLandedState::MAV_LANDED_STATE_UNDEFINED,
+            ..Default::default()
+        };
+        assert_eq!(state.landed_state_checked(), None);
+        state.landed_state = MavLandedState::MAV_LANDED_STATE_IN_AIR;
+        assert_eq!(
+            state.landed_state_checked(),
+            Some(MavLandedState::MAV_LANDED_STATE_IN_AIR)
+        );
+    }
+
+    #[test]
+    pub fn test_array_sentinel() {
+        let mut battery = BATTERY_STATUS_DATA {
+            voltages: [u16::MAX; 10],
+            ..Default::default()
+        };
+        assert_eq!(battery.voltages_checked(), None);
+
+        // unused cells are UINT16_MAX, the array is only invalid if all are
+        battery.voltages[0] = 4_200;
+        assert_eq!(battery.voltages_checked(), Some(&battery.voltages));
+    }
+
+    #[test]
+    pub fn test_nan_sentinel() {
+        let mut command = COMMAND_INT_DATA {
+            param1: f32::NAN,
+            ..Default::default()
+        };
+        assert_eq!(command.param1_checked(), None);
+        command.param1 = 1.5;
+        assert_eq!(command.param1_checked(), Some(1.5));
+    }
+}
@@ -0,0 +1,352 @@
+//! Tests of the code generator, built from the modules of the build script
+#![recursion_limit = "256"]
+
+#[allow(dead_code)]
+#[path = "../build/config.rs"]
+mod config;
+#[allow(dead_code)]
+#[path = "../build/log.rs"]
+mod log;
+#[allow(dead_code)]
+#[path = "../build/parser.rs"]
+mod parser;
+#[allow(dead_code)]
+#[cfg(feature = "uom")]
+#[path = "../build/uom.rs"]
+mod uom;
+#[allow(dead_code)]
+#[path = "../build/util.rs"]
+mod util;
+
+/// Write a definition file into a temporary directory, returning the directory
+fn write_definitions(file: &str, content: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("mavlink-codegen-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(file), content).unwrap();
+    dir
+}
+
+mod message_cfg {
+    use std::collections::HashSet;
+
+    use crate::config::{declared_features, parse_message_cfg, scan_predicate};
+
+    fn features() -> HashSet<String> {
+        declared_features(include_str!("../Cargo.toml"))
+    }
+
+    #[test]
+    pub fn test_declared_features() {
+        let features = features();
+        assert!(features.contains("ardupilotmega"));
+        assert!(features.contains("emit-description"));
+        // optional dependency without a `dep:` feature
+        assert!(features.contains("serial"));
+        assert!(!features.contains("crc-any"));
+    }
+
+    #[test]
+    pub fn test_scan_predicate() {
+        assert_eq!(
+            scan_predicate(
+                r#"all(mavlink_camera, not(feature = "embedded"), target_os = "linux")"#
+            ),
+            (vec!["mavlink_camera".into()], vec!["embedded".into()])
+        );
+        assert_eq!(
+            scan_predicate("any(a, not(b))"),
+            (vec!["a".into(), "b".into()], vec![])
+        );
+    }
+
+    #[test]
+    pub fn test_parse_message_cfg() {
+        let message_cfg = parse_message_cfg(
+            "# comment\n\nCAMERA_INFORMATION = mavlink_camera\nPING = not(feature = \"embedded\")\n",
+            &features(),
+        );
+        assert_eq!(message_cfg.len(), 2);
+        assert_eq!(message_cfg["CAMERA_INFORMATION"], "mavlink_camera");
+        assert_eq!(message_cfg["PING"], "not(feature = \"embedded\")");
+    }
+
+    #[test]
+    #[should_panic(expected = "\"camera\" is not a feature of mavlink")]
+    pub fn test_unknown_feature() {
+        parse_message_cfg("PING = feature = \"camera\"", &features());
+    }
+
+    #[test]
+    #[should_panic(expected = "\"camera-messages\" is not a cfg option name")]
+    pub fn test_feature_name_without_key() {
+        parse_message_cfg("PING = camera-messages", &features());
+    }
+}
+
+mod header {
+    use std::collections::HashSet;
+
+    use crate::parser::{parse_header_number, parse_profile};
+
+    #[test]
+    pub fn test_parse_header_number() {
+        assert_eq!(parse_header_number("3"), Ok(3));
+        assert_eq!(parse_header_number(" 255\n"), Ok(255));
+        assert!(parse_header_number("256").unwrap_err().contains("\"256\""));
+        assert!(parse_header_number("two").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "bad_version.xml: <version>: invalid value \"300\"")]
+    pub fn test_bad_version() {
+        let dir = crate::write_definitions(
+            "bad_version.xml",
+            "<?xml version=\"1.0\"?><mavlink><version>300</version></mavlink>",
+        );
+        parse_profile(&dir, &"bad_version.xml".to_string(), &mut HashSet::new());
+    }
+}
+
+mod entry_value {
+    use crate::parser::parse_entry_value;
+
+    #[test]
+    pub fn test_numbers() {
+        assert_eq!(parse_entry_value("42"), Ok(42));
+        assert_eq!(parse_entry_value("0x2A"), Ok(42));
+        assert_eq!(parse_entry_value("0X2a"), Ok(42));
+        assert_eq!(parse_entry_value("0b101010"), Ok(42));
+        assert_eq!(parse_entry_value("0B101010"), Ok(42));
+        assert_eq!(parse_entry_value(" 42 "), Ok(42));
+        assert_eq!(parse_entry_value("4294967295"), Ok(u32::MAX));
+    }
+
+    #[test]
+    pub fn test_digit_separators() {
+        assert_eq!(parse_entry_value("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse_entry_value("0xFFFF_FFFF"), Ok(u32::MAX));
+        assert_eq!(parse_entry_value("0b1000_0000"), Ok(128));
+    }
+
+    #[test]
+    pub fn test_shifts() {
+        assert_eq!(parse_entry_value("1 << 4"), Ok(16));
+        assert_eq!(parse_entry_value("1<<31"), Ok(1 << 31));
+        assert_eq!(parse_entry_value("0x3 << 0b10"), Ok(12));
+        assert_eq!(parse_entry_value("0xFFFF << 16"), Ok(0xFFFF_0000));
+    }
+
+    #[test]
+    pub fn test_negative_numbers() {
+        assert_eq!(parse_entry_value("-1"), Ok(u32::MAX));
+        assert_eq!(parse_entry_value("-0x10"), Ok(0xFFFF_FFF0));
+        assert_eq!(parse_entry_value("-2147483648"), Ok(0x8000_0000));
+        assert_eq!(parse_entry_value("-0"), Ok(0));
+    }
+
+    #[test]
+    pub fn test_overflow() {
+        assert!(parse_entry_value("4294967296").is_err());
+        assert!(parse_entry_value("0x1_0000_0000").is_err());
+        assert!(parse_entry_value("-2147483649")
+            .unwrap_err()
+            .contains("below the 32 bit minimum"));
+        assert!(parse_entry_value("1 << 32")
+            .unwrap_err()
+            .contains("does not fit in 32 bits"));
+        assert!(parse_entry_value("2 << 31").is_err());
+        assert!(parse_entry_value("0xFFFF << 17").is_err());
+    }
+
+    #[test]
+    pub fn test_malformed() {
+        for value in [
+            "",
+            "-",
+            "0x",
+            "0b",
+            "0b2",
+            "0xG",
+            "abc",
+            "1 <<",
+            "<< 2",
+            "1 << 2 << 3",
+            "1 < 2",
+            "--1",
+            "1.5",
+        ] {
+            assert!(
+                parse_entry_value(value).is_err(),
+                "{:?} should not parse",
+                value
+            );
+        }
+        assert!(parse_entry_value("1 <<")
+            .unwrap_err()
+            .ends_with("in \"1 <<\""));
+    }
+}
+
+mod naming {
+    use crate::config::{parse_naming, CodegenConfig};
+    use crate::log::BuildLog;
+    use crate::parser::generate;
+
+    const DEFINITIONS: &str = r#"<?xml version="1.0"?>
+<mavlink>
+  <enums>
+    <enum name="MAV_TYPE">
+      <entry value="0" name="MAV_TYPE_GENERIC"/>
+      <entry value="1" name="MAV_TYPE_FIXED_WING"/>
+    </enum>
+    <enum name="GPS_FIX_TYPE">
+      <entry value="0" name="GPS_FIX_TYPE_NO_GPS"/>
+    </enum>
+  </enums>
+  <messages>
+    <message id="0" name="HEARTBEAT">
+      <field type="uint8_t" name="type" enum="MAV_TYPE">Type</field>
+      <field type="uint8_t" name="fix_type" enum="GPS_FIX_TYPE">Fix</field>
+    </message>
+  </messages>
+</mavlink>"#;
+
+    /// Rust code generated from `DEFINITIONS` with the naming configuration `naming`
+    fn generate_with(file: &str, naming: &str) -> String {
+        let dir = crate::write_definitions(file, DEFINITIONS);
+        let config = CodegenConfig {
+            naming: parse_naming(naming),
+            ..Default::default()
+        };
+        let mut rust = Vec::new();
+        generate(
+            &dir,
+            &file.to_string(),
+            &mut rust,
+            &BuildLog::from_env(),
+            &config,
+        );
+        String::from_utf8(rust).unwrap()
+    }
+
+    #[test]
+    pub fn test_enum_name() {
+        let naming = parse_naming("acronyms = GPS\nMAV_TYPE = VehicleType");
+        assert_eq!(naming.enum_name("GPS_FIX_TYPE"), "GPSFixType");
+        assert_eq!(naming.enum_name("MAV_TYPE"), "VehicleType");
+        assert_eq!(naming.enum_name("MAV_CMD"), "MavCmd");
+        let naming = parse_naming("scheme = original");
+        assert_eq!(naming.enum_name("MAV_CMD"), "MAV_CMD");
+    }
+
+    #[test]
+    pub fn test_override_applied() {
+        let rust = generate_with("override.xml", "MAV_TYPE = VehicleType");
+        assert!(rust.contains("pub enum VehicleType {"));
+        assert!(rust.contains("pub mavtype : VehicleType ,"));
+        // the default name stays available
+        assert!(rust.contains("pub type MavType = VehicleType ;"));
+        assert!(!rust.contains("pub enum MavType"));
+        assert!(rust.contains("pub enum GpsFixType {"));
+    }
+
+    #[test]
+    #[should_panic(expected = "HEARTBEAT is a message, only enums can be renamed")]
+    pub fn test_message_override() {
+        generate_with("message_override.xml", "HEARTBEAT = Beat");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "MAV_TYPE_GENERIC is an entry of MAV_TYPE, only enums can be renamed"
+    )]
+    pub fn test_entry_override() {
+        generate_with("entry_override.xml", "MAV_TYPE_GENERIC = Generic");
+    }
+}
+
+mod links {
+    use crate::parser::{link_names, parse_profile};
+    use std::collections::{HashMap, HashSet};
+
+    fn targets() -> HashMap<String, String> {
+        let mut targets = HashMap::new();
+        targets.insert("HEARTBEAT".to_string(), "HEARTBEAT_DATA".to_string());
+        targets.insert("MAV_TYPE".to_string(), "MavType".to_string());
+        targets
+    }
+
+    #[test]
+    pub fn test_whole_names() {
+        assert_eq!(
+            link_names("Sent after HEARTBEAT.", &targets()),
+            "Sent after [`HEARTBEAT`](HEARTBEAT_DATA)."
+        );
+        assert_eq!(
+            link_names("One of MAV_TYPE, see HEARTBEAT", &targets()),
+            "One of [`MAV_TYPE`](MavType), see [`HEARTBEAT`](HEARTBEAT_DATA)"
+        );
+    }
+
+    #[test]
+    pub fn test_names_in_words() {
+        let text = "HEARTBEAT_RATE and MAV_TYPE_GENERIC or xHEARTBEAT and HEARTBEAT2";
+        assert_eq!(link_names(text, &targets()), text);
+    }
+
+    #[test]
+    pub fn test_names_in_code() {
+        let text = "Like `HEARTBEAT` and `foo(MAV_TYPE)`";
+        assert_eq!(link_names(text, &targets()), text);
+        assert_eq!(
+            link_names("`[HEARTBEAT]` then HEARTBEAT", &targets()),
+            "`[HEARTBEAT]` then [`HEARTBEAT`](HEARTBEAT_DATA)"
+        );
+    }
+
+    #[test]
+    pub fn test_names_in_links() {
+        for text in &[
+            "Already [`HEARTBEAT`](HEARTBEAT_DATA) linked",
+            "See [the HEARTBEAT](https://mavlink.io/en/messages/common.html#HEARTBEAT)",
+            "See [here](#MAV_TYPE)",
+            "See https://mavlink.io/en/messages/common.html#HEARTBEAT for MAV_TYPE.",
+        ] {
+            let expected = text.replace("for MAV_TYPE", "for [`MAV_TYPE`](MavType)");
+            assert_eq!(link_names(text, &targets()), expected);
+        }
+    }
+
+    #[test]
+    pub fn test_shared_name() {
+        let dir = crate::write_definitions(
+            "shared_name.xml",
+            r#"<?xml version="1.0"?>
+<mavlink>
+  <enums>
+    <enum name="HEARTBEAT">
+      <description>Not the HEARTBEAT message</description>
+      <entry value="0" name="HEARTBEAT_SLOW"/>
+    </enum>
+  </enums>
+  <messages>
+    <message id="0" name="HEARTBEAT">
+      <description>Sent as HEARTBEAT at HEARTBEAT_SLOW</description>
+      <field type="uint8_t" name="type">Type</field>
+    </message>
+  </messages>
+</mavlink>"#,
+        );
+        let profile = parse_profile(&dir, &"shared_name.xml".to_string(), &mut HashSet::new())
+            .link_descriptions();
+        // a name shared by a message and an enum links to the message
+        assert_eq!(
+            profile.messages["HEARTBEAT"].description.as_deref(),
+            Some("Sent as [`HEARTBEAT`](HEARTBEAT_DATA) at [`HEARTBEAT_SLOW`](Heartbeat::HEARTBEAT_SLOW)")
+        );
+        assert_eq!(
+            profile.enums["Heartbeat"].description.as_deref(),
+            Some("Not the [`HEARTBEAT`](HEARTBEAT_DATA) message")
+        );
+    }
+}
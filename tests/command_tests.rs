@@ -0,0 +1,248 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod command_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::commands::{CommandClient, CommandTransaction};
+    use mavlink::common::{
+        MavCmd, MavMessage, MavResult, COMMAND_ACK_DATA, COMMAND_INT_DATA, COMMAND_LONG_DATA,
+    };
+    use mavlink::error::ProtocolError;
+    use mavlink::request::{Exchange, Requester, Step};
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::{mock_connection_pair, MockConnection};
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    fn arm() -> COMMAND_LONG_DATA {
+        COMMAND_LONG_DATA {
+            param1: 1.0,
+            command: MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+            target_system: 1,
+            target_component: 1,
+            ..Default::default()
+        }
+    }
+
+    fn ack(command: MavCmd, result: MavResult) -> MavMessage {
+        MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+            command,
+            result,
+            #[cfg(feature = "emit-extensions")]
+            progress: 0,
+            #[cfg(feature = "emit-extensions")]
+            result_param2: 0,
+            #[cfg(feature = "emit-extensions")]
+            target_system: 255,
+            #[cfg(feature = "emit-extensions")]
+            target_component: 0,
+        })
+    }
+
+    fn recv(vehicle: &MockConnection<MavMessage>) -> MavMessage {
+        loop {
+            if let Ok((_, msg)) = vehicle.recv() {
+                return msg;
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_send_command() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            let command = match recv(&vehicle) {
+                MavMessage::COMMAND_LONG(command) => command.command,
+                msg => panic!("expected COMMAND_LONG, got {:?}", msg),
+            };
+            // unrelated acknowledgement first
+            vehicle
+                .send(
+                    &VEHICLE,
+                    &ack(MavCmd::MAV_CMD_DO_SET_MODE, MavResult::MAV_RESULT_FAILED),
+                )
+                .unwrap();
+            vehicle
+                .send(&VEHICLE, &ack(command, MavResult::MAV_RESULT_ACCEPTED))
+                .unwrap();
+        });
+
+        let client = CommandClient::new(&gcs);
+        assert_eq!(
+            client.send_command(arm()).unwrap(),
+            MavResult::MAV_RESULT_ACCEPTED
+        );
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_command_int_denied() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            assert!(matches!(recv(&vehicle), MavMessage::COMMAND_INT(_)));
+            vehicle
+                .send(
+                    &VEHICLE,
+                    &ack(MavCmd::MAV_CMD_DO_REPOSITION, MavResult::MAV_RESULT_DENIED),
+                )
+                .unwrap();
+        });
+
+        let client = CommandClient::new(&gcs);
+        let result = client.send_command(COMMAND_INT_DATA {
+            command: MavCmd::MAV_CMD_DO_REPOSITION,
+            target_system: 1,
+            target_component: 1,
+            ..Default::default()
+        });
+        assert_eq!(result.unwrap(), MavResult::MAV_RESULT_DENIED);
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_retries() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let requester = Requester::new(&gcs)
+            .with_timeout(Duration::from_millis(20))
+            .with_retries(2);
+        let client = CommandClient::from_requester(requester);
+        assert!(matches!(
+            client.send_command(arm()),
+            Err(ProtocolError::Timeout)
+        ));
+
+        // the confirmation field counts the retransmissions
+        let mut confirmations = Vec::new();
+        while let Ok((_, msg)) = vehicle.recv() {
+            match msg {
+                MavMessage::COMMAND_LONG(command) => confirmations.push(command.confirmation),
+                msg => panic!("expected COMMAND_LONG, got {:?}", msg),
+            }
+        }
+        assert_eq!(confirmations, vec![0, 1, 2]);
+    }
+
+    #[test]
+    pub fn test_in_progress() {
+        let mut transaction = CommandTransaction::new(arm());
+        transaction.start();
+
+        let in_progress = ack(
+            MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+            MavResult::MAV_RESULT_IN_PROGRESS,
+        );
+        assert!(matches!(
+            transaction.handle(&VEHICLE, &in_progress),
+            Ok(Step::Progress)
+        ));
+        assert!(transaction.in_progress());
+
+        // every report extends the wait for the final acknowledgement
+        assert!(matches!(
+            transaction.handle(&VEHICLE, &in_progress),
+            Ok(Step::Progress)
+        ));
+
+        // no resending once the target is working on the command
+        assert!(matches!(
+            transaction.on_timeout(),
+            Err(ProtocolError::Timeout)
+        ));
+
+        let done = ack(
+            MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+            MavResult::MAV_RESULT_ACCEPTED,
+        );
+        let other = MavHeader {
+            system_id: 2,
+            ..VEHICLE
+        };
+        assert!(matches!(transaction.handle(&other, &done), Ok(Step::Wait)));
+        assert!(matches!(
+            transaction.handle(&VEHICLE, &done),
+            Ok(Step::Done(None, MavResult::MAV_RESULT_ACCEPTED))
+        ));
+    }
+
+    #[test]
+    pub fn test_long_running_command() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            let command = match recv(&vehicle) {
+                MavMessage::COMMAND_LONG(command) => command.command,
+                msg => panic!("expected COMMAND_LONG, got {:?}", msg),
+            };
+            // takes longer than the in progress timeout, but reports progress within it
+            for _ in 0..5 {
+                vehicle
+                    .send(&VEHICLE, &ack(command, MavResult::MAV_RESULT_IN_PROGRESS))
+                    .unwrap();
+                thread::sleep(Duration::from_millis(40));
+            }
+            vehicle
+                .send(&VEHICLE, &ack(command, MavResult::MAV_RESULT_ACCEPTED))
+                .unwrap();
+        });
+
+        let client = CommandClient::new(&gcs).with_in_progress_timeout(Duration::from_millis(100));
+        assert_eq!(
+            client.send_command(arm()).unwrap(),
+            MavResult::MAV_RESULT_ACCEPTED
+        );
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "emit-extensions")]
+    pub fn test_ack_for_other_gcs() {
+        let mut transaction = CommandTransaction::new(arm()).with_sender(255, 190);
+        transaction.start();
+
+        let ack_to = |target_system, target_component| {
+            MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+                command: MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+                result: MavResult::MAV_RESULT_ACCEPTED,
+                target_system,
+                target_component,
+                ..Default::default()
+            })
+        };
+        assert!(matches!(
+            transaction.handle(&VEHICLE, &ack_to(254, 190)),
+            Ok(Step::Wait)
+        ));
+        assert!(matches!(
+            transaction.handle(&VEHICLE, &ack_to(255, 191)),
+            Ok(Step::Wait)
+        ));
+        // targets not filled in by the vehicle
+        assert!(matches!(
+            transaction.handle(&VEHICLE, &ack_to(0, 0)),
+            Ok(Step::Done(None, MavResult::MAV_RESULT_ACCEPTED))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "icarous")]
+    pub fn test_unsupported_message_set() {
+        // the icarous message set does not include the command messages
+        let (gcs, _vehicle) = mock_connection_pair::<mavlink::icarous::MavMessage>();
+
+        let client = CommandClient::new(&gcs);
+        assert!(matches!(
+            client.send_command(arm()),
+            Err(ProtocolError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod component_information_tests {
+    use std::io;
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{MavCmd, MavMessage, COMMAND_LONG_DATA};
+    use mavlink::component_information::{
+        metadata_file_crc, request_component_metadata, ComponentInformationError,
+        ComponentMetadata, ComponentMetadataServer, MetadataFetcher,
+    };
+    use mavlink::request::Requester;
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::mock_connection_pair;
+
+    const GENERAL_JSON: &[u8] = br#"{"version": 1, "metadataTypes": []}"#;
+    const URI: &str = "mftp://[;comp=100]general.json";
+
+    const GCS: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+
+    struct StaticFetcher(&'static [u8]);
+
+    impl MetadataFetcher for StaticFetcher {
+        fn fetch(&mut self, uri: &str) -> io::Result<Vec<u8>> {
+            assert_eq!(uri, URI);
+            Ok(self.0.to_vec())
+        }
+    }
+
+    fn request(message_id: f32, target_component: u8) -> MavMessage {
+        MavMessage::COMMAND_LONG(COMMAND_LONG_DATA {
+            param1: message_id,
+            command: MavCmd::MAV_CMD_REQUEST_MESSAGE,
+            target_system: 1,
+            target_component,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    pub fn test_file_crc() {
+        assert_eq!(metadata_file_crc(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    pub fn test_serve_metadata() {
+        let server =
+            ComponentMetadataServer::new(1, 100, ComponentMetadata::new(URI, GENERAL_JSON));
+
+        let replies = server.handle(&GCS, &request(397.0, 100));
+        assert_eq!(replies.len(), 2);
+        assert!(matches!(replies[0], MavMessage::COMMAND_ACK(_)));
+        assert_eq!(
+            ComponentMetadata::from_message(&replies[1]),
+            Some(ComponentMetadata {
+                uri: URI.to_string(),
+                file_crc: metadata_file_crc(GENERAL_JSON),
+            })
+        );
+
+        // deprecated message
+        let replies = server.handle(&GCS, &request(395.0, 0));
+        assert!(matches!(replies[1], MavMessage::COMPONENT_INFORMATION(_)));
+        assert_eq!(
+            ComponentMetadata::from_message(&replies[1]).as_ref(),
+            Some(server.metadata())
+        );
+
+        // other component, other message
+        assert!(server.handle(&GCS, &request(397.0, 1)).is_empty());
+        assert!(server.handle(&GCS, &request(0.0, 100)).is_empty());
+    }
+
+    #[test]
+    pub fn test_request_and_fetch() {
+        let (gcs, component) = mock_connection_pair::<MavMessage>();
+
+        let component_thread = thread::spawn(move || {
+            let server =
+                ComponentMetadataServer::new(1, 100, ComponentMetadata::new(URI, GENERAL_JSON));
+            loop {
+                if let Ok((header, msg)) = component.recv() {
+                    server.handle_and_reply(&component, &header, &msg).unwrap();
+                    return;
+                }
+            }
+        });
+
+        let requester = Requester::new(&gcs)
+            .with_header(GCS)
+            .with_timeout(Duration::from_millis(500));
+        let metadata = request_component_metadata(&requester, 1, 100).unwrap();
+        component_thread.join().unwrap();
+
+        assert_eq!(
+            metadata
+                .fetch_general(&mut StaticFetcher(GENERAL_JSON))
+                .unwrap(),
+            GENERAL_JSON
+        );
+        assert!(matches!(
+            metadata.fetch_general(&mut StaticFetcher(b"{}")),
+            Err(ComponentInformationError::CrcMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod component_tests {
+    use std::time::{Duration, Instant};
+
+    use mavlink::common::{
+        MavAutopilot, MavCmd, MavMessage, MavModeFlag, MavProtocolCapability, MavResult, MavState,
+        MavType, AUTOPILOT_VERSION_DATA, COMMAND_LONG_DATA, PROTOCOL_VERSION_DATA,
+    };
+    use mavlink::component::{Component, HEARTBEAT_INTERVAL};
+    use mavlink::latency::{LatencyProbe, ProbeKind};
+    use mavlink::{MavConnection, MavHeader, MessageData};
+
+    use crate::test_shared::mock_connection_pair;
+
+    const GCS: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+
+    fn camera() -> Component {
+        Component::new(1, 100, MavType::MAV_TYPE_CAMERA)
+    }
+
+    fn command(command: MavCmd, param1: f32, target_component: u8) -> MavMessage {
+        MavMessage::COMMAND_LONG(COMMAND_LONG_DATA {
+            param1,
+            command,
+            target_system: 1,
+            target_component,
+            ..Default::default()
+        })
+    }
+
+    fn ack_result(msg: &MavMessage) -> MavResult {
+        match msg {
+            MavMessage::COMMAND_ACK(ack) => ack.result,
+            msg => panic!("expected COMMAND_ACK, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    pub fn test_heartbeat() {
+        let mut component = camera().with_autopilot(MavAutopilot::MAV_AUTOPILOT_GENERIC);
+        component.set_mode(MavModeFlag::MAV_MODE_FLAG_CUSTOM_MODE_ENABLED, 4);
+        component.set_system_status(MavState::MAV_STATE_ACTIVE);
+
+        let start = Instant::now();
+        match component.poll(start) {
+            Some(MavMessage::HEARTBEAT(heartbeat)) => {
+                assert_eq!(heartbeat.mavtype, MavType::MAV_TYPE_CAMERA);
+                assert_eq!(heartbeat.autopilot, MavAutopilot::MAV_AUTOPILOT_GENERIC);
+                assert_eq!(heartbeat.custom_mode, 4);
+                assert_eq!(heartbeat.system_status, MavState::MAV_STATE_ACTIVE);
+                assert_eq!(heartbeat.mavlink_version, 3);
+            }
+            msg => panic!("expected HEARTBEAT, got {:?}", msg),
+        }
+        assert!(component.poll(start + HEARTBEAT_INTERVAL / 2).is_none());
+        // a late poll doesn't shift the schedule
+        assert!(component
+            .poll(start + HEARTBEAT_INTERVAL + Duration::from_millis(100))
+            .is_some());
+        assert!(component.poll(start + HEARTBEAT_INTERVAL * 2).is_some());
+        // a poll missing a whole interval restarts it
+        assert!(component.poll(start + HEARTBEAT_INTERVAL * 5).is_some());
+        assert!(component.poll(start + HEARTBEAT_INTERVAL * 6).is_some());
+    }
+
+    #[test]
+    pub fn test_ping() {
+        let component = camera();
+        let mut probe = LatencyProbe::new(ProbeKind::Ping);
+        let request = probe.request(1_000_000);
+
+        let replies = component.handle(&GCS, &request);
+        assert_eq!(replies.len(), 1);
+        let rtt = probe.handle(3_000_000, &component.header(), &replies[0]);
+        assert_eq!(rtt, Some(Duration::from_millis(2)));
+
+        // answers aren't answered
+        assert!(component.handle(&GCS, &replies[0]).is_empty());
+    }
+
+    #[test]
+    pub fn test_requests() {
+        let component = camera().with_autopilot_version(AUTOPILOT_VERSION_DATA {
+            capabilities: MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_MAVLINK2
+                | MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_FTP,
+            flight_sw_version: 0x0102_0300,
+            vendor_id: 0x1234,
+            ..Default::default()
+        });
+
+        let replies = component.handle(&GCS, &command(MavCmd::MAV_CMD_REQUEST_MESSAGE, 148.0, 100));
+        assert_eq!(ack_result(&replies[0]), MavResult::MAV_RESULT_ACCEPTED);
+        match &replies[1] {
+            MavMessage::AUTOPILOT_VERSION(version) => {
+                assert_eq!(version.flight_sw_version, 0x0102_0300);
+                assert_eq!(version.vendor_id, 0x1234);
+            }
+            msg => panic!("expected AUTOPILOT_VERSION, got {:?}", msg),
+        }
+
+        let replies = component.handle(
+            &GCS,
+            &command(
+                MavCmd::MAV_CMD_REQUEST_MESSAGE,
+                PROTOCOL_VERSION_DATA::ID as f32,
+                0,
+            ),
+        );
+        match &replies[1] {
+            MavMessage::PROTOCOL_VERSION(version) => {
+                assert_eq!(
+                    (version.version, version.min_version, version.max_version),
+                    (200, 100, 200)
+                );
+            }
+            msg => panic!("expected PROTOCOL_VERSION, got {:?}", msg),
+        }
+
+        let replies = component.handle(&GCS, &command(MavCmd::MAV_CMD_REQUEST_MESSAGE, 0.0, 100));
+        assert!(matches!(replies[1], MavMessage::HEARTBEAT(_)));
+
+        // other components, other messages and other commands are left to others
+        assert!(component
+            .handle(&GCS, &command(MavCmd::MAV_CMD_REQUEST_MESSAGE, 148.0, 1))
+            .is_empty());
+        assert!(component
+            .handle(&GCS, &command(MavCmd::MAV_CMD_REQUEST_MESSAGE, 397.0, 100))
+            .is_empty());
+        assert!(component
+            .handle(
+                &GCS,
+                &command(MavCmd::MAV_CMD_COMPONENT_ARM_DISARM, 1.0, 100)
+            )
+            .is_empty());
+    }
+
+    #[test]
+    pub fn test_mavlink1_only() {
+        let component = camera().with_capabilities(MavProtocolCapability::empty());
+        assert!(component.protocol_version().is_none());
+        let replies = component.handle(&GCS, &command(MavCmd::MAV_CMD_REQUEST_MESSAGE, 300.0, 100));
+        assert_eq!(replies.len(), 1);
+        assert_eq!(ack_result(&replies[0]), MavResult::MAV_RESULT_UNSUPPORTED);
+    }
+
+    #[test]
+    pub fn test_connection() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let mut component = camera();
+
+        component.poll_and_send(&vehicle, Instant::now()).unwrap();
+        let (header, msg) = gcs.recv().unwrap();
+        assert_eq!((header.system_id, header.component_id), (1, 100));
+        assert!(matches!(msg, MavMessage::HEARTBEAT(_)));
+        // not due yet
+        component.poll_and_send(&vehicle, Instant::now()).unwrap();
+
+        gcs.send(
+            &GCS,
+            &command(
+                MavCmd::MAV_CMD_REQUEST_MESSAGE,
+                PROTOCOL_VERSION_DATA::ID as f32,
+                100,
+            ),
+        )
+        .unwrap();
+        let (header, msg) = vehicle.recv().unwrap();
+        component.handle_and_reply(&vehicle, &header, &msg).unwrap();
+        assert!(matches!(gcs.recv().unwrap().1, MavMessage::COMMAND_ACK(_)));
+        assert!(matches!(
+            gcs.recv().unwrap().1,
+            MavMessage::PROTOCOL_VERSION(_)
+        ));
+        assert!(gcs.recv().is_err());
+    }
+}
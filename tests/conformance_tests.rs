@@ -0,0 +1,337 @@
+//! Conformance of the generated code with the MAVLink serialization rules, checked against the
+//! XML definitions of the `common` and `test` message sets. Run with
+//! `cargo test --features conformance --test conformance_tests`.
+
+#[cfg(feature = "conformance")]
+mod conformance_tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use mavlink::{MavlinkVersion, Message};
+    use serde::Serialize;
+    use serde_json::Value;
+
+    const DEFINITIONS: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/mavlink/message_definitions/v1.0"
+    );
+
+    #[derive(Debug)]
+    struct FieldDef {
+        /// Base type, e.g. `uint16_t` for `uint16_t[4]`
+        base: String,
+        array: Option<usize>,
+        name: String,
+        extension: bool,
+        has_enum: bool,
+    }
+
+    impl FieldDef {
+        fn size(&self) -> usize {
+            let size = match self.base.as_str() {
+                "char" | "int8_t" | "uint8_t" | "uint8_t_mavlink_version" => 1,
+                "int16_t" | "uint16_t" => 2,
+                "int32_t" | "uint32_t" | "float" => 4,
+                "int64_t" | "uint64_t" | "double" => 8,
+                other => panic!("unknown type {}", other),
+            };
+            size * self.array.unwrap_or(1)
+        }
+
+        fn element_size(&self) -> usize {
+            self.size() / self.array.unwrap_or(1)
+        }
+
+        /// Name of the field in the generated struct
+        fn rust_name(&self) -> &str {
+            if self.name == "type" {
+                "mavtype"
+            } else {
+                &self.name
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct MessageDef {
+        id: u32,
+        name: String,
+        fields: Vec<FieldDef>,
+    }
+
+    impl MessageDef {
+        /// Fields in wire order: the base fields sorted by decreasing type size, keeping the
+        /// definition order for equal sizes, followed by the extensions in definition order
+        fn wire_order(&self) -> Vec<&FieldDef> {
+            let mut base: Vec<_> = self.fields.iter().filter(|f| !f.extension).collect();
+            base.sort_by_key(|f| std::cmp::Reverse(f.element_size()));
+            base.extend(self.fields.iter().filter(|f| f.extension));
+            base
+        }
+
+        fn payload_len(&self) -> usize {
+            self.fields.iter().map(FieldDef::size).sum()
+        }
+    }
+
+    /// Tags of an XML document as their name and attributes, `/name` for closing tags
+    fn tags(xml: &str) -> Vec<(String, BTreeMap<String, String>)> {
+        let mut tags = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            if let Some(comment) = rest.strip_prefix("!--") {
+                rest = &comment[comment.find("-->").unwrap() + 3..];
+                continue;
+            }
+            let end = rest.find('>').unwrap();
+            let tag = rest[..end].trim_end_matches('/');
+            rest = &rest[end + 1..];
+
+            let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+            let mut attributes = BTreeMap::new();
+            let mut attrs = &tag[name_end..];
+            while let Some(eq) = attrs.find("=\"") {
+                let key = attrs[..eq].trim().to_string();
+                let value_end = attrs[eq + 2..].find('"').unwrap();
+                attributes.insert(key, attrs[eq + 2..eq + 2 + value_end].to_string());
+                attrs = &attrs[eq + 2 + value_end + 1..];
+            }
+            tags.push((tag[..name_end].to_string(), attributes));
+        }
+        tags
+    }
+
+    /// Messages of a definition file and the files it includes
+    fn definitions(file: &str) -> Vec<MessageDef> {
+        let xml = std::fs::read_to_string(Path::new(DEFINITIONS).join(file)).unwrap();
+        let mut messages = Vec::new();
+
+        // includes are the only text content we need
+        let mut rest = xml.as_str();
+        while let Some(start) = rest.find("<include>") {
+            rest = &rest[start + "<include>".len()..];
+            let end = rest.find("</include>").unwrap();
+            messages.extend(definitions(rest[..end].trim()));
+        }
+
+        let mut extension = false;
+        for (name, attributes) in tags(&xml) {
+            match name.as_str() {
+                "message" => {
+                    extension = false;
+                    messages.push(MessageDef {
+                        id: attributes["id"].parse().unwrap(),
+                        name: attributes["name"].clone(),
+                        fields: Vec::new(),
+                    });
+                }
+                "extensions" => extension = true,
+                "field" => {
+                    let ty = &attributes["type"];
+                    let (base, array) = match ty.find('[') {
+                        Some(open) => (
+                            ty[..open].to_string(),
+                            Some(ty[open + 1..ty.len() - 1].parse().unwrap()),
+                        ),
+                        None => (ty.clone(), None),
+                    };
+                    messages.last_mut().unwrap().fields.push(FieldDef {
+                        base,
+                        array,
+                        name: attributes["name"].clone(),
+                        extension,
+                        has_enum: attributes.contains_key("enum"),
+                    });
+                }
+                _ => {}
+            }
+        }
+        messages
+    }
+
+    fn x25(crc: u16, data: &[u8]) -> u16 {
+        data.iter().fold(crc, |crc, byte| {
+            let tmp = byte ^ (crc as u8);
+            let tmp = tmp ^ (tmp << 4);
+            (crc >> 8) ^ (u16::from(tmp) << 8) ^ (u16::from(tmp) << 3) ^ u16::from(tmp >> 4)
+        })
+    }
+
+    /// CRC extra computed from the definition as specified in
+    /// <https://mavlink.io/en/guide/serialization.html#crc_extra>
+    fn crc_extra(message: &MessageDef) -> u8 {
+        let mut crc = x25(0xffff, format!("{} ", message.name).as_bytes());
+        for field in message.wire_order().iter().filter(|f| !f.extension) {
+            let base = field.base.trim_end_matches("_mavlink_version");
+            crc = x25(crc, format!("{base} ").as_bytes());
+            crc = x25(crc, format!("{} ", field.name).as_bytes());
+            if let Some(len) = field.array {
+                crc = x25(crc, &[len as u8]);
+            }
+        }
+        (crc & 0xff) as u8 ^ (crc >> 8) as u8
+    }
+
+    fn check_crc_extra<M: Message>(file: &str) {
+        let messages = definitions(file);
+        assert!(!messages.is_empty());
+        for message in &messages {
+            assert_eq!(
+                M::extra_crc(message.id),
+                crc_extra(message),
+                "CRC extra of {}",
+                message.name
+            );
+        }
+    }
+
+    /// Value of the element of a field starting at `offset` of the payload
+    fn element(field: &FieldDef, payload: &[u8], offset: usize) -> Value {
+        let bytes = &payload[offset..offset + field.element_size()];
+        let mut le = [0; 8];
+        le[..bytes.len()].copy_from_slice(bytes);
+        match field.base.as_str() {
+            "char" => Value::from(bytes[0]),
+            "int8_t" => Value::from(bytes[0] as i8),
+            "int16_t" => Value::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+            "int32_t" => Value::from(i32::from_le_bytes([le[0], le[1], le[2], le[3]])),
+            "int64_t" => Value::from(i64::from_le_bytes(le)),
+            "float" => Value::from(f32::from_le_bytes([le[0], le[1], le[2], le[3]])),
+            "double" => Value::from(f64::from_le_bytes(le)),
+            _ => Value::from(u64::from_le_bytes(le)),
+        }
+    }
+
+    fn same(field: &FieldDef, expected: &Value, actual: &Value) -> bool {
+        match (field.base.as_str(), actual) {
+            // a single char is serialized as a string
+            ("char", Value::String(s)) => {
+                Value::from(s.chars().next().unwrap() as u32) == *expected
+            }
+            // NaN is serialized as null
+            ("float" | "double", Value::Null) => expected.is_null(),
+            ("float", actual) => {
+                actual.as_f64().map(|v| v as f32) == expected.as_f64().map(|v| v as f32)
+            }
+            _ => actual == expected,
+        }
+    }
+
+    /// Parse a payload of distinct bytes and check that every field was read from the offset the
+    /// wire order gives it. Messages with enum fields are skipped, as most byte patterns are no
+    /// valid enum values.
+    fn check_field_order<M: Message + Serialize>(file: &str) -> usize {
+        let mut checked = 0;
+        for message in definitions(file) {
+            if message.fields.iter().any(|f| f.has_enum) {
+                continue;
+            }
+            let payload: Vec<u8> = (0..message.payload_len()).map(|i| i as u8 + 1).collect();
+            let parsed = M::parse(MavlinkVersion::V2, message.id, &payload).unwrap();
+            let json = serde_json::to_value(&parsed).unwrap();
+
+            let mut offset = 0;
+            for field in message.wire_order() {
+                if field.extension && !cfg!(feature = "emit-extensions") {
+                    continue;
+                }
+                let actual = &json[field.rust_name()];
+                let count = field.array.unwrap_or(1);
+                for i in 0..count {
+                    let expected = element(field, &payload, offset + i * field.element_size());
+                    let actual = match field.array {
+                        Some(_) => &actual[i],
+                        None => actual,
+                    };
+                    assert!(
+                        same(field, &expected, actual),
+                        "{}.{}[{i}]: expected {expected}, got {actual}",
+                        message.name,
+                        field.name
+                    );
+                }
+                offset += field.size();
+            }
+            checked += 1;
+        }
+        checked
+    }
+
+    /// Length of the payload of the generated code, which leaves out the extensions unless the
+    /// `emit-extensions` feature is enabled
+    fn emitted_len(message: &MessageDef) -> usize {
+        message
+            .fields
+            .iter()
+            .filter(|f| cfg!(feature = "emit-extensions") || !f.extension)
+            .map(FieldDef::size)
+            .sum()
+    }
+
+    /// MAVLink 2 drops the trailing zeroes of the payload but keeps at least one byte, MAVLink 1
+    /// sends the whole payload
+    fn check_truncation<M: Message>(file: &str) {
+        let mut full = [0; 255];
+        let mut truncated = [0; 255];
+        for message in definitions(file) {
+            let msg = M::default_message_from_id(message.id).unwrap();
+            let len = msg.ser(MavlinkVersion::V1, &mut full);
+            assert_eq!(len, emitted_len(&message), "{}", message.name);
+
+            let expected = full[..len]
+                .iter()
+                .rposition(|b| *b != 0)
+                .map_or(1, |i| i + 1);
+            let truncated_len = msg.ser(MavlinkVersion::V2, &mut truncated);
+            assert_eq!(truncated_len, expected, "{}", message.name);
+            assert_eq!(truncated[..expected], full[..expected], "{}", message.name);
+
+            // zero enum values are mostly invalid
+            if message.fields.iter().any(|f| f.has_enum) {
+                continue;
+            }
+            // a zero payload parses whatever its length
+            for len in [1, emitted_len(&message), message.payload_len()] {
+                let parsed = M::parse(MavlinkVersion::V2, message.id, &vec![0; len]).unwrap();
+                assert_eq!(
+                    parsed.ser(MavlinkVersion::V2, &mut truncated),
+                    1,
+                    "{}",
+                    message.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_crc_extra() {
+        check_crc_extra::<mavlink::common::MavMessage>("common.xml");
+        check_crc_extra::<mavlink::test::MavMessage>("test.xml");
+    }
+
+    #[test]
+    pub fn test_field_order() {
+        assert!(check_field_order::<mavlink::common::MavMessage>("common.xml") > 50);
+        assert_eq!(
+            check_field_order::<mavlink::test::MavMessage>("test.xml"),
+            1
+        );
+    }
+
+    #[test]
+    pub fn test_payload_truncation() {
+        check_truncation::<mavlink::common::MavMessage>("common.xml");
+        check_truncation::<mavlink::test::MavMessage>("test.xml");
+
+        // only trailing zeroes are dropped
+        let test_types = &definitions("test.xml")[0];
+        let mut payload = vec![0; test_types.payload_len()];
+        *payload.last_mut().unwrap() = 1;
+        let msg =
+            mavlink::test::MavMessage::parse(MavlinkVersion::V2, test_types.id, &payload).unwrap();
+        let mut buf = [0; 255];
+        assert_eq!(msg.ser(MavlinkVersion::V2, &mut buf), payload.len());
+        assert_eq!(&buf[..payload.len()], &payload[..]);
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(all(feature = "std", feature = "common"))]
+mod coordinates_tests {
+    use mavlink::common::{GLOBAL_POSITION_INT_DATA, GPS_RAW_INT_DATA};
+    use mavlink::coordinates::{AltitudeMm, GlobalPosition, LatLon};
+
+    #[test]
+    pub fn test_lat_lon() {
+        let position = LatLon::from_degrees(47.3977418, -8.5455939);
+        assert_eq!(position, LatLon::new(473_977_418, -85_455_939));
+        let (lat, lon) = position.to_degrees();
+        assert!((lat - 47.3977418).abs() < 1e-9);
+        assert!((lon + 8.5455939).abs() < 1e-9);
+
+        let gps = GPS_RAW_INT_DATA {
+            lat: 473_977_418,
+            lon: -85_455_939,
+            ..Default::default()
+        };
+        assert_eq!(LatLon::from(&gps), position);
+    }
+
+    #[test]
+    pub fn test_distance() {
+        // one degree of latitude is about 111 km
+        let a = LatLon::from_degrees(47.0, 8.0);
+        let b = LatLon::from_degrees(48.0, 8.0);
+        assert!((a.distance_to(&b) - 111_195.0).abs() < 1.0);
+        assert_eq!(a.distance_to(&a), 0.0);
+    }
+
+    #[test]
+    pub fn test_altitude() {
+        assert_eq!(AltitudeMm::from_meters(488.1504), AltitudeMm(488_150));
+        assert_eq!(AltitudeMm(-1_250).meters(), -1.25);
+    }
+
+    #[test]
+    pub fn test_global_position_round_trip() {
+        let msg = GLOBAL_POSITION_INT_DATA {
+            time_boot_ms: 1000,
+            lat: 473_977_418,
+            lon: 85_455_939,
+            alt: 488_150,
+            relative_alt: 1_250,
+            vx: 150,
+            vy: -20,
+            vz: 5,
+            hdg: 35_999,
+        };
+
+        let position = GlobalPosition::from(&msg);
+        assert!((position.lat - 47.3977418).abs() < 1e-9);
+        assert_eq!(position.alt, 488.15);
+        assert_eq!(position.relative_alt, 1.25);
+        assert_eq!(position.vx, 1.5);
+        assert_eq!(position.vy, -0.2);
+        assert_eq!(position.heading, Some(359.99));
+        assert_eq!(position.to_message(1000), msg);
+    }
+
+    #[test]
+    pub fn test_unknown_heading() {
+        let msg = GLOBAL_POSITION_INT_DATA {
+            hdg: u16::MAX,
+            ..Default::default()
+        };
+        let mut position = GlobalPosition::from(&msg);
+        assert_eq!(position.heading, None);
+        assert_eq!(position.to_message(0).hdg, u16::MAX);
+
+        // headings are wrapped to 0..360
+        position.heading = Some(-90.0);
+        assert_eq!(position.to_message(0).hdg, 27_000);
+        position.heading = Some(359.999);
+        assert_eq!(position.to_message(0).hdg, 0);
+    }
+}
@@ -0,0 +1,135 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod debug_frame_tests {
+    use mavlink::common::MavMessage;
+    use mavlink::debug::DebugFrame;
+    use mavlink::{MAVLinkV1MessageRaw, MAVLinkV2MessageRaw, MavlinkVersion};
+
+    use crate::test_shared::{get_heartbeat_msg, COMMON_MSG_HEADER};
+
+    fn frame(version: MavlinkVersion) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        mavlink::write_versioned_msg(&mut buf, version, COMMON_MSG_HEADER, &heartbeat).unwrap();
+        buf
+    }
+
+    fn dump(bytes: &[u8]) -> String {
+        DebugFrame::new(bytes)
+            .with_message_set::<MavMessage>()
+            .to_string()
+    }
+
+    #[test]
+    pub fn test_v2_frame() {
+        let mut raw = MAVLinkV2MessageRaw::new();
+        raw.serialize_message(
+            COMMON_MSG_HEADER,
+            &MavMessage::HEARTBEAT(get_heartbeat_msg()),
+        );
+        assert_eq!(
+            raw.debug::<MavMessage>().to_string(),
+            "MAVLink 2 frame, 21 bytes
+    0  fd                       STX, MAVLink 2
+    1  09                       payload length 9
+    2  00                       incompatibility flags 0x00
+    3  00                       compatibility flags 0x00
+    4  ef                       sequence 239
+    5  01                       system id 1
+    6  01                       component id 1
+    7  00 00 00                 message id 0 (HEARTBEAT)
+   10  05 00 00 00 02 03 59 03  payload, 9 bytes
+   18  03
+   19  10 f0                    checksum 0xf010, valid
+"
+        );
+        assert_eq!(dump(raw.raw_bytes()), raw.debug::<MavMessage>().to_string());
+    }
+
+    #[test]
+    pub fn test_v1_frame() {
+        let mut raw = MAVLinkV1MessageRaw::new();
+        raw.serialize_message(
+            COMMON_MSG_HEADER,
+            &MavMessage::HEARTBEAT(get_heartbeat_msg()),
+        );
+        let dump = raw.debug::<MavMessage>().to_string();
+        assert!(dump.starts_with("MAVLink 1 frame, 17 bytes\n    0  fe "));
+        assert!(dump.contains("    5  00                       message id 0 (HEARTBEAT)\n"));
+        assert!(dump.contains("checksum"));
+        assert!(dump.ends_with(", valid\n"));
+    }
+
+    #[test]
+    pub fn test_invalid_checksum() {
+        let mut bytes = frame(MavlinkVersion::V2);
+        bytes[10] ^= 0x01;
+        let dump = dump(&bytes);
+        assert!(dump.contains("checksum 0xf010, invalid, expected 0x"));
+
+        // unknown messages can't be checked
+        bytes[7] = 0xff;
+        bytes[8] = 0xff;
+        let dump = DebugFrame::new(&bytes)
+            .with_message_set::<MavMessage>()
+            .to_string();
+        assert!(dump.contains("message id 65535 (unknown)"));
+        assert!(dump.contains("checksum 0xf010\n"));
+    }
+
+    #[test]
+    pub fn test_truncated() {
+        let bytes = frame(MavlinkVersion::V2);
+        let dump = dump(&bytes[..14]);
+        assert!(dump.starts_with("MAVLink 2 frame, 21 bytes, truncated to 14\n"));
+        assert!(
+            dump.ends_with("   10  05 00 00 00              payload, truncated to 4 of 9 bytes\n")
+        );
+
+        let dump = DebugFrame::new(&bytes[..19]).to_string();
+        assert!(dump.ends_with("checksum missing\n"));
+    }
+
+    #[test]
+    pub fn test_garbage_and_frames() {
+        let mut bytes = vec![0x00, 0x42];
+        bytes.extend(frame(MavlinkVersion::V1));
+        bytes.extend(frame(MavlinkVersion::V2));
+        bytes.push(0x13);
+        let dump = dump(&bytes);
+
+        assert!(dump.starts_with(
+            "2 bytes without start marker
+    0  00 42                    skipped
+MAVLink 1 frame, 17 bytes
+    2  fe "
+        ));
+        assert!(dump.contains("MAVLink 2 frame, 21 bytes\n   19  fd "));
+        assert!(dump.ends_with(
+            "1 bytes without start marker
+   40  13                       skipped
+"
+        ));
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    pub fn test_signed() {
+        let mut raw = MAVLinkV2MessageRaw::new();
+        raw.serialize_message(
+            COMMON_MSG_HEADER,
+            &MavMessage::HEARTBEAT(get_heartbeat_msg()),
+        );
+        mavlink::signing::sign_frame::<MavMessage>(&mut raw, &[7; 32], 3, 1234);
+        let dump = raw.debug::<MavMessage>().to_string();
+        assert!(dump.starts_with("MAVLink 2 frame, 34 bytes\n"));
+        assert!(dump.contains("incompatibility flags 0x01, signed\n"));
+        assert!(dump.contains(", valid\n"));
+        assert!(dump.contains("   21  03                       link id 3\n"));
+        assert!(dump
+            .contains("   22  d2 04 00 00 00 00        timestamp 1234, 10 us units since 2015\n"));
+        assert!(dump.contains("   28  "));
+        assert!(dump.ends_with("signature\n"));
+    }
+}
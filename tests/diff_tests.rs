@@ -0,0 +1,144 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common", feature = "json"))]
+mod diff_tests {
+    use std::time::Duration;
+
+    use mavlink::common::{MavMessage, ATTITUDE_DATA, HEARTBEAT_DATA};
+    use mavlink::diff::{diff, Differ};
+    use mavlink::recording::{Direction, RecordedMessage, Recording};
+    use mavlink::MavHeader;
+
+    use crate::test_shared::get_heartbeat_msg;
+
+    const START: u64 = 1_700_000_000_000_000;
+
+    fn attitude(time_boot_ms: u32, roll: f32) -> MavMessage {
+        MavMessage::ATTITUDE(ATTITUDE_DATA {
+            time_boot_ms,
+            roll,
+            ..Default::default()
+        })
+    }
+
+    /// Messages from system 1 at the given milliseconds after `start`
+    fn recording(start: u64, messages: Vec<(u64, MavMessage)>) -> Recording<MavMessage> {
+        Recording {
+            messages: messages
+                .into_iter()
+                .map(|(ms, msg)| RecordedMessage {
+                    timestamp: start + ms * 1000,
+                    direction: Direction::Received,
+                    header: MavHeader {
+                        system_id: 1,
+                        component_id: 1,
+                        sequence: 0,
+                    },
+                    msg,
+                })
+                .collect(),
+        }
+    }
+
+    /// Attitudes every 100 ms with the roll given by `roll`
+    fn attitudes(count: u64, roll: impl Fn(u64) -> f32) -> Vec<(u64, MavMessage)> {
+        (0..count)
+            .map(|i| (i * 100, attitude(i as u32 * 100, roll(i))))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_identical() {
+        let before = recording(START, attitudes(10, |i| i as f32));
+        // the streams are aligned by the time since their start, the boot times are ignored
+        let mut after = recording(START + 5_000_000, attitudes(10, |i| i as f32));
+        for message in &mut after.messages {
+            if let MavMessage::ATTITUDE(attitude) = &mut message.msg {
+                attitude.time_boot_ms += 1234;
+            }
+        }
+
+        let diff = diff(&before, &after);
+        assert!(diff.is_empty());
+        assert_eq!(diff.before_duration, Duration::from_millis(900));
+        assert_eq!(diff.messages.len(), 1);
+        assert_eq!(diff.messages[0].name, "ATTITUDE");
+        assert_eq!(diff.messages[0].before, 10);
+        assert!(diff.to_string().ends_with("no differences\n"));
+    }
+
+    #[test]
+    pub fn test_values() {
+        let before = recording(START, attitudes(10, |_| 1.0));
+        let after = recording(START, attitudes(10, |i| if i < 5 { 1.0 } else { 1.5 }));
+
+        let diff = diff(&before, &after);
+        let attitude = &diff.messages[0];
+        assert!(!attitude.rate_changed);
+        assert_eq!(attitude.fields.len(), 1);
+        let roll = &attitude.fields[0];
+        assert_eq!(roll.name, "roll");
+        assert_eq!((roll.compared, roll.differing), (10, 5));
+        assert_eq!(roll.max_difference, 0.5);
+        assert_eq!(roll.mean_difference, 0.25);
+        assert!(diff
+            .to_string()
+            .contains("1:1 ATTITUDE: 11.1 Hz -> 11.1 Hz (+0.0%)\n  roll: 5 of 10 differ, max 0.5, mean 0.25\n"));
+
+        let diff = Differ::new().with_tolerance(0.5).diff(&before, &after);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    pub fn test_rates() {
+        let mut heartbeat = get_heartbeat_msg();
+        let before = recording(
+            START,
+            (0..=10)
+                .map(|i| (i * 100, attitude(0, 0.0)))
+                .chain([(0, MavMessage::HEARTBEAT(heartbeat.clone()))])
+                .collect(),
+        );
+        heartbeat.custom_mode += 1;
+        let after = recording(
+            START,
+            (0..=5)
+                .map(|i| (i * 200, attitude(0, 0.0)))
+                .chain([(1000, MavMessage::HEARTBEAT(heartbeat))])
+                .collect(),
+        );
+
+        let diff = diff(&before, &after);
+        let changed: Vec<_> = diff.changed().map(|message| message.name).collect();
+        assert_eq!(changed, ["ATTITUDE"]);
+
+        let attitude = &diff.messages[1];
+        assert!(attitude.rate_changed);
+        assert_eq!((attitude.before_rate, attitude.after_rate), (11.0, 6.0));
+        assert!(attitude.fields.is_empty());
+
+        // the heartbeats are a second apart, so their values aren't compared
+        let heartbeat = &diff.messages[0];
+        assert!(heartbeat.fields.is_empty());
+        let diff = Differ::new()
+            .with_max_skew(Duration::from_secs(1))
+            .diff(&before, &after);
+        assert_eq!(diff.messages[0].fields[0].name, "custom_mode");
+
+        let diff = Differ::new().with_rate_tolerance(1.0).diff(&before, &after);
+        assert!(!diff.messages[1].rate_changed);
+    }
+
+    #[test]
+    pub fn test_missing() {
+        let heartbeat = MavMessage::HEARTBEAT(HEARTBEAT_DATA::default());
+        let before = recording(START, vec![(0, heartbeat.clone()), (1000, heartbeat)]);
+        let after = recording(START, attitudes(11, |_| 0.0));
+
+        let diff = diff(&before, &after);
+        assert_eq!(diff.changed().count(), 2);
+        let report = diff.to_string();
+        assert!(report.contains("1:1 HEARTBEAT: only before, 2.0 Hz\n"));
+        assert!(report.contains("1:1 ATTITUDE: only after, 11.0 Hz\n"));
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(all(feature = "std", feature = "direct-serial", feature = "common"))]
 mod test_direct_serial {
     use mavlink::common::MavMessage;
+    use mavlink::{MavHeader, MavlinkVersion};
 
     #[test]
     pub fn test_incomplete_address() {
@@ -20,4 +21,54 @@ mod test_direct_serial {
         let conn_result = mavlink::connect::<MavMessage>(bogus_port_str);
         assert!(conn_result.is_err(), "Invalid port should error");
     }
+
+    #[test]
+    pub fn test_nonexistent_port_auto_baud() {
+        let bogus_port_str = "serial:8d73ba8c-eb87-4105-8d0c-2931940e13be:auto";
+        let conn_result = mavlink::connect::<MavMessage>(bogus_port_str);
+        assert!(conn_result.is_err(), "Invalid port should error");
+    }
+
+    fn frame(version: MavlinkVersion) -> Vec<u8> {
+        let msg = MavMessage::HEARTBEAT(mavlink::common::HEARTBEAT_DATA::default());
+        let mut buf = Vec::new();
+        mavlink::write_versioned_msg(&mut buf, version, MavHeader::default(), &msg).unwrap();
+        buf
+    }
+
+    #[test]
+    pub fn test_count_valid_frames() {
+        let mut bytes = vec![0x00, 0x17, 0x42];
+        bytes.extend(frame(MavlinkVersion::V2));
+        bytes.extend(frame(MavlinkVersion::V1));
+        assert_eq!(
+            mavlink::connection::count_valid_frames::<MavMessage>(&bytes),
+            2
+        );
+
+        // a false start marker overlapping a frame doesn't hide it
+        let mut bytes = vec![0xfd, 0x09, 0x00];
+        bytes.extend(frame(MavlinkVersion::V2));
+        assert_eq!(
+            mavlink::connection::count_valid_frames::<MavMessage>(&bytes),
+            1
+        );
+
+        // truncated and corrupted frames, as received at the wrong baud rate
+        let valid = frame(MavlinkVersion::V2);
+        assert_eq!(
+            mavlink::connection::count_valid_frames::<MavMessage>(&valid[..valid.len() - 1]),
+            0
+        );
+        let corrupted: Vec<u8> = valid.iter().map(|byte| byte ^ 0x10).collect();
+        assert_eq!(
+            mavlink::connection::count_valid_frames::<MavMessage>(&corrupted),
+            0
+        );
+        let noise: Vec<u8> = (0..2000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        assert_eq!(
+            mavlink::connection::count_valid_frames::<MavMessage>(&noise),
+            0
+        );
+    }
 }
@@ -0,0 +1,146 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod discovery_tests {
+    use std::time::{Duration, Instant};
+
+    use mavlink::common::{
+        MavAutopilot, MavMessage, MavProtocolCapability, MavState, MavType, AUTOPILOT_VERSION_DATA,
+        HEARTBEAT_DATA,
+    };
+    use mavlink::discovery::{ComponentRegistry, DiscoveryEvent};
+    use mavlink::MavHeader;
+
+    fn header(system_id: u8, component_id: u8) -> MavHeader {
+        MavHeader {
+            system_id,
+            component_id,
+            sequence: 0,
+        }
+    }
+
+    fn heartbeat(mavtype: MavType, autopilot: MavAutopilot, state: MavState) -> MavMessage {
+        MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            mavtype,
+            autopilot,
+            system_status: state,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    pub fn test_discovery() {
+        let start = Instant::now();
+        let mut registry = ComponentRegistry::new().with_timeout(Duration::from_secs(3));
+
+        let autopilot = heartbeat(
+            MavType::MAV_TYPE_QUADROTOR,
+            MavAutopilot::MAV_AUTOPILOT_PX4,
+            MavState::MAV_STATE_STANDBY,
+        );
+        let camera = heartbeat(
+            MavType::MAV_TYPE_CAMERA,
+            MavAutopilot::MAV_AUTOPILOT_INVALID,
+            MavState::MAV_STATE_ACTIVE,
+        );
+
+        assert!(matches!(
+            registry.update(start, &header(1, 100), &camera),
+            Some(DiscoveryEvent::Discovered(_))
+        ));
+        assert!(matches!(
+            registry.update(start, &header(1, 1), &autopilot),
+            Some(DiscoveryEvent::Discovered(_))
+        ));
+        assert_eq!(registry.update(start, &header(1, 1), &autopilot), None);
+
+        let armed = heartbeat(
+            MavType::MAV_TYPE_QUADROTOR,
+            MavAutopilot::MAV_AUTOPILOT_PX4,
+            MavState::MAV_STATE_ACTIVE,
+        );
+        match registry.update(start + Duration::from_secs(2), &header(1, 1), &armed) {
+            Some(DiscoveryEvent::Changed(info)) => {
+                assert_eq!(info.system_status, MavState::MAV_STATE_ACTIVE);
+                assert_eq!(info.first_seen, start);
+            }
+            event => panic!("expected Changed, got {:?}", event),
+        }
+
+        let version = MavMessage::AUTOPILOT_VERSION(AUTOPILOT_VERSION_DATA {
+            capabilities: MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_MISSION_INT,
+            ..Default::default()
+        });
+        assert!(matches!(
+            registry.update(start, &header(1, 1), &version),
+            Some(DiscoveryEvent::Changed(_))
+        ));
+
+        assert_eq!(registry.components().count(), 2);
+        assert_eq!(registry.system(2).count(), 0);
+        let flight_controller = registry.autopilot(1).unwrap();
+        assert_eq!(flight_controller.component_id, 1);
+        assert_eq!(
+            flight_controller.capabilities,
+            Some(MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_MISSION_INT)
+        );
+
+        // the camera stopped sending heartbeats
+        match &registry.expire(start + Duration::from_secs(4))[..] {
+            [DiscoveryEvent::Lost(info)] => assert_eq!(info.component_id, 100),
+            events => panic!("expected the camera to be lost, got {:?}", events),
+        }
+        assert!(registry.get(1, 100).is_none());
+        assert!(registry.get(1, 1).is_some());
+    }
+
+    #[cfg(feature = "udp")]
+    #[test]
+    pub fn test_network_discovery() {
+        use mavlink::discovery::NetworkDiscovery;
+        use mavlink::MavlinkVersion;
+        use std::net::UdpSocket;
+
+        // a port in use is skipped
+        let taken = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let mut discovery = NetworkDiscovery::with_ports(&[taken_port, 0]).unwrap();
+        let ports = discovery.ports();
+        assert_eq!(ports.len(), 1);
+        assert!(NetworkDiscovery::with_ports(&[taken_port]).is_err());
+
+        let vehicle = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send = |version, header: MavHeader, msg: &MavMessage| {
+            let mut buf = Vec::new();
+            mavlink::write_versioned_msg(&mut buf, version, header, msg).unwrap();
+            vehicle.send_to(&buf, ("127.0.0.1", ports[0])).unwrap();
+        };
+        let autopilot = heartbeat(
+            MavType::MAV_TYPE_FIXED_WING,
+            MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
+            MavState::MAV_STATE_STANDBY,
+        );
+        send(MavlinkVersion::V2, header(1, 1), &autopilot);
+        send(MavlinkVersion::V2, header(1, 1), &autopilot);
+        send(MavlinkVersion::V1, header(2, 1), &autopilot);
+
+        let start = Instant::now();
+        let mut detected = Vec::new();
+        while detected.len() < 2 && start.elapsed() < Duration::from_secs(1) {
+            detected.extend(discovery.poll(Instant::now()).unwrap());
+        }
+        assert_eq!(detected.len(), 2);
+        assert_eq!(discovery.endpoints(), &detected[..]);
+        let endpoint = &detected[0];
+        assert_eq!((endpoint.system_id, endpoint.component_id), (1, 1));
+        assert_eq!(endpoint.mavtype, MavType::MAV_TYPE_FIXED_WING);
+        assert_eq!(endpoint.version, MavlinkVersion::V2);
+        assert_eq!(endpoint.source, vehicle.local_addr().unwrap());
+        assert_eq!(
+            endpoint.connection_address(),
+            format!("udpin:0.0.0.0:{}", ports[0])
+        );
+        assert_eq!(detected[1].system_id, 2);
+        assert_eq!(detected[1].version, MavlinkVersion::V1);
+    }
+}
@@ -150,4 +150,46 @@ mod test_encode_decode {
             _ => panic!("Decoded wrong message type"),
         }
     }
+
+    /// HEARTBEAT payload whose `type` field holds a value not defined in MAV_TYPE
+    const HEARTBEAT_UNKNOWN_TYPE_PAYLOAD: &[u8] =
+        &[0x05, 0x00, 0x00, 0x00, 0xfe, 0x03, 0x59, 0x03, 0x03];
+
+    #[test]
+    #[cfg(not(feature = "lenient-decoding"))]
+    pub fn test_strict_rejects_unknown_enum_value() {
+        let res = common::MavMessage::parse(
+            mavlink::MavlinkVersion::V2,
+            0,
+            HEARTBEAT_UNKNOWN_TYPE_PAYLOAD,
+        );
+        assert!(matches!(
+            res,
+            Err(mavlink::error::ParserError::InvalidEnum {
+                enum_type: "MavType",
+                value: 0xfe
+            })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "lenient-decoding")]
+    pub fn test_lenient_defaults_unknown_enum_value() {
+        let msg = common::MavMessage::parse(
+            mavlink::MavlinkVersion::V2,
+            0,
+            HEARTBEAT_UNKNOWN_TYPE_PAYLOAD,
+        )
+        .expect("lenient decoding should accept unknown enum values");
+
+        if let common::MavMessage::HEARTBEAT(msg) = msg {
+            assert_eq!(msg.mavtype, common::MavType::DEFAULT);
+            assert_eq!(
+                msg.autopilot,
+                common::MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA
+            );
+        } else {
+            panic!("Decoded wrong message type")
+        }
+    }
 }
@@ -0,0 +1,129 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod failsafe_tests {
+    use std::time::{Duration, Instant};
+
+    use mavlink::common::{MavMessage, HEARTBEAT_DATA, RADIO_STATUS_DATA, SYS_STATUS_DATA};
+    use mavlink::failsafe::{FailsafeEvent, FailsafeKind, FailsafeMonitor};
+    use mavlink::MavHeader;
+
+    fn header(system_id: u8, component_id: u8) -> MavHeader {
+        MavHeader {
+            system_id,
+            component_id,
+            sequence: 0,
+        }
+    }
+
+    fn radio_status(rssi: u8, remrssi: u8) -> MavMessage {
+        MavMessage::RADIO_STATUS(RADIO_STATUS_DATA {
+            rssi,
+            remrssi,
+            ..Default::default()
+        })
+    }
+
+    fn sys_status(battery_remaining: i8) -> MavMessage {
+        MavMessage::SYS_STATUS(SYS_STATUS_DATA {
+            voltage_battery: 12000,
+            current_battery: -1,
+            battery_remaining,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    pub fn test_heartbeat_lost() {
+        let start = Instant::now();
+        let heartbeat = MavMessage::HEARTBEAT(HEARTBEAT_DATA::default());
+        let mut monitor = FailsafeMonitor::new(1, 1);
+
+        // nothing is lost before the first heartbeat
+        assert!(monitor.check(start + Duration::from_secs(10)).is_empty());
+
+        assert!(monitor.update(start, &header(1, 1), &heartbeat).is_empty());
+        // heartbeats of other components do not count
+        let later = start + Duration::from_secs(2);
+        assert!(monitor
+            .update(later, &header(1, 100), &heartbeat)
+            .is_empty());
+        assert!(monitor.check(later).is_empty());
+
+        let later = start + Duration::from_secs(4);
+        assert_eq!(
+            monitor.check(later),
+            [FailsafeEvent::Triggered(FailsafeKind::HeartbeatLost)]
+        );
+        assert!(monitor.check(later).is_empty());
+        assert!(monitor.is_active(FailsafeKind::HeartbeatLost));
+
+        assert_eq!(
+            monitor.update(later, &header(1, 1), &heartbeat),
+            [FailsafeEvent::Cleared(FailsafeKind::HeartbeatLost)]
+        );
+        assert_eq!(monitor.active().count(), 0);
+    }
+
+    #[test]
+    pub fn test_link_degraded() {
+        let now = Instant::now();
+        let radio = header(51, 68);
+        let mut monitor = FailsafeMonitor::new(1, 1);
+
+        assert!(monitor
+            .update(now, &radio, &radio_status(120, 100))
+            .is_empty());
+        // the worse end of the link counts
+        assert_eq!(
+            monitor.update(now, &radio, &radio_status(120, 40)),
+            [FailsafeEvent::Triggered(FailsafeKind::LinkDegraded)]
+        );
+        // within the hysteresis
+        assert!(monitor
+            .update(now, &radio, &radio_status(120, 55))
+            .is_empty());
+        assert_eq!(
+            monitor.update(now, &radio, &radio_status(120, 60)),
+            [FailsafeEvent::Cleared(FailsafeKind::LinkDegraded)]
+        );
+        // unknown values are ignored
+        assert!(monitor
+            .update(now, &radio, &radio_status(u8::MAX, u8::MAX))
+            .is_empty());
+    }
+
+    #[test]
+    pub fn test_battery_thresholds() {
+        let now = Instant::now();
+        let vehicle = header(1, 1);
+        let mut monitor = FailsafeMonitor::new(1, 1);
+
+        assert!(monitor.update(now, &vehicle, &sys_status(80)).is_empty());
+        // unknown remaining capacity
+        assert!(monitor.update(now, &vehicle, &sys_status(-1)).is_empty());
+        assert_eq!(
+            monitor.update(now, &vehicle, &sys_status(29)),
+            [FailsafeEvent::Triggered(FailsafeKind::BatteryLow)]
+        );
+        assert!(monitor.update(now, &vehicle, &sys_status(31)).is_empty());
+        assert_eq!(
+            monitor.update(now, &vehicle, &sys_status(10)),
+            [FailsafeEvent::Triggered(FailsafeKind::BatteryCritical)]
+        );
+        assert_eq!(
+            monitor.update(now, &vehicle, &sys_status(20)),
+            [FailsafeEvent::Cleared(FailsafeKind::BatteryCritical)]
+        );
+        assert!(monitor.is_active(FailsafeKind::BatteryLow));
+
+        // batteries of other systems are ignored
+        assert!(monitor
+            .update(now, &header(2, 1), &sys_status(90))
+            .is_empty());
+        assert_eq!(
+            monitor.update(now, &vehicle, &sys_status(35)),
+            [FailsafeEvent::Cleared(FailsafeKind::BatteryLow)]
+        );
+    }
+}
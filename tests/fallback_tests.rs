@@ -0,0 +1,51 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common", feature = "ardupilotmega"))]
+mod fallback_tests {
+    use mavlink::error::ParserError;
+    use mavlink::fallback::Fallback;
+    use mavlink::{ardupilotmega, common, MavHeader, MavlinkVersion, Message};
+
+    type Mixed = Fallback<common::MavMessage, ardupilotmega::MavMessage>;
+
+    /// Write a message of one message set and read it back with both
+    fn round_trip<M: Message>(msg: &M) -> Mixed {
+        let mut buf = Vec::new();
+        mavlink::write_v2_msg(&mut buf, MavHeader::default(), msg).unwrap();
+        let (_, msg) =
+            mavlink::read_versioned_msg::<Mixed, _>(&mut buf.as_slice(), MavlinkVersion::V2)
+                .unwrap();
+        msg
+    }
+
+    #[test]
+    pub fn test_first_dialect() {
+        let heartbeat = common::MavMessage::HEARTBEAT(crate::test_shared::get_heartbeat_msg());
+        let msg = round_trip(&heartbeat);
+        assert_eq!(msg.dialect(), "common");
+        assert_eq!(msg, Fallback::First(heartbeat));
+    }
+
+    #[test]
+    pub fn test_fallback_dialect() {
+        let mount_status =
+            ardupilotmega::MavMessage::MOUNT_STATUS(crate::test_shared::get_apm_mount_status());
+        let msg = round_trip(&mount_status);
+        assert_eq!(msg.dialect(), "ardupilotmega");
+        assert_eq!(msg.message_name(), "MOUNT_STATUS");
+        assert_eq!(msg, Fallback::Second(mount_status));
+    }
+
+    #[test]
+    pub fn test_unknown_message() {
+        assert!(matches!(
+            Mixed::parse(MavlinkVersion::V2, 0xABCDEF, &[]),
+            Err(ParserError::UnknownMessage { id: 0xABCDEF })
+        ));
+        assert_eq!(
+            Mixed::message_id_from_name("MOUNT_STATUS"),
+            ardupilotmega::MavMessage::message_id_from_name("MOUNT_STATUS")
+        );
+        assert_eq!(Mixed::dialect_name(), "common");
+    }
+}
@@ -0,0 +1,161 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod faults_tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use mavlink::commands::CommandClient;
+    use mavlink::common::{
+        MavCmd, MavMessage, MavResult, COMMAND_ACK_DATA, COMMAND_LONG_DATA, PING_DATA,
+    };
+    use mavlink::error::MessageReadError;
+    use mavlink::faults::{FaultInjector, FaultModel, FaultStats};
+    use mavlink::request::Requester;
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::{mock_connection_pair, MockConnection};
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    fn ping(seq: u32) -> MavMessage {
+        MavMessage::PING(PING_DATA {
+            seq,
+            ..Default::default()
+        })
+    }
+
+    /// Send pings 0 to `count` from the vehicle end and collect what arrives through the faults
+    fn transfer(model: FaultModel, seed: u64, count: u32) -> (Vec<Option<u32>>, FaultStats) {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let gcs = FaultInjector::new(gcs, seed).with_recv_faults(model);
+        for seq in 0..count {
+            vehicle.send(&VEHICLE, &ping(seq)).unwrap();
+        }
+
+        let mut received = Vec::new();
+        loop {
+            match gcs.recv() {
+                Ok((_, MavMessage::PING(ping))) => received.push(Some(ping.seq)),
+                Ok((_, msg)) => panic!("expected PING, got {:?}", msg),
+                // corrupted beyond parsing
+                Err(MessageReadError::Parse(_)) => received.push(None),
+                Err(MessageReadError::Io(_)) => break,
+            }
+        }
+        (received, gcs.stats())
+    }
+
+    #[test]
+    pub fn test_no_faults() {
+        let (received, stats) = transfer(FaultModel::new(), 1, 5);
+        assert_eq!(received, vec![Some(0), Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(stats, FaultStats::default());
+    }
+
+    #[test]
+    pub fn test_drop_duplicate_reorder() {
+        let (received, stats) = transfer(FaultModel::new().with_drop(1.0), 1, 3);
+        assert!(received.is_empty());
+        assert_eq!(stats.dropped, 3);
+
+        let (received, stats) = transfer(FaultModel::new().with_duplicate(1.0), 1, 2);
+        assert_eq!(received, vec![Some(0), Some(0), Some(1), Some(1)]);
+        assert_eq!(stats.duplicated, 2);
+
+        // every message not releasing a held back one is held back
+        let (received, stats) = transfer(FaultModel::new().with_reorder(1.0), 1, 4);
+        assert_eq!(received, vec![Some(1), Some(0), Some(3), Some(2)]);
+        assert_eq!(stats.reordered, 2);
+    }
+
+    #[test]
+    pub fn test_corrupt() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let gcs = FaultInjector::new(gcs, 1).with_recv_faults(FaultModel::new().with_corrupt(1.0));
+        for seq in 0..20 {
+            vehicle.send(&VEHICLE, &ping(seq)).unwrap();
+            // a single flipped bit always changes the message
+            match gcs.recv() {
+                Ok((_, msg)) => assert_ne!(msg, ping(seq)),
+                Err(e) => assert!(matches!(e, MessageReadError::Parse(_))),
+            }
+        }
+        assert_eq!(gcs.stats().corrupted, 20);
+    }
+
+    #[test]
+    pub fn test_seeded() {
+        let model = FaultModel::new()
+            .with_drop(0.2)
+            .with_duplicate(0.2)
+            .with_reorder(0.2)
+            .with_corrupt(0.1);
+        let first = transfer(model, 42, 50);
+        assert_eq!(first, transfer(model, 42, 50));
+        assert_ne!(first, transfer(model, 43, 50));
+        assert!(first.1.dropped > 0 && first.1.duplicated > 0 && first.1.reordered > 0);
+    }
+
+    #[test]
+    pub fn test_delay() {
+        let model = FaultModel::new().with_delay(Duration::from_millis(30), Duration::ZERO);
+        let start = Instant::now();
+        let (received, _) = transfer(model, 1, 2);
+        assert_eq!(received, vec![Some(0), Some(1)]);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    fn ack() -> MavMessage {
+        MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+            command: MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+            result: MavResult::MAV_RESULT_ACCEPTED,
+            #[cfg(feature = "emit-extensions")]
+            progress: 0,
+            #[cfg(feature = "emit-extensions")]
+            result_param2: 0,
+            #[cfg(feature = "emit-extensions")]
+            target_system: 0,
+            #[cfg(feature = "emit-extensions")]
+            target_component: 0,
+        })
+    }
+
+    #[test]
+    pub fn test_command_retries() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        // this seed loses the first command
+        let model = FaultModel::new().with_drop(0.5);
+        let gcs: FaultInjector<MavMessage, MockConnection<MavMessage>> =
+            FaultInjector::new(gcs, 7).with_send_faults(model);
+
+        let vehicle_thread = thread::spawn(move || loop {
+            if let Ok((_, MavMessage::COMMAND_LONG(_))) = vehicle.recv() {
+                vehicle.send(&VEHICLE, &ack()).unwrap();
+                return;
+            }
+        });
+
+        let requester = Requester::new(&gcs)
+            .with_timeout(Duration::from_millis(20))
+            .with_retries(5);
+        let client = CommandClient::from_requester(requester);
+        let command = COMMAND_LONG_DATA {
+            param1: 1.0,
+            command: MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+            target_system: 1,
+            target_component: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            client.send_command(command).unwrap(),
+            MavResult::MAV_RESULT_ACCEPTED
+        );
+        vehicle_thread.join().unwrap();
+        assert_eq!(gcs.stats().dropped, 1);
+    }
+}
@@ -0,0 +1,139 @@
+mod test_shared;
+
+#[cfg(feature = "ffi")]
+mod ffi_tests {
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+    use std::ptr;
+
+    use mavlink::ffi::*;
+
+    fn c(s: &str) -> std::ffi::CString {
+        std::ffi::CString::new(s).unwrap()
+    }
+
+    #[test]
+    pub fn test_header() {
+        assert!(HEADER.contains("#define MAVLINK_MSG_ID_HEARTBEAT 0\n"));
+        assert!(HEADER.contains("#define MAV_TYPE_QUADROTOR 2u\n"));
+        assert!(HEADER.contains(
+            "intptr_t mavlink_parse(const uint8_t *buf, size_t len, mavlink_message **msg);"
+        ));
+    }
+
+    #[test]
+    pub fn test_build_serialize_parse() {
+        unsafe {
+            let msg = mavlink_message_new(c("STATUSTEXT").as_ptr());
+            assert!(!msg.is_null());
+            assert_eq!(
+                mavlink_message_set_string(msg, c("text").as_ptr(), c("Hello").as_ptr()),
+                MAVLINK_OK
+            );
+            assert_eq!(
+                mavlink_message_set_string(
+                    msg,
+                    c("severity").as_ptr(),
+                    c("MAV_SEVERITY_WARNING").as_ptr()
+                ),
+                MAVLINK_OK
+            );
+            mavlink_message_set_header(msg, 1, 2, 3);
+            let mut frame = [0u8; 300];
+            let len = mavlink_message_serialize(msg, 2, frame.as_mut_ptr(), frame.len());
+            mavlink_message_free(msg);
+            assert!(len > 0);
+
+            // bytes before the frame are skipped
+            let mut stream = vec![0x42];
+            stream.extend_from_slice(&frame[..len as usize]);
+            let mut parsed = ptr::null_mut();
+            assert_eq!(mavlink_parse(stream.as_ptr(), stream.len(), &mut parsed), 1);
+            assert!(parsed.is_null());
+            // incomplete frame
+            assert_eq!(mavlink_parse(stream[1..].as_ptr(), 5, &mut parsed), 0);
+            assert_eq!(
+                mavlink_parse(stream[1..].as_ptr(), stream.len() - 1, &mut parsed),
+                len
+            );
+            assert!(!parsed.is_null());
+
+            assert_eq!(mavlink_message_id(parsed), 253);
+            assert_eq!(
+                CStr::from_ptr(mavlink_message_name(parsed)).to_str(),
+                Ok("STATUSTEXT")
+            );
+            let (mut system_id, mut component_id, mut sequence) = (0, 0, 0);
+            mavlink_message_get_header(parsed, &mut system_id, &mut component_id, &mut sequence);
+            assert_eq!((system_id, component_id, sequence), (1, 2, 3));
+
+            let mut text = [0 as c_char; 32];
+            let text_len =
+                mavlink_message_get_string(parsed, c("text").as_ptr(), text.as_mut_ptr(), 32);
+            assert_eq!(text_len, 5);
+            assert_eq!(CStr::from_ptr(text.as_ptr()).to_str(), Ok("Hello"));
+            mavlink_message_get_string(parsed, c("severity").as_ptr(), text.as_mut_ptr(), 32);
+            assert_eq!(
+                CStr::from_ptr(text.as_ptr()).to_str(),
+                Ok("MAV_SEVERITY_WARNING")
+            );
+            assert_eq!(
+                mavlink_message_get_string(parsed, c("text").as_ptr(), text.as_mut_ptr(), 5),
+                MAVLINK_ERROR_BUFFER_TOO_SMALL
+            );
+            mavlink_message_free(parsed);
+        }
+    }
+
+    #[test]
+    pub fn test_fields() {
+        unsafe {
+            let msg = mavlink_message_new(c("HIL_ACTUATOR_CONTROLS").as_ptr());
+            assert_eq!(
+                mavlink_message_set_float(msg, c("controls[3]").as_ptr(), 0.5),
+                MAVLINK_OK
+            );
+            assert_eq!(
+                mavlink_message_set_int(msg, c("time_usec").as_ptr(), 1_234_567),
+                MAVLINK_OK
+            );
+            // bitmask
+            assert_eq!(
+                mavlink_message_set_int(msg, c("mode").as_ptr(), 128),
+                MAVLINK_OK
+            );
+
+            let mut float = 0.0;
+            let mut int = 0;
+            mavlink_message_get_float(msg, c("controls[3]").as_ptr(), &mut float);
+            assert_eq!(float, 0.5);
+            mavlink_message_get_int(msg, c("time_usec").as_ptr(), &mut int);
+            assert_eq!(int, 1_234_567);
+            mavlink_message_get_int(msg, c("mode").as_ptr(), &mut int);
+            assert_eq!(int, 128);
+
+            assert_eq!(
+                mavlink_message_set_int(msg, c("controls[16]").as_ptr(), 1),
+                MAVLINK_ERROR_UNKNOWN_FIELD
+            );
+            assert_eq!(
+                mavlink_message_set_int(msg, c("nope").as_ptr(), 1),
+                MAVLINK_ERROR_UNKNOWN_FIELD
+            );
+            // out of the range of the field
+            assert_eq!(
+                mavlink_message_set_int(msg, c("time_usec").as_ptr(), -1),
+                MAVLINK_ERROR_INVALID_VALUE
+            );
+            mavlink_message_free(msg);
+
+            let msg = mavlink_message_new(c("HEARTBEAT").as_ptr());
+            assert_eq!(
+                mavlink_message_set_string(msg, c("type").as_ptr(), c("MAV_TYPE_NOPE").as_ptr()),
+                MAVLINK_ERROR_INVALID_VALUE
+            );
+            mavlink_message_free(msg);
+            assert!(mavlink_message_new(c("NOPE").as_ptr()).is_null());
+        }
+    }
+}
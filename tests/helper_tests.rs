@@ -18,4 +18,81 @@ mod helper_tests {
             "Message name does not match"
         );
     }
+
+    #[test]
+    fn test_dialect_identification() {
+        assert_eq!(MavMessage::dialect_name(), "common");
+        assert_eq!(MavMessage::dialect_version(), Some(3));
+        assert_eq!(MavMessage::dialect_number(), Some(0));
+        assert!(mavlink::ENABLED_DIALECTS.contains(&"common"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_entry_alias() {
+        use mavlink::common::{MavCmd, MavFrame};
+
+        // entries replaced by another entry of their enum are aliases of it
+        assert_eq!(
+            MavCmd::MAV_CMD_REQUEST_PROTOCOL_VERSION,
+            MavCmd::MAV_CMD_REQUEST_MESSAGE
+        );
+        assert_eq!(MavFrame::MAV_FRAME_BODY_NED, MavFrame::MAV_FRAME_BODY_FRD);
+        // the others keep their own value
+        assert_eq!(MavFrame::MAV_FRAME_RESERVED_13 as u32, 13);
+    }
+
+    #[test]
+    #[cfg(feature = "ardupilotmega")]
+    fn test_dialect_version_inherited_from_include() {
+        use mavlink::ardupilotmega;
+
+        assert_eq!(ardupilotmega::MavMessage::dialect_name(), "ardupilotmega");
+        assert_eq!(ardupilotmega::MavMessage::dialect_version(), Some(3));
+        assert_eq!(ardupilotmega::MavMessage::dialect_number(), Some(2));
+        assert!(mavlink::ENABLED_DIALECTS.contains(&"ardupilotmega"));
+    }
+
+    #[test]
+    fn test_definition_provenance() {
+        use mavlink::common::{self, MavCmd, MavType, HEARTBEAT_DATA, PING_DATA};
+
+        assert!(common::INCLUDE_GRAPH.contains(&("common.xml", &["standard.xml"])));
+        assert!(common::INCLUDE_GRAPH.contains(&("standard.xml", &["minimal.xml"])));
+        assert!(common::INCLUDE_GRAPH.contains(&("minimal.xml", &[])));
+        assert_eq!(HEARTBEAT_DATA::DEFINITION_FILE, "minimal.xml");
+        assert_eq!(PING_DATA::DEFINITION_FILE, "common.xml");
+        assert_eq!(MavType::DEFINITION_FILES, ["minimal.xml"]);
+        assert_eq!(MavCmd::DEFINITION_FILES, ["common.xml"]);
+    }
+
+    #[test]
+    #[cfg(feature = "ardupilotmega")]
+    fn test_extended_enum_provenance() {
+        use mavlink::ardupilotmega::{MavCmd, INCLUDE_GRAPH};
+
+        let (_, includes) = INCLUDE_GRAPH
+            .iter()
+            .find(|(file, _)| *file == "ardupilotmega.xml")
+            .unwrap();
+        assert!(includes.contains(&"common.xml"));
+        assert_eq!(MavCmd::DEFINITION_FILES[0], "common.xml");
+        assert!(MavCmd::DEFINITION_FILES.contains(&"ardupilotmega.xml"));
+    }
+
+    #[test]
+    fn test_zero_filled_bytes() {
+        use mavlink::bytes::Bytes;
+
+        let mut buf = Bytes::new_zero_filled(&[0x01, 0x02, 0x03], 8);
+        assert_eq!(buf.get_u16_le(), 0x0201);
+        assert_eq!(buf.get_u32_le(), 0x03);
+        assert_eq!(buf.get_u8(), 0);
+        assert!(buf.remaining_bytes().is_empty());
+        assert_eq!(buf.get_i8(), 0);
+
+        let mut buf = Bytes::new_zero_filled(&[0x01, 0x02], 6);
+        assert_eq!(buf.get_array::<4>(), [0x01, 0x02, 0x00, 0x00]);
+        assert_eq!(buf.get_u16_le(), 0);
+    }
 }
@@ -0,0 +1,119 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod high_latency_tests {
+    use mavlink::common::{
+        HlFailureFlag, MavAutopilot, MavMessage, MavSysStatusSensor, MavType,
+        GLOBAL_POSITION_INT_DATA, HEARTBEAT_DATA, NAV_CONTROLLER_OUTPUT_DATA, SYS_STATUS_DATA,
+        VFR_HUD_DATA, WIND_COV_DATA,
+    };
+    use mavlink::high_latency::{expand, HighLatencySummary};
+
+    fn summary() -> HighLatencySummary {
+        let mut summary = HighLatencySummary::new();
+        assert!(summary.update(&MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode: 4,
+            mavtype: MavType::MAV_TYPE_FIXED_WING,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_PX4,
+            ..Default::default()
+        })));
+        assert!(
+            summary.update(&MavMessage::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+                time_boot_ms: 120_000,
+                lat: 473_977_420,
+                lon: 85_455_940,
+                alt: 488_500,
+                hdg: 27_100,
+                ..Default::default()
+            }))
+        );
+        assert!(summary.update(&MavMessage::VFR_HUD(VFR_HUD_DATA {
+            airspeed: 18.4,
+            groundspeed: 21.0,
+            alt: 488.5,
+            climb: -1.24,
+            heading: 271,
+            throttle: 65,
+        })));
+        assert!(summary.update(&MavMessage::NAV_CONTROLLER_OUTPUT(
+            NAV_CONTROLLER_OUTPUT_DATA {
+                alt_error: 11.6,
+                nav_bearing: 90,
+                wp_dist: 1234,
+                ..Default::default()
+            }
+        )));
+        summary
+    }
+
+    #[test]
+    pub fn test_summary() {
+        let summary = summary();
+        let data = summary.data();
+        assert_eq!(data.timestamp, 120_000);
+        assert_eq!(data.latitude, 473_977_420);
+        assert_eq!(data.altitude, 489);
+        assert_eq!(data.target_altitude, 501);
+        assert_eq!(data.heading, 135);
+        assert_eq!(data.target_heading, 45);
+        assert_eq!(data.target_distance, 123);
+        assert_eq!(data.airspeed, 92);
+        assert_eq!(data.groundspeed, 105);
+        assert_eq!(data.climb_rate, -12);
+        assert_eq!(data.throttle, 65);
+        assert_eq!(data.custom_mode, 4);
+        assert_eq!(data.mavtype, MavType::MAV_TYPE_FIXED_WING);
+        assert!(matches!(summary.message(), MavMessage::HIGH_LATENCY2(_)));
+    }
+
+    #[test]
+    pub fn test_failures_and_wind() {
+        let mut summary = HighLatencySummary::new();
+        let sensors = MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_GPS
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_MAG
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_BATTERY;
+        assert!(summary.update(&MavMessage::SYS_STATUS(SYS_STATUS_DATA {
+            onboard_control_sensors_present: sensors,
+            onboard_control_sensors_enabled: sensors,
+            onboard_control_sensors_health: MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_GPS,
+            battery_remaining: 42,
+            ..Default::default()
+        })));
+        assert_eq!(
+            summary.data().failure_flags,
+            HlFailureFlag::HL_FAILURE_FLAG_3D_MAG | HlFailureFlag::HL_FAILURE_FLAG_BATTERY
+        );
+        assert_eq!(summary.data().battery, 42);
+
+        // 4 m/s blowing towards the south, i.e. coming from the north
+        assert!(summary.update(&MavMessage::WIND_COV(WIND_COV_DATA {
+            wind_x: -4.0,
+            ..Default::default()
+        })));
+        assert_eq!(summary.data().windspeed, 20);
+        assert_eq!(summary.data().wind_heading, 0);
+
+        assert!(!summary.update(&MavMessage::PING(Default::default())));
+    }
+
+    #[test]
+    pub fn test_expand() {
+        let summary = summary();
+        let messages = expand(summary.data());
+
+        let mut expanded = HighLatencySummary::new();
+        for msg in &messages {
+            assert!(expanded.update(msg));
+        }
+        assert_eq!(expanded.data(), summary.data());
+
+        match &messages[1] {
+            MavMessage::GLOBAL_POSITION_INT(position) => {
+                assert_eq!(position.lat, 473_977_420);
+                assert_eq!(position.alt, 489_000);
+                assert_eq!(position.hdg, 27_000);
+            }
+            msg => panic!("expected GLOBAL_POSITION_INT, got {:?}", msg),
+        }
+    }
+}
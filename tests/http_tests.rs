@@ -0,0 +1,78 @@
+mod test_shared;
+
+#[cfg(all(feature = "http", feature = "common"))]
+mod http_tests {
+    use mavlink::common::{MavMessage, MavType, HEARTBEAT_DATA};
+    use mavlink::http::HttpBridge;
+    use mavlink::{MavConnection, MavHeader};
+    use serde_json::json;
+
+    use crate::test_shared::mock_connection_pair;
+
+    fn header(system_id: u8, component_id: u8) -> MavHeader {
+        MavHeader {
+            system_id,
+            component_id,
+            sequence: 0,
+        }
+    }
+
+    fn heartbeat(custom_mode: u32) -> MavMessage {
+        MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode,
+            mavtype: MavType::MAV_TYPE_QUADROTOR,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    pub fn test_get_messages() {
+        let (connection, _vehicle) = mock_connection_pair::<MavMessage>();
+        let bridge = HttpBridge::new(Box::new(connection));
+        bridge.record(&header(1, 1), &heartbeat(3));
+        bridge.record(&header(1, 1), &heartbeat(4));
+
+        let response = bridge.handle("GET", "/mavlink", "");
+        assert_eq!(response.status, 200);
+        let stored = &response.body["vehicles"]["1"]["components"]["1"]["messages"]["HEARTBEAT"];
+        assert_eq!(stored["message"]["type"], "HEARTBEAT");
+        assert_eq!(stored["message"]["custom_mode"], 4);
+        assert_eq!(stored["status"]["time"]["counter"], 2);
+
+        let response = bridge.handle(
+            "GET",
+            "/mavlink/vehicles/1/components/1/messages/HEARTBEAT/message/custom_mode",
+            "",
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, json!(4));
+
+        let response = bridge.handle("GET", "/mavlink/vehicles/2", "");
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    pub fn test_post_message() {
+        let (connection, vehicle) = mock_connection_pair::<MavMessage>();
+        let bridge = HttpBridge::new(Box::new(connection)).with_header(header(255, 190));
+
+        // the template can be posted back
+        let template = bridge.handle("GET", "/helper/mavlink?name=HEARTBEAT", "");
+        assert_eq!(template.status, 200);
+        assert_eq!(template.body["header"]["system_id"], 255);
+        let mut posted = template.body.clone();
+        posted["message"]["custom_mode"] = json!(7);
+        posted["message"]["mavtype"] = json!({"type": "MAV_TYPE_QUADROTOR"});
+
+        let response = bridge.handle("POST", "/mavlink", &posted.to_string());
+        assert_eq!(response.status, 200, "{}", response.body);
+        let (received_header, received) = vehicle.recv().unwrap();
+        assert_eq!(received_header.system_id, 255);
+        assert_eq!(received, heartbeat(7));
+
+        let response = bridge.handle("POST", "/mavlink", "{\"message\": {\"type\": \"NOPE\"}}");
+        assert_eq!(response.status, 400);
+        let response = bridge.handle("GET", "/helper/mavlink?name=NOPE", "");
+        assert_eq!(response.status, 404);
+    }
+}
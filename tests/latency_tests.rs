@@ -0,0 +1,144 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod latency_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{MavMessage, PING_DATA};
+    use mavlink::latency::{LatencyMeter, LatencyProbe, LatencyStats, ProbeKind};
+    use mavlink::timesync::TimeSync;
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::mock_connection_pair;
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    const GCS: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+
+    /// PING answer as sent by a component
+    fn ping_answer(request: &MavMessage) -> MavMessage {
+        match request {
+            MavMessage::PING(ping) => MavMessage::PING(PING_DATA {
+                target_system: GCS.system_id,
+                target_component: GCS.component_id,
+                ..ping.clone()
+            }),
+            msg => panic!("expected PING, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    pub fn test_timesync_probe() {
+        let mut probe = LatencyProbe::new(ProbeKind::Timesync);
+        let mut vehicle = TimeSync::new();
+
+        let request = probe.request(1_000_000);
+        // our own request is no answer
+        assert_eq!(probe.handle(2_000_000, &GCS, &request), None);
+
+        let answer = vehicle.handle(42, &GCS, &request).unwrap();
+        assert_eq!(
+            probe.handle(4_000_000, &VEHICLE, &answer),
+            Some(Duration::from_millis(3))
+        );
+        // every probe is answered once
+        assert_eq!(probe.handle(5_000_000, &VEHICLE, &answer), None);
+    }
+
+    #[test]
+    pub fn test_ping_probe() {
+        let mut probe = LatencyProbe::new(ProbeKind::Ping).with_target(1, 1);
+
+        let first = probe.request(1_000_000);
+        let second = probe.request(2_000_000);
+        assert_ne!(first, second);
+        assert_eq!(probe.handle(2_500_000, &GCS, &second), None);
+
+        // answers may arrive out of order, other components are ignored
+        let other = MavHeader {
+            component_id: 2,
+            ..VEHICLE
+        };
+        assert_eq!(probe.handle(3_000_000, &other, &ping_answer(&second)), None);
+        assert_eq!(
+            probe.handle(3_000_000, &VEHICLE, &ping_answer(&second)),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            probe.handle(4_000_000, &VEHICLE, &ping_answer(&first)),
+            Some(Duration::from_millis(3))
+        );
+    }
+
+    #[test]
+    pub fn test_stats() {
+        let mut stats = LatencyStats {
+            sent: 8,
+            ..Default::default()
+        };
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.jitter(), None);
+        for ms in [10, 14, 12, 20] {
+            stats.add_sample(Duration::from_millis(ms));
+        }
+
+        assert_eq!(stats.loss(), 0.5);
+        assert_eq!(stats.min(), Some(Duration::from_millis(10)));
+        assert_eq!(stats.max(), Some(Duration::from_millis(20)));
+        assert_eq!(stats.mean(), Some(Duration::from_millis(14)));
+        assert_eq!(stats.median(), Some(Duration::from_millis(13)));
+        // differences of 4, 2 and 8ms
+        assert_eq!(stats.jitter(), Some(Duration::from_millis(14) / 3));
+        let std_dev = stats.std_dev().unwrap().as_secs_f64();
+        assert!((std_dev - 14f64.sqrt() / 1000.0).abs() < 1e-9);
+        assert!(stats
+            .to_string()
+            .starts_with("8 sent, 4 answered, 50.0% loss"));
+    }
+
+    #[test]
+    pub fn test_meter() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        // the vehicle answers every other probe after 5ms
+        let vehicle_thread = thread::spawn(move || {
+            let mut timesync = TimeSync::new();
+            for i in 0..4 {
+                let (header, request) = loop {
+                    if let Ok(received) = vehicle.recv() {
+                        break received;
+                    }
+                };
+                if i % 2 == 0 {
+                    thread::sleep(Duration::from_millis(5));
+                    let answer = timesync
+                        .handle(timesync.now_ns() + 1, &header, &request)
+                        .unwrap();
+                    vehicle.send(&VEHICLE, &answer).unwrap();
+                }
+            }
+        });
+
+        let stats = LatencyMeter::new(&gcs)
+            .with_header(GCS)
+            .with_interval(Duration::from_millis(10))
+            .with_timeout(Duration::from_millis(50))
+            .measure(4)
+            .unwrap();
+        vehicle_thread.join().unwrap();
+
+        assert_eq!(stats.sent, 4);
+        assert_eq!(stats.samples.len(), 2);
+        assert_eq!(stats.loss(), 0.5);
+        assert!(stats.min().unwrap() >= Duration::from_millis(5));
+    }
+}
@@ -0,0 +1,186 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod log_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{MavMessage, LOG_DATA_DATA, LOG_ENTRY_DATA};
+    use mavlink::error::ProtocolError;
+    use mavlink::logs::{LogClient, LogDownload, LogStep, LOG_DATA_LEN};
+    use mavlink::request::{Exchange, Requester};
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::{mock_connection_pair, MockConnection};
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    fn log(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn data(log: &[u8], ofs: usize) -> MavMessage {
+        let chunk = &log[ofs.min(log.len())..(ofs + LOG_DATA_LEN).min(log.len())];
+        let mut data = [0; LOG_DATA_LEN];
+        data[..chunk.len()].copy_from_slice(chunk);
+        MavMessage::LOG_DATA(LOG_DATA_DATA {
+            ofs: ofs as u32,
+            id: 7,
+            count: chunk.len() as u8,
+            data,
+        })
+    }
+
+    fn entry(id: u16, num_logs: u16, size: u32) -> MavMessage {
+        MavMessage::LOG_ENTRY(LOG_ENTRY_DATA {
+            time_utc: 0,
+            size,
+            id,
+            num_logs,
+            last_log_num: num_logs,
+        })
+    }
+
+    fn recv(vehicle: &MockConnection<MavMessage>) -> Option<MavMessage> {
+        for _ in 0..100 {
+            if let Ok((_, msg)) = vehicle.recv() {
+                return Some(msg);
+            }
+        }
+        None
+    }
+
+    #[test]
+    pub fn test_list() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            assert!(matches!(
+                recv(&vehicle),
+                Some(MavMessage::LOG_REQUEST_LIST(_))
+            ));
+            for id in [2, 1] {
+                vehicle.send(&VEHICLE, &entry(id, 2, 1000)).unwrap();
+            }
+        });
+
+        let client = LogClient::new(&gcs, 1, 1);
+        let entries = client.list().unwrap();
+        assert_eq!(entries.iter().map(|e| e.id).collect::<Vec<_>>(), [1, 2]);
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_download() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let expected = log(1000);
+
+        let vehicle_log = expected.clone();
+        let vehicle_thread = thread::spawn(move || {
+            let mut requests = 0;
+            loop {
+                match recv(&vehicle) {
+                    Some(MavMessage::LOG_REQUEST_DATA(request)) => {
+                        requests += 1;
+                        let end = request.ofs as usize + request.count as usize;
+                        for ofs in (request.ofs as usize..end).step_by(LOG_DATA_LEN) {
+                            vehicle.send(&VEHICLE, &data(&vehicle_log, ofs)).unwrap();
+                            if ofs >= vehicle_log.len() {
+                                break;
+                            }
+                        }
+                    }
+                    Some(MavMessage::LOG_REQUEST_END(_)) => return requests,
+                    msg => panic!("expected a log request, got {:?}", msg),
+                }
+            }
+        });
+
+        let client = LogClient::new(&gcs, 1, 1).with_chunk_size(5 * LOG_DATA_LEN as u32);
+        let mut progress = Vec::new();
+        let downloaded = match entry(7, 1, 1000) {
+            MavMessage::LOG_ENTRY(entry) => client
+                .download(&entry, |p| progress.push(p.received))
+                .unwrap(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(downloaded, expected);
+        assert_eq!(progress.last(), Some(&1000));
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        // 1000 bytes in chunks of 450
+        assert_eq!(vehicle_thread.join().unwrap(), 3);
+    }
+
+    #[test]
+    pub fn test_gap_and_resume() {
+        let log = log(400);
+        let header = VEHICLE;
+        let mut transfer = LogDownload::resume(1, 1, 7, log[..180].to_vec());
+
+        match transfer.start() {
+            MavMessage::LOG_REQUEST_DATA(request) => assert_eq!(request.ofs, 180),
+            msg => panic!("expected LOG_REQUEST_DATA, got {:?}", msg),
+        }
+
+        // a lost packet: the data after it is dropped and requested again
+        assert_eq!(
+            transfer.handle(&header, &data(&log, 270)).unwrap(),
+            LogStep::Wait
+        );
+        assert_eq!(transfer.data().len(), 180);
+        match transfer.on_timeout().unwrap() {
+            LogStep::Send(MavMessage::LOG_REQUEST_DATA(request)) => assert_eq!(request.ofs, 180),
+            step => panic!("expected a new request, got {:?}", step),
+        }
+
+        assert_eq!(
+            transfer.handle(&header, &data(&log, 180)).unwrap(),
+            LogStep::Progress
+        );
+        assert_eq!(
+            transfer.handle(&header, &data(&log, 270)).unwrap(),
+            LogStep::Progress
+        );
+        match transfer.handle(&header, &data(&log, 360)).unwrap() {
+            LogStep::Done(Some(MavMessage::LOG_REQUEST_END(_)), downloaded) => {
+                assert_eq!(downloaded, log)
+            }
+            step => panic!("expected the end of the log, got {:?}", step),
+        }
+    }
+
+    #[test]
+    pub fn test_timeout_keeps_data() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let log = log(500);
+
+        let vehicle_log = log.clone();
+        let vehicle_thread = thread::spawn(move || {
+            // only the first two packets make it, then the link dies
+            if let Some(MavMessage::LOG_REQUEST_DATA(_)) = recv(&vehicle) {
+                vehicle.send(&VEHICLE, &data(&vehicle_log, 0)).unwrap();
+                vehicle.send(&VEHICLE, &data(&vehicle_log, 90)).unwrap();
+            }
+            while recv(&vehicle).is_some() {}
+        });
+
+        let requester = Requester::new(&gcs)
+            .with_timeout(Duration::from_millis(50))
+            .with_retries(2);
+        let client = LogClient::from_requester(requester, 1, 1);
+        let mut received = Vec::new();
+        let result = match entry(7, 1, 500) {
+            MavMessage::LOG_ENTRY(entry) => client.resume(&entry, &mut received, |_| {}),
+            _ => unreachable!(),
+        };
+        assert!(matches!(result, Err(ProtocolError::Timeout)));
+        assert_eq!(received, log[..180]);
+        drop(gcs);
+        vehicle_thread.join().unwrap();
+    }
+}
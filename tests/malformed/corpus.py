@@ -0,0 +1,83 @@
+#!/usr/bin/env python3
+"""Corpus of malformed frames for the parser tests.
+
+    corpus.py    write corpus.txt
+
+Each entry is a stream of malformed frames mixed with valid HEARTBEATs, listed with the sequence
+numbers of the HEARTBEATs a resynchronizing parser recovers from it. Every stream ends with zero
+bytes to complete any frame the parser may still be waiting for.
+"""
+
+import os
+import struct
+
+# HEARTBEAT (id 0, extra crc 50) of tests/test_shared
+HEARTBEAT_ID = 0
+HEARTBEAT_CRC = 50
+HEARTBEAT = struct.pack("<IBBBBB", 5, 2, 3, 0x59, 3, 3)
+PADDING = bytes(280)
+
+
+def x25(data, crc=0xFFFF):
+    for byte in data:
+        tmp = byte ^ (crc & 0xFF)
+        tmp = (tmp ^ (tmp << 4)) & 0xFF
+        crc = ((crc >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4)) & 0xFFFF
+    return crc
+
+
+def v1(sequence, payload=HEARTBEAT, msg_id=HEARTBEAT_ID, extra_crc=HEARTBEAT_CRC):
+    header = struct.pack("<BBBBB", len(payload), sequence, 1, 1, msg_id)
+    crc = x25(header + payload + bytes([extra_crc]))
+    return b"\xfe" + header + payload + struct.pack("<H", crc)
+
+
+def v2(sequence, payload=HEARTBEAT, msg_id=HEARTBEAT_ID, extra_crc=HEARTBEAT_CRC, flags=0,
+       length=None):
+    length = len(payload) if length is None else length
+    header = struct.pack("<BBBBBBB", length, flags, 0, sequence, 1, 1, msg_id & 0xFF)
+    header += struct.pack("<H", msg_id >> 8)
+    crc = x25(header + payload + bytes([extra_crc]))
+    return b"\xfd" + header + payload + struct.pack("<H", crc)
+
+
+# name, version, recovered sequences, stream
+CORPUS = [
+    # a frame cut off in the payload, the next frame starts within its claimed length
+    ("v2_cut_payload", 2, [1], v2(0)[:14] + v2(1)),
+    ("v1_cut_payload", 1, [1], v1(0)[:10] + v1(1)),
+    # start markers in the payload of a valid frame
+    ("v2_stx_in_payload", 2, [2],
+     v2(2, struct.pack("<IBBBBB", 0xFDFDFDFD, 2, 3, 0x59, 3, 3))),
+    ("v1_stx_in_payload", 1, [2],
+     v1(2, struct.pack("<IBBBBB", 0xFEFEFEFE, 2, 3, 0x59, 3, 3))),
+    # a header claiming a long payload that is not there
+    ("v2_bad_length", 2, [3], b"\xfd\xff\x00\x00\x00\x01\x01\x00\x00\x00" + v2(3)),
+    ("v1_bad_length", 1, [3], b"\xfe\xff\x00\x01\x01\x00" + v1(3)),
+    # a length beyond the checksummed payload
+    ("v2_length_mismatch", 2, [5], v2(4, length=20) + v2(5)),
+    # a signed frame missing most of its signature swallows the start of the next frame
+    ("v2_truncated_signature", 2, [6, 8], v2(6, flags=0x01) + bytes(5) + v2(7) + v2(8)),
+    # the layout of frames with unknown incompatibility flags is unknown
+    ("v2_unknown_incompat_flags", 2, [10], v2(9, flags=0x02) + v2(10)),
+    # a message unknown to the message set
+    ("v2_unknown_message", 2, [12], v2(11, bytes(4), msg_id=0xABCDEF, extra_crc=0) + v2(12)),
+    # payloads longer than the message are accepted, shorter ones are zero-filled
+    ("v2_long_payload", 2, [13, 14], v2(13, HEARTBEAT + bytes(3)) + v2(14, b"")),
+    # runs of start markers and empty headers
+    ("v2_garbage", 2, [15], b"\xfd" * 20 + b"\xfd\x00" * 10 + bytes(7) + v2(15)),
+    ("v1_garbage", 1, [15], b"\xfe" * 20 + b"\xfe\x00" * 10 + bytes(7) + v1(15)),
+]
+
+
+def generate():
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "corpus.txt")
+    with open(path, "w") as out:
+        out.write("# name version recovered_sequences stream, generated by corpus.py\n")
+        for name, version, sequences, stream in CORPUS:
+            recovered = ",".join(str(sequence) for sequence in sequences)
+            out.write(f"{name} {version} {recovered} {(stream + PADDING).hex()}\n")
+
+
+if __name__ == "__main__":
+    generate()
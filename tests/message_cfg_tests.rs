@@ -0,0 +1,23 @@
+//! Built with `MAVLINK_MESSAGE_CFG=tests/message_cfg/gated.cfg`, MEMORY_VECT is only generated
+//! with `RUSTFLAGS="--cfg mavlink_test_gated"`. The CI job building it with the option also sets
+//! `MAVLINK_TEST_GATED=1`.
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod message_cfg_tests {
+    use mavlink::common::MavMessage;
+    use mavlink::Message;
+
+    #[test]
+    pub fn test_gated_message() {
+        if option_env!("MAVLINK_MESSAGE_CFG").is_none() {
+            return;
+        }
+        let gated_in = option_env!("MAVLINK_TEST_GATED").is_some();
+        assert_eq!(
+            MavMessage::message_id_from_name("MEMORY_VECT").is_ok(),
+            gated_in
+        );
+        assert_eq!(MavMessage::default_message_from_id(249).is_ok(), gated_in);
+        assert!(MavMessage::message_id_from_name("HEARTBEAT").is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod mission_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{
+        MavMessage, MavMissionResult, MISSION_ACK_DATA, MISSION_COUNT_DATA, MISSION_ITEM_INT_DATA,
+        MISSION_REQUEST_INT_DATA,
+    };
+    use mavlink::error::ProtocolError;
+    use mavlink::missions::{MissionClient, MissionDownload, MissionError};
+    use mavlink::request::{Exchange, Requester};
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::{mock_connection_pair, MockConnection};
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    fn items(count: u16) -> Vec<MISSION_ITEM_INT_DATA> {
+        (0..count)
+            .map(|seq| MISSION_ITEM_INT_DATA {
+                x: 473_977_420 + i32::from(seq),
+                y: 85_455_940,
+                z: 10.0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn recv(vehicle: &MockConnection<MavMessage>) -> MavMessage {
+        loop {
+            if let Ok((_, msg)) = vehicle.recv() {
+                return msg;
+            }
+        }
+    }
+
+    fn ack(result: MavMissionResult) -> MavMessage {
+        MavMessage::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: 255,
+            mavtype: result,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    pub fn test_upload() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            let count = match recv(&vehicle) {
+                MavMessage::MISSION_COUNT(count) => count.count,
+                msg => panic!("expected MISSION_COUNT, got {:?}", msg),
+            };
+
+            let mut received = Vec::new();
+            for seq in 0..count {
+                let request = MavMessage::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+                    seq,
+                    target_system: 255,
+                    ..Default::default()
+                });
+                vehicle.send(&VEHICLE, &request).unwrap();
+                match recv(&vehicle) {
+                    MavMessage::MISSION_ITEM_INT(item) => received.push(item),
+                    msg => panic!("expected MISSION_ITEM_INT, got {:?}", msg),
+                }
+            }
+            vehicle
+                .send(&VEHICLE, &ack(MavMissionResult::MAV_MISSION_ACCEPTED))
+                .unwrap();
+            received
+        });
+
+        let client = MissionClient::new(&gcs, 1, 1);
+        client.upload(&items(3)).unwrap();
+
+        let received = vehicle_thread.join().unwrap();
+        assert_eq!(received.len(), 3);
+        for (seq, item) in received.iter().enumerate() {
+            assert_eq!(usize::from(item.seq), seq);
+            assert_eq!(item.target_system, 1);
+            assert_eq!(item.x, items(3)[seq].x);
+        }
+    }
+
+    #[test]
+    pub fn test_upload_rejected() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            recv(&vehicle);
+            vehicle
+                .send(&VEHICLE, &ack(MavMissionResult::MAV_MISSION_NO_SPACE))
+                .unwrap();
+        });
+
+        let client = MissionClient::new(&gcs, 1, 1);
+        let result = client.upload(&items(100));
+        vehicle_thread.join().unwrap();
+
+        assert!(matches!(
+            result,
+            Err(MissionError::Rejected(
+                MavMissionResult::MAV_MISSION_NO_SPACE
+            ))
+        ));
+    }
+
+    #[test]
+    pub fn test_download() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            assert!(matches!(
+                recv(&vehicle),
+                MavMessage::MISSION_REQUEST_LIST(_)
+            ));
+            let count = MavMessage::MISSION_COUNT(MISSION_COUNT_DATA {
+                count: 2,
+                target_system: 255,
+                ..Default::default()
+            });
+            vehicle.send(&VEHICLE, &count).unwrap();
+
+            loop {
+                match recv(&vehicle) {
+                    MavMessage::MISSION_REQUEST_INT(request) => {
+                        let mut item = items(2)[usize::from(request.seq)].clone();
+                        item.seq = request.seq;
+                        vehicle
+                            .send(&VEHICLE, &MavMessage::MISSION_ITEM_INT(item))
+                            .unwrap();
+                    }
+                    MavMessage::MISSION_ACK(ack) => return ack.mavtype,
+                    msg => panic!("unexpected {:?}", msg),
+                }
+            }
+        });
+
+        let client = MissionClient::new(&gcs, 1, 1);
+        let downloaded = client.download().unwrap();
+
+        assert_eq!(
+            vehicle_thread.join().unwrap(),
+            MavMissionResult::MAV_MISSION_ACCEPTED
+        );
+        assert_eq!(downloaded.len(), 2);
+        assert_eq!(downloaded[0].x, items(2)[0].x);
+        assert_eq!(downloaded[1].seq, 1);
+    }
+
+    #[test]
+    pub fn test_clear() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            assert!(matches!(recv(&vehicle), MavMessage::MISSION_CLEAR_ALL(_)));
+            vehicle
+                .send(&VEHICLE, &ack(MavMissionResult::MAV_MISSION_ACCEPTED))
+                .unwrap();
+        });
+
+        let client = MissionClient::new(&gcs, 1, 1);
+        client.clear().unwrap();
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_timeout() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let requester = Requester::new(&gcs)
+            .with_timeout(Duration::from_millis(20))
+            .with_retries(2);
+        let client = MissionClient::from_requester(requester, 1, 1);
+        assert!(matches!(
+            client.clear(),
+            Err(MissionError::Protocol(ProtocolError::Timeout))
+        ));
+
+        // the initial message and two retries
+        let mut sent = 0;
+        while vehicle.recv().is_ok() {
+            sent += 1;
+        }
+        assert_eq!(sent, 3);
+    }
+
+    #[test]
+    #[cfg(feature = "icarous")]
+    pub fn test_unsupported_message_set() {
+        // the icarous message set does not include the mission messages
+        let (gcs, _vehicle) = mock_connection_pair::<mavlink::icarous::MavMessage>();
+
+        let client = MissionClient::new(&gcs, 1, 1);
+        assert!(matches!(
+            client.clear(),
+            Err(MissionError::Protocol(ProtocolError::Unsupported(_)))
+        ));
+    }
+
+    #[test]
+    pub fn test_ignore_other_systems() {
+        let mut download = MissionDownload::new(1, 1);
+        download.start();
+
+        let other = MavHeader {
+            system_id: 2,
+            ..VEHICLE
+        };
+        let count = MavMessage::MISSION_COUNT(MISSION_COUNT_DATA {
+            count: 5,
+            ..Default::default()
+        });
+        assert!(matches!(
+            download.handle(&other, &count),
+            Ok(mavlink::missions::MissionStep::Wait)
+        ));
+        assert!(matches!(
+            download.handle(&VEHICLE, &count),
+            Ok(mavlink::missions::MissionStep::Send(
+                MavMessage::MISSION_REQUEST_INT(_)
+            ))
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod param_ftp_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{MavMessage, FILE_TRANSFER_PROTOCOL_DATA};
+    use mavlink::ftp::{FtpNak, FtpOpcode, FtpPayload};
+    use mavlink::params::{
+        decode_param_pck, PackedParamError, ParamClient, ParamServer, ParamValue,
+    };
+    use mavlink::request::Requester;
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::{mock_connection_pair, MockConnection};
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    /// ArduPilot packed parameters: ARMING_CHECK (int8), ARMING_RUDDER (int16, 6 byte prefix),
+    /// BATT_CAPACITY (int32), BATT_LOW_VOLT (float, 5 byte prefix)
+    fn param_pck() -> Vec<u8> {
+        let mut pck = vec![0x1b, 0x67, 4, 0, 4, 0];
+        let entries: [(u8, usize, &str, &[u8]); 4] = [
+            (1, 0, "ARMING_CHECK", &[1]),
+            (2, 7, "RUDDER", &2i16.to_le_bytes()),
+            (3, 0, "BATT_CAPACITY", &5200i32.to_le_bytes()),
+            (4, 5, "LOW_VOLT", &10.5f32.to_le_bytes()),
+        ];
+        for (ptype, common_len, name, value) in entries {
+            pck.push(ptype);
+            pck.push(((name.len() as u8 - 1) << 4) | common_len as u8);
+            pck.extend_from_slice(name.as_bytes());
+            pck.extend_from_slice(value);
+            // padding as used by ArduPilot to keep entries from crossing FTP reads
+            pck.push(0);
+        }
+        pck
+    }
+
+    fn expected() -> Vec<(String, ParamValue)> {
+        vec![
+            ("ARMING_CHECK".to_string(), ParamValue::I8(1)),
+            ("ARMING_RUDDER".to_string(), ParamValue::I16(2)),
+            ("BATT_CAPACITY".to_string(), ParamValue::I32(5200)),
+            ("BATT_LOW_VOLT".to_string(), ParamValue::F32(10.5)),
+        ]
+    }
+
+    fn ftp_reply(request: &FtpPayload, opcode: FtpOpcode, offset: u32, data: &[u8]) -> MavMessage {
+        let mut reply = FtpPayload::new(request.seq_number + 1, 0, opcode);
+        reply.req_opcode = request.opcode;
+        reply.size = data.len() as u8;
+        reply.offset = offset;
+        reply.data = data.to_vec();
+        MavMessage::FILE_TRANSFER_PROTOCOL(FILE_TRANSFER_PROTOCOL_DATA {
+            target_network: 0,
+            target_system: 255,
+            target_component: 0,
+            payload: reply.to_bytes(),
+        })
+    }
+
+    fn recv(vehicle: &MockConnection<MavMessage>) -> Option<MavMessage> {
+        for _ in 0..100 {
+            if let Ok((_, msg)) = vehicle.recv() {
+                return Some(msg);
+            }
+        }
+        None
+    }
+
+    #[test]
+    pub fn test_decode_param_pck() {
+        assert_eq!(decode_param_pck(&param_pck()).unwrap(), expected());
+
+        let mut pck = param_pck();
+        pck[0] = 0;
+        assert_eq!(
+            decode_param_pck(&pck),
+            Err(PackedParamError::BadMagic(0x6700))
+        );
+        let pck = param_pck();
+        assert_eq!(
+            decode_param_pck(&pck[..pck.len() - 4]),
+            Err(PackedParamError::Truncated)
+        );
+    }
+
+    #[test]
+    pub fn test_fetch_over_ftp() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            let file = param_pck();
+            while let Some(msg) = recv(&vehicle) {
+                let request = match msg {
+                    MavMessage::FILE_TRANSFER_PROTOCOL(ftp) => {
+                        FtpPayload::from_bytes(&ftp.payload).unwrap()
+                    }
+                    msg => panic!("expected an FTP request, got {:?}", msg),
+                };
+                let reply = match request.opcode {
+                    FtpOpcode::OpenFileRO => {
+                        assert_eq!(request.data, b"@PARAM/param.pck");
+                        ftp_reply(
+                            &request,
+                            FtpOpcode::Ack,
+                            0,
+                            &(file.len() as u32).to_le_bytes(),
+                        )
+                    }
+                    // small reads to exercise reassembly
+                    FtpOpcode::ReadFile => {
+                        let start = request.offset as usize;
+                        let end = (start + 16).min(file.len());
+                        ftp_reply(&request, FtpOpcode::Ack, request.offset, &file[start..end])
+                    }
+                    FtpOpcode::TerminateSession => return,
+                    opcode => panic!("unexpected {:?}", opcode),
+                };
+                vehicle.send(&VEHICLE, &reply).unwrap();
+            }
+        });
+
+        let client = ParamClient::new(&gcs, 1, 1).with_ftp(true);
+        assert_eq!(client.fetch_all().unwrap(), expected());
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_fallback_to_parameter_protocol() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            let mut server = ParamServer::new(1, 1, expected());
+            while let Some(msg) = recv(&vehicle) {
+                if let MavMessage::FILE_TRANSFER_PROTOCOL(ftp) = &msg {
+                    let request = FtpPayload::from_bytes(&ftp.payload).unwrap();
+                    let nak = ftp_reply(&request, FtpOpcode::Nak, 0, &[FtpNak::FileNotFound as u8]);
+                    vehicle.send(&VEHICLE, &nak).unwrap();
+                    continue;
+                }
+                let replies = server.handle(&msg);
+                // lose the second parameter of the initial list
+                let lost = matches!(msg, MavMessage::PARAM_REQUEST_LIST(_));
+                for (index, reply) in replies.iter().enumerate() {
+                    if !(lost && index == 1) {
+                        vehicle.send(&VEHICLE, reply).unwrap();
+                    }
+                }
+            }
+        });
+
+        let requester = Requester::new(&gcs).with_timeout(Duration::from_millis(100));
+        let client = ParamClient::from_requester(requester, 1, 1).with_ftp(true);
+        assert_eq!(client.fetch_all().unwrap(), expected());
+        drop(gcs);
+        vehicle_thread.join().unwrap();
+    }
+}
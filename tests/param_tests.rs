@@ -0,0 +1,171 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod param_tests {
+    use mavlink::common::{
+        MavMessage, MavParamType, PARAM_REQUEST_LIST_DATA, PARAM_REQUEST_READ_DATA, PARAM_SET_DATA,
+    };
+    use mavlink::params::{param_id, param_name, ParamEncoding, ParamServer, ParamValue};
+
+    fn server() -> ParamServer<Vec<(String, ParamValue)>> {
+        ParamServer::new(
+            1,
+            191,
+            vec![
+                ("CAM_RATE".to_string(), ParamValue::F32(2.5)),
+                ("CAM_MODE".to_string(), ParamValue::U8(3)),
+                ("CAM_OFFSET_LONG1".to_string(), ParamValue::I32(-70000)),
+            ],
+        )
+    }
+
+    fn values(replies: &[MavMessage]) -> Vec<(String, u16, u16)> {
+        replies
+            .iter()
+            .map(|msg| match msg {
+                MavMessage::PARAM_VALUE(value) => (
+                    param_name(&value.param_id).unwrap().to_string(),
+                    value.param_index,
+                    value.param_count,
+                ),
+                msg => panic!("expected PARAM_VALUE, got {:?}", msg),
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_request_list() {
+        let mut server = server();
+        let replies = server.handle(&MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: 1,
+            target_component: 0,
+        }));
+        assert_eq!(
+            values(&replies),
+            vec![
+                ("CAM_RATE".to_string(), 0, 3),
+                ("CAM_MODE".to_string(), 1, 3),
+                ("CAM_OFFSET_LONG1".to_string(), 2, 3),
+            ]
+        );
+
+        // addressed to another component
+        let replies = server.handle(&MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: 1,
+            target_component: 1,
+        }));
+        assert!(replies.is_empty());
+    }
+
+    #[test]
+    pub fn test_request_read() {
+        let mut server = server();
+        let by_name = server.handle(&MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            param_index: -1,
+            target_system: 1,
+            target_component: 191,
+            param_id: param_id("CAM_OFFSET_LONG1"),
+        }));
+        assert_eq!(
+            values(&by_name),
+            vec![("CAM_OFFSET_LONG1".to_string(), 2, 3)]
+        );
+
+        let by_index = server.handle(&MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            param_index: 1,
+            target_system: 1,
+            target_component: 191,
+            param_id: [0; 16],
+        }));
+        assert_eq!(values(&by_index), vec![("CAM_MODE".to_string(), 1, 3)]);
+
+        let unknown = server.handle(&MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            param_index: 7,
+            target_system: 1,
+            target_component: 191,
+            param_id: [0; 16],
+        }));
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    pub fn test_set() {
+        let mut server = server();
+        let replies = server.handle(&MavMessage::PARAM_SET(PARAM_SET_DATA {
+            param_value: ParamValue::U8(5).to_wire(ParamEncoding::Bytewise),
+            target_system: 1,
+            target_component: 191,
+            param_id: param_id("CAM_MODE"),
+            param_type: MavParamType::MAV_PARAM_TYPE_UINT8,
+        }));
+        assert_eq!(values(&replies), vec![("CAM_MODE".to_string(), 1, 3)]);
+        assert_eq!(server.table()[1].1, ParamValue::U8(5));
+
+        // wrong type, the current value is reported back
+        let replies = server.handle(&MavMessage::PARAM_SET(PARAM_SET_DATA {
+            param_value: 1.0,
+            target_system: 1,
+            target_component: 191,
+            param_id: param_id("CAM_MODE"),
+            param_type: MavParamType::MAV_PARAM_TYPE_REAL32,
+        }));
+        match &replies[..] {
+            [MavMessage::PARAM_VALUE(value)] => {
+                assert_eq!(value.param_type, MavParamType::MAV_PARAM_TYPE_UINT8);
+                assert_eq!(
+                    ParamValue::from_wire(
+                        value.param_value,
+                        value.param_type,
+                        ParamEncoding::Bytewise
+                    ),
+                    Some(ParamValue::U8(5))
+                );
+            }
+            replies => panic!("unexpected replies {:?}", replies),
+        }
+    }
+
+    #[test]
+    pub fn test_encodings() {
+        let values = [
+            ParamValue::U8(200),
+            ParamValue::I8(-3),
+            ParamValue::U16(65000),
+            ParamValue::I16(-1234),
+            ParamValue::U32(16_000_000),
+            ParamValue::I32(-70000),
+            ParamValue::F32(0.25),
+        ];
+        for encoding in [ParamEncoding::Bytewise, ParamEncoding::CCast] {
+            for value in values {
+                let wire = value.to_wire(encoding);
+                assert_eq!(
+                    ParamValue::from_wire(wire, value.param_type(), encoding),
+                    Some(value)
+                );
+            }
+        }
+
+        assert_eq!(
+            ParamValue::I32(-70000).to_wire(ParamEncoding::CCast),
+            -70000.0
+        );
+        assert_eq!(
+            ParamValue::from_wire(
+                1.0,
+                MavParamType::MAV_PARAM_TYPE_INT64,
+                ParamEncoding::CCast
+            ),
+            None
+        );
+    }
+
+    #[test]
+    pub fn test_param_id() {
+        assert_eq!(param_name(&param_id("CAM_RATE")), Some("CAM_RATE"));
+        assert_eq!(
+            param_name(&param_id("A_NAME_LONGER_THAN_16")),
+            Some("A_NAME_LONGER_TH")
+        );
+    }
+}
@@ -0,0 +1,193 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod parser_tests {
+    use mavlink::common::{MavMessage, COMMAND_INT_DATA, PARAM_REQUEST_LIST_DATA};
+    use mavlink::error::MessageReadError;
+    use mavlink::parser::PushParser;
+    use mavlink::{MavHeader, MavlinkVersion};
+
+    use crate::test_shared::{get_cmd_nav_takeoff_msg, get_heartbeat_msg, COMMON_MSG_HEADER};
+
+    fn frame(version: MavlinkVersion, msg: &MavMessage) -> Vec<u8> {
+        let mut buf = Vec::new();
+        mavlink::write_versioned_msg(&mut buf, version, COMMON_MSG_HEADER, msg).unwrap();
+        buf
+    }
+
+    fn parse_all(parser: &mut PushParser, bytes: &[u8]) -> Vec<(MavHeader, MavMessage)> {
+        bytes
+            .iter()
+            .filter_map(|byte| parser.push::<MavMessage>(*byte))
+            .map(|result| result.unwrap())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_push_frames() {
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        let command = MavMessage::COMMAND_INT(get_cmd_nav_takeoff_msg());
+
+        for version in [MavlinkVersion::V1, MavlinkVersion::V2] {
+            let mut bytes = vec![0x00, 0x42, 0x13];
+            bytes.extend(frame(version, &heartbeat));
+            bytes.extend([0x55; 7]);
+            bytes.extend(frame(version, &command));
+
+            let mut parser = PushParser::new(version);
+            assert_eq!(
+                parse_all(&mut parser, &bytes),
+                [
+                    (COMMON_MSG_HEADER, heartbeat.clone()),
+                    (COMMON_MSG_HEADER, command.clone())
+                ]
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_truncated_payload() {
+        // MAVLink 2 drops the trailing zeroes of the payload
+        let request = MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: 1,
+            target_component: 0,
+        });
+        let command = MavMessage::COMMAND_INT(COMMAND_INT_DATA::default());
+        let mut bytes = frame(MavlinkVersion::V2, &request);
+        bytes.extend(frame(MavlinkVersion::V2, &command));
+
+        let mut parser = PushParser::new(MavlinkVersion::V2);
+        let messages: Vec<_> = parse_all(&mut parser, &bytes)
+            .into_iter()
+            .map(|(_, msg)| msg)
+            .collect();
+        assert_eq!(messages, [request, command]);
+    }
+
+    #[test]
+    pub fn test_bad_crc() {
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        let mut corrupted = frame(MavlinkVersion::V2, &heartbeat);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        corrupted.extend(frame(MavlinkVersion::V2, &heartbeat));
+
+        let mut parser = PushParser::new(MavlinkVersion::V2);
+        assert_eq!(
+            parse_all(&mut parser, &corrupted),
+            [(COMMON_MSG_HEADER, heartbeat)]
+        );
+    }
+
+    #[test]
+    pub fn test_raw_readers() {
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        let mut corrupted = frame(MavlinkVersion::V2, &heartbeat);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        corrupted.extend(frame(MavlinkVersion::V2, &heartbeat));
+
+        // the plain reader returns any frame, whatever its checksum
+        let mut reader = corrupted.as_slice();
+        let raw = mavlink::read_v2_raw_message(&mut reader).unwrap();
+        assert!(!raw.has_valid_crc::<MavMessage>());
+
+        let mut reader = corrupted.as_slice();
+        let raw = mavlink::read_v2_raw_message_checked::<MavMessage, _>(&mut reader).unwrap();
+        assert!(raw.has_valid_crc::<MavMessage>());
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    pub fn test_signed_frame() {
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        let mut signed = frame(MavlinkVersion::V2, &heartbeat);
+        // mark the frame as signed, the checksum covers the flags
+        signed[2] |= 0x01;
+        let crc_start = signed.len() - 2;
+        let crc = {
+            let mut crc = crc_any::CRCu16::crc16mcrf4cc();
+            crc.digest(&signed[1..crc_start]);
+            crc.digest(&[50]); // HEARTBEAT extra crc
+            crc.get_crc()
+        };
+        signed[crc_start..].copy_from_slice(&crc.to_le_bytes());
+        signed.extend([0xaa; 13]);
+        signed.extend(frame(MavlinkVersion::V2, &heartbeat));
+
+        let mut parser = PushParser::new(MavlinkVersion::V2);
+        assert_eq!(parse_all(&mut parser, &signed).len(), 2);
+    }
+
+    /// Streams of malformed frames generated by `tests/malformed/corpus.py`
+    const CORPUS: &str = include_str!("malformed/corpus.txt");
+
+    struct CorpusEntry {
+        name: String,
+        version: MavlinkVersion,
+        sequences: Vec<u8>,
+        stream: Vec<u8>,
+    }
+
+    fn corpus() -> Vec<CorpusEntry> {
+        CORPUS
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let fields: Vec<&str> = line.split(' ').collect();
+                CorpusEntry {
+                    name: fields[0].to_string(),
+                    version: match fields[1] {
+                        "1" => MavlinkVersion::V1,
+                        _ => MavlinkVersion::V2,
+                    },
+                    sequences: fields[2].split(',').map(|s| s.parse().unwrap()).collect(),
+                    stream: (0..fields[3].len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&fields[3][i..i + 2], 16).unwrap())
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_corpus_resync() {
+        let entries = corpus();
+        assert!(!entries.is_empty());
+        for entry in entries {
+            let mut parser = PushParser::new(entry.version);
+            let sequences: Vec<u8> = entry
+                .stream
+                .iter()
+                .filter_map(|byte| parser.push::<MavMessage>(*byte))
+                .filter_map(Result::ok)
+                .map(|(header, msg)| {
+                    assert!(matches!(msg, MavMessage::HEARTBEAT(_)), "{}", entry.name);
+                    header.sequence
+                })
+                .collect();
+            assert_eq!(sequences, entry.sequences, "{}", entry.name);
+        }
+    }
+
+    #[test]
+    pub fn test_corpus_blocking_read() {
+        for entry in corpus() {
+            // the blocking reads resynchronize like the push parser
+            let mut reader = entry.stream.as_slice();
+            let mut sequences = Vec::new();
+            loop {
+                match mavlink::read_versioned_msg::<MavMessage, _>(&mut reader, entry.version) {
+                    Ok((header, msg)) => {
+                        assert!(matches!(msg, MavMessage::HEARTBEAT(_)), "{}", entry.name);
+                        sequences.push(header.sequence);
+                    }
+                    Err(MessageReadError::Parse(_)) => (),
+                    Err(MessageReadError::Io(_)) => break,
+                }
+            }
+            assert_eq!(sequences, entry.sequences, "{}", entry.name);
+        }
+    }
+}
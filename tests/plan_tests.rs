@@ -0,0 +1,113 @@
+mod test_shared;
+
+#[cfg(feature = "qgc-plan")]
+mod plan_tests {
+    use mavlink::common::{MavCmd, MavFrame, MISSION_ITEM_INT_DATA};
+    use mavlink::plan::{Plan, PlanError};
+
+    const PLAN: &str = r#"{
+        "fileType": "Plan",
+        "geoFence": {"circles": [], "polygons": [], "version": 2},
+        "groundStation": "QGroundControl",
+        "mission": {
+            "cruiseSpeed": 15,
+            "firmwareType": 12,
+            "hoverSpeed": 5,
+            "items": [
+                {
+                    "AMSLAltAboveTerrain": null,
+                    "Altitude": 50,
+                    "AltitudeMode": 1,
+                    "autoContinue": true,
+                    "command": 22,
+                    "doJumpId": 1,
+                    "frame": 3,
+                    "params": [0, 0, 0, null, 47.3977419, 8.5455938, 50],
+                    "type": "SimpleItem"
+                },
+                {
+                    "autoContinue": true,
+                    "command": 16,
+                    "doJumpId": 2,
+                    "frame": 3,
+                    "params": [0, 0, 0, null, 47.3980398, 8.5463771, 50],
+                    "type": "SimpleItem"
+                },
+                {
+                    "autoContinue": true,
+                    "command": 20,
+                    "doJumpId": 3,
+                    "frame": 2,
+                    "params": [0, 0, 0, 0, 0, 0, 0],
+                    "type": "SimpleItem"
+                }
+            ],
+            "plannedHomePosition": [47.3977419, 8.5455938, 488.1],
+            "vehicleType": 2,
+            "version": 2
+        },
+        "rallyPoints": {"points": [], "version": 2},
+        "version": 1
+    }"#;
+
+    #[test]
+    pub fn test_import() {
+        let plan = Plan::from_json(PLAN).unwrap();
+        assert_eq!(plan.mission.items.len(), 3);
+
+        let items = plan.mission_items(1, 1).unwrap();
+        assert_eq!(items[0].seq, 0);
+        assert_eq!(items[0].command, MavCmd::MAV_CMD_NAV_TAKEOFF);
+        assert_eq!(items[0].frame, MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT);
+        assert_eq!(items[0].x, 473977419);
+        assert_eq!(items[0].y, 85455938);
+        assert_eq!(items[0].z, 50.0);
+        assert!(items[0].param4.is_nan());
+        assert_eq!(items[1].command, MavCmd::MAV_CMD_NAV_WAYPOINT);
+        assert_eq!(items[2].command, MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH);
+        assert_eq!(items[2].frame, MavFrame::MAV_FRAME_MISSION);
+        assert!(items.iter().all(|item| item.target_system == 1));
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let items = vec![MISSION_ITEM_INT_DATA {
+            param1: 0.0,
+            param2: 2.0,
+            param3: 0.0,
+            param4: f32::NAN,
+            x: 473980398,
+            y: 85463771,
+            z: 30.0,
+            seq: 0,
+            command: MavCmd::MAV_CMD_NAV_WAYPOINT,
+            frame: MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT,
+            autocontinue: 1,
+            ..Default::default()
+        }];
+
+        let plan = Plan::from_mission_items(&items, [47.3977419, 8.5455938, 488.1]);
+        let json = plan.to_json().unwrap();
+        let parsed = Plan::from_json(&json).unwrap();
+        assert_eq!(parsed, plan);
+
+        let restored = parsed.mission_items(1, 1).unwrap();
+        assert_eq!(restored[0].x, items[0].x);
+        assert_eq!(restored[0].y, items[0].y);
+        assert_eq!(restored[0].param2, 2.0);
+        assert!(restored[0].param4.is_nan());
+    }
+
+    #[test]
+    pub fn test_complex_item() {
+        let json = PLAN.replace(
+            r#""items": ["#,
+            r#""items": [{"type": "ComplexItem", "complexItemType": "survey"},"#,
+        );
+        let plan = Plan::from_json(&json).unwrap();
+        match plan.mission_items(1, 1) {
+            Err(PlanError::ComplexItem(kind)) => assert_eq!(kind, "survey"),
+            result => panic!("expected a complex item error, got {:?}", result),
+        }
+    }
+}
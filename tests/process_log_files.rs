@@ -48,8 +48,9 @@ mod process_files {
         }
 
         println!("Number of parsed messages: {counter}");
+        // every frame of the log, including those after start markers in the timestamps
         assert!(
-            counter == 1374,
+            counter == 1426,
             "Unable to hit the necessary amount of matches"
         );
     }
@@ -0,0 +1,73 @@
+mod test_shared;
+
+#[cfg(all(feature = "protobuf", feature = "common"))]
+mod protobuf_tests {
+    use mavlink::common::{MavMessage, MavSeverity, STATUSTEXT_DATA};
+    use mavlink::protobuf::{decode, encode, ProtobufError};
+    use mavlink::MavHeader;
+
+    const HEADER: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 190,
+        sequence: 17,
+    };
+
+    fn statustext() -> MavMessage {
+        let mut text = [0u8; 50];
+        text[..11].copy_from_slice(b"Hello world");
+        MavMessage::STATUSTEXT(STATUSTEXT_DATA {
+            severity: MavSeverity::MAV_SEVERITY_WARNING,
+            text,
+        })
+    }
+
+    #[test]
+    pub fn test_schema() {
+        let schema = mavlink::protobuf::SCHEMA;
+        assert!(schema.contains("syntax = \"proto3\";"));
+        assert!(schema.contains("  MavType type = 2;"));
+        assert!(schema.contains("    HEARTBEAT heartbeat = 16;"));
+    }
+
+    #[test]
+    pub fn test_roundtrip() {
+        let messages = [
+            MavMessage::HEARTBEAT(crate::test_shared::get_heartbeat_msg()),
+            // floats and negative integers
+            MavMessage::COMMAND_INT(crate::test_shared::get_cmd_nav_takeoff_msg()),
+            // packed arrays
+            MavMessage::HIL_ACTUATOR_CONTROLS(crate::test_shared::get_hil_actuator_controls_msg()),
+            statustext(),
+        ];
+        for msg in messages {
+            let bytes = encode(&HEADER, &msg).unwrap();
+            let (header, decoded) = decode::<MavMessage>(&bytes).unwrap();
+            assert_eq!(header, HEADER);
+            assert_eq!(decoded, msg);
+        }
+    }
+
+    #[test]
+    pub fn test_encoding() {
+        let bytes = encode(&HEADER, &statustext()).unwrap();
+        // header fields, then STATUSTEXT (253) as field 269
+        let mut expected = vec![0x08, 1, 0x10, 0xbe, 0x01, 0x18, 17, 0xea, 0x10, 15];
+        // severity, then the string up to its end
+        expected.extend_from_slice(&[0x08, 4, 0x12, 11]);
+        expected.extend_from_slice(b"Hello world");
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    pub fn test_invalid() {
+        assert!(matches!(
+            decode::<MavMessage>(&[0x08, 1]),
+            Err(ProtobufError::MissingMessage)
+        ));
+        let bytes = encode(&HEADER, &statustext()).unwrap();
+        assert!(matches!(
+            decode::<MavMessage>(&bytes[..bytes.len() - 1]),
+            Err(ProtobufError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+mod test_shared;
+
+#[cfg(feature = "python")]
+mod python_tests {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    /// Run `code` with the module imported as `mavlink`
+    fn run(code: &str) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = pyo3::wrap_pymodule!(mavlink::python::mavlink_module)(py);
+            let globals = PyDict::new_bound(py);
+            globals.set_item("mavlink", module).unwrap();
+            if let Err(e) = py.run_bound(code, Some(&globals), None) {
+                e.print(py);
+                panic!("{}", e);
+            }
+        });
+    }
+
+    #[test]
+    pub fn test_message() {
+        run(r#"
+msg = mavlink.Message("STATUSTEXT", severity="MAV_SEVERITY_WARNING", text="Hello")
+assert msg.get_type() == "STATUSTEXT" and msg.get_msgId() == 253
+assert msg.text == "Hello" and msg.severity == "MAV_SEVERITY_WARNING"
+assert msg.to_dict()["mavpackettype"] == "STATUSTEXT"
+assert repr(msg).startswith("STATUSTEXT {")
+
+msg = mavlink.Message("HEARTBEAT", type="MAV_TYPE_QUADROTOR", custom_mode=4)
+msg.base_mode = 128
+assert (msg.type, msg.custom_mode, msg.base_mode) == ("MAV_TYPE_QUADROTOR", 4, 128)
+assert msg.to_dict()["type"] == "MAV_TYPE_QUADROTOR"
+
+msg = mavlink.Message("COMMAND_LONG", param1=float("nan"))
+msg.param2 = 1.5
+assert msg.param1 != msg.param1 and msg.param2 == 1.5
+
+for field, value, error in [
+    ("custom_mode", -1, ValueError),
+    ("type", "MAV_TYPE_NOPE", ValueError),
+    ("nope", 1, AttributeError),
+]:
+    try:
+        setattr(mavlink.Message("HEARTBEAT"), field, value)
+        assert False, field
+    except error:
+        pass
+try:
+    mavlink.Message("STATUSTEXT", text="x" * 51)
+    assert False
+except ValueError:
+    pass
+try:
+    mavlink.Message("NOPE")
+    assert False
+except ValueError:
+    pass
+"#);
+    }
+
+    #[test]
+    pub fn test_pack_parse() {
+        run(r#"
+msg = mavlink.Message("STATUSTEXT", text="Hello")
+msg.set_header(1, 2, 3)
+frame = msg.pack()
+assert frame[0] == 0xfd and msg.pack(1)[0] == 0xfe
+
+parser = mavlink.Parser()
+# invalid bytes are skipped, split frames are completed
+assert parser.parse_buffer(b"\x42" + frame + frame[:5]) == [msg]
+parsed = parser.parse_buffer(frame[5:])
+assert parsed == [msg]
+assert (parsed[0].get_srcSystem(), parsed[0].get_srcComponent(), parsed[0].get_seq()) == (1, 2, 3)
+assert mavlink.Parser(1).parse_buffer(msg.pack(1)) == [msg]
+"#);
+    }
+
+    #[test]
+    pub fn test_connection() {
+        let path = std::env::temp_dir().join("mavlink-python-test.bin");
+        run(&format!(
+            r#"
+path = {path:?}
+heartbeat = mavlink.Message("HEARTBEAT")
+heartbeat.set_header(7, 1)
+text = mavlink.Message("STATUSTEXT", text="Hello")
+with open(path, "wb") as f:
+    f.write(text.pack() + heartbeat.pack() + text.pack())
+
+master = mavlink.mavlink_connection("file:" + path)
+assert master.wait_heartbeat() == heartbeat
+assert (master.target_system, master.target_component) == (7, 1)
+assert master.recv_match(type=["STATUSTEXT"], timeout=1).text == "Hello"
+try:
+    master.recv_msg()
+    assert False
+except mavlink.MavlinkError:
+    pass
+"#,
+            path = path.to_str().unwrap()
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod recording_tests {
+    use std::io;
+
+    use mavlink::common::{MavMessage, HEARTBEAT_DATA, PARAM_REQUEST_LIST_DATA};
+    use mavlink::error::MessageReadError;
+    use mavlink::recording::{Direction, Recorder, Recording, ReplayConnection};
+    use mavlink::{MavConnection, MavHeader, MavlinkVersion};
+
+    use crate::test_shared::mock_connection_pair;
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    const GCS: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+
+    fn heartbeat() -> MavMessage {
+        MavMessage::HEARTBEAT(crate::test_shared::get_heartbeat_msg())
+    }
+
+    fn request() -> MavMessage {
+        MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: 1,
+            target_component: 1,
+        })
+    }
+
+    /// Session of a heartbeat received, a request sent and another heartbeat received
+    fn record_session() -> Recording<MavMessage> {
+        let (connection, vehicle) = mock_connection_pair::<MavMessage>();
+        let recorder = Recorder::new(connection);
+
+        vehicle.send(&VEHICLE, &heartbeat()).unwrap();
+        assert_eq!(recorder.recv().unwrap(), (VEHICLE, heartbeat()));
+        recorder.send(&GCS, &request()).unwrap();
+        assert_eq!(vehicle.recv().unwrap(), (GCS, request()));
+        vehicle
+            .send(&VEHICLE, &MavMessage::HEARTBEAT(HEARTBEAT_DATA::default()))
+            .unwrap();
+        recorder.recv().unwrap();
+
+        recorder.take_recording()
+    }
+
+    #[test]
+    pub fn test_record() {
+        let recording = record_session();
+        let directions: Vec<_> = recording.messages.iter().map(|m| m.direction).collect();
+        assert_eq!(
+            directions,
+            [Direction::Received, Direction::Sent, Direction::Received]
+        );
+        assert_eq!(recording.messages[1].header, GCS);
+        assert_eq!(recording.messages[1].msg, request());
+        assert!(recording
+            .messages
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        assert_eq!(recording.received().count(), 2);
+        assert_eq!(recording.sent().count(), 1);
+    }
+
+    #[test]
+    pub fn test_replay() {
+        let recording = record_session();
+        let replay = ReplayConnection::new(&recording);
+        assert_eq!(replay.remaining(), 2);
+
+        assert_eq!(replay.recv().unwrap(), (VEHICLE, heartbeat()));
+        replay.send(&GCS, &request()).unwrap();
+        assert_eq!(
+            replay.recv().unwrap().1,
+            MavMessage::HEARTBEAT(HEARTBEAT_DATA::default())
+        );
+        assert_eq!(replay.remaining(), 0);
+        assert!(matches!(
+            replay.recv(),
+            Err(MessageReadError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        // what the application sent matches the recorded session
+        let sent: Vec<_> = recording
+            .sent()
+            .map(|m| (m.header, m.msg.clone()))
+            .collect();
+        assert_eq!(replay.sent(), sent);
+    }
+
+    #[test]
+    pub fn test_tlog_round_trip() {
+        let recording = record_session();
+        let mut tlog = Vec::new();
+        recording.write_tlog(&mut tlog, MavlinkVersion::V2).unwrap();
+
+        let loaded =
+            Recording::<MavMessage>::read_tlog(&mut &tlog[..], MavlinkVersion::V2).unwrap();
+        assert_eq!(loaded.messages.len(), recording.messages.len());
+        for (loaded, recorded) in loaded.messages.iter().zip(&recording.messages) {
+            assert_eq!(loaded.timestamp, recorded.timestamp);
+            assert_eq!(loaded.header, recorded.header);
+            assert_eq!(loaded.msg, recorded.msg);
+            // a tlog does not keep the direction
+            assert_eq!(loaded.direction, Direction::Received);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod request_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{
+        MavMessage, MavParamType, MISSION_COUNT_DATA, MISSION_REQUEST_LIST_DATA,
+        PARAM_REQUEST_READ_DATA, PARAM_VALUE_DATA,
+    };
+    use mavlink::error::ProtocolError;
+    use mavlink::request::{Request, Requester};
+    use mavlink::{MavConnection, MavHeader, Message};
+
+    use crate::test_shared::mock_connection_pair;
+
+    fn header(system_id: u8, component_id: u8) -> MavHeader {
+        MavHeader {
+            system_id,
+            component_id,
+            sequence: 0,
+        }
+    }
+
+    fn param_value(param_index: u16) -> MavMessage {
+        MavMessage::PARAM_VALUE(PARAM_VALUE_DATA {
+            param_value: 1.0,
+            param_count: 10,
+            param_index,
+            param_id: [0; 16],
+            param_type: MavParamType::MAV_PARAM_TYPE_REAL32,
+        })
+    }
+
+    fn read_request(param_index: i16) -> Request<'static, MavMessage> {
+        let msg = MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            param_index,
+            target_system: 1,
+            target_component: 1,
+            param_id: [0; 16],
+        });
+        let reply_id = MavMessage::message_id_from_name("PARAM_VALUE").unwrap();
+        Request::new(msg, reply_id)
+            .from_system(1)
+            .from_component(1)
+            .matching(move |msg| {
+                matches!(msg, MavMessage::PARAM_VALUE(value) if value.param_index as i16 == param_index)
+            })
+    }
+
+    #[test]
+    pub fn test_request_reply() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let vehicle_thread = thread::spawn(move || {
+            let mut requests = 0;
+            while requests < 2 {
+                let msg = match vehicle.recv() {
+                    Ok((_, msg)) => msg,
+                    Err(_) => continue,
+                };
+                assert!(matches!(msg, MavMessage::PARAM_REQUEST_READ(_)));
+                requests += 1;
+                // the first request is lost, the replies not matching the request are ignored
+                if requests == 2 {
+                    vehicle.send(&header(1, 100), &param_value(3)).unwrap();
+                    vehicle.send(&header(1, 1), &param_value(2)).unwrap();
+                    vehicle.send(&header(1, 1), &param_value(3)).unwrap();
+                }
+            }
+        });
+
+        let requester = Requester::new(&gcs).with_timeout(Duration::from_millis(100));
+        let (reply_header, reply) = requester.request(&read_request(3)).unwrap();
+        assert_eq!(reply_header.component_id, 1);
+        assert_eq!(reply, param_value(3));
+        vehicle_thread.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_reply_to_other_system_ignored() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let mission_count = |target_system| {
+            MavMessage::MISSION_COUNT(MISSION_COUNT_DATA {
+                count: 4,
+                target_system,
+                target_component: 190,
+            })
+        };
+        // the answer to another ground station arrives first
+        vehicle.send(&header(1, 1), &mission_count(254)).unwrap();
+        vehicle.send(&header(1, 1), &mission_count(255)).unwrap();
+
+        let msg = MavMessage::MISSION_REQUEST_LIST(MISSION_REQUEST_LIST_DATA {
+            target_system: 1,
+            target_component: 1,
+        });
+        let reply_id = MavMessage::message_id_from_name("MISSION_COUNT").unwrap();
+        let requester = Requester::new(&gcs)
+            .with_header(header(255, 190))
+            .with_timeout(Duration::from_millis(50))
+            .with_retries(0);
+        let (_, reply) = requester.request(&Request::new(msg, reply_id)).unwrap();
+        assert_eq!(reply, mission_count(255));
+    }
+
+    #[test]
+    pub fn test_request_timeout() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+
+        let requester = Requester::new(&gcs)
+            .with_timeout(Duration::from_millis(20))
+            .with_retries(2);
+        assert!(matches!(
+            requester.request(&read_request(0)),
+            Err(ProtocolError::Timeout)
+        ));
+
+        // the request was sent once and resent twice
+        let mut sent = 0;
+        while vehicle.recv().is_ok() {
+            sent += 1;
+        }
+        assert_eq!(sent, 3);
+    }
+}
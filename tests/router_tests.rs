@@ -0,0 +1,146 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod router_tests {
+    use mavlink::common::{
+        MavMessage, COMMAND_LONG_DATA, HEARTBEAT_DATA, PARAM_REQUEST_LIST_DATA, SETUP_SIGNING_DATA,
+    };
+    use mavlink::error::MessageWriteError;
+    use mavlink::router::{RouteError, Router};
+    use mavlink::{MavConnection, MavHeader, MavlinkVersion, Message};
+
+    use crate::test_shared::{mock_connection_pair, MockConnection};
+
+    fn header(system_id: u8, component_id: u8) -> MavHeader {
+        MavHeader {
+            system_id,
+            component_id,
+            sequence: 0,
+        }
+    }
+
+    fn heartbeat() -> MavMessage {
+        MavMessage::HEARTBEAT(HEARTBEAT_DATA::default())
+    }
+
+    /// Router with three links, returning the far ends of the links
+    fn router(
+        link_versions: [MavlinkVersion; 3],
+    ) -> (Router<MavMessage>, Vec<MockConnection<MavMessage>>) {
+        let mut router = Router::new();
+        let mut peers = Vec::new();
+        for version in link_versions {
+            let (mut link, peer) = mock_connection_pair::<MavMessage>();
+            link.set_protocol_version(version);
+            router.add_link(Box::new(link));
+            peers.push(peer);
+        }
+        (router, peers)
+    }
+
+    fn received(peer: &MockConnection<MavMessage>) -> Vec<MavMessage> {
+        let mut messages = Vec::new();
+        while let Ok((_, msg)) = peer.recv() {
+            messages.push(msg);
+        }
+        messages
+    }
+
+    #[test]
+    pub fn test_target_system_id() {
+        let command = MavMessage::COMMAND_LONG(COMMAND_LONG_DATA {
+            target_system: 3,
+            target_component: 4,
+            ..Default::default()
+        });
+        assert_eq!(command.target_system_id(), Some(3));
+        assert_eq!(command.target_component_id(), Some(4));
+        assert_eq!(heartbeat().target_system_id(), None);
+        assert_eq!(heartbeat().target_component_id(), None);
+    }
+
+    #[test]
+    pub fn test_routing() {
+        let (router, peers) = router([MavlinkVersion::V2; 3]);
+
+        // broadcasts go everywhere but back
+        assert!(router.handle(0, &header(1, 1), &heartbeat()).is_empty());
+        assert!(router.handle(1, &header(255, 190), &heartbeat()).is_empty());
+        assert_eq!(received(&peers[0]).len(), 1);
+        assert_eq!(received(&peers[1]).len(), 1);
+        assert_eq!(received(&peers[2]).len(), 2);
+        assert_eq!(router.route(1, 1), Some(0));
+
+        // targeted messages only go to the link of the target
+        let request = MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: 1,
+            target_component: 1,
+        });
+        assert!(router.handle(1, &header(255, 190), &request).is_empty());
+        assert_eq!(received(&peers[0]), [request]);
+        assert!(received(&peers[2]).is_empty());
+
+        // unknown targets are not forwarded
+        let request = MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: 7,
+            target_component: 0,
+        });
+        assert!(router.handle(1, &header(255, 190), &request).is_empty());
+        assert!(peers.iter().all(|peer| received(peer).is_empty()));
+    }
+
+    #[test]
+    pub fn test_link_filter() {
+        let mut router = Router::new();
+        let (gcs, _gcs_peer) = mock_connection_pair::<MavMessage>();
+        let (filtered, filtered_peer) = mock_connection_pair::<MavMessage>();
+        router.add_link(Box::new(gcs));
+        router.add_link_with_filter(
+            Box::new(filtered),
+            Box::new(|_, msg: &MavMessage| msg.message_id() != 0),
+        );
+
+        let request = MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA::default());
+        assert!(router.handle(0, &header(255, 190), &heartbeat()).is_empty());
+        assert!(router.handle(0, &header(255, 190), &request).is_empty());
+        assert_eq!(received(&filtered_peer), [request]);
+    }
+
+    #[test]
+    pub fn test_v2_only_message_on_v1_link() {
+        let (router, peers) = router([MavlinkVersion::V2, MavlinkVersion::V1, MavlinkVersion::V2]);
+
+        // SETUP_SIGNING has id 256, which does not fit in a MAVLink 1 frame
+        let msg = MavMessage::SETUP_SIGNING(SETUP_SIGNING_DATA::default());
+        assert!(msg.message_id() > 255);
+        let errors = router.handle(0, &header(255, 190), &msg);
+        match &errors[..] {
+            [RouteError::MAVLink2Only {
+                link: 1,
+                message_id: 256,
+            }] => {}
+            errors => panic!("expected the V1 link to be reported, got {:?}", errors),
+        }
+        assert!(received(&peers[1]).is_empty());
+        assert_eq!(received(&peers[2]), [msg]);
+
+        // messages with small ids still reach the V1 link
+        assert!(router.handle(0, &header(255, 190), &heartbeat()).is_empty());
+        assert_eq!(received(&peers[1]).len(), 1);
+    }
+
+    #[test]
+    pub fn test_v1_writer_rejects_large_ids() {
+        let msg = MavMessage::SETUP_SIGNING(SETUP_SIGNING_DATA::default());
+        let mut buffer = Vec::new();
+        match mavlink::write_v1_msg(&mut buffer, header(1, 1), &msg) {
+            Err(MessageWriteError::MAVLink2Only) => {}
+            result => panic!("expected the message to be refused, got {:?}", result),
+        }
+        assert!(buffer.is_empty());
+        assert!(
+            mavlink::write_versioned_msg(&mut buffer, MavlinkVersion::V2, header(1, 1), &msg)
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Signed frames checked against the fixtures signed by pymavlink with
+//! `tests/signing/fixtures.py`. The ignored `test_pymavlink` exchanges frames with pymavlink
+//! itself, the `signing-interop` CI job runs it.
+
+mod test_shared;
+
+#[cfg(feature = "signing")]
+mod signing_interop_tests {
+    use std::convert::TryInto;
+    use std::process::Command;
+
+    use mavlink::common::MavMessage;
+    use mavlink::signing::{
+        frame_signature_block, sign_frame, FrameSigner, SignatureError, SignatureVerifier,
+        SigningKey, TIMESTAMP_WINDOW,
+    };
+    use mavlink::{read_v2_raw_message, MAVLinkV2MessageRaw, MavHeader, Message};
+
+    use crate::test_shared::{get_heartbeat_msg, COMMON_MSG_HEADER};
+
+    const FIXTURES: &str = include_str!("signing/fixtures.txt");
+
+    struct Fixture {
+        name: String,
+        key: [u8; 32],
+        link_id: u8,
+        timestamp: u64,
+        frame: Vec<u8>,
+    }
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn fixtures() -> Vec<Fixture> {
+        FIXTURES
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let fields: Vec<_> = line.split(' ').collect();
+                Fixture {
+                    name: fields[0].to_string(),
+                    key: hex(fields[1]).try_into().unwrap(),
+                    link_id: fields[2].parse().unwrap(),
+                    timestamp: fields[3].parse().unwrap(),
+                    frame: hex(fields[4]),
+                }
+            })
+            .collect()
+    }
+
+    fn raw(frame: &[u8]) -> MAVLinkV2MessageRaw {
+        read_v2_raw_message(&mut &frame[..]).unwrap()
+    }
+
+    fn key(fixture: &Fixture) -> SigningKey {
+        SigningKey {
+            secret_key: fixture.key,
+            initial_timestamp: 0,
+        }
+    }
+
+    #[test]
+    pub fn test_sign_fixtures() {
+        for fixture in fixtures() {
+            let unsigned = raw(&fixture.frame);
+            let header = MavHeader {
+                sequence: unsigned.sequence(),
+                system_id: unsigned.system_id(),
+                component_id: unsigned.component_id(),
+            };
+            let msg = MavMessage::parse(
+                mavlink::MavlinkVersion::V2,
+                unsigned.message_id(),
+                unsigned.payload(),
+            )
+            .unwrap();
+
+            let mut frame = MAVLinkV2MessageRaw::new();
+            frame.serialize_message(header, &msg);
+            sign_frame::<MavMessage>(&mut frame, &fixture.key, fixture.link_id, fixture.timestamp);
+            assert_eq!(frame.raw_bytes(), &fixture.frame[..], "{}", fixture.name);
+            assert!(frame.has_valid_crc::<MavMessage>(), "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    pub fn test_verify_fixture_frames() {
+        for fixture in fixtures() {
+            let frame = raw(&fixture.frame);
+            assert_eq!(
+                frame_signature_block(&frame).map(|(link_id, timestamp, _)| (link_id, timestamp)),
+                Some((fixture.link_id, fixture.timestamp)),
+                "{}",
+                fixture.name
+            );
+            let mut verifier = SignatureVerifier::new(key(&fixture));
+            assert_eq!(verifier.check(&frame), Ok(()), "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    pub fn test_invalid_signature() {
+        let fixture = &fixtures()[0];
+        let mut verifier = SignatureVerifier::new(key(fixture));
+
+        // any byte covered by the hash, here the timestamp, breaks the signature
+        let mut tampered = fixture.frame.clone();
+        let timestamp = tampered.len() - 7;
+        tampered[timestamp] ^= 1;
+        assert_eq!(
+            verifier.check(&raw(&tampered)),
+            Err(SignatureError::InvalidSignature)
+        );
+
+        let mut other_key = key(fixture);
+        other_key.secret_key[0] ^= 1;
+        assert_eq!(
+            SignatureVerifier::new(other_key).check(&raw(&fixture.frame)),
+            Err(SignatureError::InvalidSignature)
+        );
+
+        let mut unsigned = MAVLinkV2MessageRaw::new();
+        unsigned.serialize_message(
+            COMMON_MSG_HEADER,
+            &MavMessage::HEARTBEAT(get_heartbeat_msg()),
+        );
+        assert_eq!(verifier.check(&unsigned), Err(SignatureError::Unsigned));
+    }
+
+    fn signed(key: &SigningKey, system_id: u8, link_id: u8, timestamp: u64) -> MAVLinkV2MessageRaw {
+        let mut frame = MAVLinkV2MessageRaw::new();
+        let header = MavHeader {
+            system_id,
+            ..COMMON_MSG_HEADER
+        };
+        frame.serialize_message(header, &MavMessage::HEARTBEAT(get_heartbeat_msg()));
+        sign_frame::<MavMessage>(&mut frame, &key.secret_key, link_id, timestamp);
+        frame
+    }
+
+    #[test]
+    pub fn test_timestamp_window() {
+        let key = SigningKey {
+            secret_key: [3; 32],
+            initial_timestamp: 10 * TIMESTAMP_WINDOW,
+        };
+        let mut verifier = SignatureVerifier::new(key);
+        let now = key.initial_timestamp;
+
+        // a new stream may start within the window
+        assert_eq!(
+            verifier.check(&signed(&key, 1, 0, now - TIMESTAMP_WINDOW)),
+            Ok(())
+        );
+        assert_eq!(
+            verifier.check(&signed(&key, 2, 0, now - TIMESTAMP_WINDOW - 1)),
+            Err(SignatureError::TooOld)
+        );
+
+        // timestamps have to increase within a stream
+        assert_eq!(verifier.check(&signed(&key, 1, 0, now)), Ok(()));
+        assert_eq!(
+            verifier.check(&signed(&key, 1, 0, now)),
+            Err(SignatureError::Replayed)
+        );
+        // other link ids are other streams
+        assert_eq!(verifier.check(&signed(&key, 1, 1, now)), Ok(()));
+
+        // the window moves with the newest timestamp
+        let later = now + 2 * TIMESTAMP_WINDOW;
+        assert_eq!(verifier.check(&signed(&key, 1, 0, later)), Ok(()));
+        assert_eq!(verifier.timestamp(), later);
+        assert_eq!(
+            verifier.check(&signed(&key, 3, 0, now)),
+            Err(SignatureError::TooOld)
+        );
+    }
+
+    #[test]
+    pub fn test_frame_signer() {
+        let key = SigningKey::new([9; 32]);
+        let mut signer = FrameSigner::new(key, 2);
+        let mut verifier = SignatureVerifier::new(key);
+
+        let mut last = 0;
+        for _ in 0..3 {
+            let mut frame = MAVLinkV2MessageRaw::new();
+            frame.serialize_message(
+                COMMON_MSG_HEADER,
+                &MavMessage::HEARTBEAT(get_heartbeat_msg()),
+            );
+            signer.sign::<MavMessage>(&mut frame);
+            assert!(signer.timestamp() > last);
+            last = signer.timestamp();
+            assert_eq!(verifier.check(&frame), Ok(()));
+        }
+    }
+
+    fn run_fixtures_script(args: &[&str]) -> String {
+        let output = Command::new("python3")
+            .arg(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/signing/fixtures.py"
+            ))
+            .args(args)
+            .output()
+            .expect("cannot run python3");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Needs python3 with pymavlink installed
+    #[test]
+    #[ignore]
+    pub fn test_pymavlink() {
+        let key = [0x5a; 32];
+        let key_hex: String = key.iter().map(|byte| format!("{byte:02x}")).collect();
+
+        // pymavlink to us
+        let frame = hex(&run_fixtures_script(&["sign", &key_hex, "4", "123456789"]));
+        let mut verifier = SignatureVerifier::new(SigningKey {
+            secret_key: key,
+            initial_timestamp: 0,
+        });
+        assert_eq!(verifier.check(&raw(&frame)), Ok(()));
+
+        // us to pymavlink
+        let frame = signed(
+            &SigningKey {
+                secret_key: key,
+                initial_timestamp: 0,
+            },
+            1,
+            4,
+            987654321,
+        );
+        let frame_hex: String = frame
+            .raw_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(run_fixtures_script(&["verify", &key_hex, &frame_hex]), "ok");
+    }
+}
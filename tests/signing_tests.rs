@@ -0,0 +1,101 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod signing_tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use mavlink::common::MavMessage;
+    use mavlink::signing::{
+        disable_message, send_setup_signing, signing_timestamp, SigningKey, SigningSetup,
+        SigningSetupHandler, SIGNING_EPOCH_SECS,
+    };
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::mock_connection_pair;
+
+    #[test]
+    pub fn test_signing_timestamp() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(SIGNING_EPOCH_SECS);
+        assert_eq!(signing_timestamp(UNIX_EPOCH), 0);
+        assert_eq!(signing_timestamp(epoch + Duration::from_secs(1)), 100_000);
+    }
+
+    #[test]
+    pub fn test_provisioning() {
+        let (gcs, autopilot) = mock_connection_pair::<MavMessage>();
+        let key = SigningKey {
+            secret_key: [42; 32],
+            initial_timestamp: 1000,
+        };
+        send_setup_signing(&gcs, &MavHeader::default(), key.setup_message(1, 1)).unwrap();
+
+        let mut handler = SigningSetupHandler::new(1, 1);
+        let (_, msg) = autopilot.recv().unwrap();
+        assert_eq!(handler.handle(&msg), Some(SigningSetup::Enable(key)));
+        assert_eq!(handler.key(), Some(&key));
+
+        // messages for other components are ignored
+        let other = MavMessage::SETUP_SIGNING(key.setup_message(2, 1));
+        assert_eq!(handler.handle(&other), None);
+
+        // a new key may not move the timestamp back
+        let rotated = SigningKey {
+            secret_key: [7; 32],
+            initial_timestamp: 10,
+        };
+        let msg = MavMessage::SETUP_SIGNING(rotated.setup_message(1, 0));
+        match handler.handle(&msg) {
+            Some(SigningSetup::Enable(installed)) => {
+                assert_eq!(installed.secret_key, [7; 32]);
+                assert_eq!(installed.initial_timestamp, 1000);
+            }
+            setup => panic!("expected the new key, got {:?}", setup),
+        }
+
+        let msg = MavMessage::SETUP_SIGNING(disable_message(1, 1));
+        assert_eq!(handler.handle(&msg), Some(SigningSetup::Disable));
+        assert_eq!(handler.key(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "signing")]
+    pub fn test_signed_connection() {
+        use mavlink::signing::SignedConnection;
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let key = SigningKey::new([42; 32]);
+        let receiver = SignedConnection::new(server.try_clone().unwrap(), server, key, 0);
+        // a sender with another key on the same stream
+        let intruder = SignedConnection::new(
+            client.try_clone().unwrap(),
+            client.try_clone().unwrap(),
+            SigningKey::new([7; 32]),
+            1,
+        );
+        let sender = SignedConnection::new(client.try_clone().unwrap(), client, key, 1);
+
+        let heartbeat = MavMessage::HEARTBEAT(crate::test_shared::get_heartbeat_msg());
+        let forged = MavHeader {
+            system_id: 2,
+            ..MavHeader::default()
+        };
+        intruder.send(&forged, &heartbeat).unwrap();
+        sender.send_default(&heartbeat).unwrap();
+        sender.send_default(&heartbeat).unwrap();
+
+        // only the frames signed with the shared key get through
+        for sequence in 0..2 {
+            let (header, msg) = MavConnection::<MavMessage>::recv(&receiver).unwrap();
+            assert_eq!(header.system_id, MavHeader::default().system_id);
+            assert_eq!(header.sequence, sequence);
+            assert_eq!(msg, heartbeat);
+        }
+    }
+}
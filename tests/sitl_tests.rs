@@ -0,0 +1,238 @@
+//! End-to-end tests against an ArduPilot or PX4 SITL, ignored by default.
+//!
+//! `MAVLINK_SITL_COMMAND` is run with `sh -c` to launch the simulator for each test and killed
+//! afterwards, if unset a running simulator is used. `MAVLINK_SITL_ADDRESS` is the connection
+//! address, `udpin:0.0.0.0:14550` by default. Run the tests one at a time, e.g.
+//!
+//! ```sh
+//! MAVLINK_SITL_COMMAND="sim_vehicle.py -v ArduCopter --no-rebuild" \
+//!     cargo test --test sitl_tests -- --ignored --test-threads=1
+//! MAVLINK_SITL_COMMAND="cd PX4-Autopilot && make px4_sitl none_iris" \
+//!     cargo test --test sitl_tests -- --ignored --test-threads=1
+//! ```
+
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "udp", feature = "tcp", feature = "common"))]
+mod sitl_tests {
+    use std::process::{Child, Command};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::{
+        MavAutopilot, MavCmd, MavFrame, MavMessage, MavModeFlag, MavState, MavType, HEARTBEAT_DATA,
+        MISSION_ITEM_INT_DATA,
+    };
+    use mavlink::missions::MissionClient;
+    use mavlink::params::{ParamClient, ParamValue};
+    use mavlink::request::Requester;
+    use mavlink::{MavConnection, MavHeader};
+
+    /// Time for the simulator to start and send its first heartbeat
+    const BOOT_TIMEOUT: Duration = Duration::from_secs(120);
+
+    const GCS: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+
+    type Connection = Arc<Box<dyn MavConnection<MavMessage> + Send + Sync>>;
+
+    /// A running simulator, connected and receiving our heartbeats
+    struct Sitl {
+        process: Option<Child>,
+        connection: Connection,
+        running: Arc<AtomicBool>,
+        heartbeat: HEARTBEAT_DATA,
+        system_id: u8,
+        component_id: u8,
+    }
+
+    impl Sitl {
+        fn start() -> Self {
+            let process = std::env::var("MAVLINK_SITL_COMMAND").ok().map(|command| {
+                Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .spawn()
+                    .expect("cannot launch the simulator")
+            });
+            let address = std::env::var("MAVLINK_SITL_ADDRESS")
+                .unwrap_or_else(|_| "udpin:0.0.0.0:14550".to_string());
+
+            // tcpout fails until the simulator listens
+            let connection = loop {
+                match mavlink::connect::<MavMessage>(&address) {
+                    Ok(connection) => break Arc::new(connection),
+                    Err(_) if address.starts_with("tcpout:") => {
+                        thread::sleep(Duration::from_secs(1))
+                    }
+                    Err(e) => panic!("cannot connect to {}: {}", address, e),
+                }
+            };
+
+            let running = Arc::new(AtomicBool::new(true));
+            {
+                let connection = connection.clone();
+                let running = running.clone();
+                thread::spawn(move || {
+                    let heartbeat = MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+                        custom_mode: 0,
+                        mavtype: MavType::MAV_TYPE_GCS,
+                        autopilot: MavAutopilot::MAV_AUTOPILOT_INVALID,
+                        base_mode: MavModeFlag::empty(),
+                        system_status: MavState::MAV_STATE_ACTIVE,
+                        mavlink_version: 3,
+                    });
+                    while running.load(Ordering::Relaxed) {
+                        let _ = connection.send(&GCS, &heartbeat);
+                        thread::sleep(Duration::from_secs(1));
+                    }
+                });
+            }
+
+            // an udpin connection blocks until a packet arrives, so wait in another thread
+            let (sender, receiver) = mpsc::channel();
+            {
+                let connection = connection.clone();
+                thread::spawn(move || loop {
+                    match connection.recv() {
+                        Ok((header, MavMessage::HEARTBEAT(heartbeat)))
+                            if heartbeat.autopilot != MavAutopilot::MAV_AUTOPILOT_INVALID =>
+                        {
+                            let _ = sender.send((header, heartbeat));
+                            return;
+                        }
+                        _ => {}
+                    }
+                });
+            }
+            let (header, heartbeat) = receiver
+                .recv_timeout(BOOT_TIMEOUT)
+                .expect("no heartbeat from the simulator");
+
+            Self {
+                process,
+                connection,
+                running,
+                heartbeat,
+                system_id: header.system_id,
+                component_id: header.component_id,
+            }
+        }
+    }
+
+    impl Drop for Sitl {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::Relaxed);
+            if let Some(process) = &mut self.process {
+                let _ = process.kill();
+                let _ = process.wait();
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    pub fn test_heartbeat() {
+        let sitl = Sitl::start();
+        assert!(matches!(
+            sitl.heartbeat.autopilot,
+            MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA | MavAutopilot::MAV_AUTOPILOT_PX4
+        ));
+        assert_eq!(sitl.heartbeat.mavlink_version, 3);
+        assert_eq!(sitl.component_id, 1);
+
+        // the heartbeats keep coming while we send ours
+        let mut heartbeats = 0;
+        while heartbeats < 3 {
+            if let Ok((header, MavMessage::HEARTBEAT(_))) = sitl.connection.recv() {
+                if header.system_id == sitl.system_id && header.component_id == 1 {
+                    heartbeats += 1;
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    pub fn test_params() {
+        let sitl = Sitl::start();
+        let requester = Requester::new(&**sitl.connection)
+            .with_header(GCS)
+            .with_timeout(Duration::from_secs(5))
+            .with_retries(5);
+        let params = ParamClient::from_requester(requester, sitl.system_id, sitl.component_id)
+            .fetch_all()
+            .unwrap();
+        assert!(params.len() > 100);
+
+        // the parameter holding the system id of the vehicle
+        let (_, system_id) = params
+            .iter()
+            .find(|(name, _)| name == "SYSID_THISMAV" || name == "MAV_SYS_ID")
+            .expect("no system id parameter");
+        let system_id = match *system_id {
+            ParamValue::I8(value) => i64::from(value),
+            ParamValue::I16(value) => i64::from(value),
+            ParamValue::I32(value) => i64::from(value),
+            ParamValue::F32(value) => value as i64,
+            value => panic!("unexpected type of the system id: {:?}", value),
+        };
+        assert_eq!(system_id, i64::from(sitl.system_id));
+    }
+
+    #[test]
+    #[ignore]
+    pub fn test_mission_upload() {
+        let sitl = Sitl::start();
+        let requester = Requester::new(&**sitl.connection)
+            .with_header(GCS)
+            .with_timeout(Duration::from_secs(2))
+            .with_retries(5);
+        let client = MissionClient::from_requester(requester, sitl.system_id, sitl.component_id);
+
+        // waypoints around the vehicle, within the distance PX4 accepts
+        let (lat, lon) = loop {
+            if let Ok((header, MavMessage::GLOBAL_POSITION_INT(position))) = sitl.connection.recv()
+            {
+                if header.system_id == sitl.system_id {
+                    break (position.lat, position.lon);
+                }
+            }
+        };
+        let items: Vec<_> = (0..4)
+            .map(|seq| MISSION_ITEM_INT_DATA {
+                seq,
+                frame: MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT,
+                command: if seq == 1 {
+                    MavCmd::MAV_CMD_NAV_TAKEOFF
+                } else {
+                    MavCmd::MAV_CMD_NAV_WAYPOINT
+                },
+                autocontinue: 1,
+                x: lat + i32::from(seq) * 1000,
+                y: lon + i32::from(seq) * 1000,
+                z: 20.0,
+                target_system: sitl.system_id,
+                target_component: sitl.component_id,
+                ..Default::default()
+            })
+            .collect();
+        client.upload(&items).unwrap();
+
+        let downloaded = client.download().unwrap();
+        assert_eq!(downloaded.len(), items.len());
+        // ArduPilot replaces the first item with the home position
+        for (uploaded, downloaded) in items.iter().zip(&downloaded).skip(1) {
+            assert_eq!(downloaded.command, uploaded.command);
+            assert_eq!((downloaded.x, downloaded.y), (uploaded.x, uploaded.y));
+            assert_eq!(downloaded.z, uploaded.z);
+        }
+
+        client.clear().unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod stats_tests {
+    use std::time::{Duration, Instant};
+
+    use mavlink::common::{MavMessage, ATTITUDE_DATA, HEARTBEAT_DATA};
+    use mavlink::stats::{
+        frame_size, message_frame_size, message_payload_size, sequence_gap, MeteredConnection,
+        SequenceCounter, TrafficStats,
+    };
+    use mavlink::{MavConnection, MavHeader, MavlinkVersion, MessageData};
+
+    use crate::test_shared::{get_heartbeat_msg, mock_connection_pair};
+
+    const AUTOPILOT: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    const CAMERA: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 100,
+        sequence: 0,
+    };
+
+    #[test]
+    pub fn test_frame_size() {
+        assert_eq!(frame_size(MavlinkVersion::V1, 9), 17);
+        assert_eq!(frame_size(MavlinkVersion::V2, 9), 21);
+
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        assert_eq!(message_frame_size(MavlinkVersion::V1, &heartbeat), 17);
+        assert_eq!(message_payload_size(MavlinkVersion::V1, &heartbeat), 9);
+        // an empty message keeps one payload byte
+        let empty = MavMessage::ATTITUDE(ATTITUDE_DATA::default());
+        assert_eq!(message_frame_size(MavlinkVersion::V2, &empty), 13);
+    }
+
+    #[test]
+    pub fn test_snapshot() {
+        let start = Instant::now();
+        let mut stats = TrafficStats::new(start);
+        for _ in 0..10 {
+            stats.record(&AUTOPILOT, ATTITUDE_DATA::ID, 40);
+        }
+        for _ in 0..2 {
+            stats.record(&AUTOPILOT, HEARTBEAT_DATA::ID, 21);
+            stats.record(&CAMERA, HEARTBEAT_DATA::ID, 21);
+        }
+
+        let snapshot = stats.snapshot(start + Duration::from_secs(2));
+        assert_eq!(snapshot.elapsed, Duration::from_secs(2));
+        let attitude = snapshot.get(1, 1, ATTITUDE_DATA::ID).unwrap();
+        assert_eq!((attitude.messages, attitude.bytes), (10, 400));
+        assert_eq!((attitude.rate, attitude.bandwidth), (5.0, 200.0));
+        assert_eq!(attitude.interval(), Some(Duration::from_millis(200)));
+
+        let heartbeat = snapshot.message(HEARTBEAT_DATA::ID);
+        assert_eq!((heartbeat.messages, heartbeat.bytes), (4, 84));
+        assert_eq!(snapshot.component(1, 1).messages, 12);
+        assert_eq!(snapshot.component(1, 100).bandwidth, 21.0);
+        assert_eq!(snapshot.system(1).messages, 14);
+        assert_eq!(snapshot.total().bytes, 484);
+
+        let by_message: Vec<_> = snapshot.by_message().iter().map(|(id, _)| *id).collect();
+        assert_eq!(by_message, vec![ATTITUDE_DATA::ID, HEARTBEAT_DATA::ID]);
+
+        // taking a snapshot starts a new period
+        let end = start + Duration::from_secs(4);
+        assert_eq!(stats.take_snapshot(end).total().messages, 14);
+        assert_eq!(stats.snapshot(end).total().messages, 0);
+        assert_eq!(stats.snapshot(end).total().interval(), None);
+    }
+
+    #[test]
+    pub fn test_metered_connection() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let gcs = MeteredConnection::new(gcs);
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+
+        vehicle.send(&AUTOPILOT, &heartbeat).unwrap();
+        vehicle.send(&AUTOPILOT, &heartbeat).unwrap();
+        gcs.recv().unwrap();
+        gcs.recv().unwrap();
+        gcs.send(&CAMERA, &heartbeat).unwrap();
+
+        let received = gcs.received();
+        assert_eq!(received.total().messages, 2);
+        assert_eq!(
+            received.get(1, 1, HEARTBEAT_DATA::ID).unwrap().bytes,
+            2 * message_payload_size(MavlinkVersion::V2, &heartbeat) as u64
+        );
+        assert_eq!(gcs.sent().component(1, 100).messages, 1);
+
+        gcs.reset();
+        assert_eq!(gcs.received().total().messages, 0);
+    }
+
+    fn with_sequence(header: MavHeader, sequence: u8) -> MavHeader {
+        MavHeader { sequence, ..header }
+    }
+
+    #[test]
+    pub fn test_sequence_gap() {
+        assert_eq!(sequence_gap(10, 11), 0);
+        assert_eq!(sequence_gap(10, 13), 2);
+        // the counter wraps from 255 to 0
+        assert_eq!(sequence_gap(255, 0), 0);
+        assert_eq!(sequence_gap(254, 0), 1);
+        assert_eq!(sequence_gap(250, 2), 7);
+        // a repeated sequence number is a duplicate, not a full wrap
+        assert_eq!(sequence_gap(42, 42), 0);
+    }
+
+    #[test]
+    pub fn test_sequence_counter_rollover() {
+        let mut counter = SequenceCounter::new();
+        assert_eq!(counter.update(253), 0);
+        assert_eq!(counter.update(254), 0);
+        assert_eq!(counter.update(1), 2);
+        assert_eq!(counter.update(2), 0);
+        assert_eq!((counter.received, counter.lost), (4, 2));
+        assert_eq!(counter.loss(), 2.0 / 6.0);
+
+        // frames after the reset are still compared with the last one before it
+        counter.reset();
+        assert_eq!(counter.last(), Some(2));
+        assert_eq!(counter.update(4), 1);
+        assert_eq!((counter.received, counter.lost), (1, 1));
+    }
+
+    #[test]
+    pub fn test_sequences_per_component() {
+        let start = Instant::now();
+        let mut stats = TrafficStats::new(start);
+        for sequence in (250..=255).chain(0..5) {
+            stats.record_sequence(&with_sequence(AUTOPILOT, sequence));
+        }
+        // the camera counts independently and loses 0 and 1 across the wrap
+        for sequence in [254, 255, 2] {
+            stats.record_sequence(&with_sequence(CAMERA, sequence));
+        }
+
+        let snapshot = stats.take_snapshot(start + Duration::from_secs(1));
+        let autopilot = snapshot.sequence(1, 1).unwrap();
+        assert_eq!((autopilot.received, autopilot.lost), (11, 0));
+        let camera = snapshot.sequence(1, 100).unwrap();
+        assert_eq!((camera.received, camera.lost), (3, 2));
+
+        stats.record_sequence(&with_sequence(AUTOPILOT, 6));
+        let autopilot = *stats.snapshot(start).sequence(1, 1).unwrap();
+        assert_eq!((autopilot.received, autopilot.lost), (1, 1));
+    }
+
+    #[test]
+    pub fn test_metered_connection_loss() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let gcs = MeteredConnection::new(gcs);
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+
+        for sequence in [255, 0, 3] {
+            vehicle
+                .send(&with_sequence(AUTOPILOT, sequence), &heartbeat)
+                .unwrap();
+            gcs.recv().unwrap();
+        }
+        gcs.send(&CAMERA, &heartbeat).unwrap();
+
+        let autopilot = *gcs.received().sequence(1, 1).unwrap();
+        assert_eq!((autopilot.received, autopilot.lost), (3, 2));
+        // sent frames get their sequence numbers from the connection
+        assert!(gcs.sent().sequences.is_empty());
+    }
+}
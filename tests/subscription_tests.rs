@@ -0,0 +1,90 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod subscription_tests {
+    use std::time::Duration;
+
+    use mavlink::common::{MavMessage, ATTITUDE_DATA, HEARTBEAT_DATA};
+    use mavlink::subscription::Dispatcher;
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::{get_heartbeat_msg, mock_connection_pair};
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn attitude(roll: f32) -> MavMessage {
+        MavMessage::ATTITUDE(ATTITUDE_DATA {
+            roll,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    pub fn test_fan_out() {
+        let dispatcher = Dispatcher::<MavMessage>::new();
+        let ui = dispatcher.subscribe::<ATTITUDE_DATA>();
+        let controller = dispatcher.subscribe::<ATTITUDE_DATA>();
+        let heartbeats = dispatcher.subscribe::<HEARTBEAT_DATA>();
+
+        let header = MavHeader::default();
+        dispatcher.dispatch(&header, &attitude(0.5));
+        dispatcher.dispatch(&header, &MavMessage::HEARTBEAT(get_heartbeat_msg()));
+
+        assert_eq!(ui.try_recv().unwrap().1.roll, 0.5);
+        assert_eq!(controller.try_recv().unwrap().1.roll, 0.5);
+        assert!(ui.try_recv().is_none());
+        assert_eq!(heartbeats.try_recv(), Some((header, get_heartbeat_msg())));
+    }
+
+    #[test]
+    pub fn test_dropped_subscription() {
+        let dispatcher = Dispatcher::<MavMessage>::new();
+        let kept = dispatcher.subscribe::<ATTITUDE_DATA>();
+        drop(dispatcher.subscribe::<ATTITUDE_DATA>());
+        assert_eq!(dispatcher.subscription_count(), 2);
+
+        dispatcher.dispatch(&MavHeader::default(), &attitude(0.5));
+        assert_eq!(dispatcher.subscription_count(), 1);
+        assert!(kept.try_recv().is_some());
+
+        // the stream ends with the dispatcher
+        drop(dispatcher);
+        assert_eq!(kept.count(), 0);
+    }
+
+    #[test]
+    pub fn test_spawn() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let dispatcher = Dispatcher::spawn(Box::new(gcs));
+        let attitudes = dispatcher.subscribe::<ATTITUDE_DATA>();
+
+        for roll in [0.1, 0.2] {
+            vehicle
+                .send(&MavHeader::default(), &attitude(roll))
+                .unwrap();
+            vehicle
+                .send(
+                    &MavHeader::default(),
+                    &MavMessage::HEARTBEAT(get_heartbeat_msg()),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(attitudes.recv_timeout(TIMEOUT).unwrap().1.roll, 0.1);
+        assert_eq!(attitudes.recv_timeout(TIMEOUT).unwrap().1.roll, 0.2);
+    }
+
+    #[test]
+    pub fn test_drop_spawned() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let dispatcher = Dispatcher::spawn(Box::new(gcs));
+        let mut attitudes = dispatcher.subscribe::<ATTITUDE_DATA>();
+
+        vehicle.send(&MavHeader::default(), &attitude(0.1)).unwrap();
+        assert_eq!(attitudes.recv_timeout(TIMEOUT).unwrap().1.roll, 0.1);
+
+        // the thread does not keep the dispatcher alive
+        drop(dispatcher);
+        assert!(attitudes.next().is_none());
+    }
+}
@@ -0,0 +1,149 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod synthetic_tests {
+    use std::time::Duration;
+
+    use mavlink::common::{MavMessage, ATTITUDE_DATA, GPS_RAW_INT_DATA};
+    use mavlink::synthetic::{FlightPath, TelemetryGenerator};
+    use mavlink::{MavConnection, Message, MessageData};
+
+    use crate::test_shared::mock_connection_pair;
+
+    const CIRCLE: FlightPath = FlightPath::Circle {
+        lat: 47.397742,
+        lon: 8.545594,
+        alt: 50.0,
+        radius: 100.0,
+        speed: 10.0,
+    };
+
+    /// Number of messages of each kind produced in a number of seconds, polling at 100 Hz
+    fn counts(generator: &mut TelemetryGenerator, seconds: u64) -> Vec<(&'static str, usize)> {
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for step in 0..seconds * 100 {
+            for msg in generator.poll(Duration::from_millis(step * 10)) {
+                match counts
+                    .iter_mut()
+                    .find(|(name, _)| *name == msg.message_name())
+                {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((msg.message_name(), 1)),
+                }
+            }
+        }
+        counts.sort();
+        counts
+    }
+
+    #[test]
+    pub fn test_rates() {
+        let mut generator = TelemetryGenerator::new(CIRCLE);
+        assert_eq!(
+            counts(&mut generator, 2),
+            [
+                ("ATTITUDE", 20),
+                ("GLOBAL_POSITION_INT", 10),
+                ("GPS_RAW_INT", 10),
+                ("HEARTBEAT", 2)
+            ]
+        );
+
+        let mut generator = TelemetryGenerator::new(CIRCLE)
+            .with_rate(ATTITUDE_DATA::ID, 50.0)
+            .with_rate(GPS_RAW_INT_DATA::ID, 0.0);
+        assert_eq!(
+            counts(&mut generator, 1),
+            [
+                ("ATTITUDE", 50),
+                ("GLOBAL_POSITION_INT", 5),
+                ("HEARTBEAT", 1)
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_circle() {
+        // a quarter of the circle takes 2 pi r / 4 / v seconds
+        let quarter = Duration::from_secs_f64(std::f64::consts::PI * 100.0 / 2.0 / 10.0);
+        let start = CIRCLE.state(Duration::ZERO);
+        let east = CIRCLE.state(quarter);
+
+        // starts north of the center heading east, then is east of it heading south
+        assert!((start.lat - 47.397742 - 100.0 / 6_371_000.0 * 57.29578).abs() < 1e-6);
+        assert!((start.lon - 8.545594).abs() < 1e-9);
+        assert!((start.yaw.to_degrees() - 90.0).abs() < 0.01);
+        assert!((start.ve - 10.0).abs() < 0.01);
+        assert!((east.lat - 47.397742).abs() < 1e-6);
+        assert!(east.lon > 8.545594);
+        assert!((east.yaw.to_degrees() - 180.0).abs() < 0.01);
+        assert!((east.vn + 10.0).abs() < 0.01);
+
+        // banked into the turn
+        assert!(start.roll > 0.0);
+    }
+
+    #[test]
+    pub fn test_line() {
+        let path = FlightPath::Line {
+            from: (47.0, 8.0),
+            to: (47.001, 8.0),
+            alt: 20.0,
+            speed: 5.0,
+        };
+        // about 111 m long, so after 30 s it has turned back
+        let out = path.state(Duration::from_secs(10));
+        let back = path.state(Duration::from_secs(30));
+        assert!(out.lat > 47.0 && out.lat < 47.001);
+        assert!(out.vn > 4.99);
+        assert!(out.yaw.abs() < 0.01);
+        assert!(back.vn < -4.99);
+        assert!((back.yaw.to_degrees() - 180.0).abs() < 0.01);
+        assert!(back.lat > 47.0 && back.lat < 47.001);
+    }
+
+    #[test]
+    pub fn test_messages() {
+        let mut generator = TelemetryGenerator::new(FlightPath::Hover {
+            lat: 47.5,
+            lon: 8.5,
+            alt: 30.0,
+        });
+        let messages = generator.poll(Duration::ZERO);
+        assert_eq!(messages.len(), 4);
+        let gps = messages
+            .iter()
+            .find_map(|msg| match msg {
+                MavMessage::GPS_RAW_INT(gps) => Some(gps),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            (gps.lat, gps.lon, gps.alt),
+            (475_000_000, 85_000_000, 30_000)
+        );
+        assert_eq!(gps.vel, 0);
+        // nothing more is due until the next interval
+        assert!(generator.poll(Duration::from_millis(50)).is_empty());
+        assert_eq!(generator.next_due(), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    pub fn test_run() {
+        let (connection, gcs) = mock_connection_pair::<MavMessage>();
+        let mut generator = TelemetryGenerator::new(CIRCLE).with_ids(3, 1);
+        let sent = generator
+            .run(&connection, Duration::from_millis(300))
+            .unwrap();
+        // one heartbeat, four attitudes and two of each position message
+        assert_eq!(sent, 9);
+
+        let mut sequence = 0;
+        while let Ok((header, _)) = gcs.recv() {
+            assert_eq!((header.system_id, header.component_id), (3, 1));
+            assert_eq!(header.sequence, sequence);
+            sequence += 1;
+        }
+        assert_eq!(sequence, 9);
+    }
+}
@@ -0,0 +1,54 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod telemetry_tests {
+    use mavlink::common::{MavCmd, MavDataStream, MavMessage};
+    use mavlink::telemetry::{request_telemetry, telemetry_requests, TelemetryProfile};
+    use mavlink::{MavConnection, MavHeader};
+
+    use crate::test_shared::mock_connection_pair;
+
+    #[test]
+    pub fn test_requests() {
+        let requests = telemetry_requests(TelemetryProfile::Attitude, 1, 1);
+        assert_eq!(requests.len(), 2);
+
+        match &requests[0] {
+            MavMessage::REQUEST_DATA_STREAM(request) => {
+                assert_eq!(
+                    request.req_stream_id,
+                    MavDataStream::MAV_DATA_STREAM_EXTRA1 as u8
+                );
+                assert_eq!(request.req_message_rate, 10);
+                assert_eq!(request.start_stop, 1);
+                assert_eq!(request.target_system, 1);
+            }
+            msg => panic!("expected REQUEST_DATA_STREAM, got {:?}", msg),
+        }
+
+        match &requests[1] {
+            MavMessage::COMMAND_LONG(command) => {
+                assert_eq!(command.command, MavCmd::MAV_CMD_SET_MESSAGE_INTERVAL);
+                // ATTITUDE at 10Hz
+                assert_eq!(command.param1, 30.0);
+                assert_eq!(command.param2, 100_000.0);
+            }
+            msg => panic!("expected COMMAND_LONG, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    pub fn test_request_telemetry() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        request_telemetry(&gcs, &MavHeader::default(), 1, 1, TelemetryProfile::All).unwrap();
+
+        let mut received = 0;
+        while vehicle.recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(
+            received,
+            TelemetryProfile::All.streams().len() + TelemetryProfile::All.messages().len()
+        );
+    }
+}
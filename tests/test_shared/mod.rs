@@ -104,3 +104,63 @@ pub fn get_apm_mount_status() -> mavlink::ardupilotmega::MOUNT_STATUS_DATA {
         target_component: 3,
     }
 }
+
+/// In-memory connection, messages sent on one end are received on the other one
+#[cfg(feature = "std")]
+pub struct MockConnection<M> {
+    rx: std::sync::Mutex<std::sync::mpsc::Receiver<(mavlink::MavHeader, M)>>,
+    tx: std::sync::Mutex<std::sync::mpsc::Sender<(mavlink::MavHeader, M)>>,
+    protocol_version: mavlink::MavlinkVersion,
+}
+
+#[cfg(feature = "std")]
+pub fn mock_connection_pair<M>() -> (MockConnection<M>, MockConnection<M>) {
+    let (tx_a, rx_b) = std::sync::mpsc::channel();
+    let (tx_b, rx_a) = std::sync::mpsc::channel();
+    (
+        MockConnection {
+            rx: std::sync::Mutex::new(rx_a),
+            tx: std::sync::Mutex::new(tx_a),
+            protocol_version: mavlink::MavlinkVersion::V2,
+        },
+        MockConnection {
+            rx: std::sync::Mutex::new(rx_b),
+            tx: std::sync::Mutex::new(tx_b),
+            protocol_version: mavlink::MavlinkVersion::V2,
+        },
+    )
+}
+
+#[cfg(feature = "std")]
+impl<M: mavlink::Message + Clone> mavlink::MavConnection<M> for MockConnection<M> {
+    fn recv(&self) -> Result<(mavlink::MavHeader, M), mavlink::error::MessageReadError> {
+        // behave like a connection with a read timeout
+        self.rx
+            .lock()
+            .unwrap()
+            .recv_timeout(std::time::Duration::from_millis(10))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::WouldBlock).into())
+    }
+
+    fn send(
+        &self,
+        header: &mavlink::MavHeader,
+        data: &M,
+    ) -> Result<usize, mavlink::error::MessageWriteError> {
+        // refuse what the real connections cannot write either
+        if self.protocol_version == mavlink::MavlinkVersion::V1 && data.message_id() > 255 {
+            return Err(mavlink::error::MessageWriteError::MAVLink2Only);
+        }
+        // the other end may be gone at the end of a test
+        let _ = self.tx.lock().unwrap().send((*header, data.clone()));
+        Ok(0)
+    }
+
+    fn set_protocol_version(&mut self, version: mavlink::MavlinkVersion) {
+        self.protocol_version = version;
+    }
+
+    fn get_protocol_version(&self) -> mavlink::MavlinkVersion {
+        self.protocol_version
+    }
+}
@@ -0,0 +1,80 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod timestamps_tests {
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    use mavlink::common::SYSTEM_TIME_DATA;
+    use mavlink::timestamps::{
+        system_time_to_usec, usec_to_system_time, BootClock, LocalClock, Timestamp,
+    };
+
+    // 2023-01-01T00:00:00Z
+    const UNIX_USEC: u64 = 1_672_531_200_000_000;
+
+    #[test]
+    pub fn test_infer_format() {
+        assert_eq!(Timestamp::from_usec(0), None);
+        assert_eq!(
+            Timestamp::from_usec(90_000_000),
+            Some(Timestamp::SinceBoot(Duration::from_secs(90)))
+        );
+        assert_eq!(
+            Timestamp::from_usec(UNIX_USEC),
+            Some(Timestamp::Unix(
+                UNIX_EPOCH + Duration::from_secs(1_672_531_200)
+            ))
+        );
+        assert_eq!(
+            system_time_to_usec(usec_to_system_time(UNIX_USEC)),
+            UNIX_USEC
+        );
+    }
+
+    #[test]
+    pub fn test_boot_clock() {
+        let received = Instant::now() + Duration::from_secs(3600);
+        let clock = BootClock::from_system_time(
+            &SYSTEM_TIME_DATA {
+                time_unix_usec: UNIX_USEC,
+                time_boot_ms: 60_000,
+            },
+            received,
+        );
+
+        // one minute after boot
+        assert_eq!(clock.boot_ms_to_instant(60_000), received);
+        assert_eq!(
+            clock.boot_ms_at(received + Duration::from_millis(500)),
+            60_500
+        );
+        assert_eq!(
+            clock.boot_ms_to_system_time(61_000),
+            Some(usec_to_system_time(UNIX_USEC + 1_000_000))
+        );
+        assert_eq!(
+            clock.to_system_time(Timestamp::from_usec(120_000_000).unwrap()),
+            Some(usec_to_system_time(UNIX_USEC + 60_000_000))
+        );
+        assert_eq!(
+            clock.to_instant(Timestamp::from_usec(UNIX_USEC + 2_000_000).unwrap()),
+            Some(received + Duration::from_secs(2))
+        );
+
+        // without wall clock only boot relative times can be converted
+        let clock = BootClock::from_boot_ms(60_000, received);
+        assert_eq!(clock.boot_ms_to_system_time(61_000), None);
+        assert_eq!(
+            clock.to_instant(Timestamp::from_boot_ms(61_000)),
+            Some(received + Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    pub fn test_local_clock() {
+        let clock = LocalClock::with_boot(Instant::now() - Duration::from_secs(10));
+        let system_time = clock.system_time();
+        assert!(system_time.time_boot_ms >= 10_000);
+        assert!(system_time.time_unix_usec > UNIX_USEC);
+    }
+}
@@ -0,0 +1,91 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod timesync_tests {
+    use mavlink::common::{MavMessage, TIMESYNC_DATA};
+    use mavlink::timesync::TimeSync;
+    use mavlink::MavHeader;
+
+    const VEHICLE: MavHeader = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence: 0,
+    };
+
+    const GCS: MavHeader = MavHeader {
+        system_id: 255,
+        component_id: 190,
+        sequence: 0,
+    };
+
+    #[test]
+    pub fn test_respond() {
+        let mut vehicle = TimeSync::new();
+        let request = TimeSync::new().request(1_000, 1, 1);
+
+        match vehicle.handle(5_000_000, &GCS, &request) {
+            Some(MavMessage::TIMESYNC(answer)) => {
+                assert_eq!(answer.tc1, 5_000_000);
+                assert_eq!(answer.ts1, 1_000);
+            }
+            answer => panic!("expected TIMESYNC, got {:?}", answer),
+        }
+
+        // answers are not answered
+        let answer = MavMessage::TIMESYNC(TIMESYNC_DATA {
+            tc1: 1,
+            ts1: 2,
+            #[cfg(feature = "emit-extensions")]
+            target_system: 255,
+            #[cfg(feature = "emit-extensions")]
+            target_component: 190,
+        });
+        assert_eq!(vehicle.handle(0, &GCS, &answer), None);
+    }
+
+    #[test]
+    pub fn test_offset_estimate() {
+        // the vehicle clock is 1s ahead, every exchange takes 2ms with some jitter
+        const OFFSET_NS: i64 = 1_000_000_000;
+        let mut gcs = TimeSync::new();
+        let mut vehicle = TimeSync::new();
+
+        let mut now = 0;
+        for i in 0..50 {
+            let jitter = (i % 5) * 100_000;
+            let request = gcs.request(now, 1, 1);
+            let answer = vehicle
+                .handle(now + 1_000_000 + jitter + OFFSET_NS, &GCS, &request)
+                .unwrap();
+            assert_eq!(gcs.handle(now + 2_000_000, &VEHICLE, &answer), None);
+            now += 100_000_000;
+        }
+
+        let estimate = gcs.estimate(1).unwrap();
+        assert_eq!(estimate.samples, 50);
+        assert_eq!(estimate.rtt_ns, 2_000_000);
+        assert!((estimate.offset_ns - OFFSET_NS).abs() < 300_000);
+        assert_eq!(
+            gcs.to_local_ns(1, OFFSET_NS + 42),
+            Some(42 + OFFSET_NS - estimate.offset_ns)
+        );
+        assert_eq!(gcs.estimate(2), None);
+    }
+
+    #[test]
+    pub fn test_reject_stale_and_slow() {
+        let mut gcs = TimeSync::new().with_max_rtt_ns(10_000_000);
+
+        let old_request = gcs.request(0, 1, 1);
+        gcs.request(1_000, 1, 1);
+        let mut vehicle = TimeSync::new();
+        let stale = vehicle.handle(500, &GCS, &old_request).unwrap();
+        gcs.handle(2_000, &VEHICLE, &stale);
+        assert_eq!(gcs.estimate(1), None);
+
+        let request = gcs.request(10_000, 1, 1);
+        let slow = vehicle.handle(20_000, &GCS, &request).unwrap();
+        gcs.handle(50_000_000, &VEHICLE, &slow);
+        assert_eq!(gcs.estimate(1), None);
+    }
+}
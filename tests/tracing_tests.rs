@@ -0,0 +1,105 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common", feature = "tracing"))]
+mod tracing_tests {
+    use std::fmt::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use mavlink::common::MavMessage;
+    use mavlink::{MavlinkVersion, Message};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::test_shared::{get_heartbeat_msg, COMMON_MSG_HEADER};
+
+    /// Records the events as lines of `LEVEL message field=value ...`
+    #[derive(Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<String>>>);
+
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                write!(self.0, " {:?}", value).unwrap();
+            } else {
+                write!(self.0, " {}={:?}", field.name(), value).unwrap();
+            }
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line(event.metadata().level().to_string());
+            event.record(&mut line);
+            self.0.lock().unwrap().push(line.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn collect(f: impl FnOnce()) -> Vec<String> {
+        let collector = Collector::default();
+        tracing::subscriber::with_default(collector.clone(), f);
+        let lines = collector.0.lock().unwrap().clone();
+        lines
+    }
+
+    #[test]
+    pub fn test_frames() {
+        let heartbeat = MavMessage::HEARTBEAT(get_heartbeat_msg());
+        let lines = collect(|| {
+            let mut buf = Vec::new();
+            mavlink::write_versioned_msg(
+                &mut buf,
+                MavlinkVersion::V2,
+                COMMON_MSG_HEADER,
+                &heartbeat,
+            )
+            .unwrap();
+            let mut corrupted = buf.clone();
+            corrupted[12] ^= 0xff;
+            corrupted.extend(&buf);
+            let (_, msg) = mavlink::read_versioned_msg::<MavMessage, _>(
+                &mut &corrupted[..],
+                MavlinkVersion::V2,
+            )
+            .unwrap();
+            assert_eq!(msg.message_id(), 0);
+        });
+
+        assert_eq!(
+            lines,
+            [
+                "TRACE sent MAVLink 2 frame msgid=0 sysid=1 compid=1 seq=239 len=21",
+                "DEBUG dropped MAVLink 2 frame with invalid checksum msgid=0 sysid=1 compid=1",
+                "TRACE received MAVLink 2 frame msgid=0 sysid=1 compid=1 seq=239",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_connect() {
+        let lines = collect(|| {
+            assert!(mavlink::connect::<MavMessage>("bogus:address").is_err());
+        });
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "DEBUG connecting address=\"bogus:address\"");
+        assert!(lines[1].starts_with("WARN connecting failed address=\"bogus:address\""));
+    }
+}
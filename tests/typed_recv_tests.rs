@@ -0,0 +1,79 @@
+mod test_shared;
+
+#[cfg(all(feature = "std", feature = "common"))]
+mod typed_recv_tests {
+    use std::time::{Duration, Instant};
+
+    use mavlink::common::{MavMessage, HEARTBEAT_DATA, PARAM_REQUEST_LIST_DATA};
+    use mavlink::{MavConnection, MavConnectionExt, MavFrame, MavHeader, MavlinkVersion};
+
+    use crate::test_shared::{get_heartbeat_msg, mock_connection_pair};
+
+    #[test]
+    pub fn test_frame_get() {
+        let frame = MavFrame {
+            header: MavHeader::default(),
+            msg: MavMessage::HEARTBEAT(get_heartbeat_msg()),
+            protocol_version: MavlinkVersion::V2,
+        };
+        assert_eq!(frame.get::<HEARTBEAT_DATA>(), Some(&get_heartbeat_msg()));
+        assert_eq!(frame.get::<PARAM_REQUEST_LIST_DATA>(), None);
+    }
+
+    #[test]
+    pub fn test_recv_as() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        let header = MavHeader {
+            system_id: 1,
+            component_id: 1,
+            sequence: 7,
+        };
+        vehicle
+            .send(
+                &header,
+                &MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA::default()),
+            )
+            .unwrap();
+        vehicle
+            .send(&header, &MavMessage::HEARTBEAT(get_heartbeat_msg()))
+            .unwrap();
+
+        // the PARAM_REQUEST_LIST before the HEARTBEAT is skipped
+        let boxed: Box<dyn MavConnection<MavMessage>> = Box::new(gcs);
+        let (received_header, heartbeat) = boxed.recv_as::<HEARTBEAT_DATA>().unwrap();
+        assert_eq!(received_header, header);
+        assert_eq!(heartbeat, get_heartbeat_msg());
+
+        // nothing left, the timeout of the connection is returned
+        assert!(boxed.recv_as::<HEARTBEAT_DATA>().is_err());
+    }
+
+    #[test]
+    pub fn test_recv_matching() {
+        let (gcs, vehicle) = mock_connection_pair::<MavMessage>();
+        for sequence in 0..3 {
+            let header = MavHeader {
+                sequence,
+                ..Default::default()
+            };
+            vehicle
+                .send(&header, &MavMessage::HEARTBEAT(get_heartbeat_msg()))
+                .unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let (header, _) = gcs
+            .recv_matching(deadline, |header, _| header.sequence == 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.sequence, 1);
+
+        // the remaining message does not match until the deadline passes
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert!(gcs
+            .recv_matching(deadline, |header, _| header.sequence == 1)
+            .unwrap()
+            .is_none());
+        assert!(Instant::now() >= deadline);
+    }
+}
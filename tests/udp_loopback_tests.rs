@@ -2,7 +2,13 @@ mod test_shared;
 
 #[cfg(all(feature = "std", feature = "udp", feature = "common"))]
 mod test_udp_connections {
+    use std::net::UdpSocket;
+    use std::sync::Arc;
     use std::thread;
+    use std::time::{Duration, Instant};
+
+    use mavlink::common::{MavMessage, PING_DATA};
+    use mavlink::{MavHeader, MavlinkVersion, Message};
 
     /// Test whether we can send a message via UDP and receive it OK
     #[test]
@@ -44,4 +50,101 @@ mod test_udp_connections {
         }
         assert_eq!(recv_count, RECEIVE_CHECK_COUNT);
     }
+
+    fn frame(msg: &MavMessage) -> Vec<u8> {
+        let mut buf = Vec::new();
+        mavlink::write_versioned_msg(&mut buf, MavlinkVersion::V2, MavHeader::default(), msg)
+            .unwrap();
+        buf
+    }
+
+    /// Id of the message in the next datagram, `None` if none arrives
+    fn recv_id(socket: &UdpSocket) -> Option<u32> {
+        let mut buf = [0; 300];
+        let (len, _) = socket.recv_from(&mut buf).ok()?;
+        let (_, msg) =
+            mavlink::read_versioned_msg::<MavMessage, _>(&mut &buf[..len], MavlinkVersion::V2)
+                .ok()?;
+        Some(msg.message_id())
+    }
+
+    /// Send PINGs until one arrives at `socket`, panics if none does before `deadline`
+    fn poll_ping(
+        client: &dyn mavlink::MavConnection<MavMessage>,
+        socket: &UdpSocket,
+        deadline: Instant,
+        mut between: impl FnMut(),
+    ) {
+        while recv_id(socket) != Some(4) {
+            assert!(
+                Instant::now() < deadline,
+                "no PING arrived before the deadline"
+            );
+            between();
+            client
+                .send_default(&MavMessage::PING(PING_DATA::default()))
+                .unwrap();
+        }
+    }
+
+    /// Receive until no more datagrams arrive
+    fn drain(socket: &UdpSocket) {
+        while recv_id(socket).is_some() {}
+    }
+
+    /// Test whether a udpout connection with a backup destination fails over and back
+    #[test]
+    pub fn test_udp_failover() {
+        let primary = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let backup = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for socket in [&primary, &backup] {
+            socket
+                .set_read_timeout(Some(Duration::from_millis(200)))
+                .unwrap();
+        }
+        let address = format!(
+            "udpout:{},{}",
+            primary.local_addr().unwrap(),
+            backup.local_addr().unwrap()
+        );
+
+        let client =
+            Arc::new(mavlink::connect::<MavMessage>(&address).expect("Couldn't create client"));
+        // receiving keeps track of the heartbeats of the destinations
+        thread::spawn({
+            let client = client.clone();
+            move || while client.recv().is_ok() {}
+        });
+
+        let heartbeat = MavMessage::HEARTBEAT(crate::test_shared::get_heartbeat_msg());
+        let ping = MavMessage::PING(PING_DATA::default());
+
+        // heartbeats go to all destinations, everything else to the primary
+        client.send_default(&heartbeat).unwrap();
+        assert_eq!(recv_id(&primary), Some(0));
+        let mut buf = [0; 300];
+        let (_, client_addr) = backup.recv_from(&mut buf).unwrap();
+        client.send_default(&ping).unwrap();
+        assert_eq!(recv_id(&primary), Some(4));
+        assert_eq!(recv_id(&backup), None);
+
+        // only the backup answers the heartbeats
+        let deadline = Instant::now() + Duration::from_secs(10);
+        poll_ping(&**client, &backup, deadline, || {
+            backup.send_to(&frame(&heartbeat), client_addr).unwrap();
+        });
+        drain(&primary);
+        client.send_default(&ping).unwrap();
+        assert_eq!(recv_id(&backup), Some(4));
+        assert_eq!(recv_id(&primary), None);
+
+        // the primary is back
+        primary.send_to(&frame(&heartbeat), client_addr).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        poll_ping(&**client, &primary, deadline, || ());
+        drain(&backup);
+        client.send_default(&ping).unwrap();
+        assert_eq!(recv_id(&primary), Some(4));
+        assert_eq!(recv_id(&backup), None);
+    }
 }
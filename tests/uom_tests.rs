@@ -0,0 +1,46 @@
+#[cfg(all(feature = "std", feature = "common", feature = "uom"))]
+mod uom_tests {
+    use mavlink::common::{ATTITUDE_DATA, GLOBAL_POSITION_INT_DATA, VFR_HUD_DATA};
+    use uom::si::angle::{degree, radian};
+    use uom::si::length::{meter, millimeter};
+    use uom::si::velocity::meter_per_second;
+
+    #[test]
+    pub fn test_scaled_units() {
+        let position = GLOBAL_POSITION_INT_DATA {
+            lat: 473_977_418,
+            lon: 85_455_939,
+            alt: 488_150,
+            relative_alt: -1_250,
+            vx: 150,
+            hdg: 9_000,
+            ..Default::default()
+        };
+
+        assert!((position.lat_quantity().get::<degree>() - 47.3977418).abs() < 1e-9);
+        assert!((position.lon_quantity().get::<degree>() - 8.5455939).abs() < 1e-9);
+        assert!((position.alt_quantity().get::<millimeter>() - 488_150.0).abs() < 1e-6);
+        assert!((position.relative_alt_quantity().get::<meter>() + 1.25).abs() < 1e-9);
+        assert!((position.vx_quantity().get::<meter_per_second>() - 1.5).abs() < 1e-9);
+        assert!((position.hdg_quantity().get::<degree>() - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_units_are_comparable() {
+        let attitude = ATTITUDE_DATA {
+            yaw: std::f32::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        let hud = VFR_HUD_DATA {
+            heading: 90,
+            groundspeed: 2.5,
+            alt: 488.15,
+            ..Default::default()
+        };
+
+        // a heading in degrees against a yaw in radians
+        let difference = attitude.yaw_quantity() - hud.heading_quantity();
+        assert!(difference.get::<radian>().abs() < 1e-6);
+        assert_eq!(hud.groundspeed_quantity().get::<meter_per_second>(), 2.5);
+    }
+}
@@ -167,4 +167,29 @@ mod test_v2_encode_decode {
         assert_eq!(raw_msg.raw_bytes(), HEARTBEAT_V2);
         assert!(raw_msg.has_valid_crc::<mavlink::common::MavMessage>());
     }
+
+    #[test]
+    pub fn test_try_from_raw() {
+        use mavlink::common::{MavMessage, ATTITUDE_DATA, HEARTBEAT_DATA};
+        use mavlink::error::ParserError;
+        use mavlink::MessageData;
+        use std::convert::TryFrom;
+
+        let heartbeat_msg = crate::test_shared::get_heartbeat_msg();
+        let mut raw_msg = mavlink::MAVLinkV2MessageRaw::new();
+        raw_msg.serialize_message_data(crate::test_shared::COMMON_MSG_HEADER, &heartbeat_msg);
+
+        let msg = MavMessage::try_from(&raw_msg).expect("Failed to parse");
+        assert_eq!(msg, MavMessage::HEARTBEAT(heartbeat_msg.clone()));
+        let heartbeat = HEARTBEAT_DATA::try_from(&raw_msg).expect("Failed to parse");
+        assert_eq!(heartbeat, heartbeat_msg);
+
+        assert!(matches!(
+            ATTITUDE_DATA::try_from(&raw_msg),
+            Err(ParserError::UnexpectedMessage {
+                expected: ATTITUDE_DATA::ID,
+                id: HEARTBEAT_DATA::ID,
+            })
+        ));
+    }
 }
@@ -0,0 +1,20 @@
+mod test_shared;
+
+#[cfg(all(feature = "wireshark", feature = "common"))]
+mod wireshark_tests {
+    use mavlink::wireshark::DISSECTOR;
+
+    #[test]
+    pub fn test_dissector_registers_udp_ports() {
+        assert!(DISSECTOR.contains("DissectorTable.get(\"udp.port\")"));
+        assert!(DISSECTOR.contains("14550"));
+    }
+
+    #[test]
+    pub fn test_dissector_fields() {
+        // units and enum names come from the definitions
+        assert!(DISSECTOR.contains("\"roll (rad)\""));
+        assert!(DISSECTOR.contains("[2] = \"MAV_TYPE_QUADROTOR\""));
+        assert!(DISSECTOR.contains("HEARTBEAT"));
+    }
+}
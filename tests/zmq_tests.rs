@@ -0,0 +1,84 @@
+mod test_shared;
+
+#[cfg(all(feature = "zmq", feature = "common"))]
+mod zmq_tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use mavlink::common::MavMessage;
+    use mavlink::zmq::{ZmqEncoding, ZmqPublisher, ZmqReceiver};
+    use mavlink::{MavHeader, MavlinkVersion};
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_millis(500));
+
+    fn header(system_id: u8) -> MavHeader {
+        MavHeader {
+            system_id,
+            component_id: 1,
+            sequence: 5,
+        }
+    }
+
+    fn heartbeat() -> MavMessage {
+        MavMessage::HEARTBEAT(crate::test_shared::get_heartbeat_msg())
+    }
+
+    fn command_int() -> MavMessage {
+        MavMessage::COMMAND_INT(crate::test_shared::get_cmd_nav_takeoff_msg())
+    }
+
+    #[test]
+    pub fn test_publish_subscribe() {
+        for encoding in [ZmqEncoding::Raw, ZmqEncoding::Json] {
+            let context = zmq::Context::new();
+            let endpoint = format!("inproc://publish-{encoding:?}");
+            let publisher = ZmqPublisher::bind(&context, &endpoint, encoding).unwrap();
+            let receiver = ZmqReceiver::subscribe(&context, &endpoint, &["HEARTBEAT"]).unwrap();
+            // let the subscription reach the publisher
+            thread::sleep(Duration::from_millis(100));
+
+            publisher.publish(&header(1), &command_int()).unwrap();
+            publisher.publish(&header(2), &heartbeat()).unwrap();
+
+            let (received_header, received) =
+                receiver.receive::<MavMessage>(TIMEOUT).unwrap().unwrap();
+            assert_eq!(received_header, header(2));
+            assert_eq!(received, heartbeat());
+            // COMMAND_INT was filtered out
+            assert!(receiver
+                .receive::<MavMessage>(Some(Duration::from_millis(50)))
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[test]
+    pub fn test_pull() {
+        let context = zmq::Context::new();
+        let receiver = ZmqReceiver::pull(&context, "inproc://pull")
+            .unwrap()
+            .with_header(header(9));
+        let push = context.socket(zmq::PUSH).unwrap();
+        push.connect("inproc://pull").unwrap();
+
+        // single part raw MAVLink 1 frame
+        let mut frame = Vec::new();
+        mavlink::write_versioned_msg(&mut frame, MavlinkVersion::V1, header(3), &heartbeat())
+            .unwrap();
+        push.send(&frame[..], 0).unwrap();
+        let (received_header, received) = receiver.receive::<MavMessage>(TIMEOUT).unwrap().unwrap();
+        assert_eq!(received_header, header(3));
+        assert_eq!(received, heartbeat());
+
+        // JSON without header
+        let json = serde_json::json!({ "message": command_int() });
+        push.send(json.to_string().as_bytes(), 0).unwrap();
+        let (received_header, received) = receiver.receive::<MavMessage>(TIMEOUT).unwrap().unwrap();
+        assert_eq!(received_header, header(9));
+        assert_eq!(received, command_int());
+
+        push.send(&b"{\"message\": {\"type\": \"NOPE\"}}"[..], 0)
+            .unwrap();
+        assert!(receiver.receive::<MavMessage>(TIMEOUT).is_err());
+    }
+}